@@ -26,6 +26,8 @@ in
               lockFile = "${finalAttrs.src}/Cargo.lock";
             };
 
+            checkFeatures = [ "proptest" ];
+
             passthru = with config; {
               inherit (mkDerivation) hook;
               inherit writeBunScriptBin writeBunApplication;
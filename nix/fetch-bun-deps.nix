@@ -39,17 +39,35 @@ in
         {
           bunNix,
           overrides ? { },
+          store ? "extracted",
+          prune ? [ ],
+          inputs ? { },
           ...
         }@args:
         let
           attrIsBunPkg = _: value: lib.isStorePath value;
 
-          withErrCtx = builtins.addErrorContext invalidBunNixErr (pkgs.callPackage bunNix { });
+          withErrCtx = builtins.addErrorContext invalidBunNixErr (
+            pkgs.callPackage bunNix { inherit inputs; }
+          );
 
           packages = lib.filterAttrs attrIsBunPkg withErrCtx;
 
+          # Project metadata recorded by `bun2nix`, such as its name and version
+          passthru = withErrCtx.passthru or { };
+
+          # The binaries several packages provide, as settled by `bun2nix`,
+          # which the setup hook links into `node_modules/.bin`
+          binaries = passthru.binaries or { };
+          binaryLinks = pkgs.writeTextDir "share/bun2nix/binaries" (
+            lib.concatStrings (lib.mapAttrsToList (name: path: "${name}\t${path}\n") binaries)
+          );
+
           buildPackage = config.fetchBunDeps.buildPackage args;
+          buildCache = config.fetchBunDeps.buildCache args;
           overridePackage = config.fetchBunDeps.overridePackage args;
+
+          overridden = builtins.mapAttrs overridePackage packages;
         in
 
         assert lib.asserts.assertEachOneOf "overrides" (builtins.attrNames overrides) (
@@ -59,13 +77,36 @@ in
         assert lib.assertMsg (builtins.all builtins.isFunction (builtins.attrValues overrides))
           "All attr values of `overrides` must be functions taking the old, unoverrided package and returning the new source.";
 
-        pkgs.symlinkJoin {
-          name = "bun-cache";
-          paths = lib.pipe packages [
-            (builtins.mapAttrs overridePackage)
-            (builtins.mapAttrs buildPackage)
-            builtins.attrValues
-          ];
-        };
+        assert lib.assertOneOf "store" store [
+          "extracted"
+          "tarball"
+        ];
+
+        assert lib.asserts.assertEachOneOf "prune" prune [
+          "docs"
+          "tests"
+          "maps"
+        ];
+
+        lib.extendDerivation true passthru (
+          if store == "tarball" then
+            (buildCache overridden).overrideAttrs (
+              lib.optionalAttrs (binaries != { }) {
+                postCacheEntry = ''
+                  install -Dm644 ${binaryLinks}/share/bun2nix/binaries "$out/share/bun2nix/binaries"
+                '';
+              }
+            )
+          else
+            pkgs.symlinkJoin {
+              name = "bun-cache";
+              paths =
+                lib.pipe overridden [
+                  (builtins.mapAttrs buildPackage)
+                  builtins.attrValues
+                ]
+                ++ lib.optional (binaries != { }) binaryLinks;
+            }
+        );
     };
 }
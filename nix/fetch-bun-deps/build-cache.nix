@@ -0,0 +1,98 @@
+{ lib, flake-parts-lib, ... }:
+let
+  inherit (flake-parts-lib) mkPerSystemOption;
+  inherit (lib) mkOption types;
+in
+{
+  options.perSystem = mkPerSystemOption {
+    options.fetchBunDeps.buildCache = mkOption {
+      description = ''
+        Build the whole bun cache in a single derivation
+        straight from the fetched packages, for the
+        `tarball` store mode of `fetchBunDeps`.
+
+        Every package is extracted, has its scripts patched
+        if `patchShebangs` is enabled, and is given a bun
+        cache compatible symlink in $out/share/bun-cache,
+        without keeping an extracted copy of each package
+        in the Nix store.
+      '';
+      type = types.functionTo (types.functionTo types.package);
+    };
+  };
+
+  config.perSystem =
+    {
+      pkgs,
+      config,
+      self',
+      ...
+    }:
+    {
+      fetchBunDeps.buildCache =
+        {
+          patchShebangs ? true,
+          prune ? [ ],
+          ...
+        }@args:
+        let
+          bunWithNode = config.fetchBunDeps.bunWithNode args;
+        in
+        packages:
+        let
+          # One `<name>\t<path>` line per package, kept in a file
+          # as it is too long to pass through the environment
+          manifest = pkgs.writeText "bun-packages" (
+            lib.concatStrings (lib.mapAttrsToList (name: pkg: "${name}\t${pkg}\n") packages)
+          );
+        in
+        pkgs.stdenvNoCC.mkDerivation {
+          name = "bun-cache";
+
+          nativeBuildInputs = [
+            bunWithNode
+          ];
+
+          phases = [
+            "extractPhase"
+            "patchPhase"
+            "cacheEntryPhase"
+          ];
+
+          extractPhase = ''
+            runHook preExtract
+
+            # Extract as many packages at once as there are build cores
+            tr '\t' '\n' < "${manifest}" | xargs -d '\n' -n 2 -P "$NIX_BUILD_CORES" \
+              sh -c 'exec "$0" --package "$2" --out "$out/share/bun-packages/$1"' \
+              "${lib.getExe config.fetchBunDeps.extractPackage}"
+
+            ${lib.optionalString (prune != [ ]) ''
+              "${lib.getExe config.fetchBunDeps.prunePackage}" \
+                "$out/share/bun-packages" \
+                ${lib.escapeShellArgs prune}
+            ''}
+
+            runHook postExtract
+          '';
+
+          patchPhase = ''
+            runHook prePatch
+
+            ${lib.optionalString patchShebangs ''patchShebangs "$out/share/bun-packages"''}
+
+            runHook postPatch
+          '';
+
+          cacheEntryPhase = ''
+            runHook preCacheEntry
+
+            cut -f 1 "${manifest}" | xargs -d '\n' -n 1 -P "$NIX_BUILD_CORES" \
+              sh -c 'exec "$0" --out "$out/share/bun-cache" --name "$1" --package "$out/share/bun-packages/$1"' \
+              "${lib.getExe self'.packages.cacheEntryCreator}"
+
+            runHook postCacheEntry
+          '';
+        };
+    };
+}
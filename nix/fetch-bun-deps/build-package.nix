@@ -30,6 +30,7 @@ in
       fetchBunDeps.buildPackage =
         {
           patchShebangs ? true,
+          prune ? [ ],
           ...
         }@args:
         let
@@ -56,6 +57,12 @@ in
               --package "${pkg}" \
               --out "$out/share/bun-packages/${name}"
 
+            ${lib.optionalString (prune != [ ]) ''
+              "${lib.getExe config.fetchBunDeps.prunePackage}" \
+                "$out/share/bun-packages/${name}" \
+                ${lib.escapeShellArgs prune}
+            ''}
+
             runHook postExtract
           '';
 
@@ -11,6 +11,10 @@ in
 
         If the package is a tarball, extract it,
         otherwise make a copy of the input directory in $out
+
+        Ownership, file modes and timestamps are normalized,
+        so the result does not depend on the machine it was
+        extracted on or how the tarball was packed
       '';
       type = types.package;
     };
@@ -25,6 +29,8 @@ in
       fetchBunDeps.extractPackage = pkgs.writeShellApplication {
         name = "extract-bun-package";
         runtimeInputs = [
+          pkgs.coreutils
+          pkgs.findutils
           pkgs.libarchive
         ];
         text = ''
@@ -78,12 +84,19 @@ in
               --directory "$out" \
               --strip-components=1 \
               --no-same-owner \
-              --no-same-permissions
+              --no-same-permissions \
+              --no-xattrs \
+              --no-acls \
+              --no-fflags
           else
-            cp -r "$pkg/." "$out"
+            cp -r --no-preserve=all "$pkg/." "$out"
           fi
 
-          chmod -R u+rwx "$out"
+          # Group and other users get the owner's permissions, less write
+          chmod -R u+rwx,go=u-w "$out"
+
+          # Use the same timestamp as the Nix store for every file
+          find "$out" -exec touch --no-dereference --date=@1 {} +
         '';
       };
     };
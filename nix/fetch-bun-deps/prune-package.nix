@@ -0,0 +1,80 @@
+{ lib, flake-parts-lib, ... }:
+let
+  inherit (flake-parts-lib) mkPerSystemOption;
+  inherit (lib) mkOption types;
+in
+{
+  options.perSystem = mkPerSystemOption {
+    options.fetchBunDeps.prunePackage = mkOption {
+      description = ''
+        Script removing files which are not needed at
+        runtime from extracted packages, for the `prune`
+        argument of `fetchBunDeps`.
+
+        Takes the directory to prune and the kinds of
+        files to remove from it, any of `docs`, `tests`
+        and `maps`. `package.json` and license files are
+        always kept.
+      '';
+      type = types.package;
+    };
+  };
+
+  config.perSystem =
+    {
+      pkgs,
+      ...
+    }:
+    {
+      fetchBunDeps.prunePackage = pkgs.writeShellApplication {
+        name = "prune-bun-package";
+        runtimeInputs = [
+          pkgs.findutils
+        ];
+        text = ''
+          if [ "$#" -lt 1 ]; then
+            echo "Usage: prune-bun-package <dir> [docs|tests|maps]..."
+            exit 1
+          fi
+
+          dir="$1"
+          shift
+
+          remove_dirs () {
+            find "$dir" -mindepth 1 -type d \( "$@" \) -prune -exec rm -rf {} +
+          }
+
+          remove_files () {
+            find "$dir" -type f \( "$@" \) \
+              ! -name package.json \
+              ! -iname 'license*' \
+              ! -iname 'licence*' \
+              -delete
+          }
+
+          for kind in "$@"; do
+            case "$kind" in
+              docs)
+                remove_dirs -name doc -o -name docs
+                remove_files -iname '*.md' -o -iname '*.markdown' \
+                  -o -iname 'readme' -o -iname 'readme.txt' \
+                  -o -iname 'changelog' -o -iname 'changelog.txt' \
+                  -o -iname 'history' -o -iname 'history.txt'
+                ;;
+              tests)
+                remove_dirs -name test -o -name tests -o -name __tests__
+                remove_files -name '*.test.*' -o -name '*.spec.*'
+                ;;
+              maps)
+                remove_files -name '*.map'
+                ;;
+              *)
+                echo "Unknown kind of file to prune: $kind"
+                exit 1
+                ;;
+            esac
+          done
+        '';
+      };
+    };
+}
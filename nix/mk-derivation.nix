@@ -69,18 +69,19 @@ in
             }
           '';
 
-          assert lib.assertMsg (args ? pname || packageJson != null)
-            "bun2nix.mkDerivation: Either `pname` or `packageJson` must be set in order to assign a name to the package. It may be assigned manually with `pname` which always takes priority or read from the `name` field of `packageJson`.";
+          assert lib.assertMsg (args ? pname || packageJson != null || args ? bunDeps.project.name)
+            "bun2nix.mkDerivation: Either `pname` or `packageJson` must be set in order to assign a name to the package. It may be assigned manually with `pname` which always takes priority, read from the `name` field of `packageJson`, or taken from the project recorded in `bun.nix` by `bun2nix`.";
 
-          assert lib.assertMsg (args ? version || packageJson != null)
-            "bun2nix.mkDerivation: Either `version` or `packageJson` must be set in order to assign a version to the package. It may be assigned manually with `version` which always takes priority or read from the `version` field of `packageJson`.";
+          assert lib.assertMsg (args ? version || packageJson != null || args ? bunDeps.project.version)
+            "bun2nix.mkDerivation: Either `version` or `packageJson` must be set in order to assign a version to the package. It may be assigned manually with `version` which always takes priority, read from the `version` field of `packageJson`, or taken from the project recorded in `bun.nix` by `bun2nix`.";
 
           let
             pkgJsonContents = builtins.readFile packageJson;
             package = if packageJson != null then (builtins.fromJSON pkgJsonContents) else { };
+            project = args.bunDeps.project or { };
 
-            pname = args.pname or package.name or null;
-            version = args.version or package.version or null;
+            pname = args.pname or package.name or project.name or null;
+            version = args.version or package.version or project.version or null;
             module = args.module or package.module or null;
           in
 
@@ -111,6 +112,11 @@ in
 
             inherit (args) bunDeps;
 
+            # Use the update script recorded in `bun.nix`, if there is one
+            passthru =
+              lib.optionalAttrs (args.bunDeps ? updateScript) { inherit (args.bunDeps) updateScript; }
+              // args.passthru or { };
+
             bunBuildFlags = lib.optional (module != null) [
               "${module}"
               "--outfile"
@@ -47,6 +47,23 @@ function bunPatchPhase {
   runHook postPatch
 }
 
+# Link the binaries several packages provide to the package `bun2nix`
+# chose for each, rather than whichever bun happened to link last
+function bunLinkBinaries {
+  local binaries="$bunDeps/share/bun2nix/binaries"
+
+  if ! [ -f "$binaries" ]; then
+    return
+  fi
+
+  mkdir -p node_modules/.bin
+
+  local name path
+  while IFS=$'\t' read -r name path; do
+    ln -sfn "../$path" "node_modules/.bin/$name"
+  done <"$binaries"
+}
+
 function bunNodeModulesInstallPhase {
   runHook preBunNodeModulesInstallPhase
 
@@ -65,6 +82,8 @@ function bunNodeModulesInstallPhase {
   echoCmd 'bun install flags' "${flagsArray[@]}"
   bun install "${flagsArray[@]}"
 
+  bunLinkBinaries
+
   runHook postBunNodeModulesInstallPhase
 }
 
@@ -85,6 +104,8 @@ function bunLifecycleScriptsPhase {
   echoCmd 'bun lifecycle install flags' "${flagsArray[@]}"
   bun install "${flagsArray[@]}"
 
+  bunLinkBinaries
+
   runHook postBunLifecycleScriptsPhase
 }
 
@@ -0,0 +1,218 @@
+//! This module holds the reading of lockfiles from inside archives, such as release artifacts or
+//! the output of `npm pack`, without extracting them
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use flate2::read::GzDecoder;
+
+use crate::error::{Error, Result};
+
+/// The name of the lockfile looked for in archives
+const LOCKFILE: &str = "bun.lock";
+
+/// The name of the package manifest read from beside the lockfile
+const PACKAGE_JSON: &str = "package.json";
+
+/// # Archive Format
+///
+/// The kinds of archive a lockfile can be read from, going by the extension of the archive
+///
+/// ## Usage
+///```rust
+/// use std::path::Path;
+///
+/// use bun2nix::archive::ArchiveFormat;
+///
+/// assert_eq!(ArchiveFormat::from_path(Path::new("my-app-1.0.0.tgz")), Some(ArchiveFormat::TarGz));
+/// assert_eq!(ArchiveFormat::from_path(Path::new("release.tar.gz")), Some(ArchiveFormat::TarGz));
+/// assert_eq!(ArchiveFormat::from_path(Path::new("release.ZIP")), Some(ArchiveFormat::Zip));
+/// assert_eq!(ArchiveFormat::from_path(Path::new("bun.lock")), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// An uncompressed tarball
+    Tar,
+    /// A gzip compressed tarball, as written by `npm pack` and `bun pm pack`
+    TarGz,
+    /// A zip archive
+    Zip,
+}
+
+/// # Archived Project
+///
+/// The lockfile of a project found inside an archive, along with the `package.json` beside it.
+///
+/// Archives may hold their project in a subdirectory, such as the `package/` directory of
+/// `npm pack` tarballs, so the shallowest lockfile in the archive is the one read.
+///
+/// ## Usage
+///```rust
+/// use std::fs::{self, File};
+///
+/// use bun2nix::archive::ArchivedProject;
+///
+/// let dir = std::env::temp_dir().join("bun2nix-archive-doctest");
+/// fs::create_dir_all(&dir).unwrap();
+///
+/// let path = dir.join("my-app-1.0.0.tar");
+/// let mut tarball = tar::Builder::new(File::create(&path).unwrap());
+///
+/// for (name, contents) in [
+///     ("package/package.json", r#"{ "name": "my-app" }"#),
+///     ("package/bun.lock", r#"{ "lockfileVersion": 1 }"#),
+///     ("package/examples/demo/bun.lock", "{}"),
+/// ] {
+///     let mut header = tar::Header::new_gnu();
+///     header.set_size(contents.len() as u64);
+///     header.set_mode(0o644);
+///     header.set_cksum();
+///     tarball.append_data(&mut header, name, contents.as_bytes()).unwrap();
+/// }
+/// tarball.finish().unwrap();
+/// drop(tarball);
+///
+/// let project = ArchivedProject::read(&path).unwrap();
+///
+/// assert_eq!(project.lock_file, "package/bun.lock");
+/// assert_eq!(project.lockfile, r#"{ "lockfileVersion": 1 }"#);
+/// assert_eq!(project.package_json.as_deref(), Some(r#"{ "name": "my-app" }"#));
+///
+/// fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedProject {
+    /// The path of the lockfile inside the archive
+    pub lock_file: String,
+
+    /// The contents of the lockfile
+    pub lockfile: String,
+
+    /// The contents of the `package.json` beside the lockfile, if there is one
+    pub package_json: Option<String>,
+}
+
+impl ArchiveFormat {
+    /// # Archive Format From Path
+    ///
+    /// The format of the archive at a path, or `None` if it does not look like an archive
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+
+        if name.ends_with(".tgz") || name.ends_with(".tar.gz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+impl ArchivedProject {
+    /// # Read Archived Project
+    ///
+    /// Read the shallowest lockfile in the archive at the given path, and the `package.json`
+    /// beside it, failing if the archive holds no lockfile
+    pub fn read(path: &Path) -> Result<Self> {
+        let format = ArchiveFormat::from_path(path)
+            .ok_or_else(|| Error::UnknownArchiveFormat(path.display().to_string()))?;
+        let file = BufReader::new(File::open(path)?);
+
+        let mut files = match format {
+            ArchiveFormat::Tar => read_tar(file)?,
+            ArchiveFormat::TarGz => read_tar(GzDecoder::new(file))?,
+            ArchiveFormat::Zip => read_zip(file)?,
+        };
+
+        let lock_file = files
+            .keys()
+            .filter(|name| file_name(name) == LOCKFILE)
+            .min_by_key(|name| (name.matches('/').count(), name.as_str()))
+            .cloned()
+            .ok_or_else(|| Error::NoLockfileInArchive(path.display().to_string()))?;
+
+        let package_json = format!(
+            "{}{PACKAGE_JSON}",
+            &lock_file[..lock_file.len() - LOCKFILE.len()]
+        );
+
+        Ok(Self {
+            lockfile: files.remove(&lock_file).unwrap_or_default(),
+            package_json: files.remove(&package_json),
+            lock_file,
+        })
+    }
+}
+
+/// Whether an entry of an archive is one which may be read
+fn is_wanted(name: &str) -> bool {
+    matches!(file_name(name), LOCKFILE | PACKAGE_JSON)
+}
+
+/// The last component of the path of an entry in an archive
+fn file_name(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+/// Every lockfile and `package.json` in a tarball, by their normalised paths
+fn read_tar(reader: impl Read) -> Result<BTreeMap<String, String>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = BTreeMap::new();
+
+    for entry in archive.entries().map_err(Error::ArchiveRead)? {
+        let mut entry = entry.map_err(Error::ArchiveRead)?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = normalise(&entry.path().map_err(Error::ArchiveRead)?.to_string_lossy());
+
+        if is_wanted(&name) {
+            let mut contents = String::new();
+            entry
+                .read_to_string(&mut contents)
+                .map_err(Error::ArchiveRead)?;
+            files.insert(name, contents);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Every lockfile and `package.json` in a zip archive, by their normalised paths
+fn read_zip(reader: BufReader<File>) -> Result<BTreeMap<String, String>> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(|err| Error::ArchiveRead(err.into()))?;
+    let mut files = BTreeMap::new();
+
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|err| Error::ArchiveRead(err.into()))?;
+
+        let name = normalise(entry.name());
+
+        if entry.is_file() && is_wanted(&name) {
+            let mut contents = String::new();
+            entry
+                .read_to_string(&mut contents)
+                .map_err(Error::ArchiveRead)?;
+            files.insert(name, contents);
+        }
+    }
+
+    Ok(files)
+}
+
+/// The path of an entry with any leading `./` removed, so lockfiles at the root of the archive
+/// are the shallowest
+fn normalise(name: &str) -> String {
+    name.replace('\\', "/").trim_start_matches("./").to_owned()
+}
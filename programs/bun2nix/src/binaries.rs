@@ -0,0 +1,261 @@
+//! This module holds the binaries packages link into `node_modules/.bin`, so that names provided
+//! by more than one package can be found and settled before bun links whichever it meets last
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    Package,
+    error::{Error, Result},
+    out_path::normalize_path,
+};
+
+/// # Binaries
+///
+/// The packages providing each binary in the `node_modules/.bin` of the project root, from the
+/// `bin` metadata of the packages installed at the top of the tree
+///
+/// ## Usage
+///```rust
+/// use bun2nix::binaries::{BinStrategy, Binaries};
+///
+/// let lockfile = bun2nix::parse_lockfile(r#"
+/// {
+///   "lockfileVersion": 1,
+///   "workspaces": { "": { "name": "app" } },
+///   "packages": {
+///     "typescript": ["typescript@5.8.3", "", { "bin": { "tsc": "bin/tsc", "tsserver": "bin/tsserver" } }, "sha512-abc"],
+///     "tsc": ["tsc@2.0.4", "", { "bin": { "tsc": "bin/tsc" } }, "sha512-def"],
+///     "is-odd/tsc": ["tsc@1.0.0", "", { "bin": { "tsc": "bin/tsc" } }, "sha512-ghi"],
+///   }
+/// }
+/// "#)
+/// .unwrap();
+///
+/// let binaries = Binaries::new(&lockfile.packages);
+/// let collisions = binaries.collisions();
+///
+/// assert_eq!(collisions.len(), 1);
+/// assert_eq!(collisions[0].to_string(), "`tsc` is provided by `tsc` and `typescript`");
+///
+/// assert!(binaries.resolve(&BinStrategy::Error).is_err());
+///
+/// let resolved = binaries.resolve(&"prefer:typescript".parse().unwrap()).unwrap();
+/// assert_eq!(resolved[0].winner.path, "typescript/bin/tsc");
+/// assert_eq!(resolved[0].to_string(), "`tsc` is linked from `typescript`, shadowing `tsc`");
+/// ```
+#[derive(Debug, Default)]
+pub struct Binaries {
+    /// The packages providing each binary, ordered by their lockfile key
+    providers: BTreeMap<String, Vec<Provider>>,
+}
+
+/// # Binary Provider
+///
+/// A package which links a binary into `node_modules/.bin`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provider {
+    /// The lockfile key of the package, which is its name at the top of the tree
+    pub package: String,
+
+    /// The path of the binary from `node_modules`
+    pub path: String,
+}
+
+/// # Binary Collision
+///
+/// A binary name provided by more than one package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    /// The name of the binary
+    pub name: String,
+
+    /// Every package providing it, ordered by their lockfile key
+    pub providers: Vec<Provider>,
+}
+
+/// # Binary Resolution
+///
+/// The package chosen to provide a binary which several packages provide
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    /// The name of the binary
+    pub name: String,
+
+    /// The package whose binary is linked
+    pub winner: Provider,
+
+    /// The packages whose binaries are not linked
+    pub shadowed: Vec<String>,
+}
+
+/// # Binary Conflict Strategy
+///
+/// How a binary provided by several packages is settled, written as `error`, `first-wins` or
+/// `prefer:<package>[,<package>...]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinStrategy {
+    /// Fail if any binary is provided by more than one package
+    Error,
+
+    /// Link the binary of the package which comes first in the lockfile
+    FirstWins,
+
+    /// Link the binary of the first of these packages which provides it, falling back to the
+    /// package which comes first in the lockfile
+    Prefer(Vec<String>),
+}
+
+impl Binaries {
+    /// # New Binaries
+    ///
+    /// Collect the binaries of the packages of a parsed lockfile which are installed at the top
+    /// of the tree, as only these are linked into the root `node_modules/.bin`
+    pub fn new(packages: &[Package]) -> Self {
+        let mut binaries = Self::default();
+
+        let top_level = packages.iter().filter_map(|pkg| {
+            let key = pkg.lockfile_key.as_deref()?;
+
+            (key.matches('/').count() <= usize::from(key.starts_with('@'))).then_some((key, pkg))
+        });
+
+        for (key, pkg) in top_level {
+            for (name, path) in &pkg.bin {
+                let path = normalize_path(path);
+
+                binaries
+                    .providers
+                    .entry(name.clone())
+                    .or_default()
+                    .push(Provider {
+                        path: format!("{key}/{path}"),
+                        package: key.to_owned(),
+                    });
+            }
+        }
+
+        for providers in binaries.providers.values_mut() {
+            providers.sort_by(|a, b| a.package.cmp(&b.package));
+        }
+
+        binaries
+    }
+
+    /// # Binary Collisions
+    ///
+    /// Every binary provided by more than one package
+    pub fn collisions(&self) -> Vec<Collision> {
+        self.providers
+            .iter()
+            .filter(|(_, providers)| providers.len() > 1)
+            .map(|(name, providers)| Collision {
+                name: name.clone(),
+                providers: providers.clone(),
+            })
+            .collect()
+    }
+
+    /// # Resolve Collisions
+    ///
+    /// Choose the package providing each binary which several packages provide, or fail with
+    /// every collision if the strategy is [`BinStrategy::Error`]
+    pub fn resolve(&self, strategy: &BinStrategy) -> Result<Vec<Resolution>> {
+        let collisions = self.collisions();
+
+        let preferred = match strategy {
+            BinStrategy::Error if !collisions.is_empty() => {
+                return Err(Error::BinaryCollisions(
+                    collisions.iter().map(ToString::to_string).collect(),
+                ));
+            }
+            BinStrategy::Prefer(preferred) => preferred.as_slice(),
+            _ => &[],
+        };
+
+        Ok(collisions
+            .into_iter()
+            .map(|collision| {
+                let winner = preferred
+                    .iter()
+                    .find_map(|package| {
+                        collision
+                            .providers
+                            .iter()
+                            .position(|provider| &provider.package == package)
+                    })
+                    .unwrap_or(0);
+
+                let mut providers = collision.providers;
+                let winner = providers.remove(winner);
+
+                Resolution {
+                    name: collision.name,
+                    winner,
+                    shadowed: providers
+                        .into_iter()
+                        .map(|provider| provider.package)
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
+
+impl FromStr for BinStrategy {
+    type Err = Error;
+
+    fn from_str(strategy: &str) -> std::result::Result<Self, Self::Err> {
+        match strategy {
+            "error" => Ok(Self::Error),
+            "first-wins" => Ok(Self::FirstWins),
+            _ => match strategy.strip_prefix("prefer:") {
+                Some(packages) if !packages.is_empty() => Ok(Self::Prefer(
+                    packages
+                        .split(',')
+                        .map(|pkg| pkg.trim().to_owned())
+                        .collect(),
+                )),
+                _ => Err(Error::InvalidBinStrategy(strategy.to_owned())),
+            },
+        }
+    }
+}
+
+impl Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let providers: Vec<_> = self
+            .providers
+            .iter()
+            .map(|provider| format!("`{}`", provider.package))
+            .collect();
+
+        write!(
+            f,
+            "`{}` is provided by {}",
+            self.name,
+            providers.join(" and ")
+        )
+    }
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shadowed: Vec<_> = self
+            .shadowed
+            .iter()
+            .map(|package| format!("`{package}`"))
+            .collect();
+
+        write!(
+            f,
+            "`{}` is linked from `{}`, shadowing {}",
+            self.name,
+            self.winner.package,
+            shadowed.join(" and ")
+        )
+    }
+}
@@ -0,0 +1,183 @@
+//! This module holds the smoke test of a generated `bun.nix`, which builds just the project's
+//! `node_modules` from it with nix, so that problems show up as soon as it is generated rather
+//! than when the application is next built, along with measuring the size of what was built
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use askama::Template;
+
+use crate::{
+    error::{Error, Result},
+    interrupt,
+    package::Nix,
+};
+
+/// The flake the bun2nix library is taken from by default, which is the release matching this
+/// version of the command line tool
+pub const DEFAULT_FLAKE: &str = concat!(
+    "github:nix-community/bun2nix?tag=",
+    env!("CARGO_PKG_VERSION")
+);
+
+/// # Build Check
+///
+/// A nix expression building the `node_modules` of a project from its generated `bun.nix`,
+/// with the bun2nix library from a given flake
+///
+/// ## Usage
+///```rust
+/// use std::path::Path;
+///
+/// use bun2nix::build_check::{BuildCheck, DEFAULT_FLAKE};
+///
+/// let workspaces = ["", "packages/lib"].map(str::to_owned);
+/// let nix = BuildCheck::new(&workspaces, Path::new("/src/app/nix/bun.nix"), DEFAULT_FLAKE)
+///     .render()
+///     .unwrap();
+///
+/// assert!(nix.contains(r#"bunNix = /. + "/src/app/nix/bun.nix";"#));
+/// assert!(nix.contains("./packages/lib/package.json"));
+/// assert!(nix.contains(r#"builtins.getFlake "github:nix-community/bun2nix?tag="#));
+/// ```
+#[derive(Template)]
+#[template(path = "build-check.nix_template")]
+pub struct BuildCheck {
+    workspaces: Vec<String>,
+    bun_nix: String,
+    flake: String,
+    linker: &'static str,
+    store: &'static str,
+}
+
+impl BuildCheck {
+    /// # New Build Check
+    ///
+    /// Check the `bun.nix` at the given absolute path, for a project with the given workspace
+    /// paths
+    pub fn new<'a>(
+        workspaces: impl IntoIterator<Item = &'a String>,
+        bun_nix: &Path,
+        flake: &str,
+    ) -> Self {
+        let mut workspaces: Vec<String> = workspaces
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .cloned()
+            .collect();
+
+        workspaces.sort();
+
+        Self {
+            workspaces,
+            bun_nix: format!("/. + {}", nix_string(&bun_nix.to_string_lossy())),
+            flake: flake.to_owned(),
+            linker: "isolated",
+            store: "extracted",
+        }
+    }
+
+    /// # Render
+    ///
+    /// Render the check as a nix expression
+    pub fn render(&self) -> Result<String> {
+        Ok(Template::render(self)?)
+    }
+
+    /// # Run Build Check
+    ///
+    /// Build `node_modules` with `nix build` in the root directory of the project, returning
+    /// the store path it was built to, or failing with the errors nix gives if it cannot be
+    /// built
+    pub fn run(&self, root: &Path, nix: &Nix) -> Result<PathBuf> {
+        #[cfg(target_arch = "wasm32")]
+        return Err(Error::UnsupportedWASMCliAction("--build-check".to_owned()));
+
+        let output = interrupt::run(
+            Command::new(nix.bin())
+                .args([
+                    "--extra-experimental-features",
+                    "nix-command flakes",
+                    "build",
+                    "--impure",
+                    "--no-link",
+                    "--print-out-paths",
+                    "--expr",
+                    &self.render()?,
+                ])
+                .current_dir(root),
+        )?;
+
+        if !output.status.success() {
+            return Err(Error::BuildCheckFailed(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        Ok(PathBuf::from(
+            stdout.lines().next().unwrap_or_default().trim(),
+        ))
+    }
+}
+
+/// # Closure Size
+///
+/// The total size of a store path and everything it references, according to
+/// `nix path-info --closure-size`
+pub fn closure_size(store_path: &Path, nix: &Nix) -> Result<u64> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(Error::UnsupportedWASMCliAction("--closure-size".to_owned()));
+
+    let output = interrupt::run(
+        Command::new(nix.bin())
+            .args([
+                "--extra-experimental-features",
+                "nix-command",
+                "path-info",
+                "--closure-size",
+            ])
+            .arg(store_path),
+    )?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    match output.status.success() {
+        true => parse_closure_size(&stdout)
+            .ok_or_else(|| Error::BuildCheckFailed(format!("Unexpected output `{stdout}`"))),
+        false => Err(Error::BuildCheckFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )),
+    }
+}
+
+/// # Parse Closure Size
+///
+/// Read the size from the `<path> <size>` line printed by `nix path-info --closure-size`
+///
+/// ## Usage
+///```rust
+/// use bun2nix::build_check::parse_closure_size;
+///
+/// assert_eq!(
+///     parse_closure_size("/nix/store/0c5a…-node-modules\t 48213504\n"),
+///     Some(48213504)
+/// );
+/// assert_eq!(parse_closure_size(""), None);
+/// ```
+pub fn parse_closure_size(output: &str) -> Option<u64> {
+    output.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Quote a string as a nix string literal
+pub(crate) fn nix_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+
+    format!("\"{escaped}\"")
+}
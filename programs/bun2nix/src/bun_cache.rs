@@ -0,0 +1,135 @@
+//! This module holds the lookup of packages in bun's global install cache, whose extracted
+//! copies can be hashed locally instead of fetching the packages again
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::package::Fetcher;
+
+/// The prefix bun gives the cache directories of GitHub dependencies
+const GITHUB_PREFIX: &str = "@GH@";
+
+/// The separator bun puts after the name of a cache directory, before its version suffix
+const SUFFIX_SEPARATOR: &str = "@@@";
+
+/// # Bun Install Cache
+///
+/// The directory bun extracts every package it installs to, which is
+/// `$BUN_INSTALL_CACHE_DIR`, `$BUN_INSTALL/install/cache` or `~/.bun/install/cache` in that
+/// order of preference.
+///
+/// Only GitHub dependencies are looked up: npm packages already have their hashes in the
+/// lockfile, and bun names the directories of other dependencies after an internal hash of
+/// their url.
+///
+/// ## Usage
+///```rust
+/// use std::fs;
+///
+/// use bun2nix::{bun_cache::BunCache, package::Fetcher};
+///
+/// let dir = std::env::temp_dir().join("bun2nix-bun-cache-doctest");
+/// let zod = dir.join("@GH@colinhacks-zod-8552233@@@1");
+/// fs::create_dir_all(&zod).unwrap();
+///
+/// let cache = BunCache::new(&dir);
+/// let github = |rev: &str| Fetcher::FetchGitHub {
+///     owner: "colinhacks".to_owned(),
+///     repo: "zod".to_owned(),
+///     rev: rev.to_owned(),
+///     hash: String::new(),
+/// };
+///
+/// assert_eq!(cache.package_dir(&github("8552233")), Some(zod.clone()));
+/// assert_eq!(cache.package_dir(&github("85522336e8e1e5a8e0b5d1b1b2f3c4d5e6f70819")), Some(zod));
+/// assert_eq!(cache.package_dir(&github("0000000")), None);
+///
+/// fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BunCache {
+    dir: PathBuf,
+}
+
+impl BunCache {
+    /// # New Bun Cache
+    ///
+    /// Look up packages in the bun install cache at the given directory
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// # Default Bun Cache Directory
+    ///
+    /// Where bun keeps its install cache, going by the same environment variables it does
+    pub fn default_dir() -> Option<PathBuf> {
+        env::var_os("BUN_INSTALL_CACHE_DIR")
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("BUN_INSTALL").map(|dir| PathBuf::from(dir).join("install/cache"))
+            })
+            .or_else(|| {
+                env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".bun/install/cache"))
+            })
+    }
+
+    /// # Detect Bun Cache
+    ///
+    /// The bun install cache of the current user, if there is one
+    pub fn detect() -> Option<Self> {
+        Self::default_dir()
+            .filter(|dir| dir.is_dir())
+            .map(Self::new)
+    }
+
+    /// # Cache Directory
+    ///
+    /// The directory the cache is kept in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// # Package Directory
+    ///
+    /// The directory bun extracted the package fetched by a fetcher to, if it is in the cache.
+    ///
+    /// Directories of patched packages are never returned, as their contents differ from what
+    /// nix fetches.
+    pub fn package_dir(&self, fetcher: &Fetcher) -> Option<PathBuf> {
+        let Fetcher::FetchGitHub {
+            owner, repo, rev, ..
+        } = fetcher
+        else {
+            return None;
+        };
+
+        if rev.is_empty() {
+            return None;
+        }
+
+        let prefix = format!("{GITHUB_PREFIX}{owner}-{repo}-");
+
+        fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .find(|entry| {
+                let name = entry.file_name();
+                let Some(commit) = name
+                    .to_str()
+                    .and_then(|name| name.strip_prefix(&prefix))
+                    .and_then(|name| name.split_once(SUFFIX_SEPARATOR))
+                    .map(|(commit, _)| commit)
+                else {
+                    return false;
+                };
+
+                !commit.is_empty()
+                    && commit.chars().all(|c| c.is_ascii_hexdigit())
+                    && (rev.starts_with(commit) || commit.starts_with(rev.as_str()))
+                    && entry.path().is_dir()
+            })
+            .map(|entry| entry.path())
+    }
+}
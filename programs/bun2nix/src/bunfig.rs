@@ -0,0 +1,107 @@
+//! This module holds the parts of a project's `bunfig.toml` which change how its dependencies
+//! are resolved, so that resolving without a lockfile matches what `bun install` would do
+
+use std::{fs, io, path::Path, str::FromStr};
+
+use crate::error::{Error, Result};
+
+/// The name of bun's configuration file, which is read from the root of the project
+pub const BUNFIG_FILE: &str = "bunfig.toml";
+
+/// # Bun Configuration
+///
+/// The settings of a `bunfig.toml` which affect resolution. Only simple `key = value` lines
+/// are understood, under an `[install]` table or with the `install.` prefix, and everything
+/// else is ignored.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::bunfig::Bunfig;
+///
+/// let bunfig: Bunfig = r#"
+/// [install]
+/// # Take workspace packages from the registry unless `workspace:` is used
+/// linkWorkspacePackages = false
+/// "#
+/// .parse()
+/// .unwrap();
+///
+/// assert!(!bunfig.link_workspace_packages);
+///
+/// let bunfig: Bunfig = "install.linkWorkspacePackages = true".parse().unwrap();
+/// assert!(bunfig.link_workspace_packages);
+///
+/// assert!(Bunfig::default().link_workspace_packages);
+/// assert!("install.linkWorkspacePackages = \"no\"".parse::<Bunfig>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bunfig {
+    /// Whether dependencies on members of the workspace are linked to them when their version
+    /// satisfies the range, rather than only when the `workspace:` protocol is used
+    pub link_workspace_packages: bool,
+}
+
+impl Default for Bunfig {
+    fn default() -> Self {
+        Self {
+            link_workspace_packages: true,
+        }
+    }
+}
+
+impl Bunfig {
+    /// # Read Bun Configuration
+    ///
+    /// Read the `bunfig.toml` in the given project root, or the defaults if there isn't one
+    pub fn read(root: &Path) -> Result<Self> {
+        match fs::read_to_string(root.join(BUNFIG_FILE)) {
+            Ok(contents) => contents.parse(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl FromStr for Bunfig {
+    type Err = Error;
+
+    fn from_str(contents: &str) -> std::result::Result<Self, Self::Err> {
+        let mut bunfig = Self::default();
+        let mut table = String::new();
+
+        for line in contents.lines() {
+            let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                table = name.trim().to_owned();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let key = match table.is_empty() {
+                true => key.trim().to_owned(),
+                false => format!("{table}.{}", key.trim()),
+            };
+
+            if key == "install.linkWorkspacePackages" {
+                bunfig.link_workspace_packages = match value.trim() {
+                    "true" => true,
+                    "false" => false,
+                    other => {
+                        return Err(Error::InvalidBunfig(format!(
+                            "expected `true` or `false` for `{key}`, found `{other}`"
+                        )));
+                    }
+                };
+            }
+        }
+
+        Ok(bunfig)
+    }
+}
@@ -0,0 +1,860 @@
+//! This module holds the persistent cache of prefetched hashes, which is stored in a SQLite
+//! database so that packages only ever need to be prefetched once per machine, along with
+//! zstd compressed blobs such as registry metadata and downloaded tarballs
+
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use rusqlite::{
+    Connection, MAIN_DB, OptionalExtension, ToSql, TransactionBehavior, params, params_from_iter,
+};
+
+use crate::error::{Error, Result};
+
+mod export;
+mod integrity;
+
+pub use export::CacheExport;
+pub use integrity::{CacheProblem, IntegrityReport};
+
+/// The file name of the cache database inside the cache directory
+const CACHE_FILE: &str = "cache.sqlite";
+
+/// The tables of the cache database, created when it is first opened
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS prefetches (
+    url TEXT PRIMARY KEY NOT NULL,
+    hash TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS timings (
+    recorded_at INTEGER NOT NULL,
+    packages INTEGER NOT NULL,
+    fetched INTEGER NOT NULL,
+    cached INTEGER NOT NULL,
+    parse_ms INTEGER NOT NULL,
+    prefetch_ms INTEGER NOT NULL,
+    render_ms INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS closure_sizes (
+    recorded_at INTEGER NOT NULL,
+    project TEXT NOT NULL,
+    size INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS blobs (
+    key TEXT PRIMARY KEY NOT NULL,
+    data BLOB NOT NULL,
+    size INTEGER NOT NULL,
+    stored_at INTEGER NOT NULL
+);
+";
+
+/// # Shared Cache Directory
+///
+/// The machine wide cache directory, shared by the users in its group and every project on the
+/// machine
+pub const SHARED_CACHE_DIR: &str = "/var/cache/bun2nix";
+
+/// How long to wait for another process to finish writing to the cache before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The number of columns bound for each row inserted
+const COLUMNS_PER_ROW: usize = 3;
+
+/// The maximum number of rows inserted by a single statement, which keeps the number of bound
+/// parameters under the 999 allowed by SQLite versions before 3.32
+const ROWS_PER_STATEMENT: usize = 999 / COLUMNS_PER_ROW;
+
+/// The zstd level blobs are compressed with
+const COMPRESSION_LEVEL: i32 = 9;
+
+/// The prefix of the keys git lfs usage is remembered under
+const LFS_PREFIX: &str = "lfs:";
+
+/// The prefix of the keys flat hashes of downloaded tarballs are stored under
+const FETCHURL_PREFIX: &str = "fetchurl:";
+
+/// The prefix of the keys downloaded tarballs are kept under
+const TARBALL_PREFIX: &str = "tarball:";
+
+/// The prefix of the keys registry metadata is kept under
+const METADATA_PREFIX: &str = "metadata:";
+
+/// # Prefetch Cache
+///
+/// A SQLite backed store of hashes which have already been prefetched, keyed by the url they
+/// were prefetched from
+///
+/// ## Usage
+///```rust
+/// use bun2nix::cache::Cache;
+///
+/// let mut cache = Cache::open_in_memory().unwrap();
+///
+/// cache.insert("github:colinhacks/zod?ref=8552233", "sha256-abc").unwrap();
+///
+/// assert_eq!(
+///     cache.get("github:colinhacks/zod?ref=8552233").unwrap().as_deref(),
+///     Some("sha256-abc")
+/// );
+/// assert_eq!(cache.get("github:colinhacks/zod?ref=0000000").unwrap(), None);
+///
+/// let batch: Vec<_> = (0..1000)
+///     .map(|i| (format!("https://example.com/{i}.tgz"), format!("sha256-{i}")))
+///     .collect();
+/// cache.insert_many(&batch).unwrap();
+///
+/// assert_eq!(
+///     cache.get("https://example.com/999.tgz").unwrap().as_deref(),
+///     Some("sha256-999")
+/// );
+/// assert_eq!(cache.len().unwrap(), 1001);
+///
+/// cache.clear().unwrap();
+/// assert!(cache.is_empty().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct Cache {
+    connection: Connection,
+    read_only: bool,
+}
+
+impl Cache {
+    /// # Default Cache Path
+    ///
+    /// The location of the cache database, which is `$BUN2NIX_CACHE_DIR`,
+    /// `$XDG_CACHE_HOME/bun2nix` or `~/.cache/bun2nix` in that order of preference
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("BUN2NIX_CACHE_DIR")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("XDG_CACHE_HOME").map(|dir| PathBuf::from(dir).join("bun2nix")))
+            .or_else(|| env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".cache/bun2nix")))?;
+
+        Some(dir.join(CACHE_FILE))
+    }
+
+    /// # Shared Cache Path
+    ///
+    /// The location of the machine wide cache database, inside [`SHARED_CACHE_DIR`]
+    pub fn shared_path() -> PathBuf {
+        Path::new(SHARED_CACHE_DIR).join(CACHE_FILE)
+    }
+
+    /// # Open Cache
+    ///
+    /// Open (or create) the cache database at the given path
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Self::init(Connection::open(path).map_err(Error::Cache)?)
+    }
+
+    /// # Open Shared Cache
+    ///
+    /// Open (or create) a cache database used by several users at once, such as the one at
+    /// [`Cache::shared_path`].
+    ///
+    /// A directory created here is owned by the group of the current user and set to pass that
+    /// group on to what is created inside it, and it and the database are made readable and
+    /// writable by that group only. Everyone in the group can change the hashes `bun2nix`
+    /// writes into `bun.nix`, so it should only hold users who trust each other. If the current
+    /// user cannot write to an existing database, it is opened read only: hashes are still
+    /// looked up in it, but nothing new is stored.
+    pub fn open_shared(path: &Path) -> Result<Self> {
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty() && !parent.exists());
+
+        if let Some(parent) = parent {
+            fs::create_dir_all(parent)?;
+            share(parent, 0o2770);
+        }
+
+        let created = !path.exists();
+        let cache = Self::init(Connection::open(path).map_err(Error::Cache)?)?;
+
+        if created {
+            share(path, 0o660);
+        } else if writable_by_everyone(path) {
+            warn!(
+                "The shared cache at `{}` is writable by every user, any of whom could change the hashes written into `bun.nix` - restrict it to a group with `chmod 660`",
+                path.display()
+            );
+        }
+
+        if cache.read_only {
+            warn!(
+                "The shared cache at `{}` is not writable by this user, so nothing new will be stored in it",
+                path.display()
+            );
+        }
+
+        Ok(cache)
+    }
+
+    /// # Open In Memory Cache
+    ///
+    /// Open a cache which is never persisted to disk
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(Error::Cache)?)
+    }
+
+    fn init(connection: Connection) -> Result<Self> {
+        // Wait for other processes writing to the same database rather than failing at once
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .map_err(Error::Cache)?;
+
+        let read_only = connection.is_readonly(MAIN_DB).map_err(Error::Cache)?;
+
+        if !read_only {
+            connection.execute_batch(SCHEMA).map_err(Error::Cache)?;
+        }
+
+        Ok(Self {
+            connection,
+            read_only,
+        })
+    }
+
+    /// # Is Read Only
+    ///
+    /// Whether the cache could only be opened for reading, in which case nothing is stored in it
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// # Get Cached Hash
+    ///
+    /// Look up the hash previously prefetched from a given url
+    pub fn get(&self, url: &str) -> Result<Option<String>> {
+        self.connection
+            .query_row(
+                "SELECT hash FROM prefetches WHERE url = ?1",
+                params![url],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(Error::Cache)
+    }
+
+    /// # Cached Hash Count
+    ///
+    /// The number of hashes stored in the cache
+    pub fn len(&self) -> Result<usize> {
+        self.connection
+            .query_row("SELECT COUNT(*) FROM prefetches", [], |row| row.get(0))
+            .map_err(Error::Cache)
+    }
+
+    /// # Is Empty
+    ///
+    /// Whether the cache has no hashes stored
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// # Clear Cache
+    ///
+    /// Remove every stored hash and blob, returning how many hashes were removed
+    pub fn clear(&mut self) -> Result<usize> {
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::Cache)?;
+
+        transaction
+            .execute("DELETE FROM blobs", [])
+            .map_err(Error::Cache)?;
+
+        let removed = transaction
+            .execute("DELETE FROM prefetches", [])
+            .map_err(Error::Cache)?;
+
+        transaction.commit().map_err(Error::Cache)?;
+
+        Ok(removed)
+    }
+
+    /// # Cached Entries
+    ///
+    /// Every stored (url, hash) pair, the longest ago prefetched first
+    pub fn entries(&self) -> Result<Vec<(String, String)>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT url, hash FROM prefetches ORDER BY fetched_at, rowid")
+            .map_err(Error::Cache)?;
+
+        let entries = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(Error::Cache)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(Error::Cache)?;
+
+        Ok(entries)
+    }
+
+    /// # Blob Keys
+    ///
+    /// The keys of every stored blob which start with the given prefix
+    pub fn blob_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT key FROM blobs WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key")
+            .map_err(Error::Cache)?;
+
+        let keys = statement
+            .query_map(params![prefix], |row| row.get(0))
+            .map_err(Error::Cache)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(Error::Cache)?;
+
+        Ok(keys)
+    }
+
+    /// # Remove Entries
+    ///
+    /// Remove the hashes and blobs stored under any of the given keys, returning how many were
+    /// removed
+    pub fn remove(&mut self, keys: &[String]) -> Result<usize> {
+        if keys.is_empty() || self.read_only {
+            return Ok(0);
+        }
+
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::Cache)?;
+
+        let mut removed = 0;
+
+        for key in keys {
+            for sql in [
+                "DELETE FROM prefetches WHERE url = ?1",
+                "DELETE FROM blobs WHERE key = ?1",
+            ] {
+                removed += transaction
+                    .execute(sql, params![key])
+                    .map_err(Error::Cache)?;
+            }
+        }
+
+        transaction.commit().map_err(Error::Cache)?;
+
+        Ok(removed)
+    }
+
+    /// # Insert Hash
+    ///
+    /// Store the hash prefetched from a given url, replacing any existing entry
+    pub fn insert(&mut self, url: &str, hash: &str) -> Result<()> {
+        self.insert_many(&[(url.to_owned(), hash.to_owned())])
+    }
+
+    /// # Insert Many Hashes
+    ///
+    /// Store a batch of (url, hash) pairs, replacing any existing entries.
+    ///
+    /// All of the rows are written in a single transaction, so either every entry is stored or
+    /// (if the process is interrupted) none are.
+    pub fn insert_many(&mut self, entries: &[(String, String)]) -> Result<()> {
+        if entries.is_empty() || self.read_only {
+            return Ok(());
+        }
+
+        let fetched_at = unix_time();
+
+        // Take the write lock up front, so that concurrent writers queue up for it rather than
+        // failing when they find another one holds it
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::Cache)?;
+
+        for chunk in entries.chunks(ROWS_PER_STATEMENT) {
+            let placeholders = vec!["(?, ?, ?)"; chunk.len()].join(", ");
+            // Of two processes storing a hash for the same url, the most recent one wins
+            let sql = format!(
+                "INSERT INTO prefetches (url, hash, fetched_at) VALUES {placeholders}
+                    ON CONFLICT (url) DO UPDATE SET
+                        hash = excluded.hash, fetched_at = excluded.fetched_at
+                    WHERE excluded.fetched_at >= prefetches.fetched_at"
+            );
+
+            let params = chunk.iter().flat_map(|(url, hash)| {
+                [
+                    url as &dyn ToSql,
+                    hash as &dyn ToSql,
+                    &fetched_at as &dyn ToSql,
+                ]
+            });
+
+            transaction
+                .execute(&sql, params_from_iter(params))
+                .map_err(Error::Cache)?;
+        }
+
+        transaction.commit().map_err(Error::Cache)
+    }
+
+    /// # Insert Blob
+    ///
+    /// Store some data under a key, compressed with zstd, replacing any existing entry
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::cache::Cache;
+    ///
+    /// let mut cache = Cache::open_in_memory().unwrap();
+    /// let metadata = r#"{ "name": "zod", "version": "3.24.2" }"#.repeat(100);
+    ///
+    /// cache.insert_blob("metadata:zod@3.24.2", metadata.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     cache.get_blob("metadata:zod@3.24.2").unwrap().as_deref(),
+    ///     Some(metadata.as_bytes())
+    /// );
+    /// assert_eq!(cache.get_blob("metadata:zod@4.0.0").unwrap(), None);
+    ///
+    /// let stats = cache.blob_stats().unwrap();
+    ///
+    /// assert_eq!(stats.count, 1);
+    /// assert_eq!(stats.size, metadata.len() as u64);
+    /// assert!(stats.stored < stats.size);
+    /// ```
+    pub fn insert_blob(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        let compressed =
+            zstd::encode_all(data, COMPRESSION_LEVEL).map_err(Error::CacheCompression)?;
+
+        self.connection
+            .execute(
+                "INSERT INTO blobs (key, data, size, stored_at) VALUES (?1, ?2, ?3, ?4)
+                    ON CONFLICT (key) DO UPDATE SET
+                        data = excluded.data, size = excluded.size, stored_at = excluded.stored_at
+                    WHERE excluded.stored_at >= blobs.stored_at",
+                params![key, compressed, data.len() as i64, unix_time()],
+            )
+            .map_err(Error::Cache)?;
+
+        Ok(())
+    }
+
+    /// # Get Blob
+    ///
+    /// Look up and decompress the data previously stored under a key
+    pub fn get_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let compressed: Option<Vec<u8>> = self
+            .connection
+            .query_row(
+                "SELECT data FROM blobs WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(Error::Cache)?;
+
+        match compressed {
+            Some(compressed) => zstd::decode_all(compressed.as_slice())
+                .map(Some)
+                .map_err(Error::CacheCompression),
+            None => Ok(None),
+        }
+    }
+
+    /// # Blob Statistics
+    ///
+    /// How many blobs are stored, and how much space they take up compressed and uncompressed
+    pub fn blob_stats(&self) -> Result<BlobStats> {
+        self.connection
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(LENGTH(data)), 0), COALESCE(SUM(size), 0) FROM blobs",
+                [],
+                |row| {
+                    Ok(BlobStats {
+                        count: row.get::<_, i64>(0)? as usize,
+                        stored: row.get::<_, i64>(1)? as u64,
+                        size: row.get::<_, i64>(2)? as u64,
+                    })
+                },
+            )
+            .map_err(Error::Cache)
+    }
+
+    /// # Record Timing
+    ///
+    /// Store how long each phase of a run took, stamped with the current time
+    pub fn record_timing(&mut self, timing: &Timing) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        self.connection
+            .execute(
+                "INSERT INTO timings
+                    (recorded_at, packages, fetched, cached, parse_ms, prefetch_ms, render_ms)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    unix_time(),
+                    timing.packages as i64,
+                    timing.fetched as i64,
+                    timing.cached as i64,
+                    timing.parse.as_millis() as i64,
+                    timing.prefetch.as_millis() as i64,
+                    timing.render.as_millis() as i64,
+                ],
+            )
+            .map_err(Error::Cache)?;
+
+        Ok(())
+    }
+
+    /// # Timings
+    ///
+    /// The timings of the most recent runs, up to the given number of them, oldest first and
+    /// along with when each was recorded
+    ///
+    /// ## Usage
+    ///```rust
+    /// use std::time::Duration;
+    ///
+    /// use bun2nix::cache::{Cache, Timing};
+    ///
+    /// let mut cache = Cache::open_in_memory().unwrap();
+    ///
+    /// for packages in [10, 20, 30] {
+    ///     let timing = Timing {
+    ///         packages,
+    ///         prefetch: Duration::from_millis(packages as u64),
+    ///         ..Timing::default()
+    ///     };
+    ///
+    ///     cache.record_timing(&timing).unwrap();
+    /// }
+    ///
+    /// let timings = cache.timings(2).unwrap();
+    /// let packages: Vec<_> = timings.iter().map(|(_, timing)| timing.packages).collect();
+    ///
+    /// assert_eq!(packages, [20, 30]);
+    /// assert_eq!(timings[1].1.total(), Duration::from_millis(30));
+    /// ```
+    pub fn timings(&self, limit: usize) -> Result<Vec<(i64, Timing)>> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT recorded_at, packages, fetched, cached, parse_ms, prefetch_ms, render_ms
+                    FROM timings ORDER BY rowid DESC LIMIT ?1",
+            )
+            .map_err(Error::Cache)?;
+
+        let millis = |ms: i64| Duration::from_millis(ms.max(0) as u64);
+
+        let mut timings = statement
+            .query_map(params![limit as i64], |row| {
+                Ok((
+                    row.get(0)?,
+                    Timing {
+                        packages: row.get::<_, i64>(1)? as usize,
+                        fetched: row.get::<_, i64>(2)? as usize,
+                        cached: row.get::<_, i64>(3)? as usize,
+                        parse: millis(row.get(4)?),
+                        prefetch: millis(row.get(5)?),
+                        render: millis(row.get(6)?),
+                    },
+                ))
+            })
+            .map_err(Error::Cache)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(Error::Cache)?;
+
+        timings.reverse();
+
+        Ok(timings)
+    }
+
+    /// # Record Closure Size
+    ///
+    /// Store the closure size of the `node_modules` built for a project, identified by the
+    /// path of its output file, stamped with the current time
+    pub fn record_closure_size(&mut self, project: &str, size: u64) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        self.connection
+            .execute(
+                "INSERT INTO closure_sizes (recorded_at, project, size) VALUES (?1, ?2, ?3)",
+                params![unix_time(), project, size as i64],
+            )
+            .map_err(Error::Cache)?;
+
+        Ok(())
+    }
+
+    /// # Closure Sizes
+    ///
+    /// The closure sizes recorded for a project by its most recent runs, up to the given
+    /// number of them, oldest first and along with when each was recorded
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::cache::Cache;
+    ///
+    /// let mut cache = Cache::open_in_memory().unwrap();
+    ///
+    /// cache.record_closure_size("/src/app/bun.nix", 100).unwrap();
+    /// cache.record_closure_size("/src/other/bun.nix", 5).unwrap();
+    /// cache.record_closure_size("/src/app/bun.nix", 120).unwrap();
+    ///
+    /// let sizes: Vec<_> = cache
+    ///     .closure_sizes("/src/app/bun.nix", 10)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|(_, size)| size)
+    ///     .collect();
+    ///
+    /// assert_eq!(sizes, [100, 120]);
+    /// ```
+    pub fn closure_sizes(&self, project: &str, limit: usize) -> Result<Vec<(i64, u64)>> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT recorded_at, size FROM closure_sizes
+                    WHERE project = ?1 ORDER BY rowid DESC LIMIT ?2",
+            )
+            .map_err(Error::Cache)?;
+
+        let mut sizes = statement
+            .query_map(params![project, limit as i64], |row| {
+                Ok((row.get(0)?, row.get::<_, i64>(1)?.max(0) as u64))
+            })
+            .map_err(Error::Cache)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(Error::Cache)?;
+
+        sizes.reverse();
+
+        Ok(sizes)
+    }
+}
+
+/// # Blob Statistics
+///
+/// How much space the compressed blobs of a cache take up
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlobStats {
+    /// The number of blobs stored
+    pub count: usize,
+
+    /// The number of bytes the blobs take up compressed
+    pub stored: u64,
+
+    /// The number of bytes the blobs would take up uncompressed
+    pub size: u64,
+}
+
+/// # Run Timing
+///
+/// How long each phase of a run of `bun2nix` took, kept in the cache so that the effect of
+/// changes to it or to the configuration can be measured over time
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// The number of packages generated
+    pub packages: usize,
+
+    /// The number of hashes which had to be fetched
+    pub fetched: usize,
+
+    /// The number of hashes found in the cache
+    pub cached: usize,
+
+    /// How long reading and parsing the lockfile took
+    pub parse: Duration,
+
+    /// How long prefetching the hashes took
+    pub prefetch: Duration,
+
+    /// How long rendering and writing the output took
+    pub render: Duration,
+}
+
+impl Timing {
+    /// # Total Duration
+    ///
+    /// How long every recorded phase took together
+    pub fn total(&self) -> Duration {
+        self.parse + self.prefetch + self.render
+    }
+}
+
+/// # Timing Report
+///
+/// A table of the timings of recent runs, ending with how the more recent half of them compares
+/// to the earlier half
+///
+/// ## Usage
+///```rust
+/// use std::time::Duration;
+///
+/// use bun2nix::cache::{Timing, TimingReport};
+///
+/// let run = |prefetch: u64| Timing {
+///     packages: 120,
+///     prefetch: Duration::from_millis(prefetch),
+///     ..Timing::default()
+/// };
+///
+/// let report = TimingReport {
+///     timings: vec![(0, run(4000)), (3600, run(1000))],
+///     now: 7200,
+/// }
+/// .to_string();
+///
+/// assert!(report.contains("2 hours ago"));
+/// assert!(report.ends_with("Recent runs took 75% less time than earlier ones (1.00s against 4.00s)"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingReport {
+    /// The timings to show, oldest first, along with when each was recorded
+    pub timings: Vec<(i64, Timing)>,
+
+    /// The current time in seconds since the unix epoch, which the ages of runs are shown from
+    pub now: i64,
+}
+
+impl TimingReport {
+    /// # New Timing Report
+    ///
+    /// Report the given timings as of the current time
+    pub fn new(timings: Vec<(i64, Timing)>) -> Self {
+        Self {
+            timings,
+            now: unix_time(),
+        }
+    }
+
+    /// The mean total duration of some runs
+    fn mean(timings: &[(i64, Timing)]) -> Duration {
+        let total: Duration = timings.iter().map(|(_, timing)| timing.total()).sum();
+
+        total / timings.len().max(1) as u32
+    }
+}
+
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.timings.is_empty() {
+            return write!(f, "No runs have been recorded");
+        }
+
+        writeln!(
+            f,
+            "{:<16} {:>8} {:>8} {:>8} {:>9} {:>9} {:>9} {:>9}",
+            "Run", "Packages", "Fetched", "Cached", "Parse", "Prefetch", "Render", "Total"
+        )?;
+
+        for (recorded_at, timing) in &self.timings {
+            writeln!(
+                f,
+                "{:<16} {:>8} {:>8} {:>8} {:>9} {:>9} {:>9} {:>9}",
+                format_age(self.now - recorded_at),
+                timing.packages,
+                timing.fetched,
+                timing.cached,
+                format_duration(timing.parse),
+                format_duration(timing.prefetch),
+                format_duration(timing.render),
+                format_duration(timing.total()),
+            )?;
+        }
+
+        if self.timings.len() < 2 {
+            return write!(f, "Record more runs to see a trend");
+        }
+
+        let (earlier, recent) = self.timings.split_at(self.timings.len() / 2);
+        let (earlier, recent) = (Self::mean(earlier), Self::mean(recent));
+
+        let change = match earlier.is_zero() {
+            true => 0.0,
+            false => (recent.as_secs_f64() / earlier.as_secs_f64() - 1.0) * 100.0,
+        };
+
+        let trend = match change.round() {
+            change if change < 0.0 => format!("{:.0}% less time than", -change),
+            change if change > 0.0 => format!("{change:.0}% more time than"),
+            _ => "as much time as".to_owned(),
+        };
+
+        write!(
+            f,
+            "Recent runs took {trend} earlier ones ({} against {})",
+            format_duration(recent),
+            format_duration(earlier)
+        )
+    }
+}
+
+/// Format a duration to a precision suited to its size
+fn format_duration(duration: Duration) -> String {
+    match duration.as_millis() {
+        ms if ms < 1000 => format!("{ms}ms"),
+        _ => format!("{:.2}s", duration.as_secs_f64()),
+    }
+}
+
+/// Format an age in seconds as how long ago it was, in its largest whole unit
+fn format_age(seconds: i64) -> String {
+    let (count, unit) = match seconds.max(0) {
+        s if s < 60 => return "just now".to_owned(),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 60 * 60 * 24 => (s / (60 * 60), "hour"),
+        s => (s / (60 * 60 * 24), "day"),
+    };
+
+    match count {
+        1 => format!("1 {unit} ago"),
+        count => format!("{count} {unit}s ago"),
+    }
+}
+
+/// Give the group of a file or directory of a shared cache the given permissions, regardless of
+/// the umask it was created with. Failures are ignored, as they only happen when another user
+/// created it at the same time, and so is the one to share it.
+#[cfg(unix)]
+fn share(path: &Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _ = fs::set_permissions(path, fs::Permissions::from_mode(mode));
+}
+
+#[cfg(not(unix))]
+fn share(_path: &Path, _mode: u32) {}
+
+/// Whether any user of the machine may write to a file of a shared cache
+#[cfg(unix)]
+fn writable_by_everyone(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o002 != 0)
+}
+
+#[cfg(not(unix))]
+fn writable_by_everyone(_path: &Path) -> bool {
+    false
+}
+
+fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
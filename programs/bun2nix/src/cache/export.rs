@@ -0,0 +1,152 @@
+//! This module holds the export of every package known to the prefetch cache as a Nix
+//! expression, so that they can all be realised into the store (and pushed to a binary cache)
+//! with a single build
+
+use std::collections::{BTreeMap, HashMap};
+
+use askama::Template;
+use serde_json::Value;
+
+use super::{Cache, FETCHURL_PREFIX, LFS_PREFIX, METADATA_PREFIX};
+use crate::{
+    error::Result,
+    package::{Fetcher, digest_to_sri},
+};
+
+/// # Cache Export
+///
+/// A `linkFarm` of every package the cache knows the hash of, whether prefetched or found in
+/// cached registry metadata, fetched with the same fetchers as a generated `bun.nix` so that
+/// building it realises the same store paths.
+///
+/// Entries which are not well formed are skipped, as they would only fail the build.
+///
+/// ## Usage
+///```rust
+/// use askama::Template;
+/// use bun2nix::cache::{Cache, CacheExport};
+///
+/// let mut cache = Cache::open_in_memory().unwrap();
+/// let hash = "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+///
+/// cache.insert("github:colinhacks/zod?ref=8552233", hash).unwrap();
+/// cache.insert("git+https://gitlab.com/acme/lib.git?rev=ee100d8", hash).unwrap();
+/// cache.insert("lfs:git+https://gitlab.com/acme/lib.git?rev=ee100d8", "true").unwrap();
+/// cache.insert("github:colinhacks/zod?ref=0000000", "<!DOCTYPE html>").unwrap();
+///
+/// let export = CacheExport::from_cache(&cache).unwrap();
+///
+/// assert_eq!(export.len(), 2);
+/// assert_eq!(export.skipped, 1);
+///
+/// let nix = export.render().unwrap();
+///
+/// assert!(nix.contains(r#""github-colinhacks-zod-ref-8552233" = fetchFromGitHub {"#));
+/// assert!(nix.contains("fetchLFS = true;"));
+/// ```
+#[derive(Debug, Default, Template)]
+#[template(path = "cache-export.nix_template")]
+pub struct CacheExport {
+    fetchers: BTreeMap<String, Fetcher>,
+
+    /// The number of entries which were skipped as they are not well formed
+    pub skipped: usize,
+}
+
+impl CacheExport {
+    /// # Export Cache
+    ///
+    /// Collect every package whose hash is known to a cache
+    pub fn from_cache(cache: &Cache) -> Result<Self> {
+        let entries: HashMap<String, String> = cache.entries()?.into_iter().collect();
+        let mut export = Self::default();
+
+        for (key, hash) in &entries {
+            if key.starts_with(LFS_PREFIX) {
+                continue;
+            }
+
+            let fetcher = match key.strip_prefix(FETCHURL_PREFIX) {
+                Some(url) => Some(Fetcher::FetchUrl {
+                    url: url.to_owned(),
+                    hash: String::new(),
+                }),
+                None => Fetcher::from_prefetch_url(key),
+            };
+
+            let Some(mut fetcher) = fetcher else {
+                continue;
+            };
+
+            let fetch_lfs = entries.get(&format!("{LFS_PREFIX}{key}"));
+            fetcher.set_fetch_lfs(fetch_lfs.is_some_and(|fetch_lfs| fetch_lfs == "true"));
+
+            export.add(key, fetcher, hash, "sha256");
+        }
+
+        for key in cache.blob_keys(METADATA_PREFIX)? {
+            let metadata = cache
+                .get_blob(&key)
+                .ok()
+                .flatten()
+                .and_then(|metadata| serde_json::from_slice::<Value>(&metadata).ok());
+
+            let dist = metadata.as_ref().map(|metadata| &metadata["dist"]);
+            let tarball = dist.and_then(|dist| dist["tarball"].as_str());
+            let integrity = dist.and_then(|dist| dist["integrity"].as_str());
+
+            let (Some(url), Some(integrity)) = (tarball, integrity) else {
+                export.skipped += 1;
+                continue;
+            };
+
+            let fetcher = Fetcher::FetchUrl {
+                url: url.to_owned(),
+                hash: String::new(),
+            };
+            let algorithm = integrity.split('-').next().unwrap_or_default();
+
+            export.add(&format!("npm:{url}"), fetcher, integrity, algorithm);
+        }
+
+        Ok(export)
+    }
+
+    /// # Package Count
+    ///
+    /// The number of packages exported
+    pub fn len(&self) -> usize {
+        self.fetchers.len()
+    }
+
+    /// # Is Empty
+    ///
+    /// Whether no packages were exported
+    pub fn is_empty(&self) -> bool {
+        self.fetchers.is_empty()
+    }
+
+    /// Add a fetcher under a name made from the key it was found under, if its hash is well
+    /// formed
+    fn add(&mut self, key: &str, mut fetcher: Fetcher, hash: &str, algorithm: &str) {
+        if digest_to_sri(algorithm, hash).as_deref() != Some(hash) {
+            self.skipped += 1;
+            return;
+        }
+
+        fetcher.set_hash(hash.to_owned());
+        self.fetchers.insert(link_name(key), fetcher);
+    }
+}
+
+/// The name a package is linked under, made from the key it was found under with anything but
+/// the characters allowed in store paths replaced
+fn link_name(key: &str) -> String {
+    let name = key
+        .split(|c: char| !c.is_ascii_alphanumeric() && !"+._".contains(c))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    name.trim_start_matches('.').to_owned()
+}
@@ -0,0 +1,364 @@
+//! This module holds the checking of the hashes and blobs stored in the prefetch cache, so that
+//! a corrupt or poisoned entry (such as one in a cache shared between machines) is found and
+//! evicted rather than being silently written into every `bun.nix` generated from it
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    sync::Mutex,
+};
+
+use rayon::{ThreadPoolBuilder, prelude::*};
+use serde_json::Value;
+
+use super::{Cache, FETCHURL_PREFIX, LFS_PREFIX, METADATA_PREFIX, TARBALL_PREFIX};
+use crate::{
+    Package,
+    error::{Error, Result},
+    interrupt,
+    package::{Fetcher, Nix, Prefetch, digest_to_sri, hash_sri},
+};
+
+/// # Cache Problem
+///
+/// What is wrong with a corrupt entry of the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheProblem {
+    /// The stored value is not a hash, or a blob could not be read
+    Malformed(String),
+
+    /// The stored hash differs from the one found by checking it
+    Mismatch {
+        /// The hash stored in the cache
+        stored: String,
+
+        /// The hash found by checking it
+        found: String,
+
+        /// Where the hash it was checked against was found
+        source: &'static str,
+    },
+}
+
+/// # Cache Integrity Report
+///
+/// The outcome of checking the entries of a cache
+///
+/// ## Usage
+///```rust
+/// use bun2nix::cache::{Cache, CacheProblem, IntegrityReport};
+///
+/// let mut cache = Cache::open_in_memory().unwrap();
+/// let tarball = b"not really a tarball";
+///
+/// cache.insert("github:colinhacks/zod?ref=8552233", "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=").unwrap();
+/// cache.insert("github:colinhacks/zod?ref=0000000", "<!DOCTYPE html>").unwrap();
+///
+/// // A tarball kept in the cache which no longer hashes to what was stored for it
+/// cache.insert("fetchurl:https://example.com/a.tgz", "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=").unwrap();
+/// cache.insert_blob("tarball:https://example.com/a.tgz", tarball).unwrap();
+///
+/// let report = IntegrityReport::check(&cache, &[]).unwrap();
+///
+/// assert_eq!(report.checked, 4);
+/// assert_eq!(
+///     report.corrupt["github:colinhacks/zod?ref=0000000"],
+///     CacheProblem::Malformed("`<!DOCTYPE html>` is not a sha256 hash".to_owned())
+/// );
+/// assert!(matches!(
+///     report.corrupt["fetchurl:https://example.com/a.tgz"],
+///     CacheProblem::Mismatch { source: "by hashing the cached tarball", .. }
+/// ));
+///
+/// // The tarball goes along with the hash stored for it
+/// assert_eq!(report.evict(&mut cache).unwrap(), 3);
+/// assert_eq!(cache.len().unwrap(), 1);
+/// assert!(IntegrityReport::check(&cache, &[]).unwrap().is_clean());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// The number of hashes and blobs checked
+    pub checked: usize,
+
+    /// The number of hashes which were checked by fetching them again
+    pub refetched: usize,
+
+    /// The problem with each corrupt entry, keyed by what it is stored under
+    pub corrupt: BTreeMap<String, CacheProblem>,
+
+    /// The hashes which could not be fetched again to check them, and why
+    pub failed: BTreeMap<String, String>,
+}
+
+impl IntegrityReport {
+    /// # Check Cache
+    ///
+    /// Check that every hash in a cache is well formed, that the tarballs kept in it still hash
+    /// to what was stored for them, and that the registry metadata kept in it agrees with the
+    /// integrity hashes of the given lockfile packages
+    pub fn check(cache: &Cache, packages: &[Package]) -> Result<Self> {
+        let integrity: HashMap<&str, &str> = packages
+            .iter()
+            .filter_map(|pkg| match &pkg.fetcher {
+                Fetcher::FetchUrl { url, hash } if !hash.is_empty() => {
+                    Some((url.as_str(), hash.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut report = Self::default();
+        let entries: HashMap<String, String> = cache.entries()?.into_iter().collect();
+
+        for (key, value) in &entries {
+            report.checked += 1;
+
+            let well_formed = match key.starts_with(LFS_PREFIX) {
+                true => value == "true" || value == "false",
+                false => digest_to_sri("sha256", value).as_deref() == Some(value.as_str()),
+            };
+
+            if !well_formed {
+                let problem = match key.starts_with(LFS_PREFIX) {
+                    true => format!("`{value}` is not `true` or `false`"),
+                    false => format!("`{value}` is not a sha256 hash"),
+                };
+
+                report.corrupt(key, CacheProblem::Malformed(problem));
+            }
+        }
+
+        for key in cache.blob_keys(TARBALL_PREFIX)? {
+            report.checked += 1;
+
+            let url = &key[TARBALL_PREFIX.len()..];
+            let Some(tarball) = report.read_blob(cache, &key) else {
+                continue;
+            };
+
+            let hash_key = format!("{FETCHURL_PREFIX}{url}");
+
+            if let Some(stored) = entries.get(&hash_key) {
+                let found = hash_sri("sha256", &mut tarball.as_slice())
+                    .map_err(Error::FetchingFailed)?
+                    .unwrap_or_default();
+
+                report.compare(&hash_key, stored, found, "by hashing the cached tarball");
+            }
+
+            if let Some(&expected) = integrity.get(url) {
+                let algorithm = expected.split('-').next().unwrap_or_default();
+
+                if let Some(found) =
+                    hash_sri(algorithm, &mut tarball.as_slice()).map_err(Error::FetchingFailed)?
+                {
+                    report.compare(&key, &found, expected.to_owned(), "in the lockfile");
+                }
+            }
+        }
+
+        for key in cache.blob_keys(METADATA_PREFIX)? {
+            report.checked += 1;
+
+            let Some(metadata) = report.read_blob(cache, &key) else {
+                continue;
+            };
+
+            let Ok(metadata) = serde_json::from_slice::<Value>(&metadata) else {
+                report.corrupt(&key, CacheProblem::Malformed("invalid JSON".to_owned()));
+                continue;
+            };
+
+            let dist = &metadata["dist"];
+            let (Some(tarball), Some(stored)) =
+                (dist["tarball"].as_str(), dist["integrity"].as_str())
+            else {
+                continue;
+            };
+
+            if let Some(&expected) = integrity.get(tarball) {
+                report.compare(&key, stored, expected.to_owned(), "in the lockfile");
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// # Fetch a Sample Again
+    ///
+    /// Check up to the given number of the hashes prefetched longest ago by fetching them
+    /// again. Hashes which are confirmed are stored again, so that the next sample moves on to
+    /// the hashes after them.
+    pub fn refetch_sample(
+        &mut self,
+        cache: &mut Cache,
+        sample: usize,
+        nix: &Nix,
+        jobs: usize,
+    ) -> Result<()> {
+        let sample: Vec<(String, String)> = cache
+            .entries()?
+            .into_iter()
+            .filter(|(key, _)| !self.corrupt.contains_key(key) && refetchable(key))
+            .take(sample)
+            .collect();
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .map_err(Error::ThreadPool)?;
+
+        let results = Mutex::new(Vec::new());
+
+        pool.install(|| {
+            sample.par_iter().for_each(|(key, stored)| {
+                if interrupt::check().is_err() {
+                    return;
+                }
+
+                let found = refetch(key, nix);
+                results.lock().unwrap().push((key, stored, found));
+            })
+        });
+
+        interrupt::check()?;
+
+        let mut confirmed = Vec::new();
+
+        for (key, stored, found) in results.into_inner().unwrap() {
+            self.refetched += 1;
+
+            match found {
+                Ok(found) if &found == stored => confirmed.push((key.clone(), found)),
+                Ok(found) => self.compare(key, stored, found, "by fetching it again"),
+                Err(err) => {
+                    self.failed.insert(key.clone(), err.to_string());
+                }
+            }
+        }
+
+        cache.insert_many(&confirmed)
+    }
+
+    /// # Evict Corrupt Entries
+    ///
+    /// Remove every corrupt entry from a cache, along with the entries stored alongside it,
+    /// returning how many were removed
+    pub fn evict(&self, cache: &mut Cache) -> Result<usize> {
+        let keys: Vec<String> = self
+            .corrupt
+            .keys()
+            .flat_map(|key| {
+                let companion = match key.split_once(':') {
+                    Some(("fetchurl", url)) => Some(format!("{TARBALL_PREFIX}{url}")),
+                    Some(("tarball", url)) => Some(format!("{FETCHURL_PREFIX}{url}")),
+                    _ => None,
+                };
+
+                [
+                    Some(key.clone()),
+                    Some(format!("{LFS_PREFIX}{key}")),
+                    companion,
+                ]
+            })
+            .flatten()
+            .collect();
+
+        cache.remove(&keys)
+    }
+
+    /// # Is Clean
+    ///
+    /// Whether no corrupt entries were found
+    pub fn is_clean(&self) -> bool {
+        self.corrupt.is_empty()
+    }
+
+    fn corrupt(&mut self, key: &str, problem: CacheProblem) {
+        self.corrupt.insert(key.to_owned(), problem);
+    }
+
+    /// Compare a stored hash to one found by checking it, recording a mismatch
+    fn compare(&mut self, key: &str, stored: &str, found: String, source: &'static str) {
+        if stored != found {
+            self.corrupt(
+                key,
+                CacheProblem::Mismatch {
+                    stored: stored.to_owned(),
+                    found,
+                    source,
+                },
+            );
+        }
+    }
+
+    /// Read a blob, recording it as corrupt if it cannot be decompressed
+    fn read_blob(&mut self, cache: &Cache, key: &str) -> Option<Vec<u8>> {
+        match cache.get_blob(key) {
+            Ok(blob) => blob,
+            Err(err) => {
+                self.corrupt(key, CacheProblem::Malformed(err.to_string()));
+                None
+            }
+        }
+    }
+}
+
+impl Display for CacheProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(problem) => write!(f, "{problem}"),
+            Self::Mismatch {
+                stored,
+                found,
+                source,
+            } => write!(f, "stored `{stored}`, but found `{found}` {source}"),
+        }
+    }
+}
+
+impl Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Checked {} cached hash(es) and blob(s)", self.checked)?;
+
+        if self.refetched > 0 {
+            write!(f, ", fetching {} of them again", self.refetched)?;
+        }
+
+        for (key, err) in &self.failed {
+            write!(f, "\n  could not fetch `{key}` again: {err}")?;
+        }
+
+        match self.corrupt.len() {
+            0 => write!(f, "\nNo corrupt entries were found"),
+            count => {
+                write!(f, "\n{count} corrupt entr(ies):")?;
+
+                for (key, problem) in &self.corrupt {
+                    write!(f, "\n  `{key}`: {problem}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whether the hash stored under a key can be checked by fetching it again
+fn refetchable(key: &str) -> bool {
+    !key.starts_with(LFS_PREFIX)
+        && (key.starts_with(FETCHURL_PREFIX) || Fetcher::from_prefetch_url(key).is_some())
+}
+
+/// Fetch the hash stored under a key again
+fn refetch(key: &str, nix: &Nix) -> Result<String> {
+    let prefetch = match key.strip_prefix(FETCHURL_PREFIX) {
+        Some(url) => Prefetch::prefetch_file(url, nix)?,
+        None => Prefetch::prefetch_fetcher(
+            &Fetcher::from_prefetch_url(key).ok_or(Error::NothingToPrefetch)?,
+            nix,
+        )?,
+    };
+
+    Ok(prefetch.hash)
+}
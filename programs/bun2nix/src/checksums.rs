@@ -0,0 +1,70 @@
+//! This module holds rendering of a checksums manifest for the tarballs in a package set, so
+//! that the provenance of the fetched artifacts can be tracked outside of nix
+
+use std::fmt::Write;
+
+use crate::{
+    Package,
+    error::{Error, Result},
+    package::{Fetcher, sri_to_hex},
+};
+
+/// # Render Checksums Manifest
+///
+/// Render a `SHA256SUMS` style manifest listing the digest of every tarball fetched with
+/// `fetchurl`, sorted by path so that the output is stable and can be signed.
+///
+/// Each line uses the tagged format output by `sha256sum --tag`, as npm tarballs are typically
+/// hashed with sha512 rather than sha256, and the path is that of the tarball on its registry.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{Package, checksums::render_checksums, package::Fetcher};
+///
+/// let pkg = Package::new(
+///     "is-odd@3.0.1".to_owned(),
+///     Fetcher::FetchUrl {
+///         url: "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz".to_owned(),
+///         hash: "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=".to_owned(),
+///     },
+/// );
+///
+/// assert_eq!(
+///     render_checksums(&[pkg]).unwrap(),
+///     "SHA256 (is-odd/-/is-odd-3.0.1.tgz) = e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\n"
+/// );
+/// ```
+pub fn render_checksums(packages: &[Package]) -> Result<String> {
+    let mut entries = packages
+        .iter()
+        .filter_map(|pkg| match &pkg.fetcher {
+            Fetcher::FetchUrl { url, hash } => Some((url, hash)),
+            _ => None,
+        })
+        .map(|(url, hash)| {
+            let (algorithm, digest) =
+                sri_to_hex(hash).ok_or_else(|| Error::InvalidSriHash(hash.to_owned()))?;
+
+            Ok((tarball_path(url), algorithm.to_uppercase(), digest))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    entries.sort();
+    entries.dedup();
+
+    Ok(entries
+        .into_iter()
+        .fold(String::new(), |mut manifest, (path, algorithm, digest)| {
+            let _ = writeln!(manifest, "{algorithm} ({path}) = {digest}");
+            manifest
+        }))
+}
+
+/// The path of a tarball relative to the host serving it
+fn tarball_path(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+
+    without_scheme
+        .split_once('/')
+        .map_or(without_scheme, |(_, path)| path)
+}
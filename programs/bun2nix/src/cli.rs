@@ -0,0 +1,1043 @@
+//! The command line interface of `bun2nix`, declaring every subcommand and its options
+
+use std::path::PathBuf;
+
+use bun2nix::{binaries::BinStrategy, package::DEFAULT_NIX_BIN, prefetcher::DEFAULT_JOBS};
+use clap::{
+    ArgAction, ArgGroup, Args, CommandFactory, FromArgMatches, Subcommand, error::ErrorKind,
+    parser::ValueSource,
+};
+
+/// Convert Bun (v1.2+) packages to Nix expressions
+#[derive(Debug, clap::Parser)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Running without a subcommand is the same as `bun2nix generate`
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+}
+
+impl Cli {
+    /// # Parse Arguments
+    ///
+    /// Parse the command line, rejecting options for generating a Nix expression which are
+    /// given alongside a subcommand, as they would otherwise be silently ignored
+    pub fn parse_args() -> Self {
+        let mut command = Self::command();
+        let matches = command.get_matches_mut();
+
+        let cli = Self::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+        if let Some(subcommand) = matches.subcommand_name() {
+            let generate = GenerateArgs::augment_args(clap::Command::new("generate"));
+
+            // Options the subcommand declares itself, such as `cache --cache-file`, share their
+            // ids with those for generating, and are not misplaced
+            let own: Vec<_> = command
+                .find_subcommand(subcommand)
+                .map(|subcommand| {
+                    subcommand
+                        .get_arguments()
+                        .map(|arg| arg.get_id().clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let misplaced = generate.get_arguments().find(|arg| {
+                !own.contains(arg.get_id())
+                    && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            });
+
+            if let Some(arg) = misplaced.and_then(|arg| arg.get_long()) {
+                command
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        format!("the subcommand '{subcommand}' cannot be used with '--{arg}'"),
+                    )
+                    .exit();
+            }
+        }
+
+        cli
+    }
+}
+
+/// Options shared by every subcommand
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Print more detailed logs - may be given twice for debug logs.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only print errors.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Write newline delimited JSON events to stderr instead of logs,
+    /// for wrappers showing the progress of the run.
+    #[arg(long, global = true)]
+    pub events: bool,
+
+    /// The registry to resolve dependencies against when using
+    /// `--package-json`, and to look up package metadata from.
+    #[cfg(feature = "resolver")]
+    #[arg(long, global = true, default_value = bun2nix::registry::DEFAULT_REGISTRY)]
+    pub registry: String,
+
+    /// Resolve specifiers with a protocol `bun2nix` does not know of
+    /// with a WASM plugin claiming it, such as an organization's own
+    /// artifact store - may be given more than once.
+    #[cfg(feature = "plugins")]
+    #[arg(long = "plugin", global = true, value_name = "FILE")]
+    pub plugins: Vec<PathBuf>,
+}
+
+/// Subcommands of the `bun2nix` command line tool
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Generate a Nix expression from a lockfile.
+    Generate(Box<GenerateArgs>),
+
+    /// Check that a generated `bun.nix` file is up to date with the
+    /// lockfile, without fetching anything.
+    Check {
+        /// The Bun (v1.2+) lockfile the file should be generated from
+        #[arg(short, long, default_value = "./bun.lock")]
+        lock_file: PathBuf,
+
+        /// The `bun.nix` file to check
+        #[arg(default_value = "bun.nix")]
+        nix_file: PathBuf,
+    },
+
+    /// Compare the `node_modules` installed by `bun install` against a
+    /// generated `bun.nix`, reporting packages which are missing,
+    /// installed at another version or not in `bun.nix` at all.
+    Status {
+        /// The Bun (v1.2+) lockfile, used to find the names of packages
+        /// not from the registry and to leave out packages for other
+        /// platforms
+        #[arg(short, long, default_value = "./bun.lock")]
+        lock_file: PathBuf,
+
+        /// The `node_modules` directory to compare
+        #[arg(long, default_value = "./node_modules")]
+        node_modules: PathBuf,
+
+        /// The `bun.nix` file to compare against
+        #[arg(default_value = "bun.nix")]
+        nix_file: PathBuf,
+    },
+
+    /// Explain why a package is installed, printing the shortest chain
+    /// of dependencies from a workspace to each copy of it.
+    Why {
+        /// The name of the package, such as `is-number` or `@types/node`
+        name: String,
+
+        /// The Bun (v1.2+) lockfile to read dependencies from
+        #[arg(short, long, default_value = "./bun.lock")]
+        lock_file: PathBuf,
+    },
+
+    /// Print the tree of packages a workspace installs, as bun resolves
+    /// them.
+    Tree {
+        /// The path of the workspace, relative to the root of the
+        /// project (`.` for the root itself)
+        #[arg(default_value = ".")]
+        workspace: String,
+
+        /// The Bun (v1.2+) lockfile to read dependencies from
+        #[arg(short, long, default_value = "./bun.lock")]
+        lock_file: PathBuf,
+
+        /// Only print the tree this many dependencies deep
+        #[arg(short, long)]
+        depth: Option<usize>,
+    },
+
+    /// Set up a project to use `bun2nix`, writing `bun.nix` from the
+    /// lockfile and regenerating it after every `bun install`.
+    Init {
+        /// The root of the project, containing `package.json` and `bun.lock`
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+
+        /// Also install the `bun.nix` git merge driver
+        #[arg(long)]
+        merge_driver: bool,
+
+        /// Also write a `devshell.nix` providing bun and a Nix-built
+        /// `node_modules`, linked into the project on entry
+        #[arg(long)]
+        devshell: bool,
+
+        /// Options for the development shell
+        #[command(flatten)]
+        shell: DevShellArgs,
+
+        /// Also write an `.envrc` which loads the development shell with
+        /// direnv and regenerates `bun.nix` when `bun.lock` changes -
+        /// implies `--devshell`
+        #[arg(long)]
+        direnv: bool,
+
+        /// Also write a `flake.nix` which builds the project from
+        /// `bun.nix` and provides a development shell, taken from
+        /// `devshell.nix` if one is written too
+        #[arg(long)]
+        flake: bool,
+
+        /// Options for prefetching packages with unknown hashes
+        #[command(flatten)]
+        prefetch: PrefetchArgs,
+    },
+
+    /// Add `bun2nix` to the `postinstall` (or `prepare`) script in
+    /// `package.json`, so the generated file is regenerated after every
+    /// `bun install` - unless a script already runs it.
+    Setup {
+        /// The root of the project, containing `package.json`
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+
+        /// The lifecycle script to run `bun2nix` from
+        #[arg(long, value_enum, default_value_t = LifecycleScript::Postinstall)]
+        script: LifecycleScript,
+
+        /// The file `bun2nix` should write, relative to the project
+        #[arg(short, long, default_value = "bun.nix")]
+        output_file: PathBuf,
+    },
+
+    /// Look up every npm package in the lockfile in the registry,
+    /// reporting deprecated packages and the estimated unpacked size,
+    /// along with the packages trusted to run lifecycle scripts.
+    #[cfg(feature = "resolver")]
+    Audit {
+        /// The Bun (v1.2+) lockfile to audit
+        #[arg(short, long, default_value = "./bun.lock")]
+        lock_file: PathBuf,
+
+        /// Fail if the estimated unpacked size of all packages exceeds
+        /// this budget (e.g. `500M` or `2GiB`)
+        #[arg(long, value_parser = bun2nix::summary::parse_size)]
+        max_total_size: Option<u64>,
+
+        /// Do not fail if any packages are deprecated
+        #[arg(long)]
+        allow_deprecated: bool,
+
+        /// Fail instead of warning if any packages have install scripts
+        /// which will not run because they are not trusted
+        #[arg(long)]
+        strict_scripts: bool,
+
+        /// List the largest packages by unpacked size, with their share
+        /// of the total (20 unless a count is given)
+        #[arg(long, value_name = "COUNT", num_args = 0..=1, default_missing_value = "20")]
+        size_report: Option<usize>,
+
+        /// The maximum number of packages to look up at once
+        #[arg(short, long, default_value_t = DEFAULT_JOBS)]
+        jobs: usize,
+    },
+
+    /// Print the entry a single package from the registry would have
+    /// in a generated `bun.nix`, with the tools in its `bin`.
+    #[cfg(feature = "resolver")]
+    Pkg {
+        /// The package to print the entry of, as `name@range` (e.g.
+        /// `react@18.3.1`), where the range defaults to `latest`
+        spec: bun2nix::resolver::PackageSpec,
+
+        /// The format to print the entry in
+        #[arg(long, value_enum, default_value_t = EntryFormat::Nix)]
+        format: EntryFormat,
+    },
+
+    /// Package a single package from the registry, along with its
+    /// dependencies, as a Nix package running its command line tools
+    /// with bun - without a project or lockfile.
+    #[cfg(feature = "resolver")]
+    Tool {
+        /// The package to package, as `name@range` (e.g. `cowsay@^1.6`),
+        /// where the range defaults to `latest`
+        spec: bun2nix::resolver::PackageSpec,
+
+        /// The output file to write to -
+        /// if no file location is provided, print to stdout instead.
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+    },
+
+    /// Inspect, verify or clear the cache of previously prefetched hashes.
+    #[cfg(feature = "cache")]
+    Cache {
+        /// What to do with the cache
+        #[command(subcommand)]
+        command: CacheCommand,
+
+        /// The location of the prefetch cache database -
+        /// defaults to `$XDG_CACHE_HOME/bun2nix/cache.sqlite`.
+        #[arg(long, global = true)]
+        cache_file: Option<PathBuf>,
+
+        /// Use the cache shared by the users of the machine, at
+        /// `/var/cache/bun2nix/cache.sqlite` unless `--cache-file` is
+        /// given.
+        #[arg(long, global = true)]
+        shared: bool,
+    },
+
+    /// Compare two generated `bun.nix` files package by package,
+    /// reporting additions, removals and hash changes.
+    DiffNix {
+        /// The old `bun.nix` file
+        old: PathBuf,
+
+        /// The new `bun.nix` file
+        new: PathBuf,
+    },
+
+    /// Compare two Bun lockfiles package by package, reporting
+    /// additions, removals and upgrades - as markdown for posting on a
+    /// pull request, if requested.
+    DiffLock {
+        /// The old lockfile, such as the one on the base branch
+        old: PathBuf,
+
+        /// The new lockfile
+        #[arg(default_value = "./bun.lock")]
+        new: PathBuf,
+
+        /// The format to print the differences in
+        #[arg(long, value_enum, default_value_t = DiffFormat::Text)]
+        format: DiffFormat,
+
+        /// Look up the unpacked size of each added, removed or upgraded
+        /// package in the registry, to report them in the markdown
+        #[cfg(feature = "resolver")]
+        #[arg(long)]
+        sizes: bool,
+
+        /// The maximum number of packages to look up at once
+        #[cfg(feature = "resolver")]
+        #[arg(short, long, default_value_t = DEFAULT_JOBS)]
+        jobs: usize,
+    },
+
+    /// Print the JSON Schema of one of the JSON formats `bun2nix`
+    /// writes, for editors and for validating the code consuming them.
+    #[cfg(feature = "schema")]
+    Schema {
+        /// The format to print the schema of
+        #[arg(value_enum)]
+        kind: SchemaKind,
+    },
+
+    /// Work with custom templates of the output.
+    #[cfg(feature = "template")]
+    Template {
+        /// What to do with the template
+        #[command(subcommand)]
+        command: TemplateCommand,
+    },
+
+    /// Git merge driver for generated `bun.nix` files, which merges
+    /// the package sets on each side and renders the result.
+    ///
+    /// Not intended to be called directly, see `install-merge-driver`.
+    MergeDriver {
+        /// The common ancestor's version of the file (%O)
+        ancestor: PathBuf,
+
+        /// The current version of the file, which the result is written to (%A)
+        current: PathBuf,
+
+        /// The other branch's version of the file (%B)
+        other: PathBuf,
+
+        /// The path of the file being merged (%P)
+        path: Option<PathBuf>,
+    },
+
+    /// Register the `bun.nix` merge driver in the current repository's
+    /// git config and `.gitattributes`.
+    InstallMergeDriver {
+        /// The root of the git repository to install the driver into
+        #[arg(long, default_value = ".")]
+        repo: PathBuf,
+
+        /// The `.gitattributes` pattern matching generated files
+        #[arg(long, default_value = "bun.nix")]
+        pattern: String,
+    },
+
+    /// Convert the `node-packages.nix` file generated by `node2nix` into
+    /// an equivalent `bun.nix`, reusing the hashes it already contains.
+    ImportNode2nix {
+        /// The `node-packages.nix` file to import
+        #[arg(default_value = "node-packages.nix")]
+        node_packages: PathBuf,
+
+        /// The output file to write to -
+        /// if no file location is provided, print to stdout instead.
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+    },
+
+    /// Check the hashes in a generated `bun.nix` file against the network
+    /// (or the prefetch cache), such as those emitted with `--trust-lockfile`.
+    #[cfg(feature = "resolver")]
+    Verify {
+        /// The `bun.nix` file to verify
+        #[arg(default_value = "bun.nix")]
+        nix_file: PathBuf,
+
+        /// Also report packages in this lockfile which are missing from
+        /// the file, and packages in the file which are not in it.
+        #[arg(short, long)]
+        lock_file: Option<PathBuf>,
+
+        /// Options for prefetching packages to compare against
+        #[command(flatten)]
+        prefetch: PrefetchArgs,
+    },
+
+    /// Check the tools and configuration `bun2nix` relies on, such as
+    /// nix, bun, the prefetch cache and the registry, suggesting fixes
+    /// for any problems found.
+    Doctor {
+        /// The nix binary to check
+        #[arg(long, value_name = "PATH", default_value = DEFAULT_NIX_BIN)]
+        nix_bin: PathBuf,
+
+        /// The location of the prefetch cache database to check -
+        /// defaults to `$XDG_CACHE_HOME/bun2nix/cache.sqlite`.
+        #[cfg(feature = "cache")]
+        #[arg(long)]
+        cache_file: Option<PathBuf>,
+    },
+
+    /// Tools for developing `bun2nix` itself.
+    #[cfg(feature = "dev")]
+    Dev {
+        #[command(subcommand)]
+        command: DevCommand,
+    },
+}
+
+/// Tools for developing `bun2nix` itself
+#[cfg(feature = "dev")]
+#[derive(Debug, Subcommand)]
+pub enum DevCommand {
+    /// Regenerate the `bun.nix` of every example project from its
+    /// `bun.lock`, reusing the hashes already in each file.
+    RegenExamples {
+        /// The directory containing the example projects
+        #[arg(default_value = "templates")]
+        dir: PathBuf,
+
+        /// The command to format each regenerated file with, which is
+        /// given the path of the file to format in place
+        #[arg(long, default_value = "treefmt", conflicts_with = "no_format")]
+        formatter: String,
+
+        /// Leave the regenerated files unformatted
+        #[arg(long)]
+        no_format: bool,
+
+        /// Options for prefetching packages which are not in an
+        /// existing `bun.nix`
+        #[command(flatten)]
+        prefetch: PrefetchArgs,
+    },
+}
+
+/// Options for generating a Nix expression from a lockfile
+#[derive(Debug, Args)]
+#[command(group = ArgGroup::new("deployment")
+    .args(["oci_image_file", "nixos_module_file", "home_manager_module_file"])
+    .multiple(true))]
+pub struct GenerateArgs {
+    /// The Bun (v1.2+) lockfile to use to produce the Nix expression.
+    #[arg(short, long, default_value = "./bun.lock")]
+    pub lock_file: PathBuf,
+
+    /// Read the lockfile from a git revision rather than the working
+    /// tree, as `REV:PATH` (e.g. `HEAD~3:bun.lock`) - the
+    /// `--lock-file` path is read at the revision if no path is given.
+    #[arg(long, value_name = "REV:PATH", conflicts_with_all = ["update_script", "frozen", "per_workspace", "build_check", "closure_size", "max_closure_size"])]
+    pub lock_file_rev: Option<String>,
+
+    /// Read the lockfile from inside a tarball or zip archive, such as
+    /// a release artifact or the output of `npm pack`, rather than
+    /// from a checkout.
+    #[cfg(feature = "archive")]
+    #[arg(long, value_name = "ARCHIVE", conflicts_with_all = ["lock_file_rev", "update_script", "frozen", "per_workspace", "build_check", "closure_size", "max_closure_size"])]
+    #[cfg_attr(feature = "resolver", arg(conflicts_with = "package_json"))]
+    pub lock_file_archive: Option<PathBuf>,
+
+    /// The output file to write to -
+    /// if no file location is provided, print to stdout instead.
+    #[arg(short, long)]
+    pub output_file: Option<PathBuf>,
+
+    /// Also write a `SHA256SUMS` style manifest of every tarball
+    /// fetched by the Nix expression to this file.
+    #[arg(long)]
+    pub checksums_file: Option<PathBuf>,
+
+    /// Also write an npm `package-lock.json` with the same hashes to
+    /// this file, for use with `buildNpmPackage` and `importNpmLock`.
+    #[arg(long)]
+    pub npm_lock_file: Option<PathBuf>,
+
+    /// Also write a `dockerTools.streamLayeredImage` expression which
+    /// runs the project with bun to this file.
+    #[arg(long)]
+    pub oci_image_file: Option<PathBuf>,
+
+    /// Also write a NixOS module which runs the project with bun as a
+    /// systemd service to this file.
+    #[arg(long)]
+    pub nixos_module_file: Option<PathBuf>,
+
+    /// Also write a Home Manager module which installs the command line
+    /// tools in the `bin` of the project's `package.json` to this file.
+    #[arg(long)]
+    pub home_manager_module_file: Option<PathBuf>,
+
+    /// Also write a package expression following nixpkgs conventions,
+    /// as a starting point for submitting the project to nixpkgs.
+    #[arg(long)]
+    pub nixpkgs_package_file: Option<PathBuf>,
+
+    /// Also write a flake input for every git dependency to this
+    /// file, and take git dependencies from these inputs in the Nix
+    /// expression when they are passed to `fetchBunDeps`.
+    #[arg(long, value_name = "FILE")]
+    pub git_deps_as_inputs: Option<PathBuf>,
+
+    /// Fetch git dependencies cloned over SSH from HTTPS in the Nix
+    /// expression, which can be fetched inside the sandbox - from the
+    /// same host, or from the repository paths under BASE if given.
+    #[arg(long, value_name = "BASE", num_args = 0..=1)]
+    pub git_https_mirror: Option<Option<String>>,
+
+    /// How much commentary to write into the output - `verbose` notes
+    /// the version, source and dependency count of every package, and
+    /// `terse` leaves out every comment to keep diffs small.
+    #[arg(long, value_enum, default_value_t = OutputStyle::Default)]
+    pub output_style: OutputStyle,
+
+    /// How to settle a binary which several packages link into
+    /// `node_modules/.bin` - `error`, `first-wins` in lockfile order,
+    /// or `prefer:<package>[,<package>...]` - rather than leaving it
+    /// to bun.
+    #[arg(long, value_name = "STRATEGY")]
+    pub bin_conflicts: Option<BinStrategy>,
+
+    /// Render the output with a custom Jinja template rather than as
+    /// `bun.nix`, which can be checked with `bun2nix template check`.
+    #[cfg(feature = "template")]
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// Set a variable in the template context as `key=value`, which
+    /// may be repeated - a `banner` is rendered at the top of the
+    /// output, and any other key needs a `--template` using it.
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = bun2nix::nix_expression::parse_variable)]
+    pub variables: Vec<(String, String)>,
+
+    /// Check that the generated expression parses with
+    /// `nix-instantiate --parse` before writing it, failing if nix
+    /// rejects it.
+    #[arg(long)]
+    pub validate: bool,
+
+    /// After writing the output, build the project's `node_modules`
+    /// from it with `nix build` to check that it works, using the
+    /// bun2nix library from the given flake - the release matching
+    /// this version by default.
+    #[arg(long, value_name = "FLAKE", num_args = 0..=1, default_missing_value = bun2nix::build_check::DEFAULT_FLAKE, requires = "output_file")]
+    pub build_check: Option<String>,
+
+    /// Build the project's `node_modules` as with `--build-check`, and
+    /// report the total size of its closure, along with how it has
+    /// changed since the last run.
+    #[arg(long, requires = "output_file")]
+    pub closure_size: bool,
+
+    /// Fail if the closure of the built `node_modules` is larger than
+    /// the given size (e.g. `2G`), implying `--closure-size`.
+    #[arg(long, value_name = "SIZE", value_parser = bun2nix::summary::parse_size, requires = "output_file")]
+    pub max_closure_size: Option<u64>,
+
+    /// Also write a `bun.nix` into the directory of every workspace
+    /// of the lockfile, holding only the packages that workspace
+    /// installs.
+    #[arg(long)]
+    pub per_workspace: bool,
+
+    /// Leave out packages which no workspace installs, such as those
+    /// left in the lockfile after the dependencies needing them were
+    /// removed, and report them - only following the workspaces at the
+    /// given paths (`.` for the root), if any are given.
+    #[arg(long, value_name = "WORKSPACES", num_args = 0.., value_delimiter = ',')]
+    #[cfg_attr(feature = "resolver", arg(conflicts_with = "package_json"))]
+    pub minimize: Option<Vec<String>>,
+
+    /// Record a `passthru.updateScript` in the output which generates it
+    /// again, for automation such as `nixpkgs-update` - `upgrade` runs
+    /// `bun update` first.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "regenerate",
+        requires = "output_file"
+    )]
+    pub update_script: Option<UpdateMode>,
+
+    /// Strip these kinds of files from packages in the `node_modules`
+    /// of the image and modules, to shrink their closures.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "KINDS",
+        requires = "deployment"
+    )]
+    pub prune: Vec<Prune>,
+
+    /// Fail instead of warning if the `packageManager` field of the
+    /// `package.json` next to the lockfile pins a bun version whose
+    /// lockfile format has not been validated.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Fail if the lockfile does not cover exactly the dependencies
+    /// declared by the project's `package.json` files, like
+    /// `bun install --frozen-lockfile`.
+    #[arg(long)]
+    pub frozen: bool,
+
+    /// Only include packages which can be installed on this nix system
+    /// (e.g. `aarch64-darwin`), rather than those for every system -
+    /// a libc may be given as a suffix, as in `x86_64-linux-musl`.
+    #[arg(long, value_name = "SYSTEM")]
+    pub system: Option<bun2nix::platform::System>,
+
+    /// Leave out native packages built against a different C standard
+    /// library (`glibc` or `musl`), such as `@swc/core-linux-x64-musl`
+    /// when generating for glibc - overrides the libc of `--system`.
+    #[arg(long, value_name = "LIBC")]
+    pub libc: Option<bun2nix::platform::Libc>,
+
+    /// Show an interactive interface with live prefetch progress,
+    /// allowing failed packages to be retried before writing.
+    #[cfg(feature = "tui")]
+    #[arg(short, long, conflicts_with = "trust_lockfile")]
+    pub interactive: bool,
+
+    /// Resolve dependencies straight from a `package.json` against
+    /// the registry, for when no lockfile can be produced.
+    #[cfg(feature = "resolver")]
+    #[arg(long, conflicts_with_all = ["lock_file", "lock_file_rev", "npm_lock_file", "oci_image_file", "nixos_module_file", "home_manager_module_file", "nixpkgs_package_file", "update_script", "frozen", "per_workspace", "build_check", "closure_size", "max_closure_size"])]
+    pub package_json: Option<PathBuf>,
+
+    /// Look up every npm package in the registry and report any
+    /// which have been deprecated by their authors.
+    #[cfg(feature = "resolver")]
+    #[arg(long)]
+    pub check_deprecated: bool,
+
+    /// Like `--check-deprecated`, but also note each deprecation as
+    /// a comment in the output.
+    #[cfg(feature = "resolver")]
+    #[arg(long)]
+    pub annotate_deprecated: bool,
+
+    /// Sum the unpacked sizes of every npm package from the registry
+    /// and print the total before fetching anything.
+    #[cfg(feature = "resolver")]
+    #[arg(long)]
+    pub estimate_size: bool,
+
+    /// Abort if the estimated unpacked size of all packages exceeds
+    /// this budget (e.g. `500M` or `2GiB`).
+    #[cfg(feature = "resolver")]
+    #[arg(long, value_parser = bun2nix::summary::parse_size)]
+    pub max_total_size: Option<u64>,
+
+    /// Look up every npm package in the registry and fail if any have
+    /// install scripts which will not run because they are not trusted.
+    #[cfg(feature = "resolver")]
+    #[arg(long)]
+    pub strict_scripts: bool,
+
+    /// Look up every npm package in the registry and warn about any
+    /// whose `engines` rule out the version of bun or node the
+    /// generated derivation runs them with.
+    #[cfg(feature = "resolver")]
+    #[arg(long)]
+    pub check_engines: bool,
+
+    /// The version of bun to check `engines` against - by default the
+    /// one pinned by `packageManager` in the project's `package.json`,
+    /// or else that of the `bun` on the `PATH`.
+    #[cfg(feature = "resolver")]
+    #[arg(long, value_name = "VERSION", requires = "check_engines")]
+    pub bun_version: Option<semver::Version>,
+
+    /// The version of node to check `engines` against - by default the
+    /// one the `bun` on the `PATH` is compatible with, as bun stands in
+    /// for node in the generated derivation.
+    #[cfg(feature = "resolver")]
+    #[arg(long, value_name = "VERSION", requires = "check_engines")]
+    pub node_version: Option<semver::Version>,
+
+    /// A JSON policy file of packages (or versions of them) which are
+    /// denied, failing with a report of any the lockfile uses.
+    #[cfg(feature = "resolver")]
+    #[arg(long, value_name = "FILE")]
+    pub policy: Option<PathBuf>,
+
+    /// Look up every npm package in the registry and list the largest
+    /// by unpacked size, with their share of the total (20 unless a
+    /// count is given).
+    #[cfg(feature = "resolver")]
+    #[arg(long, value_name = "COUNT", num_args = 0..=1, default_missing_value = "20")]
+    pub size_report: Option<usize>,
+
+    /// A mirror of the registry to fall back to if a tarball is
+    /// unavailable from the registry - may be given multiple times,
+    /// in order of preference.
+    #[cfg(feature = "resolver")]
+    #[arg(long = "mirror", value_name = "URL")]
+    pub mirrors: Vec<String>,
+
+    /// Which url to write for tarballs when using `--mirror`.
+    #[cfg(feature = "resolver")]
+    #[arg(long, value_enum, default_value_t = EmitUrl::Primary, requires = "mirrors")]
+    pub emit_url: EmitUrl,
+
+    /// Emit hashes straight from the lockfile without fetching anything,
+    /// leaving packages not in the prefetch cache without a hash -
+    /// check these later with `bun2nix verify`.
+    #[arg(long, visible_aliases = ["offline", "no-prefetch"])]
+    pub trust_lockfile: bool,
+
+    #[command(flatten)]
+    pub prefetch: PrefetchArgs,
+}
+
+/// Options for how packages with unknown hashes are prefetched
+#[derive(Debug, Args)]
+pub struct PrefetchArgs {
+    /// A command to download tarball dependencies with instead of nix,
+    /// such as `mytool fetch {url} --out {path}`.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub fetch_command: Option<bun2nix::package::FetchCommand>,
+
+    /// Download tarball dependencies in process instead of with nix,
+    /// reusing pooled HTTP/2 connections between downloads.
+    #[cfg(feature = "resolver")]
+    #[arg(long, conflicts_with = "fetch_command")]
+    pub fetch_in_process: bool,
+
+    /// The maximum number of packages to prefetch at once.
+    #[arg(short, long, default_value_t = DEFAULT_JOBS)]
+    pub jobs: usize,
+
+    /// The nix binary to prefetch with - if it does not support
+    /// `nix flake prefetch`, the `nix-prefetch-url` next to it is used.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_NIX_BIN)]
+    pub nix_bin: PathBuf,
+
+    /// Fetch GitHub dependencies even if bun has already extracted
+    /// them to its install cache, rather than hashing its copies.
+    #[arg(long)]
+    pub no_bun_cache: bool,
+
+    /// Prefetch the packages matching a glob again, replacing their
+    /// cached hashes - matched against the npm `name@version` or name,
+    /// or the url they are fetched from. May be repeated.
+    #[arg(long, value_name = "PATTERN")]
+    pub refetch: Vec<bun2nix::package::PackagePattern>,
+
+    /// Do not read or write the cache of previously prefetched hashes.
+    #[cfg(feature = "cache")]
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// The location of the prefetch cache database -
+    /// defaults to `$XDG_CACHE_HOME/bun2nix/cache.sqlite`.
+    #[cfg(feature = "cache")]
+    #[arg(long, conflicts_with = "no_cache")]
+    pub cache_file: Option<PathBuf>,
+
+    /// Use the cache shared by the users of the machine, at
+    /// `/var/cache/bun2nix/cache.sqlite` unless `--cache-file` is
+    /// given.
+    #[cfg(feature = "cache")]
+    #[arg(long, conflicts_with = "no_cache")]
+    pub shared_cache: bool,
+
+    /// Keep tarballs downloaded with `--fetch-command` or
+    /// `--fetch-in-process` in the cache, compressed, so that
+    /// they are not downloaded again.
+    #[cfg(feature = "cache")]
+    #[arg(long, conflicts_with = "no_cache")]
+    pub cache_tarballs: bool,
+}
+
+/// Which url to write for a tarball which was found on a mirror
+#[cfg(feature = "resolver")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmitUrl {
+    /// The url on the primary registry
+    Primary,
+    /// The url the tarball was actually available from
+    Used,
+}
+
+/// Options for how the development shell builds `node_modules`
+#[derive(Debug, Args)]
+pub struct DevShellArgs {
+    /// How the development shell places `node_modules` into the
+    /// project - `copy` makes a real, writable directory tree for
+    /// tools which misbehave with symlinks into the Nix store
+    #[arg(long, value_enum, default_value_t = Materialize::Symlink)]
+    pub materialize: Materialize,
+
+    /// How the development shell stores packages in the Nix store -
+    /// `tarball` keeps only the fetched tarballs and extracts them
+    /// together when building `node_modules`
+    #[arg(long, value_enum, default_value_t = Store::Extracted)]
+    pub store: Store,
+}
+
+/// A script of `package.json` which bun runs after installing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LifecycleScript {
+    /// Run once the packages are installed
+    Postinstall,
+    /// Run after `postinstall`, and before the package is packed
+    Prepare,
+}
+
+impl From<LifecycleScript> for bun2nix::package_json::LifecycleScript {
+    fn from(script: LifecycleScript) -> Self {
+        match script {
+            LifecycleScript::Postinstall => Self::Postinstall,
+            LifecycleScript::Prepare => Self::Prepare,
+        }
+    }
+}
+
+/// How the development shell places `node_modules` into the project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Materialize {
+    /// Symlink the read only `node_modules` from the Nix store
+    Symlink,
+    /// Copy `node_modules` into the project
+    Copy,
+}
+
+impl From<Materialize> for bun2nix::devshell::Materialize {
+    fn from(materialize: Materialize) -> Self {
+        match materialize {
+            Materialize::Symlink => Self::Symlink,
+            Materialize::Copy => Self::Copy,
+        }
+    }
+}
+
+/// How packages are stored in the Nix store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Store {
+    /// Keep an extracted derivation for every package
+    Extracted,
+    /// Keep only the fetched tarballs
+    Tarball,
+}
+
+impl From<Store> for bun2nix::devshell::Store {
+    fn from(store: Store) -> Self {
+        match store {
+            Store::Extracted => Self::Extracted,
+            Store::Tarball => Self::Tarball,
+        }
+    }
+}
+
+/// A kind of file which is not needed at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Prune {
+    /// Readmes, changelogs and other markdown files
+    Docs,
+    /// Test directories and files
+    Tests,
+    /// Source maps
+    Maps,
+}
+
+impl From<Prune> for bun2nix::prune::Prune {
+    fn from(prune: Prune) -> Self {
+        match prune {
+            Prune::Docs => Self::Docs,
+            Prune::Tests => Self::Tests,
+            Prune::Maps => Self::Maps,
+        }
+    }
+}
+
+/// What the update script of a generated file does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum UpdateMode {
+    /// Generate the file again from the lockfile
+    Regenerate,
+    /// Run `bun update` before generating the file again
+    Upgrade,
+}
+
+impl From<UpdateMode> for bun2nix::nix_expression::UpdateMode {
+    fn from(mode: UpdateMode) -> Self {
+        match mode {
+            UpdateMode::Regenerate => Self::Regenerate,
+            UpdateMode::Upgrade => Self::Upgrade,
+        }
+    }
+}
+
+/// How much commentary to write into a generated file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputStyle {
+    /// Leave out every comment
+    Terse,
+    /// Write the header and any warnings about packages
+    Default,
+    /// Also describe every package
+    Verbose,
+}
+
+/// The format to print the differences between two lockfiles in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiffFormat {
+    /// A line for each package, as printed by `diff-nix`
+    Text,
+    /// Tables of the added, removed, upgraded and changed packages,
+    /// with links to them
+    Markdown,
+}
+
+/// A JSON format written by `bun2nix`
+#[cfg(feature = "schema")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// A line of the event stream written to stderr with `--events`
+    Events,
+    /// The entry of a single package printed by `pkg --format json`
+    #[cfg(feature = "resolver")]
+    Entry,
+}
+
+/// What to do with a custom template
+#[cfg(feature = "template")]
+#[derive(Debug, Subcommand)]
+pub enum TemplateCommand {
+    /// Render a Jinja template against a fixture package set, reporting
+    /// syntax errors, undefined variables and unknown filters or tests
+    /// with the lines they are on.
+    Check {
+        /// The template to check, such as `my.nix.j2`
+        file: PathBuf,
+
+        /// Print what the template rendered to.
+        #[arg(long)]
+        print: bool,
+    },
+}
+
+/// The format to print the entry of a single package in
+#[cfg(feature = "resolver")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EntryFormat {
+    /// An attribute set of just the package, which can be merged into
+    /// a package set
+    Nix,
+    /// A JSON object of its url, hash and tools
+    Json,
+}
+
+/// Actions on the prefetch cache
+#[cfg(feature = "cache")]
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Print the location of the cache database.
+    Path,
+
+    /// Print the number of hashes in the cache, and the space taken
+    /// up by its compressed metadata and tarballs.
+    Stats,
+
+    /// Remove every hash, metadata document and tarball from the
+    /// cache.
+    Clear,
+
+    /// Show how long the phases of recent runs took, and whether they
+    /// have been getting faster or slower.
+    Timings {
+        /// The number of most recent runs to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Write a Nix expression linking together every package the cache
+    /// knows the hash of, which realises them all into the store when
+    /// built - such as to pre-populate a binary cache.
+    Export {
+        /// The output file to write to -
+        /// if no file location is provided, print to stdout instead.
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+    },
+
+    /// Check that the cached hashes are well formed and that cached
+    /// tarballs and registry metadata still match them, failing if any
+    /// are corrupt.
+    Verify {
+        /// Also check the cached registry metadata and tarballs against
+        /// the integrity hashes in this lockfile.
+        #[arg(short, long)]
+        lock_file: Option<PathBuf>,
+
+        /// Fetch up to this many of the hashes prefetched longest ago
+        /// again to check them - each run moves on to the next ones.
+        #[arg(long, value_name = "COUNT", default_value_t = 0)]
+        sample: usize,
+
+        /// Remove corrupt entries from the cache rather than failing.
+        #[arg(long)]
+        evict: bool,
+
+        /// The nix binary to fetch the sample with
+        #[arg(long, value_name = "PATH", default_value = DEFAULT_NIX_BIN)]
+        nix_bin: PathBuf,
+
+        /// The maximum number of hashes to fetch at once
+        #[arg(short, long, default_value_t = DEFAULT_JOBS)]
+        jobs: usize,
+    },
+}
@@ -0,0 +1,313 @@
+//! The implementation of each subcommand of the command line tool, along with the helpers they
+//! share
+
+use std::{
+    fs::{self, File, TryLockError},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use bun2nix::{
+    Error, Prefetcher, Result,
+    bun_cache::BunCache,
+    dependency_graph::{DependencyGraph, Node},
+    diff::PackageDiff,
+    interrupt,
+    nix_expression::Parser,
+    package::Nix,
+};
+use log::{debug, warn};
+
+#[cfg(feature = "resolver")]
+use crate::cli::GlobalArgs;
+#[cfg(feature = "schema")]
+use crate::cli::SchemaKind;
+use crate::cli::{DiffFormat, PrefetchArgs};
+
+/// How often a run waiting for another to finish writing checks whether it is done
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(feature = "resolver")]
+mod audit;
+#[cfg(feature = "cache")]
+mod cache;
+mod check;
+#[cfg(feature = "dev")]
+mod dev;
+mod doctor;
+mod generate;
+mod graph;
+mod init;
+mod status;
+#[cfg(feature = "template")]
+mod template;
+#[cfg(feature = "resolver")]
+mod tool;
+#[cfg(feature = "resolver")]
+mod verify;
+
+#[cfg(feature = "resolver")]
+pub use audit::audit;
+#[cfg(feature = "cache")]
+pub use cache::cache;
+pub use check::check;
+#[cfg(feature = "dev")]
+pub use dev::dev;
+pub use doctor::doctor;
+pub use generate::generate;
+pub use graph::{tree, why};
+pub use init::{init, setup};
+pub use status::status;
+#[cfg(feature = "template")]
+pub use template::template;
+#[cfg(feature = "resolver")]
+pub use tool::{pkg, tool};
+#[cfg(feature = "resolver")]
+pub use verify::verify;
+
+/// # Diff Nix
+///
+/// Print the differences between two generated `bun.nix` files
+pub fn diff_nix(old: PathBuf, new: PathBuf) -> Result<()> {
+    let old = Parser::parse_packages(&fs::read_to_string(old)?)?;
+    let new = Parser::parse_packages(&fs::read_to_string(new)?)?;
+
+    println!("{}", PackageDiff::new(old, new));
+
+    Ok(())
+}
+
+/// # Diff Lock
+///
+/// Print the differences between the packages of two Bun lockfiles, as markdown if requested,
+/// with the unpacked size of each added, removed or upgraded package if they should be looked up
+#[cfg_attr(not(feature = "resolver"), allow(unused_variables))]
+pub fn diff_lock(
+    old: &Path,
+    new: &Path,
+    format: DiffFormat,
+    #[cfg(feature = "resolver")] sizes: bool,
+    #[cfg(feature = "resolver")] jobs: usize,
+    #[cfg(feature = "resolver")] global: &GlobalArgs,
+) -> Result<()> {
+    let old = bun2nix::parse_lockfile(&fs::read_to_string(old)?)?.packages();
+    let new = bun2nix::parse_lockfile(&fs::read_to_string(new)?)?.packages();
+
+    let diff = PackageDiff::new(normalized(old), normalized(new));
+
+    match format {
+        DiffFormat::Text => println!("{diff}"),
+        #[cfg(feature = "resolver")]
+        DiffFormat::Markdown if sizes => {
+            let changed: Vec<_> = diff.added.iter().chain(&diff.removed).cloned().collect();
+            let enrichment =
+                bun2nix::enrichment::Enrichment::fetch(&registry(global)?, &changed, jobs)?;
+
+            print!("{}", diff.markdown().with_sizes(&enrichment.sizes));
+        }
+        DiffFormat::Markdown => print!("{}", diff.markdown()),
+    }
+
+    Ok(())
+}
+
+/// Sort and deduplicate the packages of a lockfile, as they would be written to a `bun.nix`
+fn normalized(mut packages: Vec<bun2nix::Package>) -> Vec<bun2nix::Package> {
+    bun2nix::package::normalize(&mut packages);
+    packages
+}
+
+/// # Schema
+///
+/// Print the JSON Schema of one of the JSON formats written by `bun2nix`
+#[cfg(feature = "schema")]
+pub fn schema(kind: SchemaKind) -> Result<()> {
+    let schema = match kind {
+        SchemaKind::Events => schemars::schema_for!(crate::events::Event<'static>),
+        #[cfg(feature = "resolver")]
+        SchemaKind::Entry => schemars::schema_for!(bun2nix::package_entry::PackageEntry),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+
+    Ok(())
+}
+
+/// # Import node2nix
+///
+/// Convert a `node-packages.nix` file generated by `node2nix` into a `bun.nix`
+pub fn import_node2nix(node_packages: PathBuf, output_file: Option<PathBuf>) -> Result<()> {
+    let packages = Parser::parse_node2nix(&fs::read_to_string(node_packages)?)?;
+
+    write_output(output_file, bun2nix::render_nix_expression(packages)?)
+}
+
+/// # Merge Driver
+///
+/// Merge two sides of a generated `bun.nix`, writing the result over the current side
+pub fn merge_driver(ancestor: PathBuf, current: PathBuf, other: PathBuf) -> Result<()> {
+    let merged = bun2nix::merge_driver::merge(
+        &fs::read_to_string(ancestor)?,
+        &fs::read_to_string(&current)?,
+        &fs::read_to_string(other)?,
+    )?;
+
+    write_output(Some(current), merged)
+}
+
+fn prefetcher(cli: &PrefetchArgs) -> Result<Prefetcher> {
+    let mut prefetcher = Prefetcher::new(cli.jobs)
+        .with_nix(Nix::new(&cli.nix_bin))
+        .with_refetch(cli.refetch.clone());
+
+    if let Some(fetch_command) = &cli.fetch_command {
+        prefetcher = prefetcher.with_fetch_command(fetch_command.clone());
+    }
+
+    if let Some(bun_cache) = BunCache::detect().filter(|_| !cli.no_bun_cache) {
+        prefetcher = prefetcher.with_bun_cache(bun_cache);
+    }
+
+    #[cfg(feature = "resolver")]
+    if cli.fetch_in_process {
+        prefetcher = prefetcher.with_in_process_fetching()?;
+    }
+
+    #[cfg(feature = "cache")]
+    if let Some(cache) = open_cache(cli)? {
+        prefetcher = prefetcher.with_cache(cache);
+
+        if cli.cache_tarballs {
+            prefetcher = prefetcher.with_tarball_cache();
+        }
+    }
+
+    Ok(prefetcher)
+}
+
+/// Open the cache the prefetch arguments point at, unless it is disabled or there is nowhere
+/// to keep it
+#[cfg(feature = "cache")]
+fn open_cache(cli: &PrefetchArgs) -> Result<Option<bun2nix::cache::Cache>> {
+    if cli.no_cache {
+        return Ok(None);
+    }
+
+    if cli.shared_cache {
+        let path = cli
+            .cache_file
+            .clone()
+            .unwrap_or_else(bun2nix::cache::Cache::shared_path);
+
+        return bun2nix::cache::Cache::open_shared(&path).map(Some);
+    }
+
+    cli.cache_file
+        .clone()
+        .or_else(bun2nix::cache::Cache::default_path)
+        .map(|path| bun2nix::cache::Cache::open(&path))
+        .transpose()
+}
+
+fn write_output(output_file: Option<PathBuf>, nix: String) -> Result<()> {
+    if let Some(output_file) = output_file {
+        write_file(output_file, nix)?;
+    } else {
+        println!("{nix}");
+    }
+
+    Ok(())
+}
+
+/// Take an advisory lock on the directory an output file is written to, held until the
+/// returned handle is dropped, so that concurrent runs writing there (such as a watcher and a
+/// manual run) take turns rather than interleaving
+fn lock_output(path: &Path) -> Result<Option<File>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // Not every platform can open a directory as a file, in which case nothing is locked
+    let Ok(handle) = File::open(dir) else {
+        return Ok(None);
+    };
+
+    let mut waiting = false;
+
+    // Polled rather than blocking, so that waiting can still be interrupted
+    loop {
+        match handle.try_lock() {
+            Ok(()) => return Ok(Some(handle)),
+            Err(TryLockError::WouldBlock) => {
+                if !waiting {
+                    warn!(
+                        "Waiting for another run of `bun2nix` writing to `{}` to finish",
+                        dir.display()
+                    );
+                    waiting = true;
+                }
+
+                interrupt::check()?;
+                thread::sleep(LOCK_POLL_INTERVAL);
+            }
+            Err(TryLockError::Error(err)) => {
+                debug!("Not locking `{}`: {err}", dir.display());
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Write a file by way of a temporary file beside it, which is renamed over it once complete,
+/// so that an interrupted or failed run never leaves it partly written
+fn write_file(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = path.as_ref();
+    interrupt::check()?;
+
+    // Devices such as `/dev/stdout` cannot be replaced, and are written to directly
+    let special = fs::metadata(path).is_ok_and(|metadata| !metadata.is_file());
+
+    let Some(name) = path.file_name().filter(|_| !special) else {
+        return Ok(fs::write(path, contents)?);
+    };
+
+    let temp = path.with_file_name(format!(".{}.bun2nix-tmp", name.to_string_lossy()));
+
+    let written = fs::write(&temp, contents)
+        .map_err(Error::from)
+        .and_then(|()| interrupt::check())
+        .and_then(|()| Ok(fs::rename(&temp, path)?));
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    written
+}
+
+#[cfg(feature = "resolver")]
+fn registry(global: &GlobalArgs) -> Result<bun2nix::registry::Registry> {
+    bun2nix::registry::Registry::new(&global.registry)
+}
+
+/// The path of a workspace in the lockfile, where the root is given as `.`
+pub fn workspace_path<'a>(graph: &'a DependencyGraph, path: &str) -> Result<&'a str> {
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    let path = if path == "." { "" } else { path };
+
+    let workspaces = graph.workspaces();
+
+    match workspaces.iter().find(|workspace| **workspace == path) {
+        Some(workspace) => Ok(workspace),
+        None => Err(Error::UnknownWorkspace(
+            path.to_owned(),
+            workspaces
+                .iter()
+                .map(|path| Node::Workspace((*path).into()).to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
+}
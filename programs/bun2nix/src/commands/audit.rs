@@ -0,0 +1,61 @@
+//! Auditing the npm packages of a lockfile against their registry metadata
+
+use std::{fs, path::Path};
+
+use bun2nix::{Error, Result, enrichment::Enrichment};
+
+use super::registry;
+use crate::cli::GlobalArgs;
+
+/// # Audit
+///
+/// Report deprecated packages, the estimated unpacked size of a lockfile (and its largest
+/// packages, if requested) and the packages whose lifecycle scripts will run on install, failing if any packages are deprecated (unless
+/// allowed), have install scripts which will not run (if strict) or the size budget is exceeded
+pub fn audit(
+    lock_file: &Path,
+    max_total_size: Option<u64>,
+    allow_deprecated: bool,
+    strict_scripts: bool,
+    size_report: Option<usize>,
+    jobs: usize,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let lockfile = bun2nix::parse_lockfile(&fs::read_to_string(lock_file)?)?;
+    let trusted_dependencies = lockfile.trusted_dependencies();
+    let packages = lockfile.packages();
+
+    let enrichment = Enrichment::fetch(&registry(global)?, &packages, jobs)?;
+
+    print!("{enrichment}");
+
+    if let Some(count) = size_report {
+        print!("{}", enrichment.size_report(count));
+    }
+
+    let trusted = trusted_dependencies.trusted_packages(&packages);
+
+    match trusted.is_empty() {
+        true => println!("No packages are trusted to run lifecycle scripts"),
+        false => println!(
+            "{} package(s) trusted to run lifecycle scripts:",
+            trusted.len()
+        ),
+    }
+
+    for pkg in trusted {
+        println!("  {}", pkg.name);
+    }
+
+    enrichment.check_scripts(&trusted_dependencies, strict_scripts)?;
+
+    if let Some(budget) = max_total_size {
+        enrichment.check_size_budget(budget)?;
+    }
+
+    if !allow_deprecated && !enrichment.deprecations.is_empty() {
+        return Err(Error::DeprecatedPackages(enrichment.deprecations.len()));
+    }
+
+    Ok(())
+}
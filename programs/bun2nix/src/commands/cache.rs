@@ -0,0 +1,129 @@
+//! Management of the cache of previously prefetched hashes
+
+use std::{fs, path::PathBuf};
+
+use askama::Template;
+use bun2nix::{
+    Error, Result,
+    cache::{BlobStats, Cache, CacheExport, IntegrityReport, TimingReport},
+    package::Nix,
+    summary::format_size,
+};
+use log::warn;
+
+use super::write_output;
+use crate::cli::CacheCommand;
+
+/// # Cache
+///
+/// Inspect, verify, export or clear the prefetch cache at the given path (or the default or
+/// shared one), or show the run timings recorded in it
+pub fn cache(command: CacheCommand, cache_file: Option<PathBuf>, shared: bool) -> Result<()> {
+    let path = match shared {
+        true => cache_file.unwrap_or_else(Cache::shared_path),
+        false => cache_file
+            .or_else(Cache::default_path)
+            .ok_or(Error::NoCachePath)?,
+    };
+
+    let open = |path: &PathBuf| match shared {
+        true => Cache::open_shared(path),
+        false => Cache::open(path),
+    };
+
+    match command {
+        CacheCommand::Path => println!("{}", path.display()),
+        CacheCommand::Stats => {
+            let (count, blobs) = match path.exists() {
+                true => {
+                    let cache = open(&path)?;
+                    (cache.len()?, cache.blob_stats()?)
+                }
+                false => (0, BlobStats::default()),
+            };
+
+            println!("{count} hash(es) cached in {}", path.display());
+            println!(
+                "{} blob(s) taking up {} compressed ({} uncompressed)",
+                blobs.count,
+                format_size(blobs.stored),
+                format_size(blobs.size)
+            );
+        }
+        CacheCommand::Clear => {
+            let removed = match path.exists() {
+                true => open(&path)?.clear()?,
+                false => 0,
+            };
+
+            println!("Removed {removed} hash(es) from {}", path.display());
+        }
+        CacheCommand::Timings { limit } => {
+            let timings = match path.exists() {
+                true => open(&path)?.timings(limit)?,
+                false => Vec::new(),
+            };
+
+            println!("{}", TimingReport::new(timings));
+        }
+        CacheCommand::Export { output_file } => {
+            let export = match path.exists() {
+                true => CacheExport::from_cache(&open(&path)?)?,
+                false => CacheExport::default(),
+            };
+
+            if export.skipped > 0 {
+                warn!(
+                    "Skipped {} entr(ies) of the cache which are not well formed, see `bun2nix cache verify`",
+                    export.skipped
+                );
+            }
+
+            write_output(output_file, export.render()?)?;
+        }
+        CacheCommand::Verify {
+            lock_file,
+            sample,
+            evict,
+            nix_bin,
+            jobs,
+        } => {
+            let packages = match lock_file {
+                Some(lock_file) => {
+                    bun2nix::parse_lockfile(&fs::read_to_string(lock_file)?)?.packages
+                }
+                None => Vec::new(),
+            };
+
+            let mut cache = match path.exists() {
+                true => open(&path)?,
+                false => Cache::open_in_memory()?,
+            };
+            let mut report = IntegrityReport::check(&cache, &packages)?;
+
+            if sample > 0 {
+                report.refetch_sample(&mut cache, sample, &Nix::new(&nix_bin), jobs)?;
+            }
+
+            println!("{report}");
+
+            if report.is_clean() {
+                return Ok(());
+            }
+
+            if !evict {
+                return Err(Error::CorruptCache(report.corrupt.len()));
+            }
+
+            if cache.is_read_only() {
+                warn!("The cache is not writable by this user, so nothing can be evicted from it");
+                return Err(Error::CorruptCache(report.corrupt.len()));
+            }
+
+            let removed = report.evict(&mut cache)?;
+            println!("Evicted {removed} entr(ies) from {}", path.display());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Checking that an existing `bun.nix` file is up to date with its lockfile
+
+use std::{fs, path::Path};
+
+use bun2nix::{Error, Result, diff::PackageDiff, nix_expression::Parser};
+
+/// # Check
+///
+/// Compare a generated `bun.nix` file against the lockfile it should have been generated from,
+/// failing if it is out of date
+pub fn check(lock_file: &Path, nix_file: &Path) -> Result<()> {
+    let locked = bun2nix::parse_lockfile(&fs::read_to_string(lock_file)?)?.packages();
+    let generated = Parser::parse_packages(&fs::read_to_string(nix_file)?)?;
+
+    let diff = PackageDiff::against_lockfile(generated, locked);
+
+    if !diff.is_empty() {
+        println!("{diff}");
+
+        return Err(Error::OutOfDate(nix_file.display().to_string()));
+    }
+
+    println!(
+        "`{}` is up to date with `{}`",
+        nix_file.display(),
+        lock_file.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Tools for developing `bun2nix` itself, such as keeping the example projects up to date
+
+use std::{fs, path::Path, process::Command};
+
+use bun2nix::{Error, Package, Result, nix_expression::Parser};
+
+use super::prefetcher;
+use crate::cli::{DevCommand, PrefetchArgs};
+
+/// # Dev
+///
+/// Run one of the tools for developing `bun2nix`
+pub fn dev(command: DevCommand) -> Result<()> {
+    match command {
+        DevCommand::RegenExamples {
+            dir,
+            formatter,
+            no_format,
+            prefetch,
+        } => regen_examples(&dir, (!no_format).then_some(&*formatter), &prefetch),
+    }
+}
+
+/// Regenerate the `bun.nix` of every project in the given directory which has a `bun.lock`.
+///
+/// Hashes which are not in the lockfile are taken from the existing `bun.nix` where the package
+/// is unchanged, so that the output only depends on the lockfile and the current templates.
+fn regen_examples(dir: &Path, formatter: Option<&str>, prefetch: &PrefetchArgs) -> Result<()> {
+    let prefetcher = prefetcher(prefetch)?;
+
+    let mut examples: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+
+    examples.retain(|example| example.join("bun.lock").is_file());
+    examples.sort();
+
+    for example in examples {
+        let bun_nix = example.join("bun.nix");
+        let existing = fs::read_to_string(&bun_nix).ok();
+
+        let mut packages =
+            bun2nix::parse_lockfile(&fs::read_to_string(example.join("bun.lock"))?)?.packages();
+
+        if let Some(existing) = &existing {
+            reuse_hashes(&mut packages, Parser::parse_packages(existing)?);
+        }
+
+        prefetcher.prefetch_packages(&mut packages)?;
+
+        fs::write(&bun_nix, bun2nix::render_nix_expression(packages)?)?;
+
+        if let Some(formatter) = formatter {
+            format(formatter, &bun_nix)?;
+        }
+
+        match existing == Some(fs::read_to_string(&bun_nix)?) {
+            true => println!("{} is up to date", bun_nix.display()),
+            false => println!("Regenerated {}", bun_nix.display()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Fill in the hashes of packages needing a prefetch from the same packages in an existing file
+fn reuse_hashes(packages: &mut [Package], existing: Vec<Package>) {
+    for pkg in packages.iter_mut().filter(|pkg| pkg.needs_prefetch()) {
+        let known = existing.iter().find(|known| {
+            let mut fetcher = known.fetcher.clone();
+            fetcher.set_hash(String::new());
+
+            known.name == pkg.name && fetcher == pkg.fetcher
+        });
+
+        if let Some(known) = known {
+            pkg.fetcher = known.fetcher.clone();
+        }
+    }
+}
+
+/// Format a file in place with the given command, split on whitespace
+fn format(formatter: &str, path: &Path) -> Result<()> {
+    let mut args = formatter.split_whitespace();
+    let program = args.next().unwrap_or_default();
+
+    let output = Command::new(program)
+        .args(args)
+        .arg(path)
+        .output()
+        .map_err(Error::FormatterCommandFailed)?;
+
+    if !output.status.success() {
+        return Err(Error::FormatterCommandError(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Diagnosis of the environment `bun2nix` runs in
+
+use std::{
+    env,
+    io::{self, IsTerminal},
+    path::Path,
+};
+
+#[cfg(feature = "cache")]
+use std::path::PathBuf;
+
+#[cfg(feature = "cache")]
+use bun2nix::cache::Cache;
+use bun2nix::{
+    Error, Result,
+    doctor::{self, Report, Status},
+    package::Nix,
+};
+
+#[cfg(feature = "resolver")]
+use super::registry;
+use crate::cli::GlobalArgs;
+
+/// # Doctor
+///
+/// Check nix, bun, the prefetch cache and the registry, printing what was found and failing if
+/// anything will stop `bun2nix` from working
+#[cfg_attr(not(feature = "resolver"), allow(unused_variables))]
+pub fn doctor(
+    nix_bin: &Path,
+    #[cfg(feature = "cache")] cache_file: Option<PathBuf>,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let mut report = Report(doctor::diagnose_nix(&Nix::new(nix_bin)));
+
+    report.0.push(doctor::diagnose_bun());
+
+    #[cfg(feature = "cache")]
+    report.0.push(doctor::diagnose_cache(
+        cache_file.or_else(Cache::default_path).as_deref(),
+    ));
+
+    #[cfg(feature = "resolver")]
+    report.0.push(doctor::diagnose_registry(&registry(global)?));
+
+    let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    println!("{}", report.render(color));
+
+    match report.count(Status::Failure) {
+        0 => Ok(()),
+        failures => Err(Error::DoctorFailed(failures)),
+    }
+}
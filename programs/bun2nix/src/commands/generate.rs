@@ -0,0 +1,1101 @@
+//! Generation of a Nix expression from a lockfile, which is what `bun2nix` does when run
+//! without a subcommand
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, IsTerminal},
+    iter,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        Arc,
+        mpsc::{self, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+#[cfg(feature = "archive")]
+use bun2nix::archive::ArchivedProject;
+#[cfg(feature = "resolver")]
+use bun2nix::enrichment::EngineVersions;
+use bun2nix::{
+    Error, Lockfile, Package, PackageJson, Prefetcher, Result,
+    binaries::{Binaries, Collision, Resolution},
+    build_check::{BuildCheck, DEFAULT_FLAKE, closure_size},
+    dependency_graph::DependencyGraph,
+    diff::PackageDiff,
+    flake_inputs::FlakeInputs,
+    git_refs::{GitRef, RefKind},
+    git_ssh::{HttpsMirror, ssh_dependencies},
+    home_manager_module::HomeManagerModule,
+    nix_expression::{
+        Annotations, NixExpression, Parser, Project, UpdateScript, check_variables, validate,
+    },
+    nixos_module::NixosModule,
+    nixpkgs_package::NixpkgsPackage,
+    oci_image::OciImage,
+    out_path::{case_collisions, normalize_path},
+    package::{Fetcher, Nix},
+    summary::{Summary, format_size},
+};
+use log::{info, warn};
+
+#[cfg(all(feature = "resolver", feature = "cache"))]
+use super::open_cache;
+#[cfg(feature = "resolver")]
+use super::registry;
+use super::{lock_output, prefetcher, workspace_path, write_file, write_output};
+#[cfg(feature = "resolver")]
+use crate::cli::EmitUrl;
+use crate::{
+    cli::{GenerateArgs, GlobalArgs, OutputStyle, PrefetchArgs},
+    events::{self, Event},
+};
+
+/// # Generate
+///
+/// Generate a Nix expression from a lockfile, or from a `package.json` if requested
+#[cfg_attr(not(feature = "cache"), allow(unused_variables))]
+pub fn generate(cli: GenerateArgs, global: &GlobalArgs) -> Result<()> {
+    let _lock = match &cli.output_file {
+        Some(output_file) => lock_output(output_file)?,
+        None => None,
+    };
+
+    // Read before anything is fetched, so a missing template fails straight away
+    let template = template(&cli)?;
+
+    if template.is_none() {
+        check_variables(&cli.variables.iter().cloned().collect())?;
+    }
+
+    let (prefetcher, forward_events) = watch_prefetches(prefetcher(&cli.prefetch)?);
+    let prefetcher = Arc::new(prefetcher);
+
+    #[cfg(unix)]
+    crate::signals::watch(&prefetcher);
+
+    // Start prefetching packages as soon as they are parsed, rather than after the whole
+    // lockfile has been read and checked
+    let (sink, early_prefetch) = match prefetch_early(&cli) {
+        true => {
+            let (sink, packages) = mpsc::channel();
+            let prefetcher = Arc::clone(&prefetcher);
+
+            let handle = thread::spawn(move || prefetcher.prefetch_stream(packages));
+
+            (Some(sink), Some(handle))
+        }
+        false => (None, None),
+    };
+
+    events::emit(Event::ParseStart {
+        lock_file: &cli.lock_file.to_string_lossy(),
+    });
+
+    let started = Instant::now();
+    let (lockfile, graph) = lockfile(&cli, global, sink)?;
+    let parse_time = started.elapsed();
+
+    let project = project(&cli)?;
+    let update_script = update_script(&cli)?;
+    let (unsettled_binaries, binary_collisions) = settle_binaries(&cli, &lockfile.packages)?;
+
+    #[cfg(feature = "resolver")]
+    check_policy(&cli, &lockfile.packages)?;
+
+    #[cfg(feature = "resolver")]
+    let annotations = enrich(&cli, global, &lockfile)?;
+
+    let Lockfile {
+        workspaces,
+        packages,
+        ..
+    } = lockfile;
+
+    let (packages, pruned) = minimize(&cli, graph.as_ref(), packages)?;
+
+    #[cfg(feature = "resolver")]
+    let packages = fail_over(&cli, global, packages)?;
+
+    #[cfg(not(feature = "resolver"))]
+    let annotations = Annotations::new();
+
+    let annotations = describe_packages(&cli, graph.as_ref(), annotations, &packages);
+
+    let mut packages = select_platform(&cli, packages);
+    check_case_collisions(&cli, &packages)?;
+    let annotations = pin_git_refs(&mut packages, annotations);
+
+    let started = Instant::now();
+
+    if let Some(early_prefetch) = early_prefetch {
+        early_prefetch
+            .join()
+            .expect("prefetching packages while parsing panicked")?;
+    }
+
+    let Some(packages) = prefetch(&cli, &prefetcher, packages)? else {
+        return Ok(());
+    };
+    let prefetch_time = started.elapsed();
+
+    let packages = mirror_ssh_git(&cli, packages);
+
+    if let (true, Some(graph)) = (cli.per_workspace, &graph) {
+        write_workspaces(
+            &cli,
+            graph,
+            workspaces.keys(),
+            &packages,
+            &annotations,
+            template.as_ref(),
+        )?;
+    }
+
+    if let Some(checksums_file) = cli.checksums_file {
+        write_file(
+            checksums_file,
+            bun2nix::checksums::render_checksums(&packages)?,
+        )?;
+    }
+
+    if let Some(npm_lock_file) = cli.npm_lock_file {
+        write_file(
+            npm_lock_file,
+            bun2nix::npm_lock::render_npm_lock(&workspaces, &packages)?,
+        )?;
+    }
+
+    if cli.oci_image_file.is_some()
+        || cli.nixos_module_file.is_some()
+        || cli.home_manager_module_file.is_some()
+        || cli.nixpkgs_package_file.is_some()
+    {
+        let package_json = fs::read_to_string(cli.lock_file.with_file_name("package.json"))?;
+        let package_json: PackageJson = package_json.parse()?;
+
+        let prune: Vec<_> = cli.prune.into_iter().map(Into::into).collect();
+
+        if let Some(oci_image_file) = cli.oci_image_file {
+            let image = OciImage::new(&package_json, workspaces.keys())?.with_prune(&prune);
+            write_file(oci_image_file, image.render()?)?;
+        }
+
+        if let Some(nixos_module_file) = cli.nixos_module_file {
+            let module = NixosModule::new(&package_json, workspaces.keys())?.with_prune(&prune);
+            write_file(nixos_module_file, module.render()?)?;
+        }
+
+        if let Some(home_manager_module_file) = cli.home_manager_module_file {
+            let module =
+                HomeManagerModule::new(&package_json, workspaces.keys())?.with_prune(&prune);
+            write_file(home_manager_module_file, module.render()?)?;
+        }
+
+        if let Some(nixpkgs_package_file) = cli.nixpkgs_package_file {
+            let package = NixpkgsPackage::new(&package_json)?;
+            write_file(nixpkgs_package_file, package.render()?)?;
+        }
+    }
+
+    if let Some(git_deps_as_inputs) = &cli.git_deps_as_inputs {
+        write_file(git_deps_as_inputs, FlakeInputs::new(&packages).render()?)?;
+    }
+
+    // The packages as they will be rendered, with duplicates removed
+    let mut output = packages.clone();
+    output.sort();
+    output.dedup_by(|a, b| a.name == b.name);
+
+    let summary = Summary {
+        packages: output.len(),
+        prefetch: prefetcher.stats(),
+        diff: previous_packages(cli.output_file.as_deref())
+            .map(|previous| PackageDiff::new(previous, output)),
+        binary_collisions: binary_collisions.clone(),
+        unsettled_binaries,
+        pruned,
+        ..Summary::default()
+    };
+
+    let started = Instant::now();
+    let nix = NixExpression::new(packages)?
+        .with_annotations(annotations)
+        .with_project(project)
+        .with_update_script(update_script)
+        .with_binaries(
+            binary_collisions
+                .into_iter()
+                .map(|resolution| (resolution.name, resolution.winner.path))
+                .collect(),
+        )
+        .with_flake_inputs(cli.git_deps_as_inputs.is_some())
+        .with_variables(cli.variables.iter().cloned().collect())
+        .with_terse(cli.output_style == OutputStyle::Terse);
+    let nix = render(&nix, template.as_ref())?;
+
+    if cli.validate {
+        validate(&nix, &Nix::new(&cli.prefetch.nix_bin))?;
+    }
+
+    write_output(cli.output_file.clone(), nix)?;
+
+    #[cfg(feature = "cache")]
+    prefetcher.record_timing(&bun2nix::cache::Timing {
+        packages: summary.packages,
+        fetched: summary.prefetch.fetched,
+        cached: summary.prefetch.cached,
+        parse: parse_time,
+        prefetch: prefetch_time,
+        render: started.elapsed(),
+    })?;
+
+    // Every event is sent once nothing can prefetch any more
+    drop(prefetcher);
+
+    if let Some(forward_events) = forward_events {
+        forward_events
+            .join()
+            .expect("forwarding prefetch events panicked");
+    }
+
+    if !global.quiet && !global.events {
+        print_summary(Summary {
+            warnings: crate::logger::warnings(),
+            ..summary
+        });
+    }
+
+    let measure = cli.closure_size || cli.max_closure_size.is_some();
+
+    let Some(output_file) = cli
+        .output_file
+        .as_deref()
+        .filter(|_| cli.build_check.is_some() || measure)
+    else {
+        return Ok(());
+    };
+
+    let flake = cli.build_check.as_deref().unwrap_or(DEFAULT_FLAKE);
+    let check = BuildCheck::new(workspaces.keys(), &fs::canonicalize(output_file)?, flake);
+    let root = cli
+        .lock_file
+        .parent()
+        .filter(|root| !root.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let nix = Nix::new(&cli.prefetch.nix_bin);
+
+    let node_modules = build_check(check, root, &nix, output_file, global)?;
+
+    if measure {
+        check_closure_size(
+            &node_modules,
+            &nix,
+            output_file,
+            cli.max_closure_size,
+            &cli.prefetch,
+            global,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Build `node_modules` from the output which was just written, to check that it works
+fn build_check(
+    check: BuildCheck,
+    root: &Path,
+    nix: &Nix,
+    output_file: &Path,
+    global: &GlobalArgs,
+) -> Result<PathBuf> {
+    let report = !global.quiet && !global.events;
+
+    if report {
+        eprintln!(
+            "Building `node_modules` from `{}` to check that it works...",
+            output_file.display()
+        );
+    }
+
+    let node_modules = check.run(root, nix)?;
+
+    if report {
+        eprintln!("`node_modules` built successfully");
+    }
+
+    Ok(node_modules)
+}
+
+/// Measure the closure of the built `node_modules`, recording it in the cache to report how it
+/// changed since the last run, and failing if it is over budget
+#[cfg_attr(not(feature = "cache"), allow(unused_variables))]
+fn check_closure_size(
+    node_modules: &Path,
+    nix: &Nix,
+    output_file: &Path,
+    budget: Option<u64>,
+    prefetch: &PrefetchArgs,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let size = closure_size(node_modules, nix)?;
+
+    #[cfg(feature = "cache")]
+    let previous = match open_cache(prefetch)? {
+        Some(mut cache) => {
+            let project = fs::canonicalize(output_file)?;
+            let project = project.to_string_lossy();
+
+            let previous = cache.closure_sizes(&project, 1)?.pop();
+            cache.record_closure_size(&project, size)?;
+
+            previous.map(|(_, size)| size)
+        }
+        None => None,
+    };
+
+    #[cfg(not(feature = "cache"))]
+    let previous: Option<u64> = None;
+
+    if !global.quiet && !global.events {
+        let change = match previous {
+            Some(previous) if size > previous => {
+                format!(" (up {} since the last run)", format_size(size - previous))
+            }
+            Some(previous) if size < previous => {
+                format!(
+                    " (down {} since the last run)",
+                    format_size(previous - size)
+                )
+            }
+            Some(_) => " (unchanged since the last run)".to_owned(),
+            None => String::new(),
+        };
+
+        eprintln!(
+            "Closure size of `node_modules`: {}{change}",
+            format_size(size)
+        );
+    }
+
+    match budget {
+        Some(budget) if size > budget => Err(Error::ClosureBudgetExceeded {
+            size: format_size(size),
+            budget: format_size(budget),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Write an event for every hash the prefetcher finds from a thread of its own, if events are
+/// enabled, until the prefetcher is dropped
+fn watch_prefetches(prefetcher: Prefetcher) -> (Prefetcher, Option<JoinHandle<()>>) {
+    if !events::enabled() {
+        return (prefetcher, None);
+    }
+
+    let (sender, prefetched) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        for prefetched in prefetched {
+            events::emit(Event::from(&prefetched));
+        }
+    });
+
+    (prefetcher.with_events(sender), Some(handle))
+}
+
+/// Print the summary of the run to stderr, in color if it is a terminal
+fn print_summary(summary: Summary) {
+    let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+
+    eprintln!("{}", summary.render(color));
+}
+
+/// The packages of the existing output file, if there is one which can be parsed
+fn previous_packages(output_file: Option<&Path>) -> Option<Vec<Package>> {
+    let previous = fs::read_to_string(output_file?).ok()?;
+
+    Parser::parse_packages(&previous).ok()
+}
+
+/// Produce the lockfile, whose packages may still be missing the hashes of non-npm packages,
+/// along with the dependency graph of the same lockfile if it is needed
+#[cfg_attr(not(feature = "resolver"), allow(unused_variables))]
+fn lockfile(
+    cli: &GenerateArgs,
+    global: &GlobalArgs,
+    sink: Option<Sender<Package>>,
+) -> Result<(Lockfile, Option<DependencyGraph>)> {
+    #[cfg(feature = "resolver")]
+    if let Some(package_json) = &cli.package_json {
+        let root = package_json
+            .parent()
+            .filter(|root| !root.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let package_json = fs::read_to_string(package_json)?.parse()?;
+        let members = bun2nix::workspaces::discover_workspaces(root, package_json)?;
+        let bunfig = bun2nix::bunfig::Bunfig::read(root)?;
+        let registry = registry(global)?;
+
+        let lockfile = Lockfile {
+            lockfile_version: 1,
+            workspaces: bun2nix::workspaces::lockfile_workspaces(&members),
+            packages: bun2nix::resolver::Resolver::new(registry)
+                .with_link_workspace_packages(bunfig.link_workspace_packages)
+                .resolve_workspaces(&members)?,
+            trusted_dependencies: members[0].package_json.trusted_dependencies.clone(),
+        };
+
+        return Ok((lockfile, None));
+    }
+
+    let contents = lockfile_contents(cli)?;
+
+    let lockfile = match sink {
+        Some(sink) => bun2nix::parse_lockfile_streaming(&contents, sink)?,
+        None => bun2nix::parse_lockfile(&contents)?,
+    };
+
+    check_package_manager(cli, &lockfile)?;
+
+    if cli.frozen {
+        check_frozen(cli, &lockfile)?;
+    }
+
+    // Read from the same text as the packages, wherever the lockfile came from, and only once
+    // for everything needing it
+    let needs_graph =
+        cli.per_workspace || cli.minimize.is_some() || cli.output_style == OutputStyle::Verbose;
+
+    let graph = match needs_graph {
+        true => Some(contents.parse()?),
+        false => None,
+    };
+
+    Ok((lockfile, graph))
+}
+
+/// The directory of the project the lockfile belongs to
+fn project_root(cli: &GenerateArgs) -> &Path {
+    cli.lock_file
+        .parent()
+        .filter(|root| !root.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+/// The text of the lockfile, from wherever it was asked to be read from
+fn lockfile_contents(cli: &GenerateArgs) -> Result<String> {
+    #[cfg(feature = "archive")]
+    if let Some(archive) = &cli.lock_file_archive {
+        return Ok(ArchivedProject::read(archive)?.lockfile);
+    }
+
+    match &cli.lock_file_rev {
+        Some(rev) => read_git_blob(&git_object(rev, &cli.lock_file)),
+        None => Ok(fs::read_to_string(&cli.lock_file)?),
+    }
+}
+
+/// Check the lockfile covers the dependencies of every member of the project next to it
+fn check_frozen(cli: &GenerateArgs, lockfile: &Lockfile) -> Result<()> {
+    let root = project_root(cli);
+    let package_json = fs::read_to_string(root.join("package.json"))?.parse()?;
+    let members = bun2nix::workspaces::discover_workspaces(root, package_json)?;
+
+    lockfile.check_frozen(&members)
+}
+
+/// The name and version of the project from its root `package.json`, if it has one
+fn project(cli: &GenerateArgs) -> Result<Option<Project>> {
+    // The project packed alongside the lockfile
+    #[cfg(feature = "archive")]
+    if let Some(archive) = &cli.lock_file_archive {
+        let Some(package_json) = ArchivedProject::read(archive)?.package_json else {
+            return Ok(None);
+        };
+
+        return Ok(Project::from_package_json(&package_json.parse()?));
+    }
+
+    #[cfg(feature = "resolver")]
+    let package_json = cli
+        .package_json
+        .clone()
+        .unwrap_or_else(|| cli.lock_file.with_file_name("package.json"));
+
+    #[cfg(not(feature = "resolver"))]
+    let package_json = cli.lock_file.with_file_name("package.json");
+
+    // The project as it was at the revision the lockfile is read from
+    let package_json = match &cli.lock_file_rev {
+        Some(rev) => {
+            let lock_file = git_object(rev, &cli.lock_file);
+            let (rev, path) = lock_file.split_once(':').unwrap_or_default();
+            let path = Path::new(path).with_file_name("package.json");
+
+            read_git_blob(&format!("{rev}:{}", path.display()))
+        }
+        None => fs::read_to_string(package_json).map_err(Into::into),
+    };
+
+    let Ok(package_json) = package_json else {
+        return Ok(None);
+    };
+
+    Ok(Project::from_package_json(&package_json.parse()?))
+}
+
+/// The binaries which several packages link into `node_modules/.bin`. These are only settled,
+/// with an event for each, if a strategy was given, and are otherwise left for bun to link.
+fn settle_binaries(
+    cli: &GenerateArgs,
+    packages: &[Package],
+) -> Result<(Vec<Collision>, Vec<Resolution>)> {
+    let binaries = Binaries::new(packages);
+
+    let Some(strategy) = &cli.bin_conflicts else {
+        return Ok((binaries.collisions(), Vec::new()));
+    };
+
+    let resolutions = binaries.resolve(strategy)?;
+
+    for resolution in &resolutions {
+        events::emit(Event::BinaryCollision {
+            name: &resolution.name,
+            linked: &resolution.winner.package,
+            shadowed: &resolution.shadowed,
+        });
+    }
+
+    Ok((Vec::new(), resolutions))
+}
+
+/// Leave out the packages which none of the selected workspaces install, returning the names
+/// of those left out
+fn minimize(
+    cli: &GenerateArgs,
+    graph: Option<&DependencyGraph>,
+    mut packages: Vec<Package>,
+) -> Result<(Vec<Package>, Vec<String>)> {
+    let (Some(selected), Some(graph)) = (&cli.minimize, graph) else {
+        return Ok((packages, Vec::new()));
+    };
+
+    let workspaces = match selected.is_empty() {
+        true => graph.workspaces(),
+        false => selected
+            .iter()
+            .map(|path| workspace_path(graph, path))
+            .collect::<Result<_>>()?,
+    };
+
+    let mut pruned: Vec<String> = graph
+        .retain_reachable(&mut packages, &workspaces)
+        .into_iter()
+        .map(|pkg| pkg.name)
+        .collect();
+
+    pruned.sort();
+    pruned.dedup();
+
+    for name in &pruned {
+        info!("Pruning `{name}`, which no selected workspace installs");
+    }
+
+    Ok((packages, pruned))
+}
+
+/// Add a comment describing every package to the annotations if a verbose output was asked for,
+/// counting its dependencies if there is a lockfile to count them from
+fn describe_packages(
+    cli: &GenerateArgs,
+    graph: Option<&DependencyGraph>,
+    mut annotations: Annotations,
+    packages: &[Package],
+) -> Annotations {
+    if cli.output_style != OutputStyle::Verbose {
+        return annotations;
+    }
+
+    for pkg in packages {
+        let source = pkg.fetcher.source_kind();
+
+        let mut description = match pkg.npm_identifier() {
+            Some((_, version)) => format!("Version {version} from {source}"),
+            None => format!("From {source}"),
+        };
+
+        if let (Some(graph), Some(key)) = (graph, &pkg.lockfile_key) {
+            description.push_str(&match graph.dependencies(key).len() {
+                1 => " with 1 dependency".to_owned(),
+                count => format!(" with {count} dependencies"),
+            });
+        }
+
+        annotations
+            .entry(pkg.name.clone())
+            .or_default()
+            .insert(0, description);
+    }
+
+    annotations
+}
+
+/// Write a Nix expression into the directory of every workspace other than the root, holding
+/// only the packages which are installed for it
+fn write_workspaces<'a>(
+    cli: &GenerateArgs,
+    graph: &DependencyGraph,
+    workspaces: impl Iterator<Item = &'a String>,
+    packages: &[Package],
+    annotations: &Annotations,
+    template: Option<&(String, String)>,
+) -> Result<()> {
+    let root = project_root(cli);
+
+    let mut workspaces: Vec<_> = workspaces.filter(|path| !path.is_empty()).collect();
+    workspaces.sort();
+
+    for path in workspaces {
+        let closure = graph.closure(path);
+        let dir = root.join(path);
+
+        let packages = packages
+            .iter()
+            .filter(|pkg| {
+                pkg.lockfile_key
+                    .as_ref()
+                    .is_some_and(|key| closure.contains(key))
+            })
+            .cloned()
+            .map(|mut pkg| {
+                // Paths in the lockfile are relative to the root, not the workspace
+                if let Fetcher::CopyToStore { path: target } = &mut pkg.fetcher {
+                    *target = normalize_path(&format!(
+                        "{}{target}",
+                        "../".repeat(path.split('/').count())
+                    ));
+                }
+
+                pkg
+            })
+            .collect();
+
+        let project = match fs::read_to_string(dir.join("package.json")) {
+            Ok(package_json) => Project::from_package_json(&package_json.parse()?),
+            Err(_) => None,
+        };
+
+        let nix = NixExpression::new(packages)?
+            .with_annotations(annotations.clone())
+            .with_project(project)
+            .with_flake_inputs(cli.git_deps_as_inputs.is_some())
+            .with_variables(cli.variables.iter().cloned().collect())
+            .with_terse(cli.output_style == OutputStyle::Terse);
+        let nix = render(&nix, template)?;
+
+        if cli.validate {
+            validate(&nix, &Nix::new(&cli.prefetch.nix_bin))?;
+        }
+
+        write_file(dir.join("bun.nix"), nix)?;
+    }
+
+    Ok(())
+}
+
+/// The custom template to render the output with, as its name and source, if one was given
+#[cfg_attr(not(feature = "template"), allow(unused_variables))]
+fn template(cli: &GenerateArgs) -> Result<Option<(String, String)>> {
+    #[cfg(feature = "template")]
+    if let Some(template) = &cli.template {
+        return Ok(Some((
+            template.display().to_string(),
+            fs::read_to_string(template)?,
+        )));
+    }
+
+    Ok(None)
+}
+
+/// Print an expression, with the custom template if one was given
+fn render(nix: &NixExpression, template: Option<&(String, String)>) -> Result<String> {
+    match template {
+        #[cfg(feature = "template")]
+        Some((name, source)) => nix.render_template(name, source),
+        _ => nix.render(),
+    }
+}
+
+/// The git object a lockfile revision refers to, which is the `--lock-file` path at the
+/// revision if it does not name a path itself
+fn git_object(rev: &str, lock_file: &Path) -> String {
+    match rev.contains(':') {
+        true => rev.to_owned(),
+        false => {
+            let path = lock_file.to_string_lossy();
+            let path = path.trim_start_matches("./");
+
+            format!("{rev}:./{path}")
+        }
+    }
+}
+
+/// Read a file from the git object store, given as `REV:PATH`, without checking it out
+fn read_git_blob(object: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["cat-file", "blob", object])
+        .output()
+        .map_err(Error::GitCommandFailed)?;
+
+    if !output.status.success() {
+        return Err(Error::GitCommandError(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The script generating the output again, with the lockfile relative to the output's directory
+fn update_script(cli: &GenerateArgs) -> Result<Option<UpdateScript>> {
+    let (Some(mode), Some(output_file)) = (cli.update_script, &cli.output_file) else {
+        return Ok(None);
+    };
+
+    // The output may not exist yet, so only its directory can be resolved
+    let output_dir = match output_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.canonicalize()?,
+        _ => env::current_dir()?.canonicalize()?,
+    };
+    let lock_file = cli.lock_file.canonicalize()?;
+
+    let common = output_dir
+        .components()
+        .zip(lock_file.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let lock_file: Vec<_> =
+        iter::repeat_n("..".to_owned(), output_dir.components().count() - common)
+            .chain(
+                lock_file
+                    .components()
+                    .skip(common)
+                    .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+            )
+            .collect();
+
+    Ok(Some(UpdateScript {
+        mode: mode.into(),
+        lock_file: lock_file.join("/"),
+        output_file: output_file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+    }))
+}
+
+/// Check the bun version pinned by the `package.json` next to the lockfile, if it has one
+fn check_package_manager(cli: &GenerateArgs, lockfile: &Lockfile) -> Result<()> {
+    let package_json = cli.lock_file.with_file_name("package.json");
+
+    let Ok(package_json) = fs::read_to_string(package_json) else {
+        return Ok(());
+    };
+
+    let Some(package_manager) = package_json.parse::<PackageJson>()?.package_manager else {
+        return Ok(());
+    };
+
+    match lockfile.check_package_manager(&package_manager) {
+        Err(err) if !cli.strict => {
+            warn!("{err}");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Pin the git dependencies declared by a tag or branch to the commit it points at, noting the
+/// ref each was pinned from in the output and warning about branches, which move
+fn pin_git_refs(packages: &mut [Package], mut annotations: Annotations) -> Annotations {
+    let mut resolved = HashMap::new();
+
+    for pkg in packages.iter_mut() {
+        let (Some(declared), Some(url)) = (pkg.git_ref.clone(), pkg.fetcher.url()) else {
+            continue;
+        };
+
+        let git_ref = resolved
+            .entry((url.clone(), declared.clone()))
+            .or_insert_with(|| GitRef::resolve(&url, &declared));
+
+        let pinned = pkg.is_pinned();
+
+        let note = match git_ref {
+            Ok(git_ref) => {
+                if !pinned {
+                    pkg.pin_git_rev(&git_ref.commit);
+                }
+
+                git_ref.to_string()
+            }
+            // The lockfile already gives the commit, so only whether the ref moves is unknown
+            Err(_) if pinned => format!("Pinned from `{declared}`"),
+            Err(err) => {
+                warn!(
+                    "`{url}` is declared by `{declared}`, which could not be pinned to a commit and so may change between builds: {err}"
+                );
+                continue;
+            }
+        };
+
+        let notes = annotations.entry(pkg.name.clone()).or_default();
+
+        if notes.contains(&note) {
+            continue;
+        }
+
+        if matches!(git_ref, Ok(git_ref) if git_ref.kind == RefKind::Branch) {
+            warn!(
+                "`{url}` is declared by the branch `{declared}`, which has been pinned to its current commit - regenerate to pick up new commits"
+            );
+        }
+
+        notes.push(note);
+    }
+
+    annotations
+}
+
+/// Leave out the packages which cannot be installed on the selected system or libc
+fn select_platform(cli: &GenerateArgs, mut packages: Vec<Package>) -> Vec<Package> {
+    bun2nix::platform::retain_platform(&mut packages, cli.system.as_ref(), cli.libc);
+
+    packages
+}
+
+/// Warn of packages whose paths differ only by case, failing if generating for macOS, where
+/// they would overwrite each other
+fn check_case_collisions(cli: &GenerateArgs, packages: &[Package]) -> Result<()> {
+    let collisions = case_collisions(packages);
+
+    if cli
+        .system
+        .as_ref()
+        .is_some_and(|system| system.os == "darwin")
+        && !collisions.is_empty()
+    {
+        return Err(Error::CaseCollisions(
+            collisions.iter().map(ToString::to_string).collect(),
+        ));
+    }
+
+    for collision in collisions {
+        warn!("{collision}, which will overwrite each other when built on macOS");
+    }
+
+    Ok(())
+}
+
+/// Prefetch any missing hashes, returning `None` if the user aborted the interactive interface
+fn prefetch(
+    cli: &GenerateArgs,
+    prefetcher: &Arc<Prefetcher>,
+    mut packages: Vec<Package>,
+) -> Result<Option<Vec<Package>>> {
+    if cli.trust_lockfile {
+        let missing = prefetcher.prefetch_cached(&mut packages)?;
+
+        if !missing.is_empty() {
+            warn!(
+                "The following packages were not in the prefetch cache and have been left without a hash, check them with `bun2nix verify`: {}",
+                missing.join(", ")
+            );
+        }
+
+        return Ok(Some(packages));
+    }
+
+    #[cfg(feature = "tui")]
+    if cli.interactive {
+        return crate::tui::run(packages, Arc::clone(prefetcher));
+    }
+
+    prefetcher.prefetch_packages(&mut packages)?;
+
+    Ok(Some(packages))
+}
+
+/// Point the git dependencies which were cloned over SSH at their HTTPS mirror if requested,
+/// or warn that they cannot be fetched from inside the Nix sandbox
+fn mirror_ssh_git(cli: &GenerateArgs, mut packages: Vec<Package>) -> Vec<Package> {
+    if let Some(base) = &cli.git_https_mirror {
+        HttpsMirror::new(base.clone()).apply(&mut packages);
+        return packages;
+    }
+
+    // Flake inputs are fetched by `builtins.fetchGit` while evaluating, with the SSH agent
+    let ssh = ssh_dependencies(&packages);
+
+    if !ssh.is_empty() && cli.git_deps_as_inputs.is_none() {
+        warn!(
+            "The following git dependencies are fetched over SSH, which `fetchgit` cannot do inside the Nix sandbox: {}. Pass `--git-https-mirror` to fetch them over HTTPS instead, or `--git-deps-as-inputs` to fetch them with `builtins.fetchGit`, which uses your SSH agent",
+            ssh.join(", ")
+        );
+    }
+
+    packages
+}
+
+/// Whether to prefetch packages as they are parsed, which is skipped when nothing should be
+/// fetched, when packages may be left out once the whole lockfile has been read, or when the
+/// prefetches are shown interactively
+fn prefetch_early(cli: &GenerateArgs) -> bool {
+    #[cfg(feature = "tui")]
+    if cli.interactive {
+        return false;
+    }
+
+    // Packages denied by the policy should not be fetched before it is checked
+    #[cfg(feature = "resolver")]
+    if cli.policy.is_some() {
+        return false;
+    }
+
+    // Nor should packages left out of a minimized output, or those for other platforms, which
+    // can only be told apart with every package at hand
+    !cli.trust_lockfile && cli.minimize.is_none() && cli.system.is_none() && cli.libc.is_none()
+}
+
+/// Fail if any package is denied by the policy file, if one was given
+#[cfg(feature = "resolver")]
+fn check_policy(cli: &GenerateArgs, packages: &[Package]) -> Result<()> {
+    let Some(policy) = &cli.policy else {
+        return Ok(());
+    };
+
+    let policy: bun2nix::policy::Policy = fs::read_to_string(policy)?.parse()?;
+
+    policy.check(packages)
+}
+
+/// Report registry metadata for the package set if any was requested, such as its largest
+/// packages, enforcing the size budget and warning about untrusted install scripts, and
+/// returning the comments to add to the output
+#[cfg(feature = "resolver")]
+fn enrich(cli: &GenerateArgs, global: &GlobalArgs, lockfile: &Lockfile) -> Result<Annotations> {
+    let requested = cli.check_deprecated
+        || cli.annotate_deprecated
+        || cli.estimate_size
+        || cli.max_total_size.is_some()
+        || cli.strict_scripts
+        || cli.check_engines
+        || cli.size_report.is_some();
+
+    if !requested {
+        return Ok(Annotations::new());
+    }
+
+    #[cfg_attr(not(feature = "cache"), allow(unused_mut))]
+    let mut registry = registry(global)?;
+
+    #[cfg(feature = "cache")]
+    if let Some(cache) = open_cache(&cli.prefetch)? {
+        registry = registry.with_cache(cache);
+    }
+
+    let enrichment =
+        bun2nix::enrichment::Enrichment::fetch(&registry, &lockfile.packages, cli.prefetch.jobs)?;
+
+    let report = !global.quiet && !global.events;
+
+    if report {
+        eprint!("{enrichment}");
+    }
+
+    if let Some(count) = cli.size_report.filter(|_| report) {
+        eprint!("{}", enrichment.size_report(count));
+    }
+
+    if let Some(budget) = cli.max_total_size {
+        enrichment.check_size_budget(budget)?;
+    }
+
+    enrichment.check_scripts(&lockfile.trusted_dependencies(), cli.strict_scripts)?;
+
+    if cli.check_engines {
+        enrichment.check_engines(&engine_versions(cli));
+    }
+
+    Ok(match cli.annotate_deprecated {
+        true => enrichment.annotations(),
+        false => Annotations::new(),
+    })
+}
+
+/// The versions of bun and node to check the `engines` of packages against, from the command
+/// line, the `packageManager` pin of the project or the `bun` on the `PATH`
+#[cfg(feature = "resolver")]
+fn engine_versions(cli: &GenerateArgs) -> EngineVersions {
+    let pinned = fs::read_to_string(cli.lock_file.with_file_name("package.json"))
+        .ok()
+        .and_then(|package_json| package_json.parse::<PackageJson>().ok()?.package_manager)
+        .and_then(|package_manager| {
+            // Any hash pinned along with the version follows a `+`
+            let version = package_manager.strip_prefix("bun@")?.split('+').next()?;
+
+            semver::Version::parse(version).ok()
+        });
+
+    let installed = Command::new("bun")
+        .args(["-p", "process.versions.bun + ' ' + process.versions.node"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+
+    let installed = |index: usize| {
+        let version = installed.as_deref()?.split_whitespace().nth(index)?;
+
+        semver::Version::parse(version).ok()
+    };
+
+    EngineVersions {
+        bun: cli.bun_version.clone().or(pinned).or_else(|| installed(0)),
+        node: cli.node_version.clone().or_else(|| installed(1)),
+    }
+}
+
+/// Check every tarball is available from the registry or one of its mirrors, switching the
+/// emitted urls to the ones used if requested
+#[cfg(feature = "resolver")]
+fn fail_over(
+    cli: &GenerateArgs,
+    global: &GlobalArgs,
+    mut packages: Vec<Package>,
+) -> Result<Vec<Package>> {
+    if cli.mirrors.is_empty() {
+        return Ok(packages);
+    }
+
+    let registry = registry(global)?;
+    let mirrors = bun2nix::mirrors::Mirrors::new(registry, cli.mirrors.clone());
+    let sources = mirrors.fail_over(&packages, cli.prefetch.jobs)?;
+
+    if !global.quiet && !global.events {
+        eprint!("{sources}");
+    }
+
+    if cli.emit_url == EmitUrl::Used {
+        sources.apply(&mut packages);
+    }
+
+    Ok(packages)
+}
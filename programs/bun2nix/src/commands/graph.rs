@@ -0,0 +1,109 @@
+//! Querying the dependency graph of a lockfile, to explain why packages are installed
+
+use std::{collections::HashSet, fs, path::Path};
+
+use bun2nix::{
+    Error, Result,
+    dependency_graph::{DependencyGraph, Node},
+};
+
+use super::workspace_path;
+
+/// # Why
+///
+/// Print the shortest chain of dependencies from a workspace to every copy of the package
+/// installed under the given name
+pub fn why(name: &str, lock_file: &Path) -> Result<()> {
+    let graph: DependencyGraph = fs::read_to_string(lock_file)?.parse()?;
+
+    let keys = graph.keys_named(name);
+
+    if keys.is_empty() {
+        return Err(Error::PackageNotInLockfile(name.to_owned()));
+    }
+
+    for key in keys {
+        // Copies nested under another package are told apart by their place in the tree
+        let id = match (graph.id(key), key == name) {
+            (Some(id), true) => id.to_owned(),
+            (Some(id), false) => format!("{id} ({key})"),
+            (None, _) => key.to_owned(),
+        };
+
+        match graph.path_from_workspace(key) {
+            Some(path) => {
+                let chain: Vec<String> = path.iter().map(|node| label(&graph, node)).collect();
+
+                println!("{id}: {}", chain.join(" > "));
+            }
+            None => println!("{id}: not used by any workspace"),
+        }
+    }
+
+    Ok(())
+}
+
+/// # Tree
+///
+/// Print the packages the workspace at the given path installs as a tree, where a package
+/// already printed is marked with `(*)` rather than printed again
+pub fn tree(workspace: &str, lock_file: &Path, depth: Option<usize>) -> Result<()> {
+    let graph: DependencyGraph = fs::read_to_string(lock_file)?.parse()?;
+
+    let workspace = workspace_path(&graph, workspace)?;
+
+    println!("{}", Node::Workspace(workspace.into()));
+
+    let mut printed = HashSet::new();
+    let children = graph.workspace_dependencies(workspace);
+
+    print_children(&graph, &children, "", depth, &mut printed);
+
+    Ok(())
+}
+
+fn print_children<'a>(
+    graph: &'a DependencyGraph,
+    keys: &[&'a str],
+    prefix: &str,
+    depth: Option<usize>,
+    printed: &mut HashSet<&'a str>,
+) {
+    if depth == Some(0) {
+        return;
+    }
+
+    for (i, &key) in keys.iter().enumerate() {
+        let last = i + 1 == keys.len();
+        let (branch, indent) = match last {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+
+        let id = graph.id(key).unwrap_or(key);
+        let dependencies = graph.resolved_dependencies(key);
+
+        if !printed.insert(key) && !dependencies.is_empty() {
+            println!("{prefix}{branch}{id} (*)");
+            continue;
+        }
+
+        println!("{prefix}{branch}{id}");
+
+        print_children(
+            graph,
+            &dependencies,
+            &format!("{prefix}{indent}"),
+            depth.map(|depth| depth - 1),
+            printed,
+        );
+    }
+}
+
+/// The `name@version` of a package in a chain, or the path of a workspace
+fn label(graph: &DependencyGraph, node: &Node) -> String {
+    match node {
+        Node::Package(key) => graph.id(key).unwrap_or(key).to_owned(),
+        Node::Workspace(_) => node.to_string(),
+    }
+}
@@ -0,0 +1,123 @@
+//! Setting up a project to use `bun2nix`
+
+use std::{fs, path::Path};
+
+use bun2nix::{
+    Result,
+    devshell::{DevShell, Envrc},
+    flake::Flake,
+    package_json::{LifecycleScript, add_lifecycle_script},
+};
+
+use super::prefetcher;
+use crate::cli::{DevShellArgs, PrefetchArgs};
+
+/// # Init
+///
+/// Write `bun.nix` for the project in the given directory, and add `bun2nix` to its
+/// `postinstall` script so the file is kept up to date
+pub fn init(
+    dir: &Path,
+    merge_driver: bool,
+    devshell: bool,
+    shell: &DevShellArgs,
+    direnv: bool,
+    flake: bool,
+    prefetch: &PrefetchArgs,
+) -> Result<()> {
+    let mut lockfile = bun2nix::parse_lockfile(&fs::read_to_string(dir.join("bun.lock"))?)?;
+    lockfile.prefetch_packages(&prefetcher(prefetch)?)?;
+
+    let devshell = devshell.then(|| {
+        DevShell::new(
+            lockfile.workspaces().keys(),
+            shell.materialize.into(),
+            shell.store.into(),
+        )
+    });
+
+    fs::write(
+        dir.join("bun.nix"),
+        bun2nix::render_nix_expression(lockfile.packages())?,
+    )?;
+    println!("Wrote bun.nix");
+
+    setup(dir, LifecycleScript::Postinstall, Path::new("bun.nix"))?;
+
+    if flake {
+        let package_json = fs::read_to_string(dir.join("package.json"))?.parse()?;
+        let flake = Flake::new(&package_json, devshell.is_some());
+
+        if write_unless_exists(dir, "flake.nix", flake.render()?)? {
+            println!("Add flake.nix and bun.nix to git, as flakes only see tracked files");
+        }
+    }
+
+    if let Some(devshell) = devshell {
+        write_devshell(dir, devshell, flake)?;
+    }
+
+    if direnv {
+        write_unless_exists(dir, ".envrc", Envrc.render()?)?;
+
+        if !dir.join("flake.nix").exists() {
+            println!("The .envrc loads the shell with `use flake`, but there is no flake.nix yet");
+        }
+    }
+
+    if merge_driver {
+        bun2nix::merge_driver::install(dir, "bun.nix")?;
+        println!("Installed the bun.nix merge driver");
+    }
+
+    Ok(())
+}
+
+/// # Setup
+///
+/// Add `bun2nix` to a lifecycle script of the project in the given directory, so the file it
+/// writes is regenerated after every `bun install`, unless a lifecycle script already runs it
+pub fn setup(dir: &Path, script: LifecycleScript, output_file: &Path) -> Result<()> {
+    let package_json = dir.join("package.json");
+    let command = format!("bun2nix -o {}", output_file.display());
+
+    match add_lifecycle_script(&fs::read_to_string(&package_json)?, script, &command)? {
+        Some(updated) => {
+            fs::write(package_json, updated)?;
+            println!(
+                "Added `{command}` to the {} script in package.json",
+                script.name()
+            );
+        }
+        None => println!("package.json already runs `{command}` after installing"),
+    }
+
+    Ok(())
+}
+
+/// Write `devshell.nix`, explaining how to use it unless the flake written with it already does
+fn write_devshell(dir: &Path, devshell: DevShell, flake: bool) -> Result<()> {
+    if write_unless_exists(dir, "devshell.nix", devshell.render()?)? && !flake {
+        println!(
+            "Use devshell.nix as your flake's `devShells.default` with `pkgs.callPackage ./devshell.nix {{ }}`"
+        );
+    }
+
+    Ok(())
+}
+
+/// Write a file, leaving any existing file alone as it may have been edited. Returns whether
+/// the file was written.
+fn write_unless_exists(dir: &Path, name: &str, contents: String) -> Result<bool> {
+    let path = dir.join(name);
+
+    if path.exists() {
+        println!("{name} already exists, leaving it unchanged");
+        return Ok(false);
+    }
+
+    fs::write(path, contents)?;
+    println!("Wrote {name}");
+
+    Ok(true)
+}
@@ -0,0 +1,61 @@
+//! Comparing the `node_modules` installed by bun against a generated `bun.nix`
+
+use std::{collections::HashSet, fs, io, path::Path};
+
+use bun2nix::{
+    Error, Result,
+    dependency_graph::install_name,
+    nix_expression::Parser,
+    node_modules::{Drift, installed_packages},
+    platform::System,
+};
+
+/// # Status
+///
+/// Compare the packages installed to `node_modules` against a generated `bun.nix`, reporting
+/// those which are missing, installed at another version or not in `bun.nix` at all
+pub fn status(lock_file: &Path, nix_file: &Path, node_modules: &Path) -> Result<()> {
+    let mut expected = Parser::parse_packages(&fs::read_to_string(nix_file)?)?;
+    let installed = installed_packages(node_modules)?;
+
+    // The lockfile gives the names packages not from the registry are installed under, and
+    // which packages bun does not install on this system
+    let locked = match fs::read_to_string(lock_file) {
+        Ok(lockfile) => bun2nix::parse_lockfile(&lockfile)?.packages(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err.into()),
+    };
+
+    let other_names: Vec<&str> = locked
+        .iter()
+        .filter(|pkg| pkg.npm_identifier().is_none())
+        .filter_map(|pkg| Some(install_name(pkg.lockfile_key.as_deref()?)))
+        .collect();
+
+    if let Some(host) = System::host() {
+        let elsewhere: HashSet<&str> = locked
+            .iter()
+            .filter(|pkg| !pkg.platform.supports_system(&host))
+            .map(|pkg| pkg.name.as_str())
+            .collect();
+
+        expected.retain(|pkg| !elsewhere.contains(pkg.name.as_str()));
+    }
+
+    let drift = Drift::new(&expected, &installed, &other_names);
+
+    if !drift.is_empty() {
+        println!("{drift}");
+
+        return Err(Error::NodeModulesDrift(drift.count()));
+    }
+
+    println!(
+        "`{}` matches `{}` ({} packages installed)",
+        node_modules.display(),
+        nix_file.display(),
+        installed.len()
+    );
+
+    Ok(())
+}
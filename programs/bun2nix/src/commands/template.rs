@@ -0,0 +1,33 @@
+//! Checking custom templates of the output
+
+use std::fs;
+
+use bun2nix::{Error, Result, template_check::TemplateCheck};
+
+use crate::cli::TemplateCommand;
+
+/// # Template
+///
+/// Check a custom template against the fixture package set, failing with every problem found
+pub fn template(command: TemplateCommand) -> Result<()> {
+    let TemplateCommand::Check { file, print } = command;
+
+    let name = file.display().to_string();
+    let check = TemplateCheck::run(&name, &fs::read_to_string(&file)?);
+
+    if let Some(rendered) = check.rendered.as_ref().filter(|_| print) {
+        print!("{rendered}");
+    }
+
+    if !check.is_ok() {
+        return Err(Error::TemplateProblems(
+            check.problems.iter().map(ToString::to_string).collect(),
+        ));
+    }
+
+    if !print {
+        println!("`{name}` renders against the fixture package set");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+//! Packaging a single package from the registry as a standalone tool, or printing its entry
+
+use std::path::PathBuf;
+
+use bun2nix::{
+    Result,
+    package_entry::PackageEntry,
+    resolver::{PackageSpec, Resolver},
+    tool::Tool,
+};
+
+use super::{registry, write_output};
+use crate::cli::{EntryFormat, GlobalArgs};
+
+/// # Package Entry
+///
+/// Resolve a package against the registry, and print the entry it would have in a generated
+/// `bun.nix`
+pub fn pkg(spec: &PackageSpec, format: EntryFormat, global: &GlobalArgs) -> Result<()> {
+    let metadata = Resolver::new(registry(global)?).resolve_request(&spec.name, &spec.range)?;
+    let entry = PackageEntry::new(&spec.name, &metadata)?;
+
+    match format {
+        EntryFormat::Nix => println!("{}", entry.to_nix()),
+        EntryFormat::Json => println!("{}", entry.to_json()?),
+    }
+
+    Ok(())
+}
+
+/// # Tool
+///
+/// Resolve a package and its dependencies against the registry, and write a Nix package
+/// running its command line tools with bun
+pub fn tool(spec: &PackageSpec, output_file: Option<PathBuf>, global: &GlobalArgs) -> Result<()> {
+    let tool = Tool::resolve(spec, &mut Resolver::new(registry(global)?))?;
+
+    write_output(output_file, tool.render()?)
+}
@@ -0,0 +1,36 @@
+//! Verification of the hashes in an existing `bun.nix` file
+
+use std::{fs, path::PathBuf};
+
+use bun2nix::{Error, Result, nix_expression::Parser, verify::Report};
+
+use super::{prefetcher, registry};
+use crate::cli::{GlobalArgs, PrefetchArgs};
+
+/// # Verify
+///
+/// Check the hashes in a generated `bun.nix` file, and optionally that it matches a lockfile,
+/// failing if any problems were found
+pub fn verify(
+    nix_file: PathBuf,
+    lock_file: Option<PathBuf>,
+    prefetch: &PrefetchArgs,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let packages = Parser::parse_packages(&fs::read_to_string(nix_file)?)?;
+
+    let mut report = Report::verify(&packages, &prefetcher(prefetch)?, &registry(global)?)?;
+
+    if let Some(lock_file) = lock_file {
+        let lockfile = bun2nix::parse_lockfile(&fs::read_to_string(lock_file)?)?;
+
+        report.compare_lockfile(&packages, &lockfile.packages);
+    }
+
+    println!("{report}");
+
+    match report.is_verified() {
+        true => Ok(()),
+        false => Err(Error::VerificationFailed(report.problem_count())),
+    }
+}
@@ -0,0 +1,735 @@
+//! This module holds the dependencies between the packages of a bun lockfile, which the main
+//! lockfile parser skips over, so the packages needed by a single workspace can be worked out
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Display},
+    str::FromStr,
+    sync::Arc,
+};
+
+use petgraph::{
+    Direction,
+    algo::tarjan_scc,
+    graph::{DiGraph, NodeIndex},
+    visit::Dfs,
+};
+use serde::{Deserialize, de::IgnoredAny};
+
+use crate::{
+    Package,
+    error::Error,
+    intern::Interner,
+    lockfile::{EntryMetadata, JsoncDeserializer},
+};
+
+/// The specifier of a lockfile entry which is a member of the workspace
+const WORKSPACE_SPECIFIER: &str = "@workspace:";
+
+/// # Dependency Graph
+///
+/// The dependencies of each workspace and package of a bun lockfile, keyed by their place in
+/// the `./node_modules` tree. Every dependency is resolved to the copy bun installs for it when
+/// the lockfile is read, giving a directed graph from each workspace and package to the
+/// packages it uses, which the queries below walk.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::dependency_graph::DependencyGraph;
+///
+/// let graph: DependencyGraph = r#"
+/// {
+///   "lockfileVersion": 1,
+///   "workspaces": {
+///     "": { "name": "monorepo" },
+///     "packages/api": { "name": "api", "dependencies": { "lib": "workspace:*", "is-odd": "^3.0.1" } },
+///     "packages/lib": { "name": "lib", "dependencies": { "is-number": "^7.0.0" } },
+///     "packages/web": { "name": "web", "devDependencies": { "is-number": "^6.0.0" } },
+///   },
+///   "packages": {
+///     "api": ["api@workspace:packages/api"],
+///     "lib": ["lib@workspace:packages/lib"],
+///     "web": ["web@workspace:packages/web"],
+///     "is-number": ["is-number@7.0.0", "", {}, "sha512-abc"],
+///     "is-odd": ["is-odd@3.0.1", "", { "dependencies": { "is-number": "^6.0.0" } }, "sha512-def"],
+///     "is-odd/is-number": ["is-number@6.0.0", "", {}, "sha512-ghi"],
+///     "web/is-number": ["is-number@6.0.0", "", {}, "sha512-ghi"],
+///   }
+/// }
+/// "#
+/// .parse()
+/// .unwrap();
+///
+/// let mut api: Vec<_> = graph.closure("packages/api").into_iter().collect();
+/// api.sort();
+///
+/// assert_eq!(api, ["is-number", "is-odd", "is-odd/is-number", "lib"]);
+/// assert_eq!(graph.closure("packages/web").into_iter().collect::<Vec<_>>(), ["web/is-number"]);
+///
+/// assert_eq!(graph.dependencies("is-odd"), ["is-number"]);
+/// assert_eq!(graph.dependencies("lib"), ["is-number"]);
+/// assert!(graph.dependencies("is-number").is_empty());
+///
+/// assert_eq!(graph.resolved_dependencies("is-odd"), ["is-odd/is-number"]);
+/// assert_eq!(graph.workspace_dependencies("packages/api"), ["is-odd", "lib"]);
+/// assert_eq!(graph.id("is-odd/is-number"), Some("is-number@6.0.0"));
+/// ```
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    /// The workspaces of the lockfile, by their path
+    workspaces: HashMap<String, Manifest>,
+
+    /// The names of the dependencies of each package, by its lockfile key
+    packages: HashMap<Arc<str>, Vec<Arc<str>>>,
+
+    /// The paths of the workspaces linked into the tree, by their lockfile key
+    links: HashMap<Arc<str>, String>,
+
+    /// The `name@version` identifiers of each package and linked workspace, by its lockfile key
+    ids: HashMap<Arc<str>, Arc<str>>,
+
+    /// An edge from each workspace and package to every package it resolves a dependency to
+    graph: DiGraph<Node, ()>,
+
+    /// The node of each workspace, by its path
+    workspace_nodes: HashMap<String, NodeIndex>,
+
+    /// The node of each package and linked workspace, by its lockfile key
+    package_nodes: HashMap<Arc<str>, NodeIndex>,
+}
+
+/// # Dependency Graph Node
+///
+/// A node of a [`DependencyGraph`], which is either a workspace installed on its own, along
+/// with its development dependencies, or a package in the `./node_modules` tree
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Node {
+    /// A member of the workspace, by its path
+    Workspace(Arc<str>),
+
+    /// A package or linked workspace member, by its lockfile key
+    Package(Arc<str>),
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Workspace(path) if path.is_empty() => write!(f, "(root)"),
+            Self::Workspace(path) => write!(f, "{path}"),
+            Self::Package(key) => write!(f, "{key}"),
+        }
+    }
+}
+
+/// The parts of a lockfile the dependency graph is read from
+#[derive(Deserialize)]
+struct RawLockfile {
+    #[serde(default)]
+    workspaces: HashMap<String, Manifest>,
+
+    #[serde(default)]
+    packages: HashMap<String, EntryMetadata<EntryManifest>>,
+}
+
+/// The dependencies declared by a workspace, or by the metadata object of a lockfile entry
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct Manifest {
+    name: Option<String>,
+    dependencies: HashMap<String, String>,
+    dev_dependencies: HashMap<String, String>,
+    optional_dependencies: HashMap<String, String>,
+    peer_dependencies: HashMap<String, String>,
+}
+
+/// The names of the dependencies declared by the metadata object of a lockfile entry, without
+/// the ranges they were declared with
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct EntryManifest {
+    dependencies: HashMap<String, IgnoredAny>,
+    optional_dependencies: HashMap<String, IgnoredAny>,
+    peer_dependencies: HashMap<String, IgnoredAny>,
+}
+
+impl Manifest {
+    /// The dependencies which are installed when another package depends on this one
+    fn runtime_dependencies(&self) -> impl Iterator<Item = &String> {
+        self.dependencies
+            .keys()
+            .chain(self.optional_dependencies.keys())
+            .chain(self.peer_dependencies.keys())
+    }
+}
+
+impl DependencyGraph {
+    /// # Workspace Closure
+    ///
+    /// The lockfile keys of every package installed for the workspace at the given path,
+    /// following dependencies through the tree as bun resolves them - from the nearest copy
+    /// nested under the dependent package, up to the one hoisted to the root.
+    ///
+    /// The development dependencies of the workspace itself are included, but not those of
+    /// the packages or other workspaces it depends on.
+    pub fn closure(&self, path: &str) -> HashSet<String> {
+        let Some(&start) = self.workspace_nodes.get(path) else {
+            return HashSet::new();
+        };
+
+        let mut closure = HashSet::new();
+        let mut dfs = Dfs::new(&self.graph, start);
+
+        while let Some(index) = dfs.next(&self.graph) {
+            if let Node::Package(key) = &self.graph[index] {
+                closure.insert(key.to_string());
+            }
+        }
+
+        closure
+    }
+
+    /// # Reachable Packages
+    ///
+    /// The lockfile keys of every package installed for any of the workspaces at the given
+    /// paths, along with the lockfile keys those workspaces are themselves linked under
+    pub fn reachable(&self, workspaces: &[&str]) -> HashSet<String> {
+        let mut reachable: HashSet<String> = self
+            .links
+            .iter()
+            .filter(|(_, path)| workspaces.contains(&path.as_str()))
+            .map(|(key, _)| key.to_string())
+            .collect();
+
+        for path in workspaces {
+            reachable.extend(self.closure(path));
+        }
+
+        reachable
+    }
+
+    /// # Retain Reachable Packages
+    ///
+    /// Remove the packages which none of the workspaces at the given paths install, such as
+    /// those left in the lockfile after the dependencies needing them were removed, returning
+    /// the removed packages. Packages without a lockfile key are always kept.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{dependency_graph::DependencyGraph, parse_lockfile};
+    ///
+    /// let lockfile = r#"
+    /// {
+    ///   "lockfileVersion": 1,
+    ///   "workspaces": {
+    ///     "": { "name": "app", "dependencies": { "is-odd": "^3.0.1" } },
+    ///   },
+    ///   "packages": {
+    ///     "is-number": ["is-number@6.0.0", "", {}, "sha512-abc"],
+    ///     "is-odd": ["is-odd@3.0.1", "", { "dependencies": { "is-number": "^6.0.0" } }, "sha512-def"],
+    ///     "left-pad": ["left-pad@1.3.0", "", {}, "sha512-ghi"],
+    ///   }
+    /// }
+    /// "#;
+    ///
+    /// let graph: DependencyGraph = lockfile.parse().unwrap();
+    /// let mut packages = parse_lockfile(lockfile).unwrap().packages();
+    ///
+    /// let removed = graph.retain_reachable(&mut packages, &[""]);
+    ///
+    /// assert_eq!(removed.len(), 1);
+    /// assert_eq!(removed[0].name, "left-pad@1.3.0");
+    /// assert_eq!(packages.len(), 2);
+    /// ```
+    pub fn retain_reachable(
+        &self,
+        packages: &mut Vec<Package>,
+        workspaces: &[&str],
+    ) -> Vec<Package> {
+        let reachable = self.reachable(workspaces);
+
+        let (kept, removed) = packages.drain(..).partition(|pkg| {
+            pkg.lockfile_key
+                .as_ref()
+                .is_none_or(|key| reachable.contains(key))
+        });
+
+        *packages = kept;
+
+        removed
+    }
+
+    /// # Package Dependencies
+    ///
+    /// The names of the dependencies of the package or linked workspace at the given lockfile
+    /// key, which is empty if the key is not in the lockfile
+    pub fn dependencies(&self, key: &str) -> Vec<&str> {
+        match self.links.get(key) {
+            Some(path) => self
+                .workspaces
+                .get(path)
+                .map(|workspace| {
+                    workspace
+                        .runtime_dependencies()
+                        .map(String::as_str)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            None => self
+                .packages
+                .get(key)
+                .into_iter()
+                .flatten()
+                .map(|name| &**name)
+                .collect(),
+        }
+    }
+
+    /// # Resolved Dependencies
+    ///
+    /// The lockfile keys of the packages the package or linked workspace at the given key
+    /// uses, in order, which is empty if the key is not in the lockfile
+    pub fn resolved_dependencies(&self, key: &str) -> Vec<&str> {
+        self.package_nodes
+            .get(key)
+            .map(|&index| self.neighbors(index, Direction::Outgoing))
+            .unwrap_or_default()
+    }
+
+    /// # Workspace Dependencies
+    ///
+    /// The lockfile keys of the packages the workspace at the given path uses directly,
+    /// including its development dependencies, in order
+    pub fn workspace_dependencies(&self, path: &str) -> Vec<&str> {
+        self.workspace_nodes
+            .get(path)
+            .map(|&index| self.neighbors(index, Direction::Outgoing))
+            .unwrap_or_default()
+    }
+
+    /// # Dependents
+    ///
+    /// The workspaces and packages which resolve a dependency to the package at the given
+    /// lockfile key, in order
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::dependency_graph::{DependencyGraph, Node};
+    ///
+    /// let graph: DependencyGraph = r#"
+    /// {
+    ///   "lockfileVersion": 1,
+    ///   "workspaces": {
+    ///     "": { "name": "app", "dependencies": { "is-odd": "^3.0.1", "is-number": "^7.0.0" } },
+    ///   },
+    ///   "packages": {
+    ///     "is-number": ["is-number@7.0.0", "", {}, "sha512-abc"],
+    ///     "is-odd": ["is-odd@3.0.1", "", { "dependencies": { "is-number": "^7.0.0" } }, "sha512-def"],
+    ///   }
+    /// }
+    /// "#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// assert_eq!(
+    ///     graph.dependents("is-number"),
+    ///     [&Node::Workspace("".into()), &Node::Package("is-odd".into())]
+    /// );
+    /// assert_eq!(graph.dependents("is-odd"), [&Node::Workspace("".into())]);
+    /// ```
+    pub fn dependents(&self, key: &str) -> Vec<&Node> {
+        let Some(&index) = self.package_nodes.get(key) else {
+            return Vec::new();
+        };
+
+        let mut dependents: Vec<&Node> = self
+            .graph
+            .neighbors_directed(index, Direction::Incoming)
+            .map(|index| &self.graph[index])
+            .collect();
+
+        dependents.sort();
+        dependents.dedup();
+
+        dependents
+    }
+
+    /// # Path From a Workspace
+    ///
+    /// The shortest chain of dependencies through which any workspace comes to install the
+    /// package at the given lockfile key, starting with the workspace and ending with the
+    /// package, or `None` if no workspace installs it
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::dependency_graph::DependencyGraph;
+    ///
+    /// let graph: DependencyGraph = r#"
+    /// {
+    ///   "lockfileVersion": 1,
+    ///   "workspaces": {
+    ///     "": { "name": "app", "dependencies": { "is-odd": "^3.0.1" } },
+    ///   },
+    ///   "packages": {
+    ///     "is-number": ["is-number@6.0.0", "", {}, "sha512-abc"],
+    ///     "is-odd": ["is-odd@3.0.1", "", { "dependencies": { "is-number": "^6.0.0" } }, "sha512-def"],
+    ///     "left-pad": ["left-pad@1.3.0", "", {}, "sha512-ghi"],
+    ///   }
+    /// }
+    /// "#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let path: Vec<_> = graph
+    ///     .path_from_workspace("is-number")
+    ///     .unwrap()
+    ///     .iter()
+    ///     .map(ToString::to_string)
+    ///     .collect();
+    ///
+    /// assert_eq!(path, ["(root)", "is-odd", "is-number"]);
+    /// assert!(graph.path_from_workspace("left-pad").is_none());
+    /// ```
+    pub fn path_from_workspace(&self, key: &str) -> Option<Vec<&Node>> {
+        let &target = self.package_nodes.get(key)?;
+
+        // Searching back from the package finds the nearest workspace first
+        let mut next: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = VecDeque::from([target]);
+
+        while let Some(index) = queue.pop_front() {
+            if let Node::Workspace(_) = self.graph[index] {
+                let mut path = vec![&self.graph[index]];
+                let mut current = index;
+
+                while let Some(&dependency) = next.get(&current) {
+                    path.push(&self.graph[dependency]);
+                    current = dependency;
+                }
+
+                return Some(path);
+            }
+
+            let mut dependents: Vec<NodeIndex> = self
+                .graph
+                .neighbors_directed(index, Direction::Incoming)
+                .collect();
+
+            // Visit dependents in order, so that ties give the same path every time
+            dependents.sort_by(|a, b| self.graph[*a].cmp(&self.graph[*b]));
+
+            for dependent in dependents {
+                if dependent != target && !next.contains_key(&dependent) {
+                    next.insert(dependent, index);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// # Topological Order
+    ///
+    /// The lockfile keys of every package, ordered so that each package comes after the
+    /// packages it depends on. Packages which depend on each other in a cycle cannot be
+    /// ordered between themselves, so each group holds either a single package or every
+    /// package of one cycle.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::dependency_graph::DependencyGraph;
+    ///
+    /// let graph: DependencyGraph = r#"
+    /// {
+    ///   "lockfileVersion": 1,
+    ///   "workspaces": { "": { "name": "app" } },
+    ///   "packages": {
+    ///     "a": ["a@1.0.0", "", { "dependencies": { "b": "^1.0.0" } }, "sha512-abc"],
+    ///     "b": ["b@1.0.0", "", { "dependencies": { "c": "^1.0.0" } }, "sha512-def"],
+    ///     "c": ["c@1.0.0", "", { "dependencies": { "b": "^1.0.0" } }, "sha512-ghi"],
+    ///     "d": ["d@1.0.0", "", {}, "sha512-jkl"],
+    ///   }
+    /// }
+    /// "#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let order = graph.topological_order();
+    /// let position = |keys: &[&str]| order.iter().position(|group| group == keys).unwrap();
+    ///
+    /// assert_eq!(order.len(), 3);
+    /// assert!(position(&["b", "c"]) < position(&["a"]));
+    /// assert!(order.contains(&vec!["d"]));
+    /// ```
+    pub fn topological_order(&self) -> Vec<Vec<&str>> {
+        // Components come out with those an edge points to first, which are the dependencies
+        tarjan_scc(&self.graph)
+            .into_iter()
+            .map(|component| {
+                let mut keys: Vec<&str> = component
+                    .into_iter()
+                    .filter_map(|index| match &self.graph[index] {
+                        Node::Package(key) => Some(&**key),
+                        Node::Workspace(_) => None,
+                    })
+                    .collect();
+
+                keys.sort_unstable();
+                keys
+            })
+            .filter(|keys| !keys.is_empty())
+            .collect()
+    }
+
+    /// # Package Id
+    ///
+    /// The `name@version` identifier of the package or linked workspace at the given lockfile
+    /// key, such as `is-number@6.0.0` or `lib@workspace:packages/lib`
+    pub fn id(&self, key: &str) -> Option<&str> {
+        self.ids.get(key).map(|id| &**id)
+    }
+
+    /// # Keys Named
+    ///
+    /// The lockfile keys of every copy of the package installed under the given name, in order
+    pub fn keys_named(&self, name: &str) -> Vec<&str> {
+        let mut keys: Vec<&str> = self
+            .package_nodes
+            .keys()
+            .map(|key| &**key)
+            .filter(|key| install_name(key) == name)
+            .collect();
+
+        keys.sort_unstable();
+        keys
+    }
+
+    /// # Workspaces
+    ///
+    /// The paths of every workspace, in order, where the root is the empty path
+    pub fn workspaces(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self.workspaces.keys().map(String::as_str).collect();
+        paths.sort_unstable();
+        paths
+    }
+
+    /// # Graph
+    ///
+    /// The underlying graph, for queries not covered by the methods above
+    pub fn graph(&self) -> &DiGraph<Node, ()> {
+        &self.graph
+    }
+
+    /// The lockfile keys of the packages connected to a node in the given direction, in order
+    fn neighbors(&self, index: NodeIndex, direction: Direction) -> Vec<&str> {
+        let mut keys: Vec<&str> = self
+            .graph
+            .neighbors_directed(index, direction)
+            .filter_map(|index| match &self.graph[index] {
+                Node::Package(key) => Some(&**key),
+                Node::Workspace(_) => None,
+            })
+            .collect();
+
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    /// Resolve every dependency of every workspace and package into an edge of the graph,
+    /// adding nodes in order so that the graph is the same for the same lockfile
+    fn build_graph(&mut self) {
+        let mut paths: Vec<String> = self.workspaces.keys().cloned().collect();
+        paths.sort_unstable();
+
+        let mut keys: Vec<Arc<str>> = self
+            .packages
+            .keys()
+            .chain(self.links.keys())
+            .cloned()
+            .collect();
+        keys.sort_unstable();
+
+        for path in paths {
+            let index = self
+                .graph
+                .add_node(Node::Workspace(Arc::from(path.as_str())));
+            self.workspace_nodes.insert(path, index);
+        }
+
+        for key in keys {
+            let index = self.graph.add_node(Node::Package(Arc::clone(&key)));
+            self.package_nodes.insert(key, index);
+        }
+
+        let mut edges = Vec::new();
+        let mut buffer = String::new();
+
+        for (path, &index) in &self.workspace_nodes {
+            let workspace = &self.workspaces[path];
+
+            // The dependencies of a workspace which is not the root are nested under its name
+            let base = match path.is_empty() {
+                true => "",
+                false => workspace.name.as_deref().unwrap_or_default(),
+            };
+
+            for name in workspace
+                .dev_dependencies
+                .keys()
+                .chain(workspace.runtime_dependencies())
+            {
+                if let Some(dependency) = self.resolve(base, name, &mut buffer) {
+                    edges.push((index, dependency));
+                }
+            }
+        }
+
+        for (key, &index) in &self.package_nodes {
+            for name in self.dependencies(key) {
+                if let Some(dependency) = self.resolve(key, name, &mut buffer) {
+                    edges.push((index, dependency));
+                }
+            }
+        }
+
+        edges.sort_unstable();
+        edges.dedup();
+
+        for (from, to) in edges {
+            self.graph.add_edge(from, to, ());
+        }
+    }
+
+    /// The node of the copy of a dependency which the package at `parent` sees, or `None` if
+    /// it is not installed, such as for an optional peer dependency. The keys tried are built
+    /// in `buffer`, which is reused across calls rather than allocating a key for each.
+    fn resolve(&self, parent: &str, name: &str, buffer: &mut String) -> Option<NodeIndex> {
+        let mut parent = parent;
+
+        loop {
+            buffer.clear();
+
+            if !parent.is_empty() {
+                buffer.push_str(parent);
+                buffer.push('/');
+            }
+
+            buffer.push_str(name);
+
+            if let Some(&index) = self.package_nodes.get(buffer.as_str()) {
+                return Some(index);
+            }
+
+            if parent.is_empty() {
+                return None;
+            }
+
+            parent = parent_key(parent);
+        }
+    }
+}
+
+impl FromStr for DependencyGraph {
+    type Err = Error;
+
+    fn from_str(lockfile: &str) -> std::result::Result<Self, Self::Err> {
+        let lockfile: RawLockfile = JsoncDeserializer::parse(lockfile)?;
+
+        let mut graph = Self {
+            workspaces: lockfile.workspaces,
+            ..Self::default()
+        };
+
+        // Keys, identifiers and dependency names repeat throughout large lockfiles, as popular
+        // packages are depended on by, and nested under, many others
+        let mut interner = Interner::default();
+
+        for (key, entry) in lockfile.packages {
+            let key = interner.intern(&key);
+            let id = entry.id;
+
+            graph.ids.insert(Arc::clone(&key), interner.intern(&id));
+
+            if let Some(pos) = id.rfind(WORKSPACE_SPECIFIER) {
+                graph
+                    .links
+                    .insert(key, id[pos + WORKSPACE_SPECIFIER.len()..].to_owned());
+                continue;
+            }
+
+            let metadata = entry.metadata;
+            let dependencies = metadata
+                .dependencies
+                .keys()
+                .chain(metadata.optional_dependencies.keys())
+                .chain(metadata.peer_dependencies.keys())
+                .map(|name| interner.intern(name))
+                .collect();
+
+            graph.packages.insert(key, dependencies);
+        }
+
+        graph.build_graph();
+
+        Ok(graph)
+    }
+}
+
+/// The lockfile key of the package a key is nested under, keeping scoped names such as
+/// `@types/node` whole
+fn parent_key(key: &str) -> &str {
+    let Some(last) = key.rfind('/') else {
+        return "";
+    };
+
+    match key[..last].rfind('/') {
+        Some(pos) if key[pos + 1..].starts_with('@') => &key[..pos],
+        None if key.starts_with('@') => "",
+        _ => &key[..last],
+    }
+}
+
+/// # Install Name
+///
+/// The name a package is installed under, from its lockfile key such as `parent/@scope/child`
+///
+/// ## Usage
+///```rust
+/// use bun2nix::dependency_graph::install_name;
+///
+/// assert_eq!(install_name("is-odd/is-number"), "is-number");
+/// assert_eq!(install_name("lib/@types/node"), "@types/node");
+/// assert_eq!(install_name("@types/node"), "@types/node");
+/// ```
+pub fn install_name(key: &str) -> &str {
+    match parent_key(key) {
+        "" => key,
+        parent => &key[parent.len() + 1..],
+    }
+}
+
+/// # Install Path
+///
+/// The directory a package is installed to from the project root, from its lockfile key
+///
+/// ## Usage
+///```rust
+/// use bun2nix::dependency_graph::install_path;
+///
+/// assert_eq!(install_path("is-odd"), "node_modules/is-odd");
+/// assert_eq!(
+///     install_path("lib/@types/node"),
+///     "node_modules/lib/node_modules/@types/node"
+/// );
+/// ```
+pub fn install_path(key: &str) -> String {
+    match parent_key(key) {
+        "" => format!("node_modules/{key}"),
+        parent => format!(
+            "{}/node_modules/{}",
+            install_path(parent),
+            install_name(key)
+        ),
+    }
+}
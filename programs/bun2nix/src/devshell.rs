@@ -0,0 +1,156 @@
+//! This module holds rendering of a development shell which links a Nix-built `node_modules`
+//! into the project, so that nothing has to be installed impurely during development, along
+//! with a direnv `.envrc` to load it
+
+use askama::Template;
+
+use crate::error::Result;
+
+/// # Materialize
+///
+/// How the Nix-built `node_modules` is placed into the working tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Materialize {
+    /// Symlink the read only `node_modules` from the store, which is instant
+    #[default]
+    Symlink,
+
+    /// Copy a hoisted `node_modules` into the project as a real, writable directory tree, for
+    /// tools (such as Vite) which misbehave when their dependencies resolve into the store
+    Copy,
+}
+
+/// # Store
+///
+/// How the packages `node_modules` is built from are kept in the Nix store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Store {
+    /// Keep an extracted derivation for every package, so that only changed packages are
+    /// extracted again when dependencies change
+    #[default]
+    Extracted,
+
+    /// Keep only the fetched tarballs, extracting every package together when `node_modules`
+    /// is built, which takes less space in the store and binary caches
+    Tarball,
+}
+
+impl Store {
+    /// The value of the `store` argument of `fetchBunDeps`
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Extracted => "extracted",
+            Self::Tarball => "tarball",
+        }
+    }
+}
+
+/// # Development Shell
+///
+/// A `devshell.nix` providing bun and bun2nix, which builds the project's `node_modules` from `bun.nix`
+/// and symlinks or copies it into the working tree when the shell is entered
+///
+/// ## Usage
+///```rust
+/// use bun2nix::devshell::{DevShell, Materialize, Store};
+///
+/// let workspaces = ["", "packages/lib"].map(str::to_owned);
+/// let nix = DevShell::new(&workspaces, Materialize::Symlink, Store::Extracted)
+///     .render()
+///     .unwrap();
+///
+/// assert!(nix.contains("./packages/lib/package.json"));
+/// assert!(nix.contains("ln -sfn ${nodeModules}/node_modules node_modules"));
+/// assert!(!nix.contains("store = "));
+///
+/// let nix = DevShell::new(&workspaces, Materialize::Copy, Store::Tarball)
+///     .render()
+///     .unwrap();
+///
+/// assert!(nix.contains("--linker=hoisted"));
+/// assert!(nix.contains("cp -R ${nodeModules}/node_modules node_modules"));
+/// assert!(nix.contains(r#"store = "tarball";"#));
+/// ```
+#[derive(Template)]
+#[template(path = "devshell.nix_template")]
+pub struct DevShell {
+    workspaces: Vec<String>,
+    materialize: Materialize,
+    linker: &'static str,
+    store: &'static str,
+}
+
+impl DevShell {
+    /// # New Development Shell
+    ///
+    /// Create a shell for a project with the given workspace paths, whose `package.json` files
+    /// need to be present for `bun install` to succeed. The root workspace is always included.
+    ///
+    /// A copied `node_modules` is installed with bun's hoisted linker, so that packages are
+    /// real directories rather than symlinks.
+    pub fn new<'a>(
+        workspaces: impl IntoIterator<Item = &'a String>,
+        materialize: Materialize,
+        store: Store,
+    ) -> Self {
+        let mut workspaces: Vec<String> = workspaces
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .cloned()
+            .collect();
+
+        workspaces.sort();
+
+        let linker = match materialize {
+            Materialize::Symlink => "isolated",
+            Materialize::Copy => "hoisted",
+        };
+
+        Self {
+            workspaces,
+            materialize,
+            linker,
+            store: store.as_str(),
+        }
+    }
+
+    /// # Render
+    ///
+    /// Render the shell as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
+
+/// # Direnv Configuration
+///
+/// An `.envrc` which loads the development shell from the project's flake, and regenerates
+/// `bun.nix` whenever `bun.lock` is newer than it
+///
+/// ## Usage
+///```rust
+/// use bun2nix::devshell::Envrc;
+///
+/// let envrc = Envrc.render().unwrap();
+///
+/// assert!(envrc.contains("watch_file bun.lock"));
+/// assert!(envrc.contains("use flake"));
+/// ```
+#[derive(Template)]
+#[template(path = "envrc", escape = "none")]
+pub struct Envrc;
+
+impl Envrc {
+    /// # Render
+    ///
+    /// Render the `.envrc` as a shell script
+    pub fn render(&self) -> Result<String> {
+        let mut envrc = Template::render(self)?;
+        envrc.push('\n');
+
+        Ok(envrc)
+    }
+}
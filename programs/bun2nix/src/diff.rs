@@ -0,0 +1,218 @@
+//! This module holds the implementation for a package level comparison of two package sets,
+//! such as the contents of two generated `bun.nix` files
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use crate::{Package, package::Fetcher};
+
+mod markdown;
+
+pub use markdown::MarkdownReport;
+
+/// # Package Diff
+///
+/// The package level differences between an old and new package set
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PackageDiff {
+    /// Packages which only exist in the new set
+    pub added: Vec<Package>,
+
+    /// Packages which only exist in the old set
+    pub removed: Vec<Package>,
+
+    /// Packages which exist in both sets under the same name but are fetched differently
+    pub changed: Vec<Change>,
+}
+
+/// # Package Change
+///
+/// A package which exists in both sets, but with a different fetcher
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The name of the package
+    pub name: String,
+
+    /// How the package was fetched in the old set
+    pub old: Fetcher,
+
+    /// How the package is fetched in the new set
+    pub new: Fetcher,
+}
+
+impl PackageDiff {
+    /// # New Package Diff
+    ///
+    /// Compare an old and new set of packages by name
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{Package, diff::PackageDiff, package::Fetcher};
+    ///
+    /// let path = |path: &str| Fetcher::CopyToStore { path: path.to_owned() };
+    ///
+    /// let old = vec![
+    ///     Package::new("a".to_owned(), path("a")),
+    ///     Package::new("b".to_owned(), path("b")),
+    /// ];
+    /// let new = vec![
+    ///     Package::new("b".to_owned(), path("b2")),
+    ///     Package::new("c".to_owned(), path("c")),
+    /// ];
+    ///
+    /// let diff = PackageDiff::new(old, new);
+    ///
+    /// assert_eq!(diff.added[0].name, "c");
+    /// assert_eq!(diff.removed[0].name, "a");
+    /// assert_eq!(diff.changed[0].name, "b");
+    /// ```
+    pub fn new(old: Vec<Package>, mut new: Vec<Package>) -> Self {
+        let mut old: BTreeMap<String, Fetcher> =
+            old.into_iter().map(|pkg| (pkg.name, pkg.fetcher)).collect();
+
+        let mut diff = Self::default();
+
+        new.sort();
+
+        for pkg in new {
+            match old.remove(&pkg.name) {
+                None => diff.added.push(pkg),
+                Some(fetcher) if fetcher != pkg.fetcher => diff.changed.push(Change {
+                    name: pkg.name,
+                    old: fetcher,
+                    new: pkg.fetcher,
+                }),
+                Some(_) => (),
+            }
+        }
+
+        diff.removed = old
+            .into_iter()
+            .map(|(name, fetcher)| Package::new(name, fetcher))
+            .collect();
+
+        diff
+    }
+
+    /// # Against Lockfile
+    ///
+    /// Compare the packages of a generated file against those of the lockfile it should have
+    /// been generated from. The lockfile does not record the hashes of packages which must be
+    /// prefetched, so these are not compared, leaving only changes which mean the file is out of
+    /// date.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{Package, diff::PackageDiff, package::Fetcher};
+    ///
+    /// let git = |hash: &str| Fetcher::FetchGit {
+    ///     url: "https://github.com/iamkun/dayjs.git".to_owned(),
+    ///     rev: "45bf6a3".to_owned(),
+    ///     hash: hash.to_owned(),
+    ///     fetch_lfs: false,
+    /// };
+    ///
+    /// let generated = vec![Package::new("git:45bf6a3".to_owned(), git("sha256-abc"))];
+    /// let locked = vec![Package::new("git:45bf6a3".to_owned(), git(""))];
+    ///
+    /// assert!(PackageDiff::against_lockfile(generated, locked).is_empty());
+    /// ```
+    ///
+    /// A generated file which shares tarballs between packages is up to date with the lockfile
+    /// it came from
+    ///```rust
+    /// use bun2nix::{
+    ///     Package, diff::PackageDiff,
+    ///     nix_expression::{NixExpression, Parser},
+    ///     package::Fetcher,
+    /// };
+    ///
+    /// let tarball = |url: &str| Fetcher::FetchUrl {
+    ///     url: url.to_owned(),
+    ///     hash: "sha512-CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==".to_owned(),
+    /// };
+    ///
+    /// let locked = vec![
+    ///     Package::new("is-odd@3.0.1".to_owned(), tarball("https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz")),
+    ///     Package::new("my-is-odd@3.0.1".to_owned(), tarball("https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz")),
+    ///     Package::new("odd@3.0.1".to_owned(), tarball("https://registry.npmjs.org/odd/-/odd-3.0.1.tgz")),
+    ///     Package::new("other-odd@3.0.1".to_owned(), tarball("https://registry.npmjs.org/odd/-/odd-3.0.1.tgz")),
+    /// ];
+    ///
+    /// let nix = NixExpression::new(locked.clone()).unwrap().render().unwrap();
+    /// let generated = Parser::parse_packages(&nix).unwrap();
+    ///
+    /// assert!(PackageDiff::against_lockfile(generated, locked).is_empty());
+    /// ```
+    pub fn against_lockfile(generated: Vec<Package>, mut locked: Vec<Package>) -> Self {
+        let without_prefetched_hash = |mut pkg: Package| {
+            match &pkg.fetcher {
+                // Tarballs downloaded with a custom fetch command are emitted with `fetchurl`
+                Fetcher::FetchUrl { url, .. } if pkg.name.starts_with("tarball:") => {
+                    pkg.fetcher = Fetcher::FetchTarball {
+                        url: url.to_owned(),
+                        hash: String::new(),
+                    };
+                }
+                Fetcher::FetchUrl { .. } => (),
+                _ => pkg.fetcher.set_hash(String::new()),
+            }
+
+            pkg
+        };
+
+        locked.sort();
+        locked.dedup();
+
+        Self::new(
+            generated.into_iter().map(without_prefetched_hash).collect(),
+            locked.into_iter().map(without_prefetched_hash).collect(),
+        )
+    }
+
+    /// # Is Empty
+    ///
+    /// Whether both package sets were identical
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Change {
+    /// # Describe Change
+    ///
+    /// A short description of what differs between the old and new fetchers
+    pub fn describe(&self) -> &'static str {
+        match (&self.old, &self.new) {
+            (old, new) if old.source_kind() != new.source_kind() => "source changed",
+            (old, new) if old.url() != new.url() => "url changed",
+            _ => "hash changed",
+        }
+    }
+}
+
+impl Display for PackageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pkg in &self.added {
+            writeln!(f, "+ {} ({})", pkg.name, pkg.fetcher.source_kind())?;
+        }
+
+        for pkg in &self.removed {
+            writeln!(f, "- {} ({})", pkg.name, pkg.fetcher.source_kind())?;
+        }
+
+        for change in &self.changed {
+            writeln!(f, "~ {} ({})", change.name, change.describe())?;
+        }
+
+        write!(
+            f,
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+}
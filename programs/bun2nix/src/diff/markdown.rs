@@ -0,0 +1,325 @@
+//! This module holds a markdown rendering of a [`PackageDiff`], for posting the dependency
+//! changes of a pull request as a comment
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use super::{Change, PackageDiff};
+use crate::{Package, summary::format_size};
+
+/// The site linked to for packages from the npm registry
+const NPM_SITE: &str = "https://www.npmjs.com/package";
+
+/// # Markdown Report
+///
+/// A markdown report of the packages added, removed, upgraded and changed between two package
+/// sets, created with [`PackageDiff::markdown`] and rendered with its `Display` implementation.
+/// A package which was removed and added under the same npm name at another version is reported
+/// as upgraded.
+///
+/// ## Usage
+///```rust
+/// use std::collections::BTreeMap;
+///
+/// use bun2nix::{Package, diff::PackageDiff, package::Fetcher};
+///
+/// let npm = |ident: &str| {
+///     Package::new(
+///         ident.to_owned(),
+///         Fetcher::new_npm_package(ident, "sha512-abc".to_owned()).unwrap(),
+///     )
+/// };
+///
+/// let diff = PackageDiff::new(
+///     vec![npm("react@18.2.0"), npm("left-pad@1.3.0")],
+///     vec![npm("react@18.3.1"), npm("is-odd@3.0.1")],
+/// );
+///
+/// let sizes = BTreeMap::from([
+///     ("react@18.2.0".to_owned(), 2048),
+///     ("react@18.3.1".to_owned(), 3072),
+/// ]);
+///
+/// let report = diff.markdown().with_sizes(&sizes).to_string();
+///
+/// assert!(report.contains("**1 added, 1 removed, 1 upgraded** (+1.0 KiB unpacked)"));
+/// assert!(report.contains(
+///     "| [react](https://www.npmjs.com/package/react) \
+///      | [18.2.0](https://www.npmjs.com/package/react/v/18.2.0) \
+///      | [18.3.1](https://www.npmjs.com/package/react/v/18.3.1) \
+///      | 2.0 KiB → 3.0 KiB (+1.0 KiB) |"
+/// ));
+/// assert!(report.contains("| [is-odd](https://www.npmjs.com/package/is-odd/v/3.0.1) | 3.0.1 | npm | unknown |"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MarkdownReport<'a> {
+    diff: &'a PackageDiff,
+    sizes: Option<&'a BTreeMap<String, u64>>,
+}
+
+/// A package which was removed and added at another version
+struct Upgrade<'a> {
+    name: &'a str,
+    old: &'a Package,
+    new: &'a Package,
+}
+
+impl PackageDiff {
+    /// # Markdown Report
+    ///
+    /// A markdown report of the differences, such as to post on a pull request
+    pub fn markdown(&self) -> MarkdownReport<'_> {
+        MarkdownReport {
+            diff: self,
+            sizes: None,
+        }
+    }
+}
+
+impl<'a> MarkdownReport<'a> {
+    /// # With Sizes
+    ///
+    /// Give the unpacked size of each package, in bytes and keyed by package name, as collected
+    /// by [`Enrichment::fetch`](crate::enrichment::Enrichment::fetch), adding the size of each
+    /// package and the change in the total to the report
+    pub fn with_sizes(mut self, sizes: &'a BTreeMap<String, u64>) -> Self {
+        self.sizes = Some(sizes);
+        self
+    }
+
+    /// Pair the removed and added packages with the same npm name as upgrades, leaving the rest
+    fn upgrades(&self) -> (Vec<Upgrade<'a>>, Vec<&'a Package>, Vec<&'a Package>) {
+        let mut removed: BTreeMap<&str, Vec<&Package>> = BTreeMap::new();
+        let mut unpaired_removed = Vec::new();
+
+        for pkg in &self.diff.removed {
+            match pkg.npm_identifier() {
+                Some((name, _)) => removed.entry(name).or_default().push(pkg),
+                None => unpaired_removed.push(pkg),
+            }
+        }
+
+        let mut upgrades = Vec::new();
+        let mut added = Vec::new();
+
+        for pkg in &self.diff.added {
+            let old = pkg.npm_identifier().and_then(|(name, _)| {
+                let candidates = removed.get_mut(name)?;
+                (!candidates.is_empty()).then(|| (name, candidates.remove(0)))
+            });
+
+            match old {
+                Some((name, old)) => upgrades.push(Upgrade {
+                    name,
+                    old,
+                    new: pkg,
+                }),
+                None => added.push(pkg),
+            }
+        }
+
+        unpaired_removed.extend(removed.into_values().flatten());
+        unpaired_removed.sort();
+
+        (upgrades, added, unpaired_removed)
+    }
+
+    fn size(&self, pkg: &Package) -> Option<u64> {
+        self.sizes?.get(&pkg.name).copied()
+    }
+
+    fn format_size(&self, pkg: &Package) -> String {
+        self.size(pkg)
+            .map(format_size)
+            .unwrap_or_else(|| "unknown".to_owned())
+    }
+
+    fn write_packages(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        heading: &str,
+        packages: &[&Package],
+    ) -> fmt::Result {
+        if packages.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(f, "\n#### {heading}\n")?;
+
+        match self.sizes {
+            Some(_) => {
+                writeln!(f, "| Package | Version | Source | Size |")?;
+                writeln!(f, "| --- | --- | --- | --- |")?;
+            }
+            None => {
+                writeln!(f, "| Package | Version | Source |")?;
+                writeln!(f, "| --- | --- | --- |")?;
+            }
+        }
+
+        for pkg in packages {
+            let (name, version) = pkg.npm_identifier().unwrap_or((&pkg.name, ""));
+
+            write!(
+                f,
+                "| {} | {version} | {} |",
+                link(name, package_url(pkg).as_deref()),
+                pkg.fetcher.source_kind()
+            )?;
+
+            match self.sizes {
+                Some(_) => writeln!(f, " {} |", self.format_size(pkg))?,
+                None => writeln!(f)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_upgrades(&self, f: &mut fmt::Formatter<'_>, upgrades: &[Upgrade]) -> fmt::Result {
+        if upgrades.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(f, "\n#### Upgraded\n")?;
+
+        match self.sizes {
+            Some(_) => {
+                writeln!(f, "| Package | From | To | Size |")?;
+                writeln!(f, "| --- | --- | --- | --- |")?;
+            }
+            None => {
+                writeln!(f, "| Package | From | To |")?;
+                writeln!(f, "| --- | --- | --- |")?;
+            }
+        }
+
+        for upgrade in upgrades {
+            let version = |pkg: &Package| {
+                let version = pkg.npm_identifier().map_or("", |(_, version)| version);
+                link(version, package_url(pkg).as_deref())
+            };
+
+            write!(
+                f,
+                "| {} | {} | {} |",
+                link(upgrade.name, Some(&format!("{NPM_SITE}/{}", upgrade.name))),
+                version(upgrade.old),
+                version(upgrade.new)
+            )?;
+
+            match (self.size(upgrade.old), self.size(upgrade.new)) {
+                (Some(old), Some(new)) => writeln!(
+                    f,
+                    " {} → {} ({}) |",
+                    format_size(old),
+                    format_size(new),
+                    signed_size(new as i64 - old as i64)
+                )?,
+                _ if self.sizes.is_some() => writeln!(
+                    f,
+                    " {} → {} |",
+                    self.format_size(upgrade.old),
+                    self.format_size(upgrade.new)
+                )?,
+                _ => writeln!(f)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_changes(&self, f: &mut fmt::Formatter<'_>, changes: &[Change]) -> fmt::Result {
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(f, "\n#### Changed\n")?;
+        writeln!(f, "| Package | Change |")?;
+        writeln!(f, "| --- | --- |")?;
+
+        for change in changes {
+            writeln!(f, "| `{}` | {} |", change.name, change.describe())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for MarkdownReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "### Dependency changes\n")?;
+
+        if self.diff.is_empty() {
+            return writeln!(f, "No dependencies were added, removed or changed.");
+        }
+
+        let (upgrades, added, removed) = self.upgrades();
+
+        let counts: Vec<String> = [
+            (added.len(), "added"),
+            (removed.len(), "removed"),
+            (upgrades.len(), "upgraded"),
+            (self.diff.changed.len(), "changed"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{count} {what}"))
+        .collect();
+
+        write!(f, "**{}**", counts.join(", "))?;
+
+        let diff = self.diff;
+
+        if diff
+            .added
+            .iter()
+            .chain(&diff.removed)
+            .any(|pkg| self.size(pkg).is_some())
+        {
+            let total = |packages: &[Package]| -> i64 {
+                packages
+                    .iter()
+                    .filter_map(|pkg| self.size(pkg))
+                    .sum::<u64>() as i64
+            };
+
+            write!(
+                f,
+                " ({} unpacked)",
+                signed_size(total(&self.diff.added) - total(&self.diff.removed))
+            )?;
+        }
+
+        writeln!(f)?;
+
+        self.write_packages(f, "Added", &added)?;
+        self.write_packages(f, "Removed", &removed)?;
+        self.write_upgrades(f, &upgrades)?;
+        self.write_changes(f, &self.diff.changed)
+    }
+}
+
+/// The page of an npm package at its version, or where any other package is fetched from
+fn package_url(pkg: &Package) -> Option<String> {
+    match pkg.npm_identifier() {
+        Some((name, version)) => Some(format!("{NPM_SITE}/{name}/v/{version}")),
+        None => pkg.fetcher.url(),
+    }
+}
+
+fn link(text: &str, url: Option<&str>) -> String {
+    match url {
+        Some(url) => format!("[{text}]({url})"),
+        None => format!("`{text}`"),
+    }
+}
+
+/// Format a change in size, with its sign
+fn signed_size(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+
+    format!("{sign}{}", format_size(delta.unsigned_abs()))
+}
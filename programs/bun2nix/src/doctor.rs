@@ -0,0 +1,377 @@
+//! This module holds the diagnosis of the environment `bun2nix` runs in, checking the tools and
+//! configuration it relies on and suggesting fixes for anything which looks wrong
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    process::Command,
+};
+
+#[cfg(feature = "cache")]
+use std::path::Path;
+
+#[cfg(feature = "cache")]
+use crate::cache::Cache;
+use crate::package::Nix;
+#[cfg(feature = "resolver")]
+use crate::registry::Registry;
+
+/// The experimental features `nix flake prefetch` needs
+const PREFETCH_FEATURES: [&str; 2] = ["nix-command", "flakes"];
+
+/// ANSI escape codes for the colors of each status
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+const RED: &str = "31";
+
+/// # Status
+///
+/// How a single check of the environment went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Nothing is wrong
+    Ok,
+
+    /// Something may cause problems, but `bun2nix` can still run
+    Warning,
+
+    /// Something will stop `bun2nix` from working
+    Failure,
+}
+
+/// # Diagnosis
+///
+/// The outcome of a single check of the environment, with a suggested fix if it did not pass
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    /// What was checked
+    pub check: &'static str,
+
+    /// How the check went
+    pub status: Status,
+
+    /// What was found
+    pub detail: String,
+
+    /// How to fix what was found, if anything needs fixing
+    pub fix: Option<String>,
+}
+
+/// # Doctor Report
+///
+/// Every diagnosis made of the environment, in the order they were made
+///
+/// ## Usage
+///```rust
+/// use bun2nix::doctor::{Diagnosis, Report, Status};
+///
+/// let report = Report(vec![
+///     Diagnosis::ok("nix", "nix (Nix) 2.24.9"),
+///     Diagnosis::warning("bun", "not found on the `PATH`", "Install bun to produce lockfiles"),
+/// ]);
+///
+/// assert_eq!(report.count(Status::Warning), 1);
+/// assert_eq!(
+///     report.to_string().lines().collect::<Vec<_>>(),
+///     [
+///         "ok    nix: nix (Nix) 2.24.9",
+///         "warn  bun: not found on the `PATH`",
+///         "        fix: Install bun to produce lockfiles",
+///     ]
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Report(pub Vec<Diagnosis>);
+
+impl Diagnosis {
+    /// # Passing Diagnosis
+    ///
+    /// A check which found nothing wrong
+    pub fn ok(check: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    /// # Warning Diagnosis
+    ///
+    /// A check which found something that may cause problems, and how to fix it
+    pub fn warning(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: Status::Warning,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    /// # Failed Diagnosis
+    ///
+    /// A check which found something that will stop `bun2nix` working, and how to fix it
+    pub fn failure(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: Status::Failure,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl Report {
+    /// # Count
+    ///
+    /// The number of diagnoses with the given status
+    pub fn count(&self, status: Status) -> usize {
+        self.0
+            .iter()
+            .filter(|diagnosis| diagnosis.status == status)
+            .count()
+    }
+
+    /// # Render Report
+    ///
+    /// Render the report, colored with ANSI escape codes if requested
+    pub fn render(&self, color: bool) -> String {
+        let paint = |text: &str, code: &str| match color {
+            true => format!("\x1b[{code}m{text}\x1b[0m"),
+            false => text.to_owned(),
+        };
+
+        let mut lines = Vec::new();
+
+        for diagnosis in &self.0 {
+            let status = match diagnosis.status {
+                Status::Ok => paint("ok  ", GREEN),
+                Status::Warning => paint("warn", YELLOW),
+                Status::Failure => paint("fail", RED),
+            };
+
+            lines.push(format!(
+                "{status}  {}: {}",
+                diagnosis.check, diagnosis.detail
+            ));
+
+            if let Some(fix) = &diagnosis.fix {
+                lines.push(format!("        fix: {fix}"));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+/// # Diagnose Nix
+///
+/// Check that nix can be run, and that its configuration suits prefetching packages and
+/// building the expressions `bun2nix` generates
+pub fn diagnose_nix(nix: &Nix) -> Vec<Diagnosis> {
+    let Some(version) = version_of(Command::new(nix.bin()).arg("--version")) else {
+        return vec![Diagnosis::failure(
+            "nix",
+            format!("`{}` could not be run", nix.bin().display()),
+            "Install nix from https://nixos.org/download, or point `--nix-bin` at it",
+        )];
+    };
+
+    let mut diagnoses = vec![Diagnosis::ok("nix", version)];
+
+    // `nix show-config` was renamed to `nix config show`, and older versions only have the former
+    let config = ["config show", "show-config"].into_iter().find_map(|args| {
+        let output = Command::new(nix.bin())
+            .args(args.split(' '))
+            .args(["--extra-experimental-features", "nix-command"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    });
+
+    match config {
+        Some(config) => diagnoses.extend(diagnose_nix_config(&parse_nix_config(&config))),
+        None => diagnoses.push(Diagnosis::warning(
+            "nix config",
+            "could not be read",
+            "Check that `nix config show` runs",
+        )),
+    }
+
+    diagnoses
+}
+
+/// # Parse Nix Config
+///
+/// Parse the `key = value` lines printed by `nix config show` into a map
+///
+/// ## Usage
+///```rust
+/// use bun2nix::doctor::parse_nix_config;
+///
+/// let config = parse_nix_config("sandbox = true\nsubstituters = https://cache.nixos.org/\n");
+///
+/// assert_eq!(config["sandbox"], "true");
+/// assert_eq!(config["substituters"], "https://cache.nixos.org/");
+/// ```
+pub fn parse_nix_config(config: &str) -> HashMap<String, String> {
+    config
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
+
+/// # Diagnose Nix Config
+///
+/// Check the experimental features, sandboxing and substituters of a nix configuration
+///
+/// ## Usage
+///```rust
+/// use bun2nix::doctor::{Status, diagnose_nix_config, parse_nix_config};
+///
+/// let config = parse_nix_config(
+///     "experimental-features = nix-command\nsandbox = false\nsubstituters = https://cache.nixos.org/\n",
+/// );
+///
+/// let statuses: Vec<_> = diagnose_nix_config(&config)
+///     .into_iter()
+///     .map(|diagnosis| (diagnosis.check, diagnosis.status))
+///     .collect();
+///
+/// assert_eq!(
+///     statuses,
+///     [
+///         ("experimental features", Status::Warning),
+///         ("sandbox", Status::Warning),
+///         ("substituters", Status::Ok),
+///     ]
+/// );
+/// ```
+pub fn diagnose_nix_config(config: &HashMap<String, String>) -> Vec<Diagnosis> {
+    let value = |key: &str| config.get(key).map(String::as_str).unwrap_or_default();
+
+    let features: Vec<&str> = value("experimental-features").split_whitespace().collect();
+    let missing: Vec<&str> = PREFETCH_FEATURES
+        .into_iter()
+        .filter(|feature| !features.contains(feature))
+        .collect();
+
+    let features = match missing.is_empty() {
+        true => Diagnosis::ok("experimental features", features.join(" ")),
+        false => Diagnosis::warning(
+            "experimental features",
+            format!("`{}` not enabled", missing.join("` and `")),
+            format!(
+                "Add `experimental-features = {}` to `nix.conf`, otherwise packages are prefetched with the slower `nix-prefetch-url`",
+                PREFETCH_FEATURES.join(" ")
+            ),
+        ),
+    };
+
+    let sandbox = match value("sandbox") {
+        "true" => Diagnosis::ok("sandbox", "enabled"),
+        other => Diagnosis::warning(
+            "sandbox",
+            format!("`{other}`"),
+            "Set `sandbox = true` in `nix.conf` so builds match those on other machines",
+        ),
+    };
+
+    let substituters = match value("substituters") {
+        "" => Diagnosis::warning(
+            "substituters",
+            "none configured",
+            "Add `substituters = https://cache.nixos.org/` to `nix.conf` to avoid building bun and nixpkgs from source",
+        ),
+        substituters => Diagnosis::ok("substituters", substituters),
+    };
+
+    vec![features, sandbox, substituters]
+}
+
+/// # Diagnose Bun
+///
+/// Check that bun can be run, which is needed to produce the lockfile
+pub fn diagnose_bun() -> Diagnosis {
+    match version_of(Command::new("bun").arg("--version")) {
+        Some(version) => Diagnosis::ok("bun", version),
+        None => Diagnosis::warning(
+            "bun",
+            "not found on the `PATH`",
+            "Install bun from https://bun.sh to produce and update `bun.lock`",
+        ),
+    }
+}
+
+/// # Diagnose Cache
+///
+/// Check that the prefetch cache at the given path can be opened and written to
+#[cfg(feature = "cache")]
+pub fn diagnose_cache(path: Option<&Path>) -> Diagnosis {
+    let fix = "Set `$BUN2NIX_CACHE_DIR` or pass `--cache-file` to use a writable location, or `--no-cache` to skip the cache";
+
+    let Some(path) = path else {
+        return Diagnosis::warning("cache", "no location could be found", fix);
+    };
+
+    match Cache::open(path) {
+        Ok(cache) if cache.is_read_only() => {
+            Diagnosis::failure("cache", format!("`{}` is read only", path.display()), fix)
+        }
+        Ok(_) => Diagnosis::ok("cache", format!("`{}` is writable", path.display())),
+        Err(err) => Diagnosis::failure(
+            "cache",
+            format!("`{}` could not be opened: {err}", path.display()),
+            fix,
+        ),
+    }
+}
+
+/// # Diagnose Registry
+///
+/// Check that the registry can be reached, which any response from it shows
+#[cfg(feature = "resolver")]
+pub fn diagnose_registry(registry: &Registry) -> Diagnosis {
+    let reached = crate::registry::http_client()
+        .ok()
+        .map(|client| client.get(registry.url()).send());
+
+    match reached {
+        Some(Ok(response)) => Diagnosis::ok(
+            "registry",
+            format!("{} responded with {}", registry.url(), response.status()),
+        ),
+        Some(Err(err)) => Diagnosis::failure(
+            "registry",
+            format!("{} could not be reached: {err}", registry.url()),
+            "Check your network connection and proxy settings, or pass `--registry` to use a mirror",
+        ),
+        None => Diagnosis::failure(
+            "registry",
+            "no HTTP client could be created",
+            "Check the TLS configuration of your system",
+        ),
+    }
+}
+
+/// The first line printed by a `--version` command, if it runs successfully
+fn version_of(command: &mut Command) -> Option<String> {
+    let output = command
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_owned())
+}
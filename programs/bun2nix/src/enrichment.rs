@@ -0,0 +1,432 @@
+//! This module holds enrichment of a package set with metadata from the registry which is not
+//! present in the bun lockfile, such as deprecation notices and package sizes
+
+use std::{collections::BTreeMap, fmt, sync::Mutex};
+
+use log::warn;
+use rayon::{ThreadPoolBuilder, prelude::*};
+use semver::Version;
+
+use crate::{
+    Package,
+    error::{Error, Result},
+    nix_expression::Annotations,
+    registry::{Registry, VersionMetadata},
+    resolver::VersionRange,
+    trusted_dependencies::TrustedDependencies,
+};
+
+pub use crate::summary::{format_size, parse_size};
+
+/// # Registry Enrichment
+///
+/// Metadata collected from the registry for the npm packages in a package set
+#[derive(Debug, Default, Clone)]
+pub struct Enrichment {
+    /// Deprecation messages, keyed by the name of the deprecated package
+    pub deprecations: BTreeMap<String, String>,
+
+    /// Unpacked sizes in bytes, keyed by package name
+    pub sizes: BTreeMap<String, u64>,
+
+    /// The names of the packages with install scripts, keyed by package
+    pub install_scripts: BTreeMap<String, String>,
+
+    /// The version ranges of the engines each package runs on, keyed by package
+    pub engines: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// The number of npm packages which were looked up
+    pub looked_up: usize,
+}
+
+/// # Engine Versions
+///
+/// The versions of the engines which packages are run with by the generated derivation, to
+/// check the `engines` of each package against. Engines whose version is unknown are not
+/// checked.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EngineVersions {
+    /// The version of bun
+    pub bun: Option<Version>,
+
+    /// The version of node, which is the version bun is compatible with unless a real node is
+    /// used
+    pub node: Option<Version>,
+}
+
+/// # Engine Mismatch
+///
+/// A package whose `engines` rule out the version of an engine it will be run with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineMismatch<'a> {
+    /// The package with the mismatched engine
+    pub package: &'a str,
+
+    /// The engine, such as `node` or `bun`
+    pub engine: &'a str,
+
+    /// The version range the package requires
+    pub range: &'a str,
+
+    /// The version it will be run with
+    pub version: &'a Version,
+}
+
+/// # Size Report
+///
+/// The largest packages of a package set by unpacked size, created with
+/// [`Enrichment::size_report`] and rendered with its `Display` implementation
+#[derive(Debug, Clone, Copy)]
+pub struct SizeReport<'a> {
+    enrichment: &'a Enrichment,
+    count: usize,
+}
+
+impl Enrichment {
+    /// # Fetch Enrichment
+    ///
+    /// Look up the registry metadata for every npm package in the set, running up to `jobs`
+    /// requests at once. Packages whose metadata cannot be fetched are skipped with a warning.
+    pub fn fetch(registry: &Registry, packages: &[Package], jobs: usize) -> Result<Self> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(jobs.max(1))
+            .build()
+            .map_err(Error::ThreadPool)?;
+
+        let enrichment = Mutex::new(Self::default());
+
+        pool.install(|| {
+            packages.par_iter().for_each(|pkg| {
+                let Some((name, version)) = pkg.npm_identifier() else {
+                    return;
+                };
+
+                enrichment.lock().unwrap().looked_up += 1;
+
+                match registry.fetch_version(name, version) {
+                    Ok(metadata) => enrichment.lock().unwrap().add(pkg, metadata),
+                    Err(err) => warn!("Failed to look up `{}` in the registry: {err}", pkg.name),
+                }
+            })
+        });
+
+        Ok(enrichment.into_inner().unwrap())
+    }
+
+    fn add(&mut self, pkg: &Package, metadata: VersionMetadata) {
+        if let Some(message) = metadata.deprecated {
+            self.deprecations.insert(pkg.name.clone(), message);
+        }
+
+        if let Some(size) = metadata.dist.unpacked_size {
+            self.sizes.insert(pkg.name.clone(), size);
+        }
+
+        if let Some((name, _)) = pkg.npm_identifier().filter(|_| metadata.has_install_script) {
+            self.install_scripts
+                .insert(pkg.name.clone(), name.to_owned());
+        }
+
+        if !metadata.engines.is_empty() {
+            self.engines
+                .insert(pkg.name.clone(), metadata.engines.into_iter().collect());
+        }
+    }
+
+    /// # Total Size
+    ///
+    /// The sum of the unpacked sizes of all packages whose size is known, in bytes
+    pub fn total_size(&self) -> u64 {
+        self.sizes.values().sum()
+    }
+
+    /// # Largest Packages
+    ///
+    /// The `count` packages with the largest unpacked sizes, largest first
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::enrichment::Enrichment;
+    ///
+    /// let mut enrichment = Enrichment::default();
+    ///
+    /// for (pkg, size) in [("is-odd@3.0.1", 4_000), ("typescript@5.8.3", 22_000_000), ("esbuild@0.25.0", 130_000)] {
+    ///     enrichment.sizes.insert(pkg.to_owned(), size);
+    /// }
+    ///
+    /// assert_eq!(
+    ///     enrichment.largest(2),
+    ///     [("typescript@5.8.3", 22_000_000), ("esbuild@0.25.0", 130_000)]
+    /// );
+    ///
+    /// let report = enrichment.size_report(2).to_string();
+    ///
+    /// assert!(report.contains("typescript@5.8.3"));
+    /// assert!(!report.contains("is-odd@3.0.1"));
+    /// ```
+    pub fn largest(&self, count: usize) -> Vec<(&str, u64)> {
+        let mut sizes: Vec<(&str, u64)> = self
+            .sizes
+            .iter()
+            .map(|(name, size)| (name.as_str(), *size))
+            .collect();
+
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        sizes.truncate(count);
+
+        sizes
+    }
+
+    /// # Size Report
+    ///
+    /// A report of the `count` largest packages and their share of the total unpacked size,
+    /// for finding the dependencies which contribute most to the size of `node_modules`
+    pub fn size_report(&self, count: usize) -> SizeReport<'_> {
+        SizeReport {
+            enrichment: self,
+            count,
+        }
+    }
+
+    /// # Check Size Budget
+    ///
+    /// Fail if the total unpacked size of the package set exceeds the budget in bytes
+    pub fn check_size_budget(&self, budget: u64) -> Result<()> {
+        let total = self.total_size();
+
+        if total > budget {
+            return Err(Error::SizeBudgetExceeded {
+                total: format_size(total),
+                budget: format_size(budget),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// # Untrusted Scripts
+    ///
+    /// The packages with install scripts which bun will not run, because they are not trusted
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{enrichment::Enrichment, trusted_dependencies::TrustedDependencies};
+    ///
+    /// let mut enrichment = Enrichment::default();
+    ///
+    /// for (pkg, name) in [("esbuild@0.25.0", "esbuild"), ("my-addon@1.0.0", "my-addon")] {
+    ///     enrichment.install_scripts.insert(pkg.to_owned(), name.to_owned());
+    /// }
+    ///
+    /// assert_eq!(
+    ///     enrichment.untrusted_scripts(&TrustedDependencies::default()),
+    ///     ["my-addon@1.0.0"]
+    /// );
+    /// ```
+    pub fn untrusted_scripts(&self, trusted: &TrustedDependencies) -> Vec<&str> {
+        self.install_scripts
+            .iter()
+            .filter(|(_, name)| !trusted.is_trusted(name))
+            .map(|(pkg, _)| pkg.as_str())
+            .collect()
+    }
+
+    /// # Check Scripts
+    ///
+    /// Warn about every package with install scripts which will not run because it is not
+    /// trusted, failing instead if `strict` is set
+    pub fn check_scripts(&self, trusted: &TrustedDependencies, strict: bool) -> Result<()> {
+        let untrusted = self.untrusted_scripts(trusted);
+
+        if strict && !untrusted.is_empty() {
+            return Err(Error::UntrustedScripts(
+                untrusted.into_iter().map(str::to_owned).collect(),
+            ));
+        }
+
+        for pkg in untrusted {
+            warn!(
+                "`{pkg}` has install scripts which bun will not run, as `{}` is not in `trustedDependencies` - add it to `trustedDependencies` in your `package.json` if it fails at runtime",
+                self.install_scripts[pkg]
+            );
+        }
+
+        Ok(())
+    }
+
+    /// # Engine Mismatches
+    ///
+    /// Every package whose `engines` rule out the version of bun or node it will be run with.
+    /// Prereleases are checked as the release they lead up to, and ranges which cannot be
+    /// parsed are skipped.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::enrichment::{Enrichment, EngineVersions};
+    /// use semver::Version;
+    ///
+    /// let mut enrichment = Enrichment::default();
+    ///
+    /// for (pkg, engine, range) in [
+    ///     ("vite@7.0.0", "node", "^20.19.0 || >=22.12.0"),
+    ///     ("elysia@1.3.0", "bun", ">=1.2.0"),
+    ///     ("legacy@1.0.0", "node", "<10"),
+    /// ] {
+    ///     enrichment
+    ///         .engines
+    ///         .entry(pkg.to_owned())
+    ///         .or_default()
+    ///         .insert(engine.to_owned(), range.to_owned());
+    /// }
+    ///
+    /// let versions = EngineVersions {
+    ///     bun: Some(Version::parse("1.1.38").unwrap()),
+    ///     node: Some(Version::parse("22.6.0").unwrap()),
+    /// };
+    ///
+    /// let mismatches: Vec<_> = enrichment
+    ///     .engine_mismatches(&versions)
+    ///     .into_iter()
+    ///     .map(|mismatch| mismatch.package)
+    ///     .collect();
+    ///
+    /// assert_eq!(mismatches, ["elysia@1.3.0", "legacy@1.0.0", "vite@7.0.0"]);
+    /// ```
+    pub fn engine_mismatches<'a>(
+        &'a self,
+        versions: &'a EngineVersions,
+    ) -> Vec<EngineMismatch<'a>> {
+        let mut mismatches = Vec::new();
+
+        for (package, engines) in &self.engines {
+            for (engine, range) in engines {
+                let version = match engine.as_str() {
+                    "bun" => versions.bun.as_ref(),
+                    "node" => versions.node.as_ref(),
+                    _ => None,
+                };
+
+                let (Some(version), Ok(parsed)) = (version, range.parse::<VersionRange>()) else {
+                    continue;
+                };
+
+                let release = Version::new(version.major, version.minor, version.patch);
+
+                if !parsed.matches(&release) {
+                    mismatches.push(EngineMismatch {
+                        package,
+                        engine,
+                        range,
+                        version,
+                    });
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    /// # Check Engines
+    ///
+    /// Warn about every package whose `engines` rule out the version of bun or node it will be
+    /// run with
+    pub fn check_engines(&self, versions: &EngineVersions) {
+        for mismatch in self.engine_mismatches(versions) {
+            warn!("{mismatch}");
+        }
+    }
+
+    /// # Annotations
+    ///
+    /// Comments noting each deprecated package, to be rendered into the nix expression
+    pub fn annotations(&self) -> Annotations {
+        self.deprecations
+            .iter()
+            .map(|(name, message)| (name.clone(), vec![format!("DEPRECATED: {message}")]))
+            .collect()
+    }
+}
+
+impl fmt::Display for Enrichment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Estimated unpacked size: {} across {} package(s)",
+            format_size(self.total_size()),
+            self.sizes.len()
+        )?;
+
+        match self.looked_up - self.sizes.len() {
+            0 => writeln!(f)?,
+            unknown => writeln!(f, " ({unknown} of unknown size)")?,
+        }
+
+        if self.deprecations.is_empty() {
+            return writeln!(f, "No deprecated packages");
+        }
+
+        writeln!(f, "{} deprecated package(s):", self.deprecations.len())?;
+
+        for (name, message) in &self.deprecations {
+            writeln!(f, "  {name}: {}", message.replace('\n', "\n    "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for EngineMismatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` requires {} `{}`, but will be run with {} {}",
+            self.package, self.engine, self.range, self.engine, self.version
+        )
+    }
+}
+
+impl fmt::Display for SizeReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.enrichment.total_size();
+        let largest = self.enrichment.largest(self.count);
+
+        if largest.is_empty() {
+            return writeln!(f, "No package sizes are known");
+        }
+
+        writeln!(f, "Largest {} package(s) by unpacked size:", largest.len())?;
+
+        let width = largest
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+
+        for (name, size) in &largest {
+            writeln!(
+                f,
+                "  {name:<width$}  {:>10}  {:>5.1}%",
+                format_size(*size),
+                percent(*size, total)
+            )?;
+        }
+
+        let shown: u64 = largest.iter().map(|(_, size)| size).sum();
+
+        writeln!(
+            f,
+            "These account for {} ({:.1}%) of {}",
+            format_size(shown),
+            percent(shown, total),
+            format_size(total)
+        )
+    }
+}
+
+/// The share of a total a size makes up, as a percentage
+fn percent(size: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.0,
+        total => size as f64 * 100.0 / total as f64,
+    }
+}
@@ -22,10 +22,32 @@ Please make sure your bun lockfile is formatted correctly, try deleting it and r
     ParseJsonc(#[from] jsonc_parser::errors::ParseError),
     #[error("Failed to parse lockfile related JSON as rust type: \n{0}")]
     ParseRustType(#[from] serde_json::Error),
+    #[error("Failed to deserialize lockfile: \n{0}")]
+    Deserialize(String),
+    #[cfg(feature = "simd-json")]
+    #[error("Failed to parse lockfile with simd-json: \n{0}")]
+    SimdJson(simd_json::Error),
+    #[error(
+        "Invalid lockfile{}: {message}.
+
+Please make sure your bun lockfile is formatted correctly, try deleting it and running `bun install` again to produce a fresh one",
+        lockfile_location(path)
+    )]
+    InvalidLockfile { path: String, message: String },
     #[error(
         "Failed to parse empty lockfile, make sure you are providing a file with text contents"
     )]
     NoJsoncValue,
+    #[error(
+        "The lockfile (version {lockfile_version}) may not be compatible with `packageManager` '{package_manager}': {reason}.
+
+Check that the output is correct, or update bun2nix to a version which supports this version of bun"
+    )]
+    UnvalidatedBunVersion {
+        package_manager: String,
+        lockfile_version: u8,
+        reason: String,
+    },
     #[error("Missing @ for package name and version declaration.
 
 Make sure all versions in your bun lockfile are formatted properly or try deleting it and running `bun install` to produce a fresh one"
@@ -48,6 +70,8 @@ Consider updating your local package or contributing to `bun2nix` if this versio
     MissingGitRef,
     #[error("A github url was formatted incorrectly")]
     ImproperGithubUrl,
+    #[error("No tag or branch was found for the git ref {0}")]
+    UnknownGitRef(String),
     #[error("Unexpected package entry length: \n{0}")]
     UnexpectedPackageEntryLength(usize),
     #[error("Failed to render template: '\n{0}'")]
@@ -64,6 +88,201 @@ Please switch to the native cli instead to use this dependency.
 "
     )]
     UnsupportedWASMCliAction(String),
+    #[cfg(feature = "resolver")]
+    #[error("Request to the package registry failed: \n{0}")]
+    RegistryRequest(reqwest::Error),
+    #[error("Failed to parse version range: '{0}'")]
+    InvalidVersionRange(String),
+    #[error("No published version satisfies the dependency `{0}`")]
+    UnresolvableDependency(String),
+    #[error(
+        "The dependency `{0}` is not hosted on a package registry and cannot be resolved without a lockfile.
+
+Try running `bun install` to produce a lockfile instead"
+    )]
+    UnsupportedResolverSpecifier(String),
+    #[error("The registry did not provide an integrity hash for `{0}`")]
+    MissingIntegrity(String),
+    #[error(
+        "Failed to parse nix expression: {0}.
+
+Make sure the file was generated by `bun2nix`, or by `node2nix` when importing one"
+    )]
+    InvalidNixExpression(String),
+    #[error(
+        "The following packages were changed differently on both sides of the merge: {}.
+
+Resolve the conflicts in `bun.lock` and regenerate the file with `bun2nix -o bun.nix`",
+        .0.join(", ")
+    )]
+    MergeConflict(Vec<String>),
+    #[error(
+        "Invalid size '{0}', expected a number of bytes optionally followed by a unit such as `500M` or `2GiB`"
+    )]
+    InvalidSize(String),
+    #[error(
+        "Invalid template variable '{0}', expected a `key=value` pair such as `banner=Acme Corp`"
+    )]
+    InvalidTemplateVariable(String),
+    #[error(
+        "`bun.nix` does not use the variable(s) {}, only {} - pass `--template` to render them with a custom template",
+        .0.iter().map(|key| format!("`{key}`")).collect::<Vec<_>>().join(", "),
+        crate::nix_expression::VARIABLES.map(|key| format!("`{key}`")).join(", ")
+    )]
+    UnusedTemplateVariables(Vec<String>),
+    #[error(
+        "The estimated unpacked size of all packages ({total}) exceeds the budget of {budget}.
+
+Check for unexpectedly large dependencies, or raise the budget with `--max-total-size`"
+    )]
+    SizeBudgetExceeded { total: String, budget: String },
+    #[error("Invalid sri hash: '{0}'")]
+    InvalidSriHash(String),
+    #[error(
+        "The following tarballs could not be downloaded from the registry or any mirror: {}",
+        .0.join(", ")
+    )]
+    TarballUnavailable(Vec<String>),
+    #[error("Invalid fetch command '{0}', it must include both `{{url}}` and `{{path}}`")]
+    InvalidFetchCommand(String),
+    #[error("{0} package(s) failed verification")]
+    VerificationFailed(usize),
+    #[error("Failed to run `nix-instantiate` to validate the output: \n{0}")]
+    ValidationFailed(std::io::Error),
+    #[error("Nix rejected the generated expression: \n\n{0}")]
+    RejectedNixExpression(String),
+    #[error("Failed to build `node_modules` from the generated expression: \n\n{0}")]
+    BuildCheckFailed(String),
+    #[error(
+        "The closure of `node_modules` ({size}) exceeds the budget of {budget}.
+
+Check for unexpectedly large dependencies, or raise the budget with `--max-closure-size`"
+    )]
+    ClosureBudgetExceeded { size: String, budget: String },
+    #[error("Interrupted before finishing")]
+    Interrupted,
+    #[error("{0} problem(s) found which will stop `bun2nix` from working")]
+    DoctorFailed(usize),
+    #[error("`{0}` is out of date with the lockfile, regenerate it with `bun2nix -o {0}`")]
+    OutOfDate(String),
+    #[error(
+        "{0} package(s) in `node_modules` differ from `bun.nix`, run `bun install` and regenerate it"
+    )]
+    NodeModulesDrift(usize),
+    #[error("No package named `{0}` is in the lockfile")]
+    PackageNotInLockfile(String),
+    #[error("No workspace at `{0}` is in the lockfile, expected one of: {1}")]
+    UnknownWorkspace(String, String),
+    #[error(
+        "The lockfile is out of date with the project's `package.json` files, run `bun install` to update it:
+{}",
+        .0.iter().map(|change| format!("  - {change}")).collect::<Vec<_>>().join("\n")
+    )]
+    FrozenLockfile(Vec<String>),
+    #[error(
+        "The following packages have install scripts which bun will not run, add them to `trustedDependencies` in your `package.json`: {}",
+        .0.join(", ")
+    )]
+    UntrustedScripts(Vec<String>),
+    #[error("Invalid bunfig.toml: {0}")]
+    InvalidBunfig(String),
+    #[error("Invalid policy file: {0}")]
+    InvalidPolicy(String),
+    #[error(
+        "The following packages are denied by the policy:
+{}",
+        .0.iter().map(|violation| format!("  - {violation}")).collect::<Vec<_>>().join("\n")
+    )]
+    PolicyViolations(Vec<String>),
+    #[error(
+        "The following binaries are provided by more than one package, pass `--bin-conflicts` to choose between them:
+{}",
+        .0.iter().map(|collision| format!("  - {collision}")).collect::<Vec<_>>().join("\n")
+    )]
+    BinaryCollisions(Vec<String>),
+    #[cfg(feature = "template")]
+    #[error(
+        "The template has the following problems:
+{}",
+        .0.iter().map(|problem| format!("  - {problem}")).collect::<Vec<_>>().join("\n")
+    )]
+    TemplateProblems(Vec<String>),
+    #[cfg(feature = "plugins")]
+    #[error("The resolver plugin `{plugin}` failed: {reason}")]
+    Plugin { plugin: String, reason: String },
+    #[error(
+        "The following paths differ only by case, and will overwrite each other on the case-insensitive filesystem of macOS:
+{}",
+        .0.iter().map(|collision| format!("  - {collision}")).collect::<Vec<_>>().join("\n")
+    )]
+    CaseCollisions(Vec<String>),
+    #[error(
+        "Invalid binary conflict strategy '{0}', expected `error`, `first-wins` or `prefer:<package>`"
+    )]
+    InvalidBinStrategy(String),
+    #[error("{0} deprecated package(s) found, pass `--allow-deprecated` to ignore them")]
+    DeprecatedPackages(usize),
+    #[error("Invalid libc '{0}', expected `glibc` or `musl`")]
+    InvalidLibc(String),
+    #[error(
+        "Invalid system '{0}', expected a nix system such as `x86_64-linux` or `aarch64-darwin`"
+    )]
+    InvalidSystem(String),
+    #[error("Invalid package.json: {0}")]
+    InvalidPackageJson(String),
+    #[error("`{0}` does not provide any command line tools in its `bin`")]
+    NoBinaries(String),
+    #[cfg(feature = "dev")]
+    #[error("Failed to run the formatter: \n{0}")]
+    FormatterCommandFailed(io::Error),
+    #[cfg(feature = "dev")]
+    #[error("\nThe formatter returned an error: \n\n{0}")]
+    FormatterCommandError(String),
+    #[error("Failed to run git: \n{0}")]
+    GitCommandFailed(io::Error),
+    #[error("\nGit returned an error: \n\n{0}")]
+    GitCommandError(String),
+    #[error("Failed to drive the interactive terminal interface: \n{0}")]
+    TerminalError(io::Error),
+    #[error("Package hash is already known, nothing to prefetch")]
+    NothingToPrefetch,
+    #[error("Failed to start the prefetch worker pool: \n{0}")]
+    ThreadPool(rayon::ThreadPoolBuildError),
+    #[cfg(feature = "cache")]
+    #[error("Failed to access the prefetch cache: \n{0}")]
+    Cache(rusqlite::Error),
+    #[cfg(feature = "cache")]
+    #[error(
+        "Could not work out where to store the prefetch cache, set `$BUN2NIX_CACHE_DIR` or pass `--cache-file`"
+    )]
+    NoCachePath,
+    #[cfg(feature = "cache")]
+    #[error("Failed to compress or decompress a blob in the prefetch cache: \n{0}")]
+    CacheCompression(io::Error),
+    #[cfg(feature = "cache")]
+    #[error("{0} entr(ies) of the prefetch cache are corrupt, pass `--evict` to remove them")]
+    CorruptCache(usize),
+    #[cfg(feature = "archive")]
+    #[error("Failed to read the archive: \n{0}")]
+    ArchiveRead(io::Error),
+    #[cfg(feature = "archive")]
+    #[error(
+        "Could not tell what kind of archive `{0}` is, expected a `.tar`, `.tgz`, `.tar.gz` or `.zip` file"
+    )]
+    UnknownArchiveFormat(String),
+    #[cfg(feature = "archive")]
+    #[error("No `bun.lock` was found inside the archive `{0}`")]
+    NoLockfileInArchive(String),
+    #[error(
+        "The lockfile entry `{id}` uses the `{protocol}` protocol, which {reason}.
+
+Lockfiles migrated from other package managers may still contain their protocols, try running `bun install` to resolve them again"
+    )]
+    UnsupportedProtocol {
+        id: String,
+        protocol: String,
+        reason: &'static str,
+    },
     #[error("IO Error Occurred: `\n{0}`.
 
 Make sure that the bun lockfile path you gave points to a valid path.
@@ -77,6 +296,12 @@ Try `bun2nix -h` for help.
     ReadLockfileError(#[from] io::Error),
 }
 
+impl serde::de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Deserialize(msg.to_string())
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::JsValue;
 
@@ -86,3 +311,11 @@ impl From<Error> for JsValue {
         JsValue::from_str(&err.to_string())
     }
 }
+
+/// Where in the lockfile an error was found, if not at the top level
+fn lockfile_location(path: &str) -> String {
+    match path.is_empty() {
+        true => String::new(),
+        false => format!(" at `{path}`"),
+    }
+}
@@ -0,0 +1,111 @@
+//! A stream of newline delimited JSON events on stderr, for wrappers such as editors and CI bots
+//! to show the progress of a run without parsing the human readable logs
+
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bun2nix::prefetcher::Prefetched;
+use serde::Serialize;
+
+/// Whether events are written rather than human readable logs
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// # Event
+///
+/// Something which happened during a run, written as a single line of JSON tagged by its kind
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'a> {
+    /// Parsing of the lockfile has started
+    ParseStart {
+        /// The lockfile being parsed
+        lock_file: &'a str,
+    },
+
+    /// The hash of a package has been found
+    PackageFetched {
+        /// Where the package is fetched from
+        url: Option<String>,
+
+        /// The kind of source the package is fetched from, such as `npm` or `git`
+        source: &'static str,
+
+        /// The hash of the package
+        hash: Option<&'a str>,
+
+        /// Whether the hash came from the cache rather than being calculated
+        cached: bool,
+    },
+
+    /// A binary is provided by more than one package
+    BinaryCollision {
+        /// The name of the binary
+        name: &'a str,
+
+        /// The package the binary is linked from
+        linked: &'a str,
+
+        /// The packages whose binaries of the same name are not linked
+        shadowed: &'a [String],
+    },
+
+    /// A warning was logged
+    Warning {
+        /// The text of the warning
+        message: String,
+    },
+
+    /// The run has finished
+    Done {
+        /// Whether the run succeeded
+        success: bool,
+
+        /// Why the run failed, if it did
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl<'a> From<&'a Prefetched> for Event<'a> {
+    fn from(prefetched: &'a Prefetched) -> Self {
+        Event::PackageFetched {
+            url: prefetched.fetcher.url(),
+            source: prefetched.fetcher.source_kind(),
+            hash: prefetched.fetcher.sri_hash(),
+            cached: prefetched.cached,
+        }
+    }
+}
+
+/// # Enable Events
+///
+/// Write events to stderr from now on
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// # Events Enabled
+///
+/// Whether events are being written to stderr
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// # Emit Event
+///
+/// Write an event to stderr as a line of JSON, if events are enabled
+pub fn emit(event: Event) {
+    if !enabled() {
+        return;
+    }
+
+    let Ok(line) = serde_json::to_string(&event) else {
+        return;
+    };
+
+    // Lock stderr so lines written from several threads are never interleaved
+    let _ = writeln!(io::stderr().lock(), "{line}");
+}
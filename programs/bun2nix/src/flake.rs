@@ -0,0 +1,73 @@
+//! This module holds rendering of a `flake.nix` for a project which does not have one yet,
+//! building it with `bun2nix.mkDerivation` and providing a development shell
+
+use askama::Template;
+
+use crate::{
+    PackageJson,
+    build_check::{DEFAULT_FLAKE, nix_string},
+    error::Result,
+};
+
+/// # Flake
+///
+/// A `flake.nix` exposing the project as its default package, built from `bun.nix`, along with
+/// a development shell - taken from `devshell.nix` if one is written too, and otherwise
+/// providing bun and bun2nix
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{PackageJson, flake::Flake};
+///
+/// let package_json: PackageJson = r#"{ "name": "my-app", "description": "My \"app\"" }"#
+///     .parse()
+///     .unwrap();
+///
+/// let nix = Flake::new(&package_json, false).render().unwrap();
+///
+/// assert!(nix.contains(r#"description = "My \"app\"";"#));
+/// assert!(nix.contains("bunNix = ./bun.nix;"));
+/// assert!(nix.contains("mkShell"));
+///
+/// let nix = Flake::new(&package_json, true).render().unwrap();
+///
+/// assert!(nix.contains("callPackage ./devshell.nix { }"));
+/// ```
+#[derive(Template)]
+#[template(path = "flake.nix_template")]
+pub struct Flake {
+    /// The description, quoted as a nix string
+    description: String,
+    bun2nix: &'static str,
+    devshell: bool,
+}
+
+impl Flake {
+    /// # New Flake
+    ///
+    /// Create a flake for the project described by the given `package.json`, whose development
+    /// shell is taken from `devshell.nix` if requested
+    pub fn new(package_json: &PackageJson, devshell: bool) -> Self {
+        let description = package_json
+            .description
+            .clone()
+            .or_else(|| package_json.name.clone())
+            .unwrap_or_else(|| "A bun project packaged with bun2nix".to_owned());
+
+        Self {
+            description: nix_string(&description),
+            bun2nix: DEFAULT_FLAKE,
+            devshell,
+        }
+    }
+
+    /// # Render
+    ///
+    /// Render the flake as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
@@ -0,0 +1,179 @@
+//! This module holds rendering of the git dependencies of a project as flake inputs, for
+//! projects which prefer their sources to be locked and updated by `nix flake`
+
+use std::collections::BTreeMap;
+
+use askama::Template;
+
+use crate::{Package, error::Result, package::Fetcher};
+
+/// The length revisions are shortened to in the names of inputs
+const SHORT_REV_LENGTH: usize = 7;
+
+/// # Flake Input
+///
+/// A git dependency declared as a non-flake input of a `flake.nix`, pinned to the revision
+/// from the lockfile
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{flake_inputs::FlakeInput, package::Fetcher};
+///
+/// let github = Fetcher::FetchGitHub {
+///     owner: "colinhacks".to_owned(),
+///     repo: "zod".to_owned(),
+///     rev: "5bfc8f2".to_owned(),
+///     hash: String::new(),
+/// };
+///
+/// let input = FlakeInput::for_fetcher(&github).unwrap();
+///
+/// assert_eq!(input.name, "bun-colinhacks-zod-5bfc8f2");
+/// assert_eq!(input.url, "github:colinhacks/zod/5bfc8f2");
+///
+/// let git = Fetcher::FetchGit {
+///     url: "https://gitlab.com/gitlab-examples/semantic-release-npm.git".to_owned(),
+///     rev: "ee100d81f12ae315a81c2a664979a6cc1bce99a2".to_owned(),
+///     hash: String::new(),
+///     fetch_lfs: false,
+/// };
+///
+/// let input = FlakeInput::for_fetcher(&git).unwrap();
+///
+/// assert_eq!(input.name, "bun-semantic-release-npm-ee100d8");
+/// assert_eq!(
+///     input.url,
+///     "git+https://gitlab.com/gitlab-examples/semantic-release-npm.git?rev=ee100d81f12ae315a81c2a664979a6cc1bce99a2&submodules=1"
+/// );
+///
+/// let tarball = Fetcher::FetchTarball {
+///     url: "https://example.com/pkg.tgz".to_owned(),
+///     hash: String::new(),
+/// };
+///
+/// assert_eq!(FlakeInput::for_fetcher(&tarball), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlakeInput {
+    /// The name of the input, which is a valid nix identifier
+    pub name: String,
+
+    /// The flake reference the input is fetched from
+    pub url: String,
+}
+
+impl FlakeInput {
+    /// # Flake Input For Fetcher
+    ///
+    /// The input fetching the same source as a git or GitHub fetcher, or `None` for any other
+    /// fetcher
+    pub fn for_fetcher(fetcher: &Fetcher) -> Option<Self> {
+        match fetcher {
+            Fetcher::FetchGitHub {
+                owner, repo, rev, ..
+            } => Some(Self {
+                name: input_name(&format!("{owner}-{repo}"), rev),
+                url: format!("github:{owner}/{repo}/{rev}"),
+            }),
+            Fetcher::FetchGit {
+                url,
+                rev,
+                fetch_lfs,
+                ..
+            } => {
+                let repo = url
+                    .trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(url)
+                    .trim_end_matches(".git");
+                let separator = match url.contains('?') {
+                    true => '&',
+                    false => '?',
+                };
+
+                let lfs = match fetch_lfs {
+                    true => "&lfs=1",
+                    false => "",
+                };
+
+                Some(Self {
+                    name: input_name(repo, rev),
+                    url: format!("git+{url}{separator}rev={rev}&submodules=1{lfs}"),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// # Flake Inputs
+///
+/// A `flake-inputs.nix` listing an input for every git dependency of a project, to be copied
+/// into the `inputs` of its `flake.nix`
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{Package, flake_inputs::FlakeInputs, package::Fetcher};
+///
+/// let zod = Package::new(
+///     "github:colinhacks-zod-5bfc8f2".to_owned(),
+///     Fetcher::FetchGitHub {
+///         owner: "colinhacks".to_owned(),
+///         repo: "zod".to_owned(),
+///         rev: "5bfc8f2".to_owned(),
+///         hash: String::new(),
+///     },
+/// );
+///
+/// let nix = FlakeInputs::new(&[zod]).render().unwrap();
+///
+/// assert!(nix.contains("bun-colinhacks-zod-5bfc8f2 = {"));
+/// assert!(nix.contains(r#"url = "github:colinhacks/zod/5bfc8f2";"#));
+/// ```
+#[derive(Template)]
+#[template(path = "flake-inputs.nix_template")]
+pub struct FlakeInputs {
+    inputs: BTreeMap<String, String>,
+}
+
+impl FlakeInputs {
+    /// # New Flake Inputs
+    ///
+    /// Collect the inputs for the git dependencies of a package set
+    pub fn new(packages: &[Package]) -> Self {
+        Self {
+            inputs: packages
+                .iter()
+                .filter_map(|pkg| FlakeInput::for_fetcher(&pkg.fetcher))
+                .map(|input| (input.name, input.url))
+                .collect(),
+        }
+    }
+
+    /// # Render
+    ///
+    /// Render the inputs as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
+
+/// The name of an input for a repository at a given revision, with every character which
+/// cannot be used in a nix identifier replaced
+fn input_name(repo: &str, rev: &str) -> String {
+    let rev: String = rev.chars().take(SHORT_REV_LENGTH).collect();
+
+    format!("bun-{repo}-{rev}")
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '-',
+            },
+        )
+        .collect()
+}
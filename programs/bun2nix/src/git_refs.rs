@@ -0,0 +1,160 @@
+//! This module holds the pinning of git dependencies declared by a tag or branch to the commit
+//! it points at, as the commit a ref points at can change between generations
+
+use std::{
+    fmt::{self, Display},
+    process::Command,
+};
+
+use crate::{
+    error::{Error, Result},
+    interrupt,
+};
+
+/// # Is Commit
+///
+/// Whether a git revision is a commit hash, which may be abbreviated, rather than the name of a
+/// tag or branch
+///
+/// ## Usage
+///```rust
+/// use bun2nix::git_refs::is_commit;
+///
+/// assert!(is_commit("ee100d81f12ae315a81c2a664979a6cc1bce99a2"));
+/// assert!(is_commit("5bfc8f2"));
+/// assert!(!is_commit("v3.22.4"));
+/// assert!(!is_commit("main"));
+/// ```
+pub fn is_commit(rev: &str) -> bool {
+    (7..=40).contains(&rev.len()) && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// # Ref Kind
+///
+/// Whether a git ref is a tag, which is expected to stay put, or a branch, which moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefKind {
+    /// A tag, under `refs/tags`
+    Tag,
+
+    /// A branch, under `refs/heads`, or the `HEAD` of the repository
+    Branch,
+}
+
+/// # Git Ref
+///
+/// A tag or branch of a git repository and the commit it points at
+///
+/// ## Usage
+///```rust
+/// use bun2nix::git_refs::{GitRef, RefKind};
+///
+/// let ls_remote = "\
+/// 1111111111111111111111111111111111111111\trefs/tags/v3.22.4
+/// 2222222222222222222222222222222222222222\trefs/tags/v3.22.4^{}
+/// 3333333333333333333333333333333333333333\trefs/heads/v3.22.4-fixes
+/// ";
+///
+/// // Annotated tags are followed to the commit they point at
+/// let tag = GitRef::parse_ls_remote("v3.22.4", ls_remote).unwrap();
+///
+/// assert_eq!(tag.kind, RefKind::Tag);
+/// assert_eq!(tag.commit, "2222222222222222222222222222222222222222");
+/// assert_eq!(tag.to_string(), "Pinned from tag `v3.22.4`");
+///
+/// let branch = GitRef::parse_ls_remote(
+///     "main",
+///     "4444444444444444444444444444444444444444\trefs/heads/main\n",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(branch.kind, RefKind::Branch);
+/// assert_eq!(
+///     branch.to_string(),
+///     "Pinned from branch `main`, regenerate to pick up new commits"
+/// );
+///
+/// assert_eq!(GitRef::parse_ls_remote("missing", ls_remote), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRef {
+    /// The name of the ref, as written after the `#` of the dependency
+    pub name: String,
+
+    /// Whether the ref is a tag or a branch
+    pub kind: RefKind,
+
+    /// The commit the ref points at
+    pub commit: String,
+}
+
+impl GitRef {
+    /// # Resolve Git Ref
+    ///
+    /// Find the commit a tag or branch of a remote repository points at, with `git ls-remote`
+    pub fn resolve(url: &str, name: &str) -> Result<Self> {
+        #[cfg(target_arch = "wasm32")]
+        return Err(Error::UnsupportedWASMCliAction(url.to_owned()));
+
+        let output = interrupt::run(Command::new("git").env("GIT_TERMINAL_PROMPT", "0").args([
+            "ls-remote",
+            url,
+            name,
+        ]))?;
+
+        if !output.status.success() {
+            return Err(Error::FetchingError(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+
+        Self::parse_ls_remote(name, &String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| Error::UnknownGitRef(format!("`{name}` of `{url}`")))
+    }
+
+    /// # Parse `git ls-remote` Output
+    ///
+    /// Find the ref of the given name in the `<commit>\t<ref>` lines printed by `git ls-remote`,
+    /// preferring tags to branches and following annotated tags to their commit
+    pub fn parse_ls_remote(name: &str, output: &str) -> Option<Self> {
+        let refs: Vec<(&str, &str)> = output
+            .lines()
+            .filter_map(|line| line.trim().split_once('\t'))
+            .collect();
+
+        let find = |full_name: &str| {
+            refs.iter()
+                .find(|(_, name)| *name == full_name)
+                .map(|(commit, _)| (*commit).to_owned())
+        };
+
+        let tag = format!("refs/tags/{name}");
+        let candidates = [
+            (format!("{tag}^{{}}"), RefKind::Tag),
+            (tag, RefKind::Tag),
+            (format!("refs/heads/{name}"), RefKind::Branch),
+            (name.to_owned(), RefKind::Branch),
+        ];
+
+        candidates.into_iter().find_map(|(full_name, kind)| {
+            Some(Self {
+                name: name.to_owned(),
+                kind,
+                commit: find(&full_name)?,
+            })
+        })
+    }
+}
+
+impl Display for GitRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            RefKind::Tag => write!(f, "Pinned from tag `{}`", self.name),
+            RefKind::Branch => write!(
+                f,
+                "Pinned from branch `{}`, regenerate to pick up new commits",
+                self.name
+            ),
+        }
+    }
+}
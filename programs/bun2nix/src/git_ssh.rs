@@ -0,0 +1,134 @@
+//! This module holds the handling of git dependencies fetched over SSH, such as those on private
+//! GitHub or GitLab hosts, which are cloned with the user's SSH agent during generation but
+//! cannot be fetched the same way from inside the Nix sandbox
+
+use crate::{Package, package::Fetcher};
+
+/// # Is SSH Url
+///
+/// Whether a git url is fetched over SSH, either as `ssh://[user@]host/path` or in the scp-like
+/// `[user@]host:path` form
+///
+/// ## Usage
+///```rust
+/// use bun2nix::git_ssh::is_ssh_url;
+///
+/// assert!(is_ssh_url("ssh://git@github.com/acme/private.git"));
+/// assert!(is_ssh_url("git@gitlab.com:acme/private.git"));
+/// assert!(!is_ssh_url("https://github.com/acme/public.git"));
+/// assert!(!is_ssh_url("github:acme/public"));
+/// ```
+pub fn is_ssh_url(url: &str) -> bool {
+    split_ssh_url(url).is_some()
+}
+
+/// # HTTPS Mirror
+///
+/// Where git dependencies fetched over SSH are fetched from in the emitted expression, which is
+/// either the same host over HTTPS or a base url the repository paths are appended to
+///
+/// ## Usage
+///```rust
+/// use bun2nix::git_ssh::HttpsMirror;
+///
+/// let same_host = HttpsMirror::new(None);
+///
+/// assert_eq!(
+///     same_host.rewrite("ssh://git@github.com:22/acme/private.git").as_deref(),
+///     Some("https://github.com/acme/private.git")
+/// );
+/// assert_eq!(
+///     same_host.rewrite("git@gitlab.com:acme/private.git").as_deref(),
+///     Some("https://gitlab.com/acme/private.git")
+/// );
+/// assert_eq!(same_host.rewrite("https://github.com/acme/public.git"), None);
+///
+/// let mirror = HttpsMirror::new(Some("https://git.internal.example/mirror/".to_owned()));
+///
+/// assert_eq!(
+///     mirror.rewrite("git@github.com:acme/private.git").as_deref(),
+///     Some("https://git.internal.example/mirror/acme/private.git")
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpsMirror {
+    base: Option<String>,
+}
+
+impl HttpsMirror {
+    /// # New HTTPS Mirror
+    ///
+    /// Fetch from the given base url, or from the same host over HTTPS if there isn't one
+    pub fn new(base: Option<String>) -> Self {
+        Self {
+            base: base.map(|base| base.trim_end_matches('/').to_owned()),
+        }
+    }
+
+    /// # Rewrite Url
+    ///
+    /// The HTTPS url a repository fetched over SSH is mirrored at, or `None` if the url is not
+    /// fetched over SSH
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        let (host, path) = split_ssh_url(url)?;
+
+        Some(match &self.base {
+            Some(base) => format!("{base}/{path}"),
+            None => format!("https://{host}/{path}"),
+        })
+    }
+
+    /// # Apply Mirror
+    ///
+    /// Rewrite the url of every git dependency fetched over SSH to its HTTPS mirror. The hash is
+    /// kept, as the mirror serves the same revision.
+    pub fn apply(&self, packages: &mut [Package]) {
+        for pkg in packages {
+            if let Fetcher::FetchGit { url, .. } = &mut pkg.fetcher {
+                if let Some(mirrored) = self.rewrite(url) {
+                    *url = mirrored;
+                }
+            }
+        }
+    }
+}
+
+/// # SSH Git Dependencies
+///
+/// The urls of the git dependencies in a package set which are fetched over SSH
+pub fn ssh_dependencies(packages: &[Package]) -> Vec<&str> {
+    packages
+        .iter()
+        .filter_map(|pkg| match &pkg.fetcher {
+            Fetcher::FetchGit { url, .. } if is_ssh_url(url) => Some(url.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Split an SSH url into its host, without any user or port, and the path of the repository
+fn split_ssh_url(url: &str) -> Option<(&str, &str)> {
+    let (authority, path) = match url.split_once("://") {
+        Some(("ssh" | "git+ssh", rest)) => rest.split_once('/')?,
+        Some(_) => return None,
+        None => {
+            let (authority, path) = url.split_once(':')?;
+
+            // `github:owner/repo` shorthands and local paths have no host
+            if !authority.contains('@') || authority.contains('/') {
+                return None;
+            }
+
+            (authority, path)
+        }
+    };
+
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    let path = path.trim_start_matches('/');
+
+    match host.is_empty() || path.is_empty() {
+        true => None,
+        false => Some((host, path)),
+    }
+}
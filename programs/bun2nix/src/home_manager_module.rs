@@ -0,0 +1,111 @@
+//! This module holds rendering of a Home Manager module which installs the command line tools
+//! of a bun project for a user
+
+use std::collections::BTreeMap;
+
+use askama::Template;
+
+use crate::{
+    PackageJson,
+    error::{Error, Result},
+    oci_image::image_name,
+    prune::{Prune, render_prune_list},
+};
+
+/// # Home Manager Module
+///
+/// A `home.nix` exposing `programs.<name>`, which installs each tool in the `bin` of the
+/// project's `package.json`, run with bun and the `node_modules` built from `bun.nix`, along
+/// with the shell completions of the tool named after the project if it can print them
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{PackageJson, home_manager_module::HomeManagerModule};
+///
+/// let package_json: PackageJson = r#"{ "name": "@acme/cli", "bin": { "acme": "bin/acme.ts" } }"#
+///     .parse()
+///     .unwrap();
+///
+/// let nix = HomeManagerModule::new(&package_json, &[]).unwrap().render().unwrap();
+///
+/// assert!(nix.contains(r#"options.programs."acme-cli" = {"#));
+/// assert!(nix.contains(r#"makeWrapper ${lib.getExe pkgs.bun} "$out/bin/acme""#));
+/// assert!(nix.contains(r#"mainProgram = "acme";"#));
+///
+/// let library: PackageJson = r#"{ "name": "acme-lib" }"#.parse().unwrap();
+/// assert!(HomeManagerModule::new(&library, &[]).is_err());
+/// ```
+#[derive(Template)]
+#[template(path = "home-manager-module.nix_template")]
+pub struct HomeManagerModule {
+    name: String,
+    version: String,
+    binaries: BTreeMap<String, String>,
+    main_program: String,
+    workspaces: Vec<String>,
+    prune: String,
+}
+
+impl HomeManagerModule {
+    /// # New Home Manager Module
+    ///
+    /// Create a module for the project with the given `package.json` and workspace paths,
+    /// which must provide at least one tool in its `bin`
+    pub fn new<'a>(
+        package_json: &PackageJson,
+        workspaces: impl IntoIterator<Item = &'a String>,
+    ) -> Result<Self> {
+        let binaries = package_json.binaries();
+        let name = image_name(package_json.name.as_deref().unwrap_or("app"));
+
+        let main_program = binaries
+            .keys()
+            .find(|bin| **bin == name)
+            .or_else(|| binaries.keys().next())
+            .cloned()
+            .ok_or_else(|| {
+                Error::InvalidPackageJson(
+                    "there is no `bin` to install the project's tools from".to_owned(),
+                )
+            })?;
+
+        let mut workspaces: Vec<String> = workspaces
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .cloned()
+            .collect();
+
+        workspaces.sort();
+
+        Ok(Self {
+            name,
+            version: package_json
+                .version
+                .as_deref()
+                .unwrap_or("latest")
+                .to_owned(),
+            binaries,
+            main_program,
+            workspaces,
+            prune: String::new(),
+        })
+    }
+
+    /// # With Prune
+    ///
+    /// Remove the given kinds of files from packages when building the module's `node_modules`
+    pub fn with_prune(mut self, prune: &[Prune]) -> Self {
+        self.prune = render_prune_list(prune);
+        self
+    }
+
+    /// # Render
+    ///
+    /// Render the module as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
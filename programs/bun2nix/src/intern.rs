@@ -0,0 +1,58 @@
+//! This module holds an interner for the strings repeated throughout a lockfile, such as the
+//! names of popular dependencies and the identifiers of packages nested under many others, so
+//! that large lockfiles hold each only once
+
+use std::{collections::HashSet, sync::Arc};
+
+/// # Interner
+///
+/// A set of shared strings, giving out the same allocation for every copy of a string
+///
+/// ## Usage
+///```rust
+/// use std::sync::Arc;
+///
+/// use bun2nix::intern::Interner;
+///
+/// let mut interner = Interner::default();
+///
+/// let a = interner.intern("is-number@7.0.0");
+/// let b = interner.intern(&String::from("is-number@7.0.0"));
+///
+/// assert!(Arc::ptr_eq(&a, &b));
+/// assert_eq!(interner.len(), 1);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    /// # Intern
+    ///
+    /// The shared copy of a string, which is added if it has not been seen before
+    pub fn intern(&mut self, string: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(string) {
+            return Arc::clone(interned);
+        }
+
+        let interned: Arc<str> = Arc::from(string);
+        self.strings.insert(Arc::clone(&interned));
+
+        interned
+    }
+
+    /// # Length
+    ///
+    /// The number of distinct strings interned
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// # Is Empty
+    ///
+    /// Whether no strings have been interned
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
@@ -0,0 +1,113 @@
+//! This module holds the flag set when a run is interrupted, such as by `SIGINT`, which stops
+//! new prefetches from starting and kills the commands already running so that the run can
+//! finish early while still cleaning up after itself
+
+use std::{
+    io::Read,
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use crate::error::{Error, Result};
+
+/// How often a running command checks whether the run has been interrupted
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Whether the run has been interrupted
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// # Interrupt
+///
+/// Mark the run as interrupted, which is safe to call from a signal handling thread
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// # Interrupted
+///
+/// Whether the run has been interrupted
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// # Check For Interrupt
+///
+/// Fail with [`Error::Interrupted`] if the run has been interrupted, to stop before starting
+/// more work
+pub fn check() -> Result<()> {
+    match interrupted() {
+        true => Err(Error::Interrupted),
+        false => Ok(()),
+    }
+}
+
+/// # Run Command
+///
+/// Run a command to completion and collect its output, as with [`Command::output`], but kill
+/// it and fail with [`Error::Interrupted`] if the run is interrupted first
+///
+/// ## Usage
+///```rust
+/// use std::process::Command;
+///
+/// use bun2nix::interrupt;
+///
+/// let output = interrupt::run(Command::new("echo").arg("hello")).unwrap();
+///
+/// assert_eq!(output.stdout, b"hello\n");
+/// ```
+pub fn run(command: &mut Command) -> Result<Output> {
+    check()?;
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(Error::FetchingFailed)?;
+
+    // Both pipes are drained while waiting, so a command with a lot of output cannot block
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = wait(&mut child)?;
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Wait for a child to exit, killing it if the run is interrupted
+fn wait(child: &mut Child) -> Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait().map_err(Error::FetchingFailed)? {
+            return Ok(status);
+        }
+
+        if interrupted() {
+            let _ = child.kill();
+            let _ = child.wait();
+
+            return Err(Error::Interrupted);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Read a pipe to its end on another thread
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+
+        buf
+    })
+}
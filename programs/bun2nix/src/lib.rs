@@ -3,16 +3,73 @@
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod binaries;
+pub mod build_check;
+pub mod bun_cache;
+pub mod bunfig;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod checksums;
+pub mod dependency_graph;
+pub mod devshell;
+pub mod diff;
+pub mod doctor;
+#[cfg(feature = "resolver")]
+pub mod enrichment;
 pub mod error;
+pub mod flake;
+pub mod flake_inputs;
+pub mod git_refs;
+pub mod git_ssh;
+pub mod home_manager_module;
+pub mod intern;
+pub mod interrupt;
 pub mod lockfile;
+pub mod merge_driver;
+#[cfg(feature = "resolver")]
+pub mod mirrors;
 pub mod nix_expression;
+pub mod nixos_module;
+pub mod nixpkgs_package;
+pub mod node_modules;
+pub mod npm_lock;
+pub mod oci_image;
+pub mod out_path;
 pub mod package;
+#[cfg(feature = "resolver")]
+pub mod package_entry;
+pub mod package_json;
+pub mod platform;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+#[cfg(feature = "resolver")]
+pub mod policy;
+pub mod prefetcher;
+pub mod prune;
+#[cfg(feature = "resolver")]
+pub mod registry;
+#[cfg(feature = "resolver")]
+pub mod resolver;
+pub mod summary;
+#[cfg(feature = "template")]
+pub mod template;
+#[cfg(feature = "template")]
+pub mod template_check;
+#[cfg(feature = "resolver")]
+pub mod tool;
+pub mod trusted_dependencies;
+#[cfg(feature = "resolver")]
+pub mod verify;
+pub mod workspaces;
 
-use askama::Template;
 pub use error::{Error, Result};
 pub use lockfile::Lockfile;
-use nix_expression::NixExpression;
+use nix_expression::{Annotations, NixExpression};
 pub use package::Package;
+pub use package_json::PackageJson;
+pub use prefetcher::{PrefetchOptions, Prefetcher};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -23,15 +80,110 @@ use wasm_bindgen::prelude::*;
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[cfg_attr(target_arch = "wasm32", no_mangle)]
 pub fn convert_lockfile_to_nix_expression(contents: String) -> Result<String> {
-    let lockfile = contents.parse::<Lockfile>()?;
+    let packages = prefetch_lockfile(parse_lockfile(&contents)?, &PrefetchOptions::default())?;
 
+    render_nix_expression(packages)
+}
+
+/// # Prefetch Bun Lockfile
+///
+/// Takes a parsed bun lockfile and returns its packages with every hash filled in, selected for
+/// the requested platform and normalized as they would be rendered, so that they can be
+/// filtered or rendered differently before any Nix is produced
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{PrefetchOptions, package::Fetcher, parse_lockfile, prefetch_lockfile};
+///
+/// let lockfile = parse_lockfile(r#"
+/// {
+///   "lockfileVersion": 1,
+///   "workspaces": { "": { "name": "app" } },
+///   "packages": {
+///     "typescript": ["typescript@5.7.3", "", {}, "sha512-84MVSjMEHP+FQRPy3pX9sTVV/INIex71s9TL2Gm5FG/WG1SqXeKyZ0k7/blY/4FdOzI12CBy1vGc4og/eus0fw=="],
+///     "@esbuild/darwin-arm64": ["@esbuild/darwin-arm64@0.25.0", "", { "os": "darwin", "cpu": "arm64" }, "sha512-abc"],
+///     "@esbuild/linux-x64": ["@esbuild/linux-x64@0.25.0", "", { "os": "linux", "cpu": "x64" }, "sha512-def"],
+///   }
+/// }
+/// "#).unwrap();
+///
+/// let options = PrefetchOptions::default().with_system("x86_64-linux".parse().unwrap());
+/// let packages = prefetch_lockfile(lockfile, &options).unwrap();
+///
+/// let names: Vec<_> = packages.iter().map(|pkg| pkg.name.as_str()).collect();
+/// assert_eq!(names, ["@esbuild/linux-x64@0.25.0", "typescript@5.7.3"]);
+///
+/// assert!(matches!(
+///     &packages[1].fetcher,
+///     Fetcher::FetchUrl { hash, .. } if hash.starts_with("sha512-")
+/// ));
+/// ```
+pub fn prefetch_lockfile(lockfile: Lockfile, options: &PrefetchOptions) -> Result<Vec<Package>> {
+    let mut packages = lockfile.packages();
+
+    platform::retain_platform(&mut packages, options.system.as_ref(), options.libc);
+    options.prefetcher.prefetch_packages(&mut packages)?;
+    package::normalize(&mut packages);
+
+    Ok(packages)
+}
+
+/// # Convert package.json to a Nix expression
+///
+/// Resolves the dependencies of a `package.json` against the given registry without a lockfile,
+/// and converts the resulting package set into a ready to use Nix expression
+#[cfg(feature = "resolver")]
+pub fn convert_package_json_to_nix_expression(contents: &str, registry: &str) -> Result<String> {
+    let package_json = contents.parse::<PackageJson>()?;
+
+    let registry = registry::Registry::new(registry)?;
+    let packages = resolver::Resolver::new(registry).resolve(&package_json)?;
+
+    render_nix_expression(packages)
+}
+
+/// # Parse Bun Lockfile
+///
+/// Parses the contents of a bun lockfile, checking that it is of a supported version
+pub fn parse_lockfile(contents: &str) -> Result<Lockfile> {
+    check_lockfile_version(contents.parse::<Lockfile>()?)
+}
+
+/// # Parse Bun Lockfile Streaming
+///
+/// Parses the contents of a bun lockfile like [`parse_lockfile`], while sending each package to
+/// `sink` as soon as it is read, such as to start prefetching it before the parse has finished
+pub fn parse_lockfile_streaming(
+    contents: &str,
+    sink: std::sync::mpsc::Sender<Package>,
+) -> Result<Lockfile> {
+    check_lockfile_version(Lockfile::parse_streaming(contents, sink)?)
+}
+
+fn check_lockfile_version(lockfile: Lockfile) -> Result<Lockfile> {
     if lockfile.lockfile_version != 1 {
         return Err(Error::UnsupportedLockfileVersion(lockfile.lockfile_version));
     };
 
-    let mut packages = lockfile.packages();
-    packages.sort();
-    packages.dedup_by(|a, b| a.name == b.name);
+    Ok(lockfile)
+}
 
-    Ok(NixExpression::new(packages)?.render()?)
+/// # Render Nix Expression
+///
+/// Renders a set of (already prefetched) packages into a ready to use Nix expression
+pub fn render_nix_expression(packages: Vec<Package>) -> Result<String> {
+    render_annotated_nix_expression(packages, Annotations::new())
+}
+
+/// # Render Annotated Nix Expression
+///
+/// Renders a set of (already prefetched) packages into a ready to use Nix expression, with
+/// comments above the entries of the given packages
+pub fn render_annotated_nix_expression(
+    packages: Vec<Package>,
+    annotations: Annotations,
+) -> Result<String> {
+    NixExpression::new(packages)?
+        .with_annotations(annotations)
+        .render()
 }
@@ -1,23 +1,40 @@
 //! This module holds everything related to deserialization of the bun lockfile, including type
 //! mappings and custom deserialization methods
 
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, ops::RangeInclusive, str::FromStr, sync::mpsc::Sender};
 
-use log::warn;
+use log::{info, warn};
 use serde::{Deserialize, Deserializer, Serialize};
-use serde_json::Value;
 
 use crate::{
-    Package,
+    Package, Prefetcher,
     error::{Error, Result},
+    trusted_dependencies::TrustedDependencies,
+    workspaces::WorkspaceMember,
 };
 
+mod entry_metadata;
+mod entry_value;
+mod jsonc_deserializer;
 mod package_deserializer;
 mod package_visitor;
+mod schema;
+#[cfg(feature = "simd-json")]
+mod simd;
+pub use entry_metadata::EntryMetadata;
+pub use entry_value::{EntryValue, PackageMetadata};
+pub use jsonc_deserializer::JsoncDeserializer;
 pub use package_deserializer::{
-    PackageDeserializer, drop_prefix, split_once_owned, swap_remove_value,
+    LockfileEntry, PackageDeserializer, drop_prefix, split_once_owned, swap_remove_value,
 };
-pub use package_visitor::PackageVisitor;
+pub use package_visitor::{PackageVisitor, stream_packages};
+pub use schema::validate_schema;
+#[cfg(feature = "simd-json")]
+pub use simd::strip_jsonc;
+
+/// The range of bun versions, by major and minor version, whose lockfiles `bun2nix` has been
+/// validated against
+pub const VALIDATED_BUN_VERSIONS: RangeInclusive<(u64, u64)> = (1, 2)..=(1, 3);
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -67,6 +84,24 @@ pub use package_visitor::PackageVisitor;
 ///
 /// assert!(value.lockfile_version == 1);
 /// ```
+///
+/// The root workspace is never included in the packages, even if the lockfile lists it:
+///
+/// ```rust
+/// use bun2nix::Lockfile;
+///
+/// let lockfile: Lockfile = r#"
+/// {
+///   "lockfileVersion": 1,
+///   "workspaces": { "": { "name": "app" } },
+///   "packages": { "app": ["app@workspace:."] },
+/// }
+/// "#
+/// .parse()
+/// .unwrap();
+///
+/// assert!(lockfile.packages.is_empty());
+/// ```
 pub struct Lockfile {
     /// The version field of the bun lockfile
     pub lockfile_version: u8,
@@ -78,6 +113,11 @@ pub struct Lockfile {
     /// The list of all packages needed by the lockfile
     #[serde(deserialize_with = "Lockfile::deserialize_packages")]
     pub packages: Vec<Package>,
+
+    /// The packages allowed to run lifecycle scripts, if the project overrides bun's default
+    /// allowlist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_dependencies: Option<Vec<String>>,
 }
 
 impl Lockfile {
@@ -88,6 +128,14 @@ impl Lockfile {
         self.packages
     }
 
+    /// # Prefetch Packages
+    ///
+    /// Calculate the hashes for any packages which are not
+    /// already known from the lockfile with the given prefetcher
+    pub fn prefetch_packages(&mut self, prefetcher: &Prefetcher) -> Result<()> {
+        prefetcher.prefetch_packages(&mut self.packages)
+    }
+
     /// # Lockfile Workspaces
     ///
     /// Get a reference to the lockfile's workspaces
@@ -95,6 +143,34 @@ impl Lockfile {
         &self.workspaces
     }
 
+    /// # Trusted Dependencies
+    ///
+    /// The packages this lockfile allows to run lifecycle scripts
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::Lockfile;
+    ///
+    /// let lockfile: Lockfile = r#"{ "lockfileVersion": 1, "packages": {} }"#.parse().unwrap();
+    /// assert!(lockfile.trusted_dependencies().is_trusted("esbuild"));
+    ///
+    /// let lockfile: Lockfile = r#"
+    /// {
+    ///   "lockfileVersion": 1,
+    ///   "trustedDependencies": ["my-native-addon"],
+    ///   "packages": {}
+    /// }
+    /// "#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// assert!(lockfile.trusted_dependencies().is_trusted("my-native-addon"));
+    /// assert!(!lockfile.trusted_dependencies().is_trusted("esbuild"));
+    /// ```
+    pub fn trusted_dependencies(&self) -> TrustedDependencies {
+        TrustedDependencies::new(self.trusted_dependencies.as_deref())
+    }
+
     /// # Has Workspaces
     ///
     /// Check if the lockfile has any non-root workspaces
@@ -102,12 +178,245 @@ impl Lockfile {
         self.workspaces.iter().any(|(key, _)| !key.is_empty())
     }
 
-    /// # Parse to Value
+    /// # Check Package Manager
+    ///
+    /// Check that the bun version pinned by the `packageManager` field of a `package.json` (such
+    /// as `bun@1.2.19`) is one whose lockfile format `bun2nix` has been validated against.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::Lockfile;
+    ///
+    /// let lockfile: Lockfile = r#"{ "lockfileVersion": 1, "packages": {} }"#.parse().unwrap();
     ///
-    /// Parse the lockfile into a serde json value
-    pub fn parse_to_value(lockfile: &str) -> Result<Value> {
-        jsonc_parser::parse_to_serde_value(lockfile, &Default::default())?
-            .ok_or(Error::NoJsoncValue)
+    /// assert!(lockfile.check_package_manager("bun@1.2.19").is_ok());
+    /// assert!(lockfile.check_package_manager("bun@1.1.30").is_err());
+    /// assert!(lockfile.check_package_manager("bun@9.0.0").is_err());
+    /// assert!(lockfile.check_package_manager("pnpm@9.1.0").is_err());
+    /// ```
+    pub fn check_package_manager(&self, package_manager: &str) -> Result<()> {
+        let unvalidated = |reason: String| Error::UnvalidatedBunVersion {
+            package_manager: package_manager.to_owned(),
+            lockfile_version: self.lockfile_version,
+            reason,
+        };
+
+        let Some(version) = package_manager.strip_prefix("bun@") else {
+            return Err(unvalidated("the project is not managed by bun".to_owned()));
+        };
+
+        let mut parts = version
+            .split(['+', '-'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(str::parse::<u64>);
+
+        let (Some(Ok(major)), Some(Ok(minor))) = (parts.next(), parts.next()) else {
+            return Err(unvalidated(format!(
+                "`{version}` is not a valid bun version"
+            )));
+        };
+
+        let (min, max) = (VALIDATED_BUN_VERSIONS.start(), VALIDATED_BUN_VERSIONS.end());
+
+        if (major, minor) < *min {
+            return Err(unvalidated(format!(
+                "bun versions below {}.{} do not produce a supported textual lockfile",
+                min.0, min.1
+            )));
+        }
+
+        if (major, minor) > *max {
+            return Err(unvalidated(format!(
+                "the lockfile format has only been validated up to bun {}.{}",
+                max.0, max.1
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// # Check Frozen
+    ///
+    /// Check that the lockfile covers exactly the dependencies declared by the `package.json` of
+    /// every member of the project, as `bun install --frozen-lockfile` does, so that a stale
+    /// lockfile is noticed rather than silently used.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{Lockfile, workspaces::WorkspaceMember};
+    ///
+    /// let lockfile: Lockfile = r#"
+    /// {
+    ///   "lockfileVersion": 1,
+    ///   "workspaces": {
+    ///     "": { "name": "example", "dependencies": { "is-odd": "^3.0.1" } },
+    ///   },
+    ///   "packages": {},
+    /// }
+    /// "#
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let member = |package_json: &str| WorkspaceMember {
+    ///     path: String::new(),
+    ///     package_json: package_json.parse().unwrap(),
+    /// };
+    ///
+    /// let current = member(r#"{ "dependencies": { "is-odd": "^3.0.1" } }"#);
+    /// assert!(lockfile.check_frozen(&[current]).is_ok());
+    ///
+    /// let stale = member(r#"{ "dependencies": { "is-odd": "^3.0.2", "is-even": "^1.0.0" } }"#);
+    /// let err = lockfile.check_frozen(&[stale]).unwrap_err().to_string();
+    ///
+    /// assert!(err.contains("`package.json` adds dependency `is-even@^1.0.0`"));
+    /// assert!(err.contains("`package.json` changes dependency `is-odd` from `^3.0.1` to `^3.0.2`"));
+    /// ```
+    pub fn check_frozen(&self, members: &[WorkspaceMember]) -> Result<()> {
+        let mut changes = Vec::new();
+
+        for member in members {
+            let location = match member.path.is_empty() {
+                true => "package.json".to_owned(),
+                false => format!("{}/package.json", member.path),
+            };
+
+            let Some(locked) = self.workspaces.get(&member.path) else {
+                changes.push(format!("`{location}` is not a workspace in the lockfile"));
+                continue;
+            };
+
+            let declared = Workspace::from(member);
+
+            for ((kind, locked), (_, declared)) in locked
+                .dependency_kinds()
+                .into_iter()
+                .zip(declared.dependency_kinds())
+            {
+                let mut names: Vec<&String> = locked.keys().chain(declared.keys()).collect();
+                names.sort();
+                names.dedup();
+
+                for name in names {
+                    match (locked.get(name), declared.get(name)) {
+                        (None, Some(spec)) => {
+                            changes.push(format!("`{location}` adds {kind} `{name}@{spec}`"))
+                        }
+                        (Some(_), None) => {
+                            changes.push(format!("`{location}` removes {kind} `{name}`"))
+                        }
+                        (Some(old), Some(new)) if old != new => changes.push(format!(
+                            "`{location}` changes {kind} `{name}` from `{old}` to `{new}`"
+                        )),
+                        _ => (),
+                    }
+                }
+            }
+
+            for name in &declared.optional_peers {
+                if !locked.optional_peers.contains(name) {
+                    changes.push(format!(
+                        "`{location}` makes peer dependency `{name}` optional"
+                    ));
+                }
+            }
+
+            for name in &locked.optional_peers {
+                if !declared.optional_peers.contains(name) {
+                    changes.push(format!(
+                        "`{location}` makes peer dependency `{name}` required"
+                    ));
+                }
+            }
+        }
+
+        let mut removed: Vec<&String> = self
+            .workspaces
+            .keys()
+            .filter(|path| !members.iter().any(|member| member.path == **path))
+            .collect();
+        removed.sort();
+
+        changes.extend(
+            removed
+                .into_iter()
+                .map(|path| format!("`{path}` is a workspace in the lockfile but not the project")),
+        );
+
+        match changes.is_empty() {
+            true => Ok(()),
+            false => Err(Error::FrozenLockfile(changes)),
+        }
+    }
+
+    /// Remove the entry for the root workspace from the packages, which some lockfiles list
+    /// under the root's name, as the project itself is never fetched. An entry is only taken to
+    /// be the root if no other member of the workspace has the same name.
+    fn remove_root_package(&mut self) {
+        let Some(root) = self
+            .workspaces
+            .get("")
+            .and_then(|root| root.name.as_deref())
+        else {
+            return;
+        };
+
+        let shadowed = self
+            .workspaces
+            .iter()
+            .any(|(path, workspace)| !path.is_empty() && workspace.name.as_deref() == Some(root));
+
+        if shadowed {
+            return;
+        }
+
+        self.packages.retain(|pkg| {
+            let is_root = pkg.lockfile_key.as_deref() == Some(root);
+
+            if is_root {
+                info!(
+                    "Skipping `{}`, which is the root of the workspace",
+                    pkg.name
+                );
+            }
+
+            !is_root
+        });
+    }
+
+    /// # Parse Streaming
+    ///
+    /// Parse a lockfile in the same way as [`str::parse`], but also send each package to `sink`
+    /// as soon as it is deserialized, so that work on the packages can start before the rest of
+    /// the lockfile has been parsed
+    ///
+    /// ## Usage
+    ///```rust
+    /// use std::sync::mpsc;
+    ///
+    /// use bun2nix::Lockfile;
+    ///
+    /// let lockfile = r#"
+    /// {
+    ///   "lockfileVersion": 1,
+    ///   "workspaces": { "": { "name": "app" } },
+    ///   "packages": {
+    ///     "is-number": ["is-number@7.0.0", "", {}, "sha512-41Cifkg6e8TylSpdtTpeLVMqvSBEVzTttHvERD741+pnZ8ANv0004MRL43QKPDlK9cGvNp6NZWZUBlbGXYxxng=="]
+    ///   }
+    /// }
+    /// "#;
+    ///
+    /// let (sink, received) = mpsc::channel();
+    /// let parsed = Lockfile::parse_streaming(lockfile, sink).unwrap();
+    ///
+    /// let received: Vec<_> = received.iter().map(|pkg| pkg.name).collect();
+    ///
+    /// assert_eq!(received, ["is-number@7.0.0"]);
+    /// assert_eq!(parsed.packages.len(), 1);
+    /// ```
+    pub fn parse_streaming(lockfile: &str, sink: Sender<Package>) -> Result<Self> {
+        stream_packages(sink, || lockfile.parse())
     }
 
     /// # Deserialize Packages
@@ -125,9 +434,17 @@ impl FromStr for Lockfile {
     type Err = Error;
 
     fn from_str(lockfile: &str) -> std::result::Result<Self, Self::Err> {
-        let value = Self::parse_to_value(lockfile)?;
+        validate_schema(lockfile)?;
+
+        #[cfg(feature = "simd-json")]
+        let mut parsed: Self = simd::parse(lockfile)?;
 
-        Ok(serde_json::from_value(value)?)
+        #[cfg(not(feature = "simd-json"))]
+        let mut parsed: Self = JsoncDeserializer::parse(lockfile)?;
+
+        parsed.remove_root_package();
+
+        Ok(parsed)
     }
 }
 
@@ -149,9 +466,31 @@ pub struct Workspace {
     /// Dev dependencies of the workspace
     #[serde(default, deserialize_with = "Workspace::deserialize_dependencies")]
     pub dev_dependencies: Dependencies,
+
+    /// Optional dependencies of the workspace
+    #[serde(default, deserialize_with = "Workspace::deserialize_dependencies")]
+    pub optional_dependencies: Dependencies,
+
+    /// Peer dependencies of the workspace
+    #[serde(default, deserialize_with = "Workspace::deserialize_dependencies")]
+    pub peer_dependencies: Dependencies,
+
+    /// The peer dependencies of the workspace which are optional, and so are not installed
+    /// unless something else depends on them
+    pub optional_peers: Vec<String>,
 }
 
 impl Workspace {
+    /// Each kind of dependency of the workspace, along with its name
+    fn dependency_kinds(&self) -> [(&'static str, &Dependencies); 4] {
+        [
+            ("dependency", &self.dependencies),
+            ("dev dependency", &self.dev_dependencies),
+            ("optional dependency", &self.optional_dependencies),
+            ("peer dependency", &self.peer_dependencies),
+        ]
+    }
+
     /// # Deserialize Dependencies
     ///
     /// Wraps the default deserialization method in order to add checking for unresolved deps
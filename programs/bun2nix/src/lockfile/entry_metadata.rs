@@ -0,0 +1,172 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::de::{
+    self, Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor,
+    value::MapAccessDeserializer,
+};
+
+/// # Entry Metadata
+///
+/// The identifier of a package's tuple in the bun lockfile, along with its metadata object
+/// read as `M`.
+///
+/// Like [`EntryValue`](super::EntryValue), everything else in the tuple is skipped over as it
+/// is deserialized, rather than being held as a generic JSON value, which matters for the
+/// passes reading the dependencies or binaries of every package in a large lockfile.
+///
+/// ## Usage
+///```rust
+/// use std::collections::HashMap;
+///
+/// use bun2nix::lockfile::{EntryMetadata, JsoncDeserializer};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Default)]
+/// struct Metadata {
+///     #[serde(default)]
+///     dependencies: HashMap<String, String>,
+/// }
+///
+/// let entry: EntryMetadata<Metadata> = JsoncDeserializer::parse(
+///     r#"["is-odd@3.0.1", "", { "dependencies": { "is-number": "^6.0.0" } }, "sha512-abc"]"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(entry.id, "is-odd@3.0.1");
+/// assert_eq!(entry.metadata.dependencies["is-number"], "^6.0.0");
+///
+/// let entry: EntryMetadata<Metadata> =
+///     JsoncDeserializer::parse(r#"["lib@workspace:packages/lib"]"#).unwrap();
+///
+/// assert!(entry.metadata.dependencies.is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryMetadata<M> {
+    /// The identifier of the package, such as `is-odd@3.0.1`, which is empty if the tuple
+    /// does not start with one
+    pub id: String,
+
+    /// The metadata object of the package, or the default if it has none
+    pub metadata: M,
+}
+
+impl<'de, M> Deserialize<'de> for EntryMetadata<M>
+where
+    M: Deserialize<'de> + Default,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(EntryMetadataVisitor(PhantomData))
+    }
+}
+
+struct EntryMetadataVisitor<M>(PhantomData<M>);
+
+impl<'de, M> Visitor<'de> for EntryMetadataVisitor<M>
+where
+    M: Deserialize<'de> + Default,
+{
+    type Value = EntryMetadata<M>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a package tuple")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(
+        self,
+        mut seq: S,
+    ) -> std::result::Result<Self::Value, S::Error> {
+        let mut entry = EntryMetadata {
+            id: String::new(),
+            metadata: M::default(),
+        };
+        let mut found = false;
+
+        while let Some(item) = seq.next_element::<Item<M>>()? {
+            match item {
+                Item::String(id) if entry.id.is_empty() => entry.id = id,
+                Item::Metadata(metadata) if !found => {
+                    entry.metadata = metadata;
+                    found = true;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(entry)
+    }
+}
+
+/// A single item of a package's tuple, keeping only strings and the metadata object
+enum Item<M> {
+    String(String),
+    Metadata(M),
+    Ignored,
+}
+
+impl<'de, M> Deserialize<'de> for Item<M>
+where
+    M: Deserialize<'de> + Default,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ItemVisitor(PhantomData))
+    }
+}
+
+struct ItemVisitor<M>(PhantomData<M>);
+
+impl<'de, M> Visitor<'de> for ItemVisitor<M>
+where
+    M: Deserialize<'de> + Default,
+{
+    type Value = Item<M>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a package tuple item")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Self::Value, E> {
+        Ok(Item::String(value.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> std::result::Result<Self::Value, E> {
+        Ok(Item::String(value))
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> std::result::Result<Self::Value, E> {
+        Ok(Item::Ignored)
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> std::result::Result<Self::Value, E> {
+        Ok(Item::Ignored)
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> std::result::Result<Self::Value, E> {
+        Ok(Item::Ignored)
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> std::result::Result<Self::Value, E> {
+        Ok(Item::Ignored)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+        Ok(Item::Ignored)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<Self::Value, A::Error> {
+        Ok(Item::Metadata(M::deserialize(MapAccessDeserializer::new(
+            map,
+        ))?))
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, seq: S) -> std::result::Result<Self::Value, S::Error> {
+        IgnoredAny.visit_seq(seq)?;
+
+        Ok(Item::Ignored)
+    }
+}
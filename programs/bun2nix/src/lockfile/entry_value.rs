@@ -0,0 +1,167 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{
+    Deserialize,
+    de::{self, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor, value::MapAccessDeserializer},
+};
+
+use crate::platform::{Platform, string_or_list};
+
+/// # Entry Value
+///
+/// A single item of a package's tuple in the bun lockfile.
+///
+/// Only the strings in a tuple are needed to work out how to fetch a package, along with the
+/// platforms it supports and the binaries it links from the metadata object, so any other value
+/// (such as the package's dependencies) is skipped over as it is deserialized rather than being
+/// kept in memory.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::lockfile::{EntryValue, JsoncDeserializer};
+///
+/// let values: Vec<EntryValue> =
+///     JsoncDeserializer::parse(r#"["is-odd@3.0.1", "", { "dependencies": {} }, "sha512-abc"]"#)
+///         .unwrap();
+///
+/// assert_eq!(
+///     values,
+///     [
+///         EntryValue::String("is-odd@3.0.1".to_owned()),
+///         EntryValue::String(String::new()),
+///         EntryValue::Ignored,
+///         EntryValue::String("sha512-abc".to_owned()),
+///     ]
+/// );
+///
+/// let value: EntryValue = JsoncDeserializer::parse(r#"{ "os": "linux", "cpu": ["x64"] }"#).unwrap();
+///
+/// assert!(matches!(value, EntryValue::Metadata(metadata) if metadata.platform.os == ["linux"]));
+///
+/// let value: EntryValue = JsoncDeserializer::parse(r#"{ "bin": { "tsc": "./bin/tsc" } }"#).unwrap();
+///
+/// assert!(matches!(value, EntryValue::Metadata(metadata) if metadata.bin["tsc"] == "./bin/tsc"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryValue {
+    /// A string, such as the package identifier or its hash
+    String(String),
+
+    /// The platforms a package supports and the binaries it links, from a metadata object
+    /// which declares either
+    Metadata(PackageMetadata),
+
+    /// Anything else, which is not kept
+    Ignored,
+}
+
+/// # Package Metadata
+///
+/// The parts of the metadata object of a package's tuple which are kept
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageMetadata {
+    /// The platforms the package supports
+    pub platform: Platform,
+
+    /// The binaries the package links into `node_modules/.bin`, by name, with their paths
+    /// inside the package
+    pub bin: BTreeMap<String, String>,
+}
+
+/// The fields of a metadata object which are read, with everything else skipped
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawMetadata {
+    #[serde(deserialize_with = "string_or_list")]
+    os: Vec<String>,
+
+    #[serde(deserialize_with = "string_or_list")]
+    cpu: Vec<String>,
+
+    #[serde(deserialize_with = "string_or_list")]
+    libc: Vec<String>,
+
+    bin: Option<Bin>,
+}
+
+/// The `bin` of a metadata object, which is only understood as a map of names to paths
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Bin {
+    Map(BTreeMap<String, String>),
+    Other(IgnoredAny),
+}
+
+impl<'de> Deserialize<'de> for EntryValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(EntryValueVisitor)
+    }
+}
+
+struct EntryValueVisitor;
+
+impl<'de> Visitor<'de> for EntryValueVisitor {
+    type Value = EntryValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a package tuple item")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Self::Value, E> {
+        Ok(EntryValue::String(value.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> std::result::Result<Self::Value, E> {
+        Ok(EntryValue::String(value))
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> std::result::Result<Self::Value, E> {
+        Ok(EntryValue::Ignored)
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> std::result::Result<Self::Value, E> {
+        Ok(EntryValue::Ignored)
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> std::result::Result<Self::Value, E> {
+        Ok(EntryValue::Ignored)
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> std::result::Result<Self::Value, E> {
+        Ok(EntryValue::Ignored)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+        Ok(EntryValue::Ignored)
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, map: M) -> std::result::Result<Self::Value, M::Error> {
+        let raw = RawMetadata::deserialize(MapAccessDeserializer::new(map))?;
+
+        let metadata = PackageMetadata {
+            platform: Platform {
+                os: raw.os,
+                cpu: raw.cpu,
+                libc: raw.libc,
+            },
+            bin: match raw.bin {
+                Some(Bin::Map(bin)) => bin,
+                Some(Bin::Other(_)) | None => BTreeMap::new(),
+            },
+        };
+
+        Ok(match metadata.platform.is_any() && metadata.bin.is_empty() {
+            true => EntryValue::Ignored,
+            false => EntryValue::Metadata(metadata),
+        })
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, seq: S) -> std::result::Result<Self::Value, S::Error> {
+        IgnoredAny.visit_seq(seq)?;
+
+        Ok(EntryValue::Ignored)
+    }
+}
@@ -0,0 +1,251 @@
+use std::borrow::Cow;
+
+use jsonc_parser::{Scanner, tokens::Token};
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+
+use crate::error::{Error, Result};
+
+/// # JSONC Deserializer
+///
+/// A streaming serde deserializer reading straight from the tokens of a JSONC document, such as
+/// a bun lockfile.
+///
+/// Unlike parsing to a `serde_json::Value` first, no intermediate representation of the whole
+/// document is ever built, which keeps peak memory low for very large lockfiles. Comments and
+/// trailing commas are skipped over as they are encountered.
+///
+/// ## Usage
+///```rust
+/// use std::collections::HashMap;
+///
+/// use bun2nix::lockfile::JsoncDeserializer;
+///
+/// let value: HashMap<String, Vec<u8>> =
+///     JsoncDeserializer::parse("{ \"a\": [1, 2,], /* comment */ }").unwrap();
+///
+/// assert_eq!(value["a"], [1, 2]);
+/// ```
+pub struct JsoncDeserializer<'a> {
+    text: &'a str,
+    scanner: Scanner<'a>,
+    peeked: Option<Token<'a>>,
+}
+
+impl<'a> JsoncDeserializer<'a> {
+    /// # New Deserializer
+    ///
+    /// Create a deserializer reading from the given JSONC text
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            scanner: Scanner::new(text),
+            peeked: None,
+        }
+    }
+
+    /// # Parse
+    ///
+    /// Deserialize a value from JSONC text, checking that nothing follows it
+    pub fn parse<T: de::Deserialize<'a>>(text: &'a str) -> Result<T> {
+        let mut deserializer = Self::new(text);
+
+        if deserializer.peek()?.is_none() {
+            return Err(Error::NoJsoncValue);
+        }
+
+        let value = T::deserialize(&mut deserializer)?;
+
+        match deserializer.next()? {
+            None => Ok(value),
+            Some(token) => Err(deserializer.unexpected(&token, "the end of the file")),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token<'a>>> {
+        if self.peeked.is_none() {
+            self.peeked = self.scan()?;
+        }
+
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.scan(),
+        }
+    }
+
+    fn scan(&mut self) -> Result<Option<Token<'a>>> {
+        loop {
+            match self.scanner.scan()? {
+                Some(Token::CommentLine(_) | Token::CommentBlock(_)) => continue,
+                token => return Ok(token),
+            }
+        }
+    }
+
+    fn expect_next(&mut self) -> Result<Token<'a>> {
+        self.next()?
+            .ok_or_else(|| de::Error::custom("unexpected end of file"))
+    }
+
+    /// Consume the separator after an item in an object or array, returning whether the
+    /// container has ended
+    fn end_of_item(&mut self, close: &Token<'a>) -> Result<bool> {
+        match self.expect_next()? {
+            Token::Comma => Ok(false),
+            token if &token == close => Ok(true),
+            token => Err(self.unexpected(&token, &format!("`,` or `{}`", close.as_str()))),
+        }
+    }
+
+    /// Consume the closing token of an object or array if it is next, allowing for a trailing
+    /// comma before it
+    fn try_close(&mut self, close: &Token<'a>) -> Result<bool> {
+        if self.peek()? == Some(close) {
+            self.next()?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Error for a token which should not be where it is, pointing to the line it is on
+    fn unexpected(&self, token: &Token, expected: &str) -> Error {
+        let line = self.text[..self.scanner.token_start()]
+            .lines()
+            .count()
+            .max(1);
+
+        de::Error::custom(format!(
+            "unexpected token `{}` on line {line}, expected {expected}",
+            token.as_str()
+        ))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut JsoncDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.expect_next()? {
+            Token::OpenBrace => visitor.visit_map(Container::new(self, Token::CloseBrace)),
+            Token::OpenBracket => visitor.visit_seq(Container::new(self, Token::CloseBracket)),
+            Token::String(Cow::Borrowed(string)) | Token::Word(string) => {
+                visitor.visit_borrowed_str(string)
+            }
+            Token::String(Cow::Owned(string)) => visitor.visit_string(string),
+            Token::Boolean(boolean) => visitor.visit_bool(boolean),
+            Token::Null => visitor.visit_unit(),
+            Token::Number(number) => visit_number(number, visitor),
+            token => Err(self.unexpected(&token, "a value")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.peek()? {
+            Some(Token::Null) => {
+                self.next()?;
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// # Container Access
+///
+/// Gives serde access to the items of an object or array
+struct Container<'a, 'de> {
+    deserializer: &'a mut JsoncDeserializer<'de>,
+    close: Token<'de>,
+    ended: bool,
+}
+
+impl<'a, 'de> Container<'a, 'de> {
+    fn new(deserializer: &'a mut JsoncDeserializer<'de>, close: Token<'de>) -> Self {
+        Self {
+            deserializer,
+            close,
+            ended: false,
+        }
+    }
+
+    /// Whether there are no more items, consuming the closing token if so
+    fn at_end(&mut self) -> Result<bool> {
+        if !self.ended {
+            self.ended = self.deserializer.try_close(&self.close)?;
+        }
+
+        Ok(self.ended)
+    }
+
+    fn end_of_item(&mut self) -> Result<()> {
+        self.ended = self.deserializer.end_of_item(&self.close)?;
+
+        Ok(())
+    }
+}
+
+impl<'de> MapAccess<'de> for Container<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.at_end()? {
+            return Ok(None);
+        }
+
+        let key = seed.deserialize(&mut *self.deserializer)?;
+
+        match self.deserializer.expect_next()? {
+            Token::Colon => Ok(Some(key)),
+            token => Err(self.deserializer.unexpected(&token, "`:`")),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = seed.deserialize(&mut *self.deserializer)?;
+
+        self.end_of_item()?;
+
+        Ok(value)
+    }
+}
+
+impl<'de> SeqAccess<'de> for Container<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.at_end()? {
+            return Ok(None);
+        }
+
+        let value = seed.deserialize(&mut *self.deserializer)?;
+
+        self.end_of_item()?;
+
+        Ok(Some(value))
+    }
+}
+
+fn visit_number<'de, V: Visitor<'de>>(number: &str, visitor: V) -> Result<V::Value> {
+    if let Ok(number) = number.parse::<u64>() {
+        return visitor.visit_u64(number);
+    }
+
+    if let Ok(number) = number.parse::<i64>() {
+        return visitor.visit_i64(number);
+    }
+
+    match number.parse::<f64>() {
+        Ok(number) => visitor.visit_f64(number),
+        Err(_) => Err(de::Error::custom(format!("invalid number `{number}`"))),
+    }
+}
@@ -1,13 +1,68 @@
+use std::mem;
+
+use super::{EntryValue, PackageMetadata};
 use crate::{
     Package,
     error::{Error, Result},
+    git_refs::is_commit,
+    out_path::normalize_path,
     package::Fetcher,
 };
 
-mod prefetch;
-pub use prefetch::Prefetch;
-
-type Values = Vec<serde_json::Value>;
+type Values = Vec<EntryValue>;
+
+/// Specifiers of lockfile entries which have no artifact of their own to fetch
+const UNFETCHABLE_SPECIFIERS: [&str; 2] = ["root:", "link:"];
+
+/// Resolves the package an entry of arity 2 describes, from the name and identifier of the
+/// entry and its specifier
+type ProtocolResolver = fn(String, &str, &str) -> Result<Package>;
+
+/// The resolvers of the protocols the specifiers of entries of arity 2 may be written with, by
+/// their prefix. Those of other package managers occasionally appear in migrated lockfiles, and
+/// are handled here, or clearly reported, rather than being mistaken for paths.
+const PROTOCOL_RESOLVERS: [(&str, ProtocolResolver); 5] = [
+    ("http:", |_, _, spec| {
+        PackageDeserializer::deserialize_tarball_package(spec.to_owned())
+    }),
+    ("https:", |_, _, spec| {
+        PackageDeserializer::deserialize_tarball_package(spec.to_owned())
+    }),
+    ("file:", |name, _, spec| {
+        PackageDeserializer::deserialize_file_package(name, after_protocol(spec))
+    }),
+    ("portal:", |name, _, spec| {
+        PackageDeserializer::deserialize_file_package(name, after_protocol(spec))
+    }),
+    ("patch:", |_, id, _| {
+        Err(Error::UnsupportedProtocol {
+            id: id.to_owned(),
+            protocol: "patch:".to_owned(),
+            reason: "bun does not apply, list the patch under `patchedDependencies` in `package.json` instead",
+        })
+    }),
+];
+
+/// # Lockfile Entry
+///
+/// What an entry of the `packages` of a bun lockfile describes - either a package with a source
+/// which can be fetched, or one which has no artifact of its own and is linked into place by bun
+/// at install time
+#[derive(Debug)]
+pub enum LockfileEntry {
+    /// A package with a source which can be fetched
+    Package(Box<Package>),
+
+    /// A package with no artifact to fetch, such as a reference to the root package of the project
+    /// (`root:`) or a package linked with `bun link` (`link:`)
+    Unfetchable {
+        /// The identifier of the package in the lockfile
+        id: String,
+
+        /// The specifier the package is resolved to, such as `root:` or `link:my-package`
+        spec: String,
+    },
+}
 
 /// # Package Deserializer
 ///
@@ -18,24 +73,85 @@ pub struct PackageDeserializer {
     /// The name for the package
     pub name: String,
 
-    /// The list of serde json values for the tuple in question
+    /// The list of values for the tuple in question
     pub values: Values,
 }
 
 impl PackageDeserializer {
     /// # Deserialize package
     ///
-    /// Deserialize a given package from it's lockfile representation
-    pub fn deserialize_package(name: String, values: Values) -> Result<Package> {
+    /// Deserialize a given package from it's lockfile representation, detecting entries which
+    /// have nothing to fetch rather than giving them a bogus source
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::lockfile::{EntryValue, LockfileEntry, PackageDeserializer};
+    ///
+    /// let values = vec![
+    ///     EntryValue::String("my-lib@link:my-lib".to_owned()),
+    ///     EntryValue::Ignored,
+    /// ];
+    ///
+    /// let entry = PackageDeserializer::deserialize_package("my-lib".to_owned(), values).unwrap();
+    ///
+    /// assert!(matches!(
+    ///     entry,
+    ///     LockfileEntry::Unfetchable { spec, .. } if spec == "link:my-lib"
+    /// ));
+    /// ```
+    pub fn deserialize_package(name: String, mut values: Values) -> Result<LockfileEntry> {
         let arity = values.len();
+
+        if let Some((id, spec)) = Self::unfetchable_spec(&values).filter(|_| arity == 2) {
+            return Ok(LockfileEntry::Unfetchable {
+                id: id.to_owned(),
+                spec: spec.to_owned(),
+            });
+        }
+
+        let PackageMetadata { mut platform, bin } = Self::take_metadata(&mut values);
         let deserializer = Self { name, values };
 
-        match arity {
+        let pkg = match arity {
             1 => deserializer.deserialize_workspace_package(),
             2 => deserializer.deserialize_tarball_or_file_package(),
             3 => deserializer.deserialize_git_or_github_package(),
             4 => deserializer.deserialize_npm_package(),
             x => Err(Error::UnexpectedPackageEntryLength(x)),
+        }?;
+
+        platform.infer_libc(&pkg.name);
+
+        Ok(LockfileEntry::Package(Box::new(
+            pkg.with_platform(platform).with_bin(bin),
+        )))
+    }
+
+    /// The identifier and specifier of an entry, if it resolves to a specifier with nothing to
+    /// fetch
+    fn unfetchable_spec(values: &Values) -> Option<(&str, &str)> {
+        let Some(EntryValue::String(id)) = values.first() else {
+            return None;
+        };
+
+        let spec = &id[id.rfind('@')? + 1..];
+
+        UNFETCHABLE_SPECIFIERS
+            .iter()
+            .any(|prefix| spec.starts_with(prefix))
+            .then_some((id, spec))
+    }
+
+    /// Take the platforms the package supports and the binaries it links out of its metadata,
+    /// leaving it ignored
+    fn take_metadata(values: &mut Values) -> PackageMetadata {
+        let metadata = values
+            .iter_mut()
+            .find(|value| matches!(value, EntryValue::Metadata(_)));
+
+        match metadata.map(|value| mem::replace(value, EntryValue::Ignored)) {
+            Some(EntryValue::Metadata(metadata)) => metadata,
+            _ => PackageMetadata::default(),
         }
     }
 
@@ -66,14 +182,38 @@ impl PackageDeserializer {
     pub fn deserialize_git_or_github_package(mut self) -> Result<Package> {
         let mut id = swap_remove_value(&mut self.values, 0);
 
-        let at_pos = id.rfind('@').ok_or(Error::NoAtInPackageIdentifier)?;
+        // The url may contain a user, as in `git+ssh://git@host/repo`, so the version starts
+        // at the first `@` after any leading `@` of a scoped name
+        let at_pos = version_at(&id)?;
         id.drain(..=at_pos);
 
-        if id.starts_with("github:") {
-            Self::deserialize_github_package(id)
+        // The entry ends with what bun resolved the ref to, which the removal moved to the front,
+        // as `<commit>` for git and `<owner>-<repo>-<commit>` for GitHub
+        let resolved = match self.values.first() {
+            Some(EntryValue::String(resolved)) => resolved.rsplit('-').next(),
+            _ => None,
+        };
+
+        let mut pkg = if id.starts_with("github:") {
+            Self::deserialize_github_package(id)?
         } else {
-            Self::deserialize_git_package(id)
+            Self::deserialize_git_package(id)?
+        };
+
+        let declared = match &pkg.fetcher {
+            Fetcher::FetchGit { rev, .. } | Fetcher::FetchGitHub { rev, .. } if !is_commit(rev) => {
+                rev.clone()
+            }
+            _ => return Ok(pkg),
+        };
+
+        // Refs bun could not pin are pinned while generating instead
+        match resolved.filter(|resolved| is_commit(resolved)) {
+            Some(commit) => pkg.pin_git_rev(commit),
+            None => pkg.git_ref = Some(declared),
         }
+
+        Ok(pkg)
     }
 
     /// # Deserialize a Github Package
@@ -84,9 +224,6 @@ impl PackageDeserializer {
     pub fn deserialize_github_package(id: String) -> Result<Package> {
         let (url, rev) = split_once_owned(id, '#').ok_or(Error::MissingGitRef)?;
 
-        let prefetch_url = format!("{}?ref={}", &url, &rev);
-        let prefetch = Prefetch::prefetch_package(&prefetch_url)?;
-
         let (owner_with_pre, repo) = split_once_owned(url, '/').ok_or(Error::ImproperGithubUrl)?;
         let owner = drop_prefix(owner_with_pre, "github:");
 
@@ -96,7 +233,7 @@ impl PackageDeserializer {
             owner,
             repo,
             rev,
-            hash: prefetch.hash,
+            hash: String::new(),
         };
 
         Ok(Package::new(id_with_ver, fetcher))
@@ -111,15 +248,13 @@ impl PackageDeserializer {
         let git_url = drop_prefix(id, "git+");
         let (url, rev) = split_once_owned(git_url, '#').ok_or(Error::MissingGitRef)?;
 
-        let prefetch_url = format!("git+{}?rev={}", &url, &rev);
-        let prefetch = Prefetch::prefetch_package(&prefetch_url)?;
-
         let id_with_rev = format!("git:{}", &rev);
 
         let fetcher = Fetcher::FetchGit {
             url,
             rev,
-            hash: prefetch.hash,
+            hash: String::new(),
+            fetch_lfs: false,
         };
 
         Ok(Package::new(id_with_rev, fetcher))
@@ -132,16 +267,71 @@ impl PackageDeserializer {
     ///
     /// These are grouped together as both lockfile
     /// representations are a tupe of arity 2, hence
-    /// paths starting with `http` are considered
-    /// tarballs
+    /// they are told apart by the protocol of their
+    /// specifier, with specifiers without one
+    /// considered paths
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{
+    ///     Error,
+    ///     lockfile::{EntryValue, LockfileEntry, PackageDeserializer},
+    ///     package::Fetcher,
+    /// };
+    ///
+    /// let entry = |id: &str| {
+    ///     let values = vec![EntryValue::String(id.to_owned()), EntryValue::Ignored];
+    ///
+    ///     PackageDeserializer::deserialize_package("my-lib".to_owned(), values)
+    /// };
+    ///
+    /// // A yarn portal is copied into place like any other local package
+    /// let Ok(LockfileEntry::Package(pkg)) = entry("my-lib@portal:../my-lib") else {
+    ///     panic!("expected a package");
+    /// };
+    /// assert_eq!(pkg.fetcher, Fetcher::CopyToStore { path: "../my-lib".to_owned() });
+    ///
+    /// // A yarn patch is reported, as bun does not apply it
+    /// assert!(matches!(
+    ///     entry("my-lib@patch:my-lib@npm%3A1.0.0#./patches/my-lib.patch"),
+    ///     Err(Error::UnsupportedProtocol { protocol, .. }) if protocol == "patch:"
+    /// ));
+    ///
+    /// // As is any other protocol which is not known
+    /// assert!(matches!(
+    ///     entry("my-lib@exec:./build.js"),
+    ///     Err(Error::UnsupportedProtocol { protocol, .. }) if protocol == "exec:"
+    /// ));
+    ///
+    /// // Identifiers without a version are reported rather than panicking
+    /// assert!(matches!(entry(""), Err(Error::NoAtInPackageIdentifier)));
+    /// assert!(matches!(entry("é@file:x"), Ok(LockfileEntry::Package(_))));
+    /// assert!(matches!(entry("é"), Err(Error::NoAtInPackageIdentifier)));
+    /// ```
     pub fn deserialize_tarball_or_file_package(mut self) -> Result<Package> {
         let id = swap_remove_value(&mut self.values, 0);
-        let path = Self::drain_after_substring(id, "@").ok_or(Error::NoAtInPackageIdentifier)?;
+        let spec = &id[version_at(&id)? + 1..];
 
-        if path.starts_with("http") {
-            Self::deserialize_tarball_package(path)
-        } else {
-            Self::deserialize_file_package(self.name, path)
+        let resolver = PROTOCOL_RESOLVERS
+            .iter()
+            .find(|(protocol, _)| spec.starts_with(protocol));
+
+        if let Some((_, resolve)) = resolver {
+            return resolve(self.name, &id, spec);
+        }
+
+        #[cfg(feature = "plugins")]
+        if let Some(resolved) = crate::plugins::resolve_with_active(&id, spec) {
+            return resolved;
+        }
+
+        match protocol_of(spec) {
+            Some(protocol) => Err(Error::UnsupportedProtocol {
+                id: id.clone(),
+                protocol: protocol.to_owned(),
+                reason: "is not known to `bun2nix`",
+            }),
+            None => Self::deserialize_file_package(self.name, spec.to_owned()),
         }
     }
 
@@ -156,6 +346,8 @@ impl PackageDeserializer {
             "File path can never contain http, because then it would be a tarball"
         );
 
+        let path = normalize_path(&path);
+
         Ok(Package::new(name, Fetcher::CopyToStore { path }))
     }
 
@@ -167,12 +359,10 @@ impl PackageDeserializer {
     pub fn deserialize_tarball_package(url: String) -> Result<Package> {
         debug_assert!(url.contains("http"), "Expected tarball url to contain http");
 
-        let prefetch = Prefetch::prefetch_package(&url)?;
-
         let name = format!("tarball:{}", url);
         let fetcher = Fetcher::FetchTarball {
             url,
-            hash: prefetch.hash,
+            hash: String::new(),
         };
 
         Ok(Package::new(name, fetcher))
@@ -188,6 +378,8 @@ impl PackageDeserializer {
         let path = Self::drain_after_substring(id, "workspace:")
             .ok_or(Error::MissingWorkspaceSpecifier)?;
 
+        let path = normalize_path(&path);
+
         Ok(Package::new(self.name, Fetcher::CopyToStore { path }))
     }
 
@@ -200,18 +392,17 @@ impl PackageDeserializer {
 
 /// # Swap Remove `Value`
 ///
-/// Remove a value from a serde_json `Values` array, and take ownership
-/// of it in a fast way by swapping in the final value of the array.
+/// Remove a string from a package tuple, and take ownership of it in a
+/// fast way by swapping in the final value of the tuple.
 ///
 ///```rust
-/// use bun2nix::lockfile::swap_remove_value;
-/// use serde_json::json;
+/// use bun2nix::lockfile::{EntryValue, swap_remove_value};
 ///
 /// let mut values = vec![
-///  json!("@types/bun@1.2.4"),
-///  json!({}),
-///  json!([]),
-///  json!("sha512-QtuV5OMR8/rdKJs213iwXDpfVvnskPXY/S0ZiFbsTjQZycuqPbMW8Gf/XhLfwE5njW8sxI2WjISURXPlHypMFA==")
+///  EntryValue::String("@types/bun@1.2.4".to_owned()),
+///  EntryValue::Ignored,
+///  EntryValue::Ignored,
+///  EntryValue::String("sha512-QtuV5OMR8/rdKJs213iwXDpfVvnskPXY/S0ZiFbsTjQZycuqPbMW8Gf/XhLfwE5njW8sxI2WjISURXPlHypMFA==".to_owned())
 /// ];
 ///
 /// assert_eq!(
@@ -224,12 +415,13 @@ impl PackageDeserializer {
 /// );
 /// ```
 pub fn swap_remove_value(values: &mut Values, index: usize) -> String {
-    let mut value = values.swap_remove(index).to_string();
-
-    debug_assert!(value.starts_with('"'), "Value should start with a quote");
-    debug_assert!(value.ends_with('"'), "Value should end with a quote");
-
-    value.drain(1..value.len() - 1).collect()
+    match values.swap_remove(index) {
+        EntryValue::String(value) => value,
+        EntryValue::Ignored | EntryValue::Metadata(_) => {
+            debug_assert!(false, "Value should be a string");
+            String::new()
+        }
+    }
 }
 
 /// # Split Once (Owned)
@@ -278,3 +470,33 @@ pub fn drop_prefix(mut input: String, prefix: &str) -> String {
 
     input
 }
+
+/// Where the `@` before the version of a package identifier is, skipping the leading `@` of a
+/// scoped name
+fn version_at(id: &str) -> Result<usize> {
+    id.char_indices()
+        .skip(1)
+        .find(|(_, c)| *c == '@')
+        .map(|(pos, _)| pos)
+        .ok_or(Error::NoAtInPackageIdentifier)
+}
+
+/// What a specifier gives after its protocol
+fn after_protocol(spec: &str) -> String {
+    spec.split_once(':')
+        .map_or(spec, |(_, rest)| rest)
+        .to_owned()
+}
+
+/// The protocol a specifier is written with, such as `patch:`, if it has one
+fn protocol_of(spec: &str) -> Option<&str> {
+    let end = spec.find(':')? + 1;
+    let scheme = &spec[..end - 1];
+
+    let is_scheme = !scheme.is_empty()
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    is_scheme.then_some(&spec[..end])
+}
@@ -1,10 +1,29 @@
-use std::fmt;
+use std::{cell::RefCell, fmt, sync::mpsc::Sender};
 
+use log::info;
 use serde::de::{self, MapAccess, Visitor};
 
-use super::PackageDeserializer;
+use super::{EntryValue, LockfileEntry, PackageDeserializer};
 use crate::Package;
 
+thread_local! {
+    /// Where packages deserialized on this thread are also sent as soon as they are read, if
+    /// anywhere
+    static SINK: RefCell<Option<Sender<Package>>> = const { RefCell::new(None) };
+}
+
+/// # Stream Packages
+///
+/// Run `parse`, sending every package deserialized on this thread during it to `sink` as soon
+/// as it is read. The sink is dropped afterwards, so receivers see the end of the packages.
+pub fn stream_packages<T>(sink: Sender<Package>, parse: impl FnOnce() -> T) -> T {
+    SINK.with(|cell| cell.replace(Some(sink)));
+    let parsed = parse();
+    SINK.with(|cell| cell.take());
+
+    parsed
+}
+
 /// # Package Visitor
 ///
 /// Used for a custom serde deserialize method as the most ergonomic rust package data type does
@@ -24,12 +43,29 @@ impl<'de> Visitor<'de> for PackageVisitor {
     {
         let mut packages = Vec::new();
 
-        while let Some((name, values)) = map.next_entry::<String, Vec<serde_json::Value>>()? {
-            let pkg = PackageDeserializer::deserialize_package(name, values).map_err(|err| {
-                de::Error::custom(format!("Failed to deserialize package: {}", err))
-            })?;
+        while let Some((name, values)) = map.next_entry::<String, Vec<EntryValue>>()? {
+            let entry =
+                PackageDeserializer::deserialize_package(name.clone(), values).map_err(|err| {
+                    de::Error::custom(format!("Failed to deserialize package: {}", err))
+                })?;
+
+            match entry {
+                LockfileEntry::Package(pkg) => {
+                    let pkg = pkg.with_lockfile_key(name);
+
+                    SINK.with(|cell| {
+                        if let Some(sink) = &*cell.borrow() {
+                            // The receiver hanging up only means nobody wants the packages early
+                            let _ = sink.send(pkg.clone());
+                        }
+                    });
 
-            packages.push(pkg);
+                    packages.push(pkg);
+                }
+                LockfileEntry::Unfetchable { id, spec } => {
+                    info!("Skipping `{id}`, which bun links to `{spec}` rather than fetching")
+                }
+            }
         }
 
         Ok(packages)
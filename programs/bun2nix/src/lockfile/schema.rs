@@ -0,0 +1,361 @@
+use std::{cell::RefCell, fmt};
+
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+use super::JsoncDeserializer;
+use crate::error::{Error, Result};
+
+/// The shape a value in the lockfile must have
+#[derive(Debug)]
+enum Shape {
+    String,
+    Integer,
+    /// Either a string or an array of strings
+    StringOrList,
+    /// An object with the given fields, where any other field may have any value
+    Object {
+        fields: &'static [(&'static str, Shape)],
+        required: &'static [&'static str],
+    },
+    /// An object whose values all have the given shape
+    Map(&'static Shape),
+    /// An array whose items all have the given shape
+    List(&'static Shape),
+    /// The tuple of a package, whose items depend on how many there are
+    Package,
+    /// An item of a package tuple, whose kind is checked once the length of the tuple is known
+    PackageItem,
+}
+
+/// The kind of a value which was found in the lockfile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    String,
+    Number,
+    Boolean,
+    Null,
+    Object,
+    Array,
+}
+
+static LOCKFILE: Shape = Shape::Object {
+    fields: &[
+        ("lockfileVersion", Shape::Integer),
+        ("workspaces", Shape::Map(&WORKSPACE)),
+        ("packages", Shape::Map(&Shape::Package)),
+        ("trustedDependencies", Shape::List(&Shape::String)),
+    ],
+    required: &["lockfileVersion", "packages"],
+};
+
+static WORKSPACE: Shape = Shape::Object {
+    fields: &[
+        ("name", Shape::String),
+        ("version", Shape::String),
+        ("dependencies", Shape::Map(&Shape::String)),
+        ("devDependencies", Shape::Map(&Shape::String)),
+        ("optionalDependencies", Shape::Map(&Shape::String)),
+        ("peerDependencies", Shape::Map(&Shape::String)),
+        ("optionalPeers", Shape::List(&Shape::String)),
+    ],
+    required: &[],
+};
+
+static METADATA: Shape = Shape::Object {
+    fields: &[
+        ("dependencies", Shape::Map(&Shape::String)),
+        ("devDependencies", Shape::Map(&Shape::String)),
+        ("optionalDependencies", Shape::Map(&Shape::String)),
+        ("peerDependencies", Shape::Map(&Shape::String)),
+        ("optionalPeers", Shape::List(&Shape::String)),
+        ("bin", Shape::Map(&Shape::String)),
+        ("binDir", Shape::String),
+        ("os", Shape::StringOrList),
+        ("cpu", Shape::StringOrList),
+    ],
+    required: &[],
+};
+
+/// The kinds of the items of a package tuple, by the number of items it has
+const PACKAGE_TUPLES: [&[Kind]; 4] = [
+    // Workspace packages
+    &[Kind::String],
+    // Tarball and file packages
+    &[Kind::String, Kind::Object],
+    // Git and GitHub packages
+    &[Kind::String, Kind::Object, Kind::String],
+    // npm packages
+    &[Kind::String, Kind::String, Kind::Object, Kind::String],
+];
+
+/// # Validate Lockfile Schema
+///
+/// Check that a bun lockfile has the structure `bun2nix` expects, reporting the path to the
+/// first value which does not, such as `packages["is-odd"][3]`.
+///
+/// The lockfile is streamed rather than parsed into memory, and only the parts of it which
+/// `bun2nix` reads are checked. Syntax errors are left to be reported when the lockfile is
+/// deserialized.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::lockfile::validate_schema;
+///
+/// let lockfile = r#"
+/// {
+///   "lockfileVersion": 1,
+///   "packages": {
+///     "is-odd": ["is-odd@3.0.1", "", { "bin": "cli.js" }, "sha512-abc"],
+///   },
+/// }
+/// "#;
+///
+/// assert_eq!(
+///     validate_schema(lockfile).unwrap_err().to_string().lines().next().unwrap(),
+///     r#"Invalid lockfile at `packages["is-odd"][2].bin`: expected an object, found a string."#
+/// );
+///
+/// assert!(validate_schema(r#"{ "lockfileVersion": 1, "packages": {} }"#).is_ok());
+/// ```
+pub fn validate_schema(lockfile: &str) -> Result<()> {
+    let invalid = RefCell::new(None);
+
+    let check = Check {
+        shape: &LOCKFILE,
+        path: String::new(),
+        invalid: &invalid,
+    };
+
+    let _ = check.deserialize(&mut JsoncDeserializer::new(lockfile));
+
+    match invalid.into_inner() {
+        Some((path, message)) => Err(Error::InvalidLockfile { path, message }),
+        None => Ok(()),
+    }
+}
+
+/// Checks a value against a shape, recording the first mismatch found
+struct Check<'a> {
+    shape: &'static Shape,
+    path: String,
+    invalid: &'a RefCell<Option<(String, String)>>,
+}
+
+impl Check<'_> {
+    fn child(&self, shape: &'static Shape, path: String) -> Self {
+        Self {
+            shape,
+            path,
+            invalid: self.invalid,
+        }
+    }
+
+    fn field(&self, name: &str, shape: &'static Shape) -> Self {
+        match self.path.is_empty() {
+            true => self.child(shape, name.to_owned()),
+            false => self.child(shape, format!("{}.{name}", self.path)),
+        }
+    }
+
+    fn key(&self, key: &str, shape: &'static Shape) -> Self {
+        self.child(shape, format!("{}[{key:?}]", self.path))
+    }
+
+    fn index(&self, index: usize, shape: &'static Shape) -> Self {
+        self.child(shape, format!("{}[{index}]", self.path))
+    }
+
+    /// Record a mismatch at this value, returning an error to stop deserializing
+    fn fail<E: de::Error>(&self, message: String) -> E {
+        self.invalid
+            .borrow_mut()
+            .get_or_insert_with(|| (self.path.clone(), message));
+
+        E::custom("invalid lockfile")
+    }
+
+    fn mismatch<E: de::Error>(&self, found: Kind) -> E {
+        self.fail(format!(
+            "expected {}, found {}",
+            self.shape.expected(),
+            found.describe()
+        ))
+    }
+
+    fn accept<E: de::Error>(&self, found: Kind) -> std::result::Result<Kind, E> {
+        let accepted = match self.shape {
+            Shape::PackageItem => true,
+            Shape::String | Shape::StringOrList => found == Kind::String,
+            Shape::Integer => found == Kind::Number,
+            Shape::Object { .. } | Shape::Map(_) => found == Kind::Object,
+            Shape::List(_) | Shape::Package => found == Kind::Array,
+        };
+
+        match accepted {
+            true => Ok(found),
+            false => Err(self.mismatch(found)),
+        }
+    }
+
+    fn check_package<'de, S: SeqAccess<'de>>(
+        &self,
+        mut seq: S,
+    ) -> std::result::Result<Kind, S::Error> {
+        let mut kinds = Vec::new();
+
+        while let Some(kind) =
+            seq.next_element_seed(self.index(kinds.len(), &Shape::PackageItem))?
+        {
+            kinds.push(kind);
+        }
+
+        let Some(expected) = PACKAGE_TUPLES.get(kinds.len().wrapping_sub(1)) else {
+            return Err(self.fail(format!(
+                "expected a package tuple of 1 to 4 items, found {} items",
+                kinds.len()
+            )));
+        };
+
+        match kinds
+            .iter()
+            .zip(*expected)
+            .position(|(kind, expected)| kind != expected)
+        {
+            Some(i) => Err(self.index(i, &Shape::PackageItem).fail(format!(
+                "expected {} in a package tuple of {} items, found {}",
+                expected[i].describe(),
+                kinds.len(),
+                kinds[i].describe()
+            ))),
+            None => Ok(Kind::Array),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Check<'_> {
+    type Value = Kind;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Kind, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Check<'_> {
+    type Value = Kind;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.shape.expected())
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> std::result::Result<Kind, E> {
+        self.accept(Kind::String)
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> std::result::Result<Kind, E> {
+        self.accept(Kind::Boolean)
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> std::result::Result<Kind, E> {
+        self.accept(Kind::Number)
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> std::result::Result<Kind, E> {
+        self.accept(Kind::Number)
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> std::result::Result<Kind, E> {
+        match self.shape {
+            Shape::Integer => Err(self.fail("expected an integer, found a fraction".to_owned())),
+            _ => self.accept(Kind::Number),
+        }
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Kind, E> {
+        self.accept(Kind::Null)
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> std::result::Result<Kind, M::Error> {
+        match self.shape {
+            Shape::Object { fields, required } => {
+                let mut seen = Vec::new();
+
+                while let Some(name) = map.next_key::<String>()? {
+                    match fields.iter().find(|(field, _)| *field == name) {
+                        Some((field, shape)) => {
+                            map.next_value_seed(self.field(field, shape))?;
+                            seen.push(*field);
+                        }
+                        None => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                match required.iter().find(|field| !seen.contains(field)) {
+                    Some(field) => Err(self.fail(format!("missing field `{field}`"))),
+                    None => Ok(Kind::Object),
+                }
+            }
+            Shape::Map(shape) => {
+                while let Some(key) = map.next_key::<String>()? {
+                    map.next_value_seed(self.key(&key, shape))?;
+                }
+
+                Ok(Kind::Object)
+            }
+            Shape::PackageItem => self.child(&METADATA, self.path.clone()).visit_map(map),
+            _ => Err(self.mismatch(Kind::Object)),
+        }
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> std::result::Result<Kind, S::Error> {
+        let shape = match self.shape {
+            Shape::List(shape) => shape,
+            Shape::StringOrList => &Shape::String,
+            Shape::Package => return self.check_package(seq),
+            Shape::PackageItem => {
+                IgnoredAny.visit_seq(seq)?;
+
+                return Ok(Kind::Array);
+            }
+            _ => return Err(self.mismatch(Kind::Array)),
+        };
+
+        let mut index = 0;
+        while seq.next_element_seed(self.index(index, shape))?.is_some() {
+            index += 1;
+        }
+
+        Ok(Kind::Array)
+    }
+}
+
+impl Shape {
+    /// A description of the values which have this shape
+    fn expected(&self) -> &'static str {
+        match self {
+            Self::PackageItem => "a string or an object",
+            Self::String => "a string",
+            Self::Integer => "an integer",
+            Self::StringOrList => "a string or an array of strings",
+            Self::Object { .. } | Self::Map(_) => "an object",
+            Self::List(_) | Self::Package => "an array",
+        }
+    }
+}
+
+impl Kind {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::String => "a string",
+            Self::Number => "a number",
+            Self::Boolean => "a boolean",
+            Self::Null => "null",
+            Self::Object => "an object",
+            Self::Array => "an array",
+        }
+    }
+}
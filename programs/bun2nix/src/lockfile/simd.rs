@@ -0,0 +1,83 @@
+use super::Lockfile;
+use crate::error::{Error, Result};
+
+/// # Parse With simd-json
+///
+/// Parse a bun lockfile by first stripping it down to plain JSON and then handing it to
+/// simd-json, which is considerably faster than the portable JSONC parser on large lockfiles.
+pub fn parse(lockfile: &str) -> Result<Lockfile> {
+    let mut json = strip_jsonc(lockfile).into_bytes();
+
+    if json.iter().all(u8::is_ascii_whitespace) {
+        return Err(Error::NoJsoncValue);
+    }
+
+    simd_json::serde::from_slice(&mut json).map_err(Error::SimdJson)
+}
+
+/// # Strip JSONC
+///
+/// Turn JSONC text into plain JSON by removing comments and trailing commas, leaving strings
+/// untouched.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::lockfile::strip_jsonc;
+///
+/// assert_eq!(
+///     strip_jsonc("{ \"a\": [1, 2,], /* \"b\": 3 */ } // \"c\",\n"),
+///     "{ \"a\": [1, 2 ]   } \n"
+/// );
+/// assert_eq!(strip_jsonc("\"// not a comment,]\""), "\"// not a comment,]\"");
+/// ```
+pub fn strip_jsonc(text: &str) -> String {
+    let mut json = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '"' => {
+                json.push(char);
+
+                while let Some(char) = chars.next() {
+                    json.push(char);
+
+                    match char {
+                        '\\' => json.extend(chars.next()),
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&char| char != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                while let Some(char) = chars.next() {
+                    if char == '*' && chars.next_if_eq(&'/').is_some() {
+                        break;
+                    }
+                }
+            }
+            '}' | ']' => {
+                drop_trailing_comma(&mut json);
+                json.push(char);
+            }
+            char => json.push(char),
+        }
+    }
+
+    json
+}
+
+/// Replace a comma before the closing brace or bracket about to be written with a space
+fn drop_trailing_comma(json: &mut String) {
+    let trimmed = json.trim_end_matches(|char: char| char.is_ascii_whitespace());
+
+    if trimmed.ends_with(',') {
+        let comma = trimmed.len() - 1;
+        json.replace_range(comma..=comma, " ");
+    }
+}
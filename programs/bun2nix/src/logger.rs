@@ -0,0 +1,58 @@
+//! Logging for the command line tool, which counts the warnings logged so that they can be
+//! reported at the end of a run
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use env_logger::{Env, Logger};
+use log::{Level, Log, Metadata, Record};
+
+use crate::events::{self, Event};
+
+/// The number of warnings logged so far
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// An `env_logger` logger which counts the warnings it logs
+struct CountingLogger(Logger);
+
+impl Log for CountingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() == Level::Warn && self.0.matches(record) {
+            WARNINGS.fetch_add(1, Ordering::Relaxed);
+
+            events::emit(Event::Warning {
+                message: record.args().to_string(),
+            });
+        }
+
+        // Only events are written when they are enabled, so stderr stays machine readable
+        if !events::enabled() {
+            self.0.log(record)
+        }
+    }
+
+    fn flush(&self) {
+        self.0.flush()
+    }
+}
+
+/// # Initialize Logger
+///
+/// Log at the given level, unless overridden by `RUST_LOG`
+pub fn init(default_level: &str) {
+    let env = Env::default().default_filter_or(default_level);
+    let logger = env_logger::Builder::from_env(env).build();
+
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(CountingLogger(logger))).expect("logger is only set once");
+}
+
+/// # Warnings
+///
+/// The number of warnings logged so far
+pub fn warnings() -> usize {
+    WARNINGS.load(Ordering::Relaxed)
+}
@@ -3,59 +3,208 @@
 
 #![warn(missing_docs)]
 
-use bun2nix::{Result, convert_lockfile_to_nix_expression};
-use log::error;
-
-use std::{
-    fs::{self, File},
-    io::Write,
-    path::PathBuf,
-};
-
-use clap::Parser;
-use env_logger::Env;
-
-/// Convert Bun (v1.2+) packages to Nix expressions
-#[derive(Debug, Parser)]
-#[command(version, about, long_about = None)]
-pub struct Cli {
-    /// The Bun (v1.2+) lockfile to use to produce the Nix expression.
-    #[arg(short, long, default_value = "./bun.lock")]
-    lock_file: PathBuf,
-
-    /// The output file to write to -
-    /// if no file location is provided, print to stdout instead.
-    #[arg(short, long)]
-    output_file: Option<PathBuf>,
-}
+use bun2nix::{Error, Result};
+use log::{error, warn};
+
+mod cli;
+mod commands;
+mod events;
+mod logger;
+#[cfg(unix)]
+mod signals;
+#[cfg(feature = "tui")]
+mod tui;
+
+use cli::{Cli, Command, GlobalArgs};
+use events::Event;
 
 fn main() {
-    let log_env = Env::default().default_filter_or("warn");
-    env_logger::Builder::from_env(log_env).init();
+    let cli = Cli::parse_args();
+
+    if cli.global.events {
+        events::enable();
+    }
+
+    logger::init(log_level(&cli.global));
+
+    #[cfg(unix)]
+    if let Err(err) = signals::install() {
+        warn!("Failed to install signal handlers, interrupting will abort immediately: {err}");
+    }
+
+    let result = run(cli);
+
+    events::emit(Event::Done {
+        success: result.is_ok(),
+        error: result.as_ref().err().map(ToString::to_string),
+    });
 
-    match run() {
+    match result {
         Ok(()) => (),
         Err(err) => {
             error!("\n{err}\n");
 
-            std::process::exit(1)
+            std::process::exit(exit_code(&err))
         }
     }
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
+/// The status to exit with after a failed run, which is distinct when it was interrupted
+fn exit_code(err: &Error) -> i32 {
+    match err {
+        #[cfg(unix)]
+        Error::Interrupted => signals::interrupted_exit_code(),
+        #[cfg(not(unix))]
+        Error::Interrupted => 130,
+        _ => 1,
+    }
+}
 
-    let lockfile = fs::read_to_string(&cli.lock_file)?;
+fn log_level(global: &GlobalArgs) -> &'static str {
+    match (global.quiet, global.verbose) {
+        (true, _) => "error",
+        (false, 0) => "warn",
+        (false, 1) => "info",
+        (false, _) => "debug",
+    }
+}
 
-    let nix = convert_lockfile_to_nix_expression(lockfile)?;
+/// Run the command, consulting the resolver plugins for every lockfile it parses
+fn run(cli: Cli) -> Result<()> {
+    #[cfg(feature = "plugins")]
+    if !cli.global.plugins.is_empty() {
+        let plugins = bun2nix::plugins::Plugins::load(&cli.global.plugins)?;
 
-    if let Some(output_file) = cli.output_file {
-        let mut output = File::create(output_file)?;
-        write!(output, "{nix}")?;
-    } else {
-        println!("{nix}");
+        return bun2nix::plugins::with_plugins(plugins, || dispatch(cli));
     }
 
-    Ok(())
+    dispatch(cli)
+}
+
+fn dispatch(cli: Cli) -> Result<()> {
+    let global = &cli.global;
+
+    match cli.command {
+        None => commands::generate(cli.generate, global),
+        Some(Command::Generate(generate)) => commands::generate(*generate, global),
+        Some(Command::Check {
+            lock_file,
+            nix_file,
+        }) => commands::check(&lock_file, &nix_file),
+        Some(Command::Status {
+            lock_file,
+            node_modules,
+            nix_file,
+        }) => commands::status(&lock_file, &nix_file, &node_modules),
+        Some(Command::Why { name, lock_file }) => commands::why(&name, &lock_file),
+        Some(Command::Tree {
+            workspace,
+            lock_file,
+            depth,
+        }) => commands::tree(&workspace, &lock_file, depth),
+        Some(Command::Init {
+            dir,
+            merge_driver,
+            devshell,
+            shell,
+            direnv,
+            flake,
+            prefetch,
+        }) => commands::init(
+            &dir,
+            merge_driver,
+            devshell || direnv,
+            &shell,
+            direnv,
+            flake,
+            &prefetch,
+        ),
+        Some(Command::Setup {
+            dir,
+            script,
+            output_file,
+        }) => commands::setup(&dir, script.into(), &output_file),
+        #[cfg(feature = "resolver")]
+        Some(Command::Audit {
+            lock_file,
+            max_total_size,
+            allow_deprecated,
+            strict_scripts,
+            size_report,
+            jobs,
+        }) => commands::audit(
+            &lock_file,
+            max_total_size,
+            allow_deprecated,
+            strict_scripts,
+            size_report,
+            jobs,
+            global,
+        ),
+        #[cfg(feature = "resolver")]
+        Some(Command::Pkg { spec, format }) => commands::pkg(&spec, format, global),
+        #[cfg(feature = "resolver")]
+        Some(Command::Tool { spec, output_file }) => commands::tool(&spec, output_file, global),
+        #[cfg(feature = "cache")]
+        Some(Command::Cache {
+            command,
+            cache_file,
+            shared,
+        }) => commands::cache(command, cache_file, shared),
+        Some(Command::DiffNix { old, new }) => commands::diff_nix(old, new),
+        Some(Command::DiffLock {
+            old,
+            new,
+            format,
+            #[cfg(feature = "resolver")]
+            sizes,
+            #[cfg(feature = "resolver")]
+            jobs,
+        }) => commands::diff_lock(
+            &old,
+            &new,
+            format,
+            #[cfg(feature = "resolver")]
+            sizes,
+            #[cfg(feature = "resolver")]
+            jobs,
+            #[cfg(feature = "resolver")]
+            global,
+        ),
+        #[cfg(feature = "schema")]
+        Some(Command::Schema { kind }) => commands::schema(kind),
+        #[cfg(feature = "template")]
+        Some(Command::Template { command }) => commands::template(command),
+        Some(Command::MergeDriver {
+            ancestor,
+            current,
+            other,
+            ..
+        }) => commands::merge_driver(ancestor, current, other),
+        Some(Command::InstallMergeDriver { repo, pattern }) => {
+            bun2nix::merge_driver::install(&repo, &pattern)
+        }
+        Some(Command::ImportNode2nix {
+            node_packages,
+            output_file,
+        }) => commands::import_node2nix(node_packages, output_file),
+        #[cfg(feature = "resolver")]
+        Some(Command::Verify {
+            nix_file,
+            lock_file,
+            prefetch,
+        }) => commands::verify(nix_file, lock_file, &prefetch, global),
+        Some(Command::Doctor {
+            nix_bin,
+            #[cfg(feature = "cache")]
+            cache_file,
+        }) => commands::doctor(
+            &nix_bin,
+            #[cfg(feature = "cache")]
+            cache_file,
+            global,
+        ),
+        #[cfg(feature = "dev")]
+        Some(Command::Dev { command }) => commands::dev(command),
+    }
 }
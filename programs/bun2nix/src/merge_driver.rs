@@ -0,0 +1,188 @@
+//! This module holds the implementation of a git merge driver for generated `bun.nix` files
+//!
+//! Each lockfile package maps to exactly one entry in the generated file, so rather than merging
+//! the text, the package sets on each side of the merge are merged and the file is rendered again
+
+use std::{collections::BTreeMap, fs, path::Path, process::Command};
+
+use crate::{
+    Package,
+    diff::PackageDiff,
+    error::{Error, Result},
+    nix_expression::{NixExpression, Parser},
+    package::Fetcher,
+};
+
+/// The name the driver is registered under in git's config
+pub const DRIVER_NAME: &str = "bun2nix";
+
+/// # Merge Generated Files
+///
+/// Three-way merge the contents of generated `bun.nix` files, returning the newly rendered
+/// file, or an error listing the packages which were changed differently on both sides.
+pub fn merge(ancestor: &str, current: &str, other: &str) -> Result<String> {
+    // Take the project, update script, binaries and use of flake inputs from the other side only
+    // if they were changed there
+    let project = match Parser::parse_project(other)? {
+        project if project != Parser::parse_project(ancestor)? => project,
+        _ => Parser::parse_project(current)?,
+    };
+
+    let update_script = match Parser::parse_update_script(other)? {
+        update_script if update_script != Parser::parse_update_script(ancestor)? => update_script,
+        _ => Parser::parse_update_script(current)?,
+    };
+
+    let binaries = match Parser::parse_binaries(other)? {
+        binaries if binaries != Parser::parse_binaries(ancestor)? => binaries,
+        _ => Parser::parse_binaries(current)?,
+    };
+
+    let flake_inputs = match Parser::parse_flake_inputs(other)? {
+        flake_inputs if flake_inputs != Parser::parse_flake_inputs(ancestor)? => flake_inputs,
+        _ => Parser::parse_flake_inputs(current)?,
+    };
+
+    let ancestor = Parser::parse_packages(ancestor)?;
+    let current = Parser::parse_packages(current)?;
+    let other = Parser::parse_packages(other)?;
+
+    let packages = merge_package_sets(ancestor, current, other)?;
+
+    NixExpression::new(packages)?
+        .with_project(project)
+        .with_update_script(update_script)
+        .with_binaries(binaries)
+        .with_flake_inputs(flake_inputs)
+        .render()
+}
+
+/// # Merge Package Sets
+///
+/// Apply the changes made between `ancestor` and `other` on top of `current`.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{Package, merge_driver::merge_package_sets, package::Fetcher};
+///
+/// let pkg = |name: &str| Package::new(name.to_owned(), Fetcher::CopyToStore { path: name.to_owned() });
+///
+/// let ancestor = vec![pkg("a"), pkg("b")];
+/// let current = vec![pkg("a"), pkg("b"), pkg("c")];
+/// let other = vec![pkg("b"), pkg("d")];
+///
+/// let merged = merge_package_sets(ancestor, current, other).unwrap();
+/// let names: Vec<_> = merged.iter().map(|pkg| pkg.name.as_str()).collect();
+///
+/// assert_eq!(names, ["b", "c", "d"]);
+/// ```
+pub fn merge_package_sets(
+    ancestor: Vec<Package>,
+    current: Vec<Package>,
+    other: Vec<Package>,
+) -> Result<Vec<Package>> {
+    let mut merged: BTreeMap<String, Fetcher> = current
+        .into_iter()
+        .map(|pkg| (pkg.name, pkg.fetcher))
+        .collect();
+
+    let base: BTreeMap<&str, &Fetcher> = ancestor
+        .iter()
+        .map(|pkg| (pkg.name.as_str(), &pkg.fetcher))
+        .collect();
+
+    let theirs = PackageDiff::new(ancestor.clone(), other);
+    let mut conflicts = Vec::new();
+
+    for pkg in theirs.added {
+        match merged.get(&pkg.name) {
+            Some(ours) if ours != &pkg.fetcher => conflicts.push(pkg.name),
+            Some(_) => (),
+            None => {
+                merged.insert(pkg.name, pkg.fetcher);
+            }
+        }
+    }
+
+    for pkg in theirs.removed {
+        match merged.get(&pkg.name) {
+            Some(ours) if ours != &pkg.fetcher => conflicts.push(pkg.name),
+            Some(_) => {
+                merged.remove(&pkg.name);
+            }
+            None => (),
+        }
+    }
+
+    for change in theirs.changed {
+        match merged.get(&change.name) {
+            Some(ours) if Some(&ours) == base.get(change.name.as_str()) => {
+                merged.insert(change.name, change.new);
+            }
+            Some(ours) if ours == &change.new => (),
+            _ => conflicts.push(change.name),
+        }
+    }
+
+    if !conflicts.is_empty() {
+        conflicts.sort();
+        return Err(Error::MergeConflict(conflicts));
+    }
+
+    Ok(merged
+        .into_iter()
+        .map(|(name, fetcher)| Package::new(name, fetcher))
+        .collect())
+}
+
+/// # Install Merge Driver
+///
+/// Register the merge driver in the git config of the repository at `repo_root`, and assign it
+/// to files matching `pattern` in the repository's `.gitattributes`
+pub fn install(repo_root: &Path, pattern: &str) -> Result<()> {
+    let driver_key = format!("merge.{DRIVER_NAME}.driver");
+    let name_key = format!("merge.{DRIVER_NAME}.name");
+
+    git_config(repo_root, &name_key, "bun2nix generated file merge driver")?;
+    git_config(repo_root, &driver_key, "bun2nix merge-driver %O %A %B %P")?;
+
+    let attributes_path = repo_root.join(".gitattributes");
+    let attribute = format!("{pattern} merge={DRIVER_NAME}");
+
+    let mut attributes = match fs::read_to_string(&attributes_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+
+    if attributes.lines().any(|line| line.trim() == attribute) {
+        return Ok(());
+    }
+
+    if !attributes.is_empty() && !attributes.ends_with('\n') {
+        attributes.push('\n');
+    }
+
+    attributes.push_str(&attribute);
+    attributes.push('\n');
+
+    fs::write(attributes_path, attributes)?;
+
+    Ok(())
+}
+
+fn git_config(repo_root: &Path, key: &str, value: &str) -> Result<()> {
+    let output = Command::new("git")
+        .current_dir(repo_root)
+        .args(["config", key, value])
+        .output()
+        .map_err(Error::GitCommandFailed)?;
+
+    if !output.status.success() {
+        return Err(Error::GitCommandError(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//! This module holds failover between a registry and an ordered list of its mirrors, checking
+//! that every tarball in a package set can actually be downloaded from at least one of them
+
+use std::{collections::BTreeMap, fmt, sync::Mutex};
+
+use rayon::{ThreadPoolBuilder, prelude::*};
+
+use crate::{
+    Package,
+    error::{Error, Result},
+    package::Fetcher,
+    registry::Registry,
+};
+
+/// # Registry Mirrors
+///
+/// A primary registry along with mirrors to fail over to, in order of preference
+#[derive(Debug, Clone)]
+pub struct Mirrors {
+    registry: Registry,
+    mirrors: Vec<String>,
+}
+
+/// # Tarball Sources
+///
+/// The url each tarball was found to be available from
+#[derive(Debug, Default, Clone)]
+pub struct Sources {
+    /// The url which succeeded, keyed by package name
+    pub used: BTreeMap<String, String>,
+
+    /// The primary url of each package which was served by a mirror, keyed by package name
+    pub primary: BTreeMap<String, String>,
+}
+
+impl Mirrors {
+    /// # New Mirrors
+    ///
+    /// Fail over from the given registry to each of the mirror base urls in order
+    pub fn new(registry: Registry, mirrors: Vec<String>) -> Self {
+        let mirrors = mirrors
+            .into_iter()
+            .map(|mirror| mirror.trim_end_matches('/').to_owned())
+            .collect();
+
+        Self { registry, mirrors }
+    }
+
+    /// # Candidate Urls
+    ///
+    /// Every url a tarball may be downloaded from, in order of preference. Tarballs which are not
+    /// hosted on the primary registry only have their own url as a candidate.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{mirrors::Mirrors, registry::{Registry, DEFAULT_REGISTRY}};
+    ///
+    /// let mirrors = Mirrors::new(
+    ///     Registry::new(DEFAULT_REGISTRY).unwrap(),
+    ///     vec!["https://registry.npmmirror.com/".to_owned()],
+    /// );
+    ///
+    /// assert_eq!(
+    ///     mirrors.candidates("https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz"),
+    ///     [
+    ///         "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz",
+    ///         "https://registry.npmmirror.com/is-odd/-/is-odd-3.0.1.tgz",
+    ///     ]
+    /// );
+    /// assert_eq!(
+    ///     mirrors.candidates("https://example.com/is-odd.tgz"),
+    ///     ["https://example.com/is-odd.tgz"]
+    /// );
+    /// ```
+    pub fn candidates(&self, url: &str) -> Vec<String> {
+        let primary = format!("{}/", self.registry.url());
+
+        let Some(path) = url.strip_prefix(&primary) else {
+            return vec![url.to_owned()];
+        };
+
+        std::iter::once(url.to_owned())
+            .chain(self.mirrors.iter().map(|mirror| format!("{mirror}/{path}")))
+            .collect()
+    }
+
+    /// # Fail Over
+    ///
+    /// Find the first candidate url each tarball in the package set is available from, checking
+    /// up to `jobs` packages at once. Fails if any tarball is unavailable from every candidate.
+    pub fn fail_over(&self, packages: &[Package], jobs: usize) -> Result<Sources> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(jobs.max(1))
+            .build()
+            .map_err(Error::ThreadPool)?;
+
+        let sources = Mutex::new(Sources::default());
+        let unavailable = Mutex::new(Vec::new());
+
+        pool.install(|| {
+            packages.par_iter().for_each(|pkg| {
+                let Fetcher::FetchUrl { url, .. } = &pkg.fetcher else {
+                    return;
+                };
+
+                let available = self
+                    .candidates(url)
+                    .into_iter()
+                    .find(|candidate| self.registry.tarball_available(candidate));
+
+                match available {
+                    Some(used) => sources.lock().unwrap().add(pkg, url, used),
+                    None => unavailable.lock().unwrap().push(pkg.name.clone()),
+                }
+            })
+        });
+
+        let mut unavailable = unavailable.into_inner().unwrap();
+
+        if !unavailable.is_empty() {
+            unavailable.sort();
+            return Err(Error::TarballUnavailable(unavailable));
+        }
+
+        Ok(sources.into_inner().unwrap())
+    }
+}
+
+impl Sources {
+    fn add(&mut self, pkg: &Package, primary: &str, used: String) {
+        if used != primary {
+            self.primary.insert(pkg.name.clone(), primary.to_owned());
+        }
+
+        self.used.insert(pkg.name.clone(), used);
+    }
+
+    /// # Apply Used Urls
+    ///
+    /// Rewrite the url of each tarball to the one which was found to be available
+    pub fn apply(&self, packages: &mut [Package]) {
+        for pkg in packages {
+            if let (Fetcher::FetchUrl { url, .. }, Some(used)) =
+                (&mut pkg.fetcher, self.used.get(&pkg.name))
+            {
+                *url = used.to_owned();
+            }
+        }
+    }
+}
+
+impl fmt::Display for Sources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.primary.is_empty() {
+            return writeln!(f, "All tarballs are available from the primary registry");
+        }
+
+        writeln!(
+            f,
+            "{} tarball(s) were served by a mirror:",
+            self.primary.len()
+        )?;
+
+        for name in self.primary.keys() {
+            writeln!(f, "  {name}: {}", self.used[name])?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,28 +1,674 @@
 //! This module handles construction of the rendered nix code as the output
 
+pub mod ast;
 mod nix_escaper;
+mod parser;
 
 pub use nix_escaper::NixEscaper;
+pub use parser::Parser;
 
-use crate::error::Result;
-use askama::Template;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
 
-use crate::Package;
+use crate::error::{Error, Result};
+
+use crate::{
+    Package, PackageJson,
+    flake_inputs::FlakeInput,
+    package::{Fetcher, Nix},
+};
+use ast::{AttrSet, Binding, Document, Expr};
+
+/// How many characters of a tarball hash name the binding it is shared through
+const TARBALL_BINDING_LEN: usize = 12;
+
+/// The comment at the top of every expression which isn't terse
+const HEADER: [&str; 7] = [
+    "Autogenerated by `bun2nix`, editing manually is not recommended",
+    "",
+    "Set of Bun packages to install",
+    "",
+    "Consume this with `fetchBunDeps` (recommended)",
+    "or `pkgs.callPackage` if you wish to handle",
+    "it manually.",
+];
 
 /// # Nix Expression
 ///
-/// A chunk of nix code to be written to stdout or a file
-#[derive(Template)]
-#[template(path = "output.nix_template")]
+/// A chunk of nix code to be written to stdout or a file, built as a [`Document`] which
+/// can be changed before it is printed
 pub struct NixExpression {
     packages: Vec<Package>,
+    tarballs: BTreeMap<Fetcher, String>,
+    annotations: Annotations,
+    project: Option<Project>,
+    update_script: Option<UpdateScript>,
+    binaries: BinLinks,
+    flake_inputs: bool,
+    variables: Variables,
+    terse: bool,
+}
+
+/// # Project
+///
+/// The name and version of the project the packages are installed for, taken from its root
+/// `package.json` and passed through to `fetchBunDeps` consumers so they don't need repeating
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    /// The name of the project
+    pub name: String,
+
+    /// The version of the project, if it has one
+    pub version: Option<String>,
+}
+
+impl Project {
+    /// # Project From package.json
+    ///
+    /// The project described by a root `package.json`, if it is named
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{PackageJson, nix_expression::Project};
+    ///
+    /// let package_json: PackageJson = r#"{ "name": "app", "version": "1.2.0" }"#.parse().unwrap();
+    /// let project = Project::from_package_json(&package_json).unwrap();
+    ///
+    /// assert_eq!(project.name, "app");
+    /// assert_eq!(project.version.as_deref(), Some("1.2.0"));
+    ///
+    /// let unnamed: PackageJson = r#"{ "version": "1.2.0" }"#.parse().unwrap();
+    /// assert!(Project::from_package_json(&unnamed).is_none());
+    /// ```
+    pub fn from_package_json(package_json: &PackageJson) -> Option<Self> {
+        Some(Self {
+            name: package_json.name.clone()?,
+            version: package_json.version.clone(),
+        })
+    }
+}
+
+/// # Update Script
+///
+/// A command generating the expression again, rendered as its `passthru.updateScript` for
+/// automation such as `nixpkgs-update`. The paths are relative to the directory of the
+/// generated file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateScript {
+    /// What the script does before generating the file
+    pub mode: UpdateMode,
+
+    /// The lockfile the expression is generated from
+    pub lock_file: String,
+
+    /// The file the expression is written to
+    pub output_file: String,
+}
+
+/// # Update Mode
+///
+/// What an update script does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// Generate the file again from the lockfile
+    Regenerate,
+
+    /// Run `bun update` next to the lockfile before generating the file again
+    Upgrade,
+}
+
+/// Comments to render above the entries for given package names
+pub type Annotations = BTreeMap<String, Vec<String>>;
+
+/// The paths from `node_modules` of the binaries to link into `node_modules/.bin`, by their name
+pub type BinLinks = BTreeMap<String, String>;
+
+/// Values given on the command line with `--set`, by their key
+pub type Variables = BTreeMap<String, String>;
+
+/// The variables `bun.nix` itself is rendered with, rather than a custom template
+pub const VARIABLES: [&str; 1] = ["banner"];
+
+/// # Check Variables
+///
+/// Check that `bun.nix` uses every variable given, as any other would be silently dropped
+/// unless a custom template renders it
+///
+/// ## Usage
+///```rust
+/// use bun2nix::nix_expression::{Variables, check_variables};
+///
+/// let variables = Variables::from([("banner".to_owned(), "Acme Corp".to_owned())]);
+/// assert!(check_variables(&variables).is_ok());
+///
+/// let variables = Variables::from([
+///     ("banner".to_owned(), "Acme Corp".to_owned()),
+///     ("attr".to_owned(), "deps".to_owned()),
+/// ]);
+/// assert_eq!(
+///     check_variables(&variables).unwrap_err().to_string(),
+///     "`bun.nix` does not use the variable(s) `attr`, only `banner` - pass `--template` to render them with a custom template"
+/// );
+/// ```
+pub fn check_variables(variables: &Variables) -> Result<()> {
+    let unused: Vec<String> = variables
+        .keys()
+        .filter(|key| !VARIABLES.contains(&key.as_str()))
+        .cloned()
+        .collect();
+
+    match unused.is_empty() {
+        true => Ok(()),
+        false => Err(Error::UnusedTemplateVariables(unused)),
+    }
+}
+
+/// # Parse Variable
+///
+/// Parse a template variable given as `key=value`, where the value may be empty
+///
+/// ## Usage
+///```rust
+/// use bun2nix::nix_expression::parse_variable;
+///
+/// assert_eq!(
+///     parse_variable("banner=Acme Corp").unwrap(),
+///     ("banner".to_owned(), "Acme Corp".to_owned())
+/// );
+/// assert_eq!(parse_variable("attr=").unwrap(), ("attr".to_owned(), String::new()));
+/// assert!(parse_variable("banner").is_err());
+/// assert!(parse_variable("=value").is_err());
+/// ```
+pub fn parse_variable(variable: &str) -> Result<(String, String)> {
+    match variable.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_owned(), value.to_owned()))
+        }
+        _ => Err(Error::InvalidTemplateVariable(variable.to_owned())),
+    }
+}
+
+/// # Validate Expression
+///
+/// Check that a rendered expression parses with `nix-instantiate --parse`, which catches
+/// mistakes in the rendering before a broken file is written
+pub fn validate(expression: &str, nix: &Nix) -> Result<()> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(Error::UnsupportedWASMCliAction("--validate".to_owned()));
+
+    let mut child = Command::new(nix.instantiate_bin())
+        .args(["--parse", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(Error::ValidationFailed)?;
+
+    // Written from another thread, as nix may not read all of a large expression before it
+    // starts writing errors
+    let mut stdin = child
+        .stdin
+        .take()
+        .expect("stdin of nix-instantiate is piped");
+    let expression = expression.to_owned();
+    let writer = thread::spawn(move || stdin.write_all(expression.as_bytes()));
+
+    let output = child.wait_with_output().map_err(Error::ValidationFailed)?;
+    let _ = writer.join();
+
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(Error::RejectedNixExpression(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )),
+    }
 }
 
 impl NixExpression {
     /// # New Nix Expression
     ///
-    /// Produce a new, ready to render, nix expression from a package list
-    pub fn new(packages: Vec<Package>) -> Result<Self> {
-        Ok(Self { packages })
+    /// Produce a new, ready to render, nix expression from a package list, sorting it and
+    /// removing duplicates.
+    ///
+    /// Tarballs fetched from the same url with the same hash, such as those of aliases, are
+    /// fetched once in a `let` binding which every package using them refers to.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{
+    ///     Package,
+    ///     nix_expression::{NixExpression, Parser},
+    ///     package::Fetcher,
+    /// };
+    ///
+    /// let tarball = |url: &str| Fetcher::FetchUrl {
+    ///     url: url.to_owned(),
+    ///     hash: "sha512-CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==".to_owned(),
+    /// };
+    ///
+    /// let nix = NixExpression::new(vec![
+    ///     Package::new("is-odd@3.0.1".to_owned(), tarball("https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz")),
+    ///     Package::new("my-is-odd@3.0.1".to_owned(), tarball("https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz")),
+    ///     Package::new("odd@3.0.1".to_owned(), tarball("https://registry.npmjs.org/odd/-/odd-3.0.1.tgz")),
+    /// ])
+    /// .unwrap()
+    /// .render()
+    /// .unwrap();
+    ///
+    /// assert_eq!(nix.matches("fetchurl {").count(), 2);
+    /// assert!(nix.contains(r#""is-odd@3.0.1" = tarball-CQpnWPrDwmP1;"#));
+    /// assert!(nix.contains(r#""my-is-odd@3.0.1" = tarball-CQpnWPrDwmP1;"#));
+    ///
+    /// // Tarballs from another url keep their own, even with the same hash
+    /// let packages = Parser::parse_packages(&nix).unwrap();
+    /// assert_eq!(packages[0].fetcher, packages[1].fetcher);
+    /// assert_eq!(packages[2].fetcher.url().unwrap(), "https://registry.npmjs.org/odd/-/odd-3.0.1.tgz");
+    /// ```
+    pub fn new(mut packages: Vec<Package>) -> Result<Self> {
+        crate::package::normalize(&mut packages);
+
+        Ok(Self {
+            tarballs: shared_tarballs(&packages),
+            packages,
+            annotations: Annotations::new(),
+            project: None,
+            update_script: None,
+            binaries: BinLinks::new(),
+            flake_inputs: false,
+            variables: Variables::new(),
+            terse: false,
+        })
+    }
+
+    /// # With Annotations
+    ///
+    /// Render the given comments above the entries for each package
+    pub fn with_annotations(mut self, annotations: Annotations) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// # With Project
+    ///
+    /// Render the name and version of the project in the `passthru` of the expression
+    pub fn with_project(mut self, project: Option<Project>) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// # With Update Script
+    ///
+    /// Render a `passthru.updateScript` which generates the expression again
+    pub fn with_update_script(mut self, update_script: Option<UpdateScript>) -> Self {
+        self.update_script = update_script;
+        self
+    }
+
+    /// # With Binaries
+    ///
+    /// Render a `passthru.binaries` of the binaries to link into `node_modules/.bin` after
+    /// installing, settling those which several packages provide. See [`crate::binaries`].
+    pub fn with_binaries(mut self, binaries: BinLinks) -> Self {
+        self.binaries = binaries;
+        self
+    }
+
+    /// # With Flake Inputs
+    ///
+    /// Take git dependencies from the flake inputs passed to the expression as `inputs` where
+    /// they are given, falling back to fetching them. See [`crate::flake_inputs`].
+    pub fn with_flake_inputs(mut self, flake_inputs: bool) -> Self {
+        self.flake_inputs = flake_inputs;
+        self
+    }
+
+    /// # With Variables
+    ///
+    /// Parameterise the expression with the given values. A `banner` variable is rendered as a
+    /// comment at the top of the expression.
+    pub fn with_variables(mut self, variables: Variables) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// # With Terse
+    ///
+    /// Leave every comment out of the expression, including its header and annotations, to
+    /// keep the diffs of the generated file small
+    pub fn with_terse(mut self, terse: bool) -> Self {
+        self.terse = terse;
+        self
+    }
+
+    /// # Render
+    ///
+    /// Print the expression as nix code
+    pub fn render(&self) -> Result<String> {
+        Ok(self.to_ast().to_string())
+    }
+
+    /// # Render Template
+    ///
+    /// Print the expression with a custom Jinja template instead, given the packages, project,
+    /// variables and style of the expression. See [`crate::template::context`].
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{Package, nix_expression::NixExpression, package::Fetcher};
+    ///
+    /// let fetcher = Fetcher::FetchUrl {
+    ///     url: "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz".to_owned(),
+    ///     hash: "sha512-CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==".to_owned(),
+    /// };
+    ///
+    /// let nix = NixExpression::new(vec![Package::new("is-odd@3.0.1".to_owned(), fetcher)])
+    ///     .unwrap()
+    ///     .with_variables([("attr".to_owned(), "deps".to_owned())].into());
+    ///
+    /// let template = "{ {{ variables.attr }} = {\n{%- for pkg in packages %}\n  \"{{ pkg.name }}\" = {{ pkg.nix }};\n{%- endfor %}\n}; }\n";
+    ///
+    /// let rendered = nix.render_template("deps.nix.j2", template).unwrap();
+    ///
+    /// assert!(rendered.starts_with("{ deps = {\n  \"is-odd@3.0.1\" = fetchurl {"));
+    ///
+    /// // A variable which was not set fails rather than rendering empty
+    /// assert_eq!(
+    ///     nix.render_template("deps.nix.j2", "{{ variables.banner }}").unwrap_err().to_string(),
+    ///     "The template has the following problems:\n  - deps.nix.j2:1: undefined value"
+    /// );
+    /// ```
+    #[cfg(feature = "template")]
+    pub fn render_template(&self, name: &str, source: &str) -> Result<String> {
+        let context = crate::template::context(
+            &self.packages,
+            self.project.as_ref(),
+            &self.variables,
+            self.terse,
+        );
+
+        crate::template::render(name, source, &context)
+    }
+
+    /// # To Syntax Tree
+    ///
+    /// Build the syntax tree of the expression, which can be changed, such as to inject
+    /// overrides into the package set, before printing it with its `Display` implementation
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{
+    ///     Package,
+    ///     nix_expression::{NixExpression, Parser, ast::{Binding, Expr}},
+    ///     package::Fetcher,
+    /// };
+    ///
+    /// let fetcher = Fetcher::FetchUrl {
+    ///     url: "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz".to_owned(),
+    ///     hash: "sha512-CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==".to_owned(),
+    /// };
+    ///
+    /// let mut document = NixExpression::new(vec![Package::new("is-odd@3.0.1".to_owned(), fetcher)])
+    ///     .unwrap()
+    ///     .to_ast();
+    ///
+    /// // Take a package from a local checkout instead
+    /// let packages = document.expr.attrs_mut().unwrap();
+    /// packages.insert(Binding::quoted(
+    ///     "is-odd@3.0.1",
+    ///     Expr::apply(Expr::ident("copyPathToStore"), Expr::Path("./vendor/is-odd".to_owned())),
+    /// ));
+    ///
+    /// let nix = document.to_string();
+    ///
+    /// assert!(nix.contains(r#""is-odd@3.0.1" = copyPathToStore ./vendor/is-odd;"#));
+    /// assert_eq!(
+    ///     Parser::parse_packages(&nix).unwrap()[0].fetcher,
+    ///     Fetcher::CopyToStore { path: "vendor/is-odd".to_owned() }
+    /// );
+    /// ```
+    pub fn to_ast(&self) -> Document {
+        let mut formals = ["copyPathToStore", "fetchFromGitHub", "fetchgit", "fetchurl"]
+            .map(|name| (name.to_owned(), None))
+            .to_vec();
+
+        if self.flake_inputs {
+            formals.push(("inputs".to_owned(), Some(Expr::Attrs(AttrSet::default()))));
+        }
+
+        let mut bindings = Vec::new();
+
+        if let Some(passthru) = self.passthru() {
+            bindings.push(Binding::new("passthru", Expr::Attrs(passthru)));
+        }
+
+        for pkg in &self.packages {
+            let value = match (self.flake_input(pkg), self.shared_tarball(pkg)) {
+                (Some(input), _) => Expr::Select {
+                    expr: Box::new(Expr::ident("inputs")),
+                    path: vec![input],
+                    default: Some(Box::new(Expr::from(&pkg.fetcher))),
+                },
+                (None, Some(binding)) => Expr::Ident(binding),
+                (None, None) => Expr::from(&pkg.fetcher),
+            };
+
+            bindings
+                .push(Binding::quoted(&pkg.name, value).with_comments(self.comments(&pkg.name)));
+        }
+
+        let mut body = Expr::Attrs(AttrSet::new(bindings));
+
+        if !self.tarballs.is_empty() {
+            let mut tarballs: Vec<Binding> = self
+                .tarballs
+                .iter()
+                .map(|(fetcher, binding)| Binding::new(binding, Expr::from(fetcher)))
+                .collect();
+
+            tarballs.sort_by(|a, b| a.name.cmp(&b.name));
+
+            if !self.terse {
+                tarballs[0].comments =
+                    vec!["Tarballs shared by several packages, fetched only once".to_owned()];
+            }
+
+            body = Expr::Let {
+                bindings: tarballs,
+                body: Box::new(body),
+            };
+        }
+
+        Document {
+            comments: self.header(),
+            expr: Expr::Lambda {
+                formals,
+                ellipsis: true,
+                body: Box::new(body),
+            },
+        }
+    }
+
+    /// The lines of the comment at the top of the expression, ending with the banner if one
+    /// was set
+    fn header(&self) -> Vec<String> {
+        if self.terse {
+            return Vec::new();
+        }
+
+        let mut header: Vec<String> = HEADER.iter().map(|&line| line.to_owned()).collect();
+
+        let banner: Vec<&str> = self
+            .variables
+            .get("banner")
+            .into_iter()
+            .flat_map(|banner| banner.lines())
+            .map(str::trim_end)
+            .collect();
+
+        if !banner.is_empty() {
+            header.push(String::new());
+            header.extend(banner.iter().map(|line| line.to_string()));
+        }
+
+        header
+    }
+
+    /// The `passthru` attributes of the expression, if it has any
+    fn passthru(&self) -> Option<AttrSet> {
+        let mut passthru = AttrSet::default();
+
+        if let Some(project) = &self.project {
+            let mut attrs = AttrSet::new(vec![Binding::new("name", Expr::string(&project.name))]);
+
+            if let Some(version) = &project.version {
+                attrs.insert(Binding::new("version", Expr::string(version)));
+            }
+
+            passthru.insert(Binding::new("project", Expr::Attrs(attrs)));
+        }
+
+        if let Some(script) = &self.update_script {
+            passthru.insert(Binding::new("updateScript", script.to_expr()));
+        }
+
+        if !self.binaries.is_empty() {
+            let binaries = self
+                .binaries
+                .iter()
+                .map(|(name, path)| Binding::quoted(name, Expr::string(path)))
+                .collect();
+
+            passthru.insert(Binding::new(
+                "binaries",
+                Expr::Attrs(AttrSet::new(binaries)),
+            ));
+        }
+
+        match passthru.bindings.is_empty() {
+            true => None,
+            false => Some(passthru),
+        }
+    }
+
+    /// The name of the flake input a package is taken from, if any
+    fn flake_input(&self, pkg: &Package) -> Option<String> {
+        match self.flake_inputs {
+            true => FlakeInput::for_fetcher(&pkg.fetcher).map(|input| input.name),
+            false => None,
+        }
+    }
+
+    /// The binding of the tarball a package shares with others, if any
+    fn shared_tarball(&self, pkg: &Package) -> Option<String> {
+        self.tarballs.get(&pkg.fetcher).cloned()
+    }
+
+    fn comments(&self, name: &str) -> Vec<String> {
+        self.annotations
+            .get(name)
+            .filter(|_| !self.terse)
+            .into_iter()
+            .flatten()
+            .flat_map(|comment| comment.lines())
+            .map(|line| line.trim_end().to_owned())
+            .collect()
+    }
+}
+
+impl UpdateScript {
+    /// The command, as a list of its arguments
+    fn to_expr(&self) -> Expr {
+        let relative = |path: &str| {
+            Expr::Add(
+                Box::new(Expr::apply(
+                    Expr::ident("toString"),
+                    Expr::Path("./.".to_owned()),
+                )),
+                Box::new(Expr::string(format!("/{path}"))),
+            )
+        };
+
+        let args = match self.mode {
+            UpdateMode::Regenerate => vec![
+                Expr::string("bun2nix"),
+                Expr::string("--lock-file"),
+                relative(&self.lock_file),
+                Expr::string("--output-file"),
+                relative(&self.output_file),
+                Expr::string("--update-script"),
+                Expr::string("regenerate"),
+            ],
+            UpdateMode::Upgrade => vec![
+                Expr::string("sh"),
+                Expr::string("-c"),
+                Expr::string(
+                    r#"cd "$(dirname "$0")" && bun update && bun2nix --lock-file "$0" --output-file "$1" --update-script upgrade"#,
+                ),
+                relative(&self.lock_file),
+                relative(&self.output_file),
+            ],
+        };
+
+        Expr::List(args)
+    }
+}
+
+/// The tarballs fetched by more than one package, with the name of the binding they are
+/// shared through.
+///
+/// Only tarballs with the same url as well as hash are shared, so that every package keeps
+/// its own url. Tarballs from different urls with the same hash are told apart by a suffix.
+fn shared_tarballs(packages: &[Package]) -> BTreeMap<Fetcher, String> {
+    let mut users: BTreeMap<&Fetcher, usize> = BTreeMap::new();
+
+    for pkg in packages {
+        if matches!(pkg.fetcher, Fetcher::FetchUrl { .. }) {
+            *users.entry(&pkg.fetcher).or_default() += 1;
+        }
+    }
+
+    let mut taken: HashMap<String, usize> = HashMap::new();
+
+    users
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .filter_map(|(fetcher, _)| {
+            let binding = tarball_binding(fetcher)?;
+            let seen = taken.entry(binding.clone()).or_default();
+            *seen += 1;
+
+            let binding = match *seen {
+                1 => binding,
+                n => format!("{binding}-{n}"),
+            };
+
+            Some((fetcher.clone(), binding))
+        })
+        .collect()
+}
+
+/// The name of the binding a tarball would be shared through, taken from its hash so that it
+/// stays the same between runs
+fn tarball_binding(fetcher: &Fetcher) -> Option<String> {
+    let Fetcher::FetchUrl { hash, .. } = fetcher else {
+        return None;
+    };
+
+    let digest = hash
+        .split_once('-')
+        .map_or(hash.as_str(), |(_, digest)| digest);
+    let name: String = digest
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(TARBALL_BINDING_LEN)
+        .collect();
+
+    match name.is_empty() {
+        true => None,
+        false => Some(format!("tarball-{name}")),
     }
 }
@@ -0,0 +1,575 @@
+//! This module holds a small typed syntax tree for the subset of nix which `bun2nix` emits,
+//! which the output is built as and then pretty-printed from, so that it can be changed
+//! programmatically (such as to inject overrides or merge in other attributes) before it is
+//! written
+
+use std::fmt::{self, Display, Write};
+
+use crate::package::Fetcher;
+
+/// The number of spaces each level of nesting is indented by
+const INDENT: usize = 2;
+
+/// Words which cannot be used as attribute names without quoting them
+const KEYWORDS: [&str; 11] = [
+    "assert", "else", "if", "in", "inherit", "let", "or", "rec", "then", "with", "throw",
+];
+
+/// # Nix Document
+///
+/// A whole nix file: an expression, preceded by lines of comments
+///
+/// ## Usage
+///```rust
+/// use bun2nix::nix_expression::ast::{AttrSet, Binding, Document, Expr};
+///
+/// let document = Document {
+///     comments: vec!["Pinned by hand".to_owned()],
+///     expr: Expr::Lambda {
+///         formals: vec![("fetchurl".to_owned(), None)],
+///         ellipsis: true,
+///         body: Box::new(Expr::Attrs(AttrSet::new(vec![Binding::quoted(
+///             "is-odd@3.0.1",
+///             Expr::apply(
+///                 Expr::ident("fetchurl"),
+///                 Expr::Attrs(AttrSet::new(vec![
+///                     Binding::new("url", Expr::string("https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz")),
+///                     Binding::new("hash", Expr::string("sha512-abc")),
+///                 ])),
+///             ),
+///         )]))),
+///     },
+/// };
+///
+/// assert_eq!(
+///     document.to_string(),
+///     [
+///         "# Pinned by hand",
+///         "{",
+///         "  fetchurl,",
+///         "  ...",
+///         "}:",
+///         "{",
+///         r#"  "is-odd@3.0.1" = fetchurl {"#,
+///         r#"    url = "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz";"#,
+///         r#"    hash = "sha512-abc";"#,
+///         "  };",
+///         "}",
+///     ]
+///     .join("\n")
+/// );
+///
+/// // Strings are escaped so they cannot be interpolated into
+/// assert_eq!(Expr::string(r#"${x} "y""#).to_string(), r#""\${x} \"y\"""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    /// The lines of the comment at the top of the file, where an empty line is a bare `#`
+    pub comments: Vec<String>,
+
+    /// The expression the file evaluates to
+    pub expr: Expr,
+}
+
+/// # Nix Expression
+///
+/// A node of the syntax tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A variable, such as `fetchurl`, or a path of attributes selected from one, such as
+    /// `builtins.fetchTarball`
+    Ident(String),
+
+    /// A string, which is escaped when printed
+    Str(String),
+
+    /// A path, such as `./packages/lib`
+    Path(String),
+
+    /// A list of expressions
+    List(Vec<Expr>),
+
+    /// An attribute set
+    Attrs(AttrSet),
+
+    /// A function applied to an argument
+    Apply {
+        /// The function
+        function: Box<Expr>,
+
+        /// The argument it is applied to
+        argument: Box<Expr>,
+    },
+
+    /// Two expressions added together, such as a path and a string
+    Add(Box<Expr>, Box<Expr>),
+
+    /// An attribute selected from an expression, falling back to a default if given
+    Select {
+        /// The expression the attribute is selected from
+        expr: Box<Expr>,
+
+        /// The attribute names selected, one after the other
+        path: Vec<String>,
+
+        /// The value if the attribute is missing, after `or`
+        default: Option<Box<Expr>>,
+    },
+
+    /// A function taking an attribute set of named arguments
+    Lambda {
+        /// The names of the arguments, along with their default values
+        formals: Vec<(String, Option<Expr>)>,
+
+        /// Whether other arguments are accepted, with `...`
+        ellipsis: bool,
+
+        /// The body of the function
+        body: Box<Expr>,
+    },
+
+    /// Bindings which are in scope for the expression after `in`
+    Let {
+        /// The bindings
+        bindings: Vec<Binding>,
+
+        /// The expression the bindings are in scope for
+        body: Box<Expr>,
+    },
+}
+
+/// # Attribute Set
+///
+/// The bindings of an attribute set, in the order they are printed
+///
+/// ## Usage
+///```rust
+/// use bun2nix::nix_expression::ast::{AttrSet, Binding, Expr};
+///
+/// let mut attrs = AttrSet::new(vec![
+///     Binding::new("name", Expr::string("app")),
+///     Binding::new("version", Expr::string("1.0.0")),
+/// ]);
+///
+/// // As with `//`, the attributes of the other set win
+/// attrs.merge(AttrSet::new(vec![
+///     Binding::new("version", Expr::string("1.1.0")),
+///     Binding::new("license", Expr::string("MIT")),
+/// ]));
+///
+/// assert_eq!(attrs.get("version"), Some(&Expr::string("1.1.0")));
+/// assert_eq!(attrs.bindings.len(), 3);
+///
+/// attrs.remove("license");
+/// assert_eq!(
+///     Expr::Attrs(attrs).to_string(),
+///     "{\n  name = \"app\";\n  version = \"1.1.0\";\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttrSet {
+    /// The bindings of the set
+    pub bindings: Vec<Binding>,
+}
+
+/// # Binding
+///
+/// A name bound to a value, in an attribute set or a `let`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    /// The name
+    pub name: String,
+
+    /// The value
+    pub value: Expr,
+
+    /// Lines of comments printed above the binding
+    pub comments: Vec<String>,
+
+    /// Whether the name is quoted even if it does not need to be, which the names of packages
+    /// always are
+    pub quoted: bool,
+}
+
+impl Expr {
+    /// # Identifier
+    ///
+    /// A variable, or a path of attributes selected from one
+    pub fn ident(name: impl Into<String>) -> Self {
+        Self::Ident(name.into())
+    }
+
+    /// # String
+    ///
+    /// A string with the given contents
+    pub fn string(contents: impl Into<String>) -> Self {
+        Self::Str(contents.into())
+    }
+
+    /// # Apply
+    ///
+    /// A function applied to an argument
+    pub fn apply(function: Expr, argument: Expr) -> Self {
+        Self::Apply {
+            function: Box::new(function),
+            argument: Box::new(argument),
+        }
+    }
+
+    /// # Result Attribute Set
+    ///
+    /// The attribute set an expression evaluates to, looking through the bodies of functions
+    /// and `let` expressions, such as the package set of a generated `bun.nix`
+    pub fn attrs_mut(&mut self) -> Option<&mut AttrSet> {
+        match self {
+            Self::Attrs(attrs) => Some(attrs),
+            Self::Lambda { body, .. } | Self::Let { body, .. } => body.attrs_mut(),
+            _ => None,
+        }
+    }
+
+    /// Whether the expression must be parenthesized as an argument or list item
+    fn is_compound(&self) -> bool {
+        match self {
+            Self::Apply { .. } | Self::Add(..) | Self::Lambda { .. } | Self::Let { .. } => true,
+            Self::Select { default, .. } => default.is_some(),
+            _ => false,
+        }
+    }
+
+    fn write(&self, f: &mut impl Write, indent: usize) -> fmt::Result {
+        let pad = " ".repeat(indent);
+
+        match self {
+            Self::Ident(name) | Self::Path(name) => f.write_str(name),
+            Self::Str(contents) => write_string(f, contents),
+            Self::List(items) if items.is_empty() => f.write_str("[ ]"),
+            Self::List(items) => {
+                f.write_str("[\n")?;
+
+                for item in items {
+                    f.write_str(&" ".repeat(indent + INDENT))?;
+                    item.write_operand(f, indent + INDENT)?;
+                    f.write_char('\n')?;
+                }
+
+                write!(f, "{pad}]")
+            }
+            Self::Attrs(attrs) if attrs.bindings.is_empty() => f.write_str("{ }"),
+            Self::Attrs(attrs) => {
+                f.write_str("{\n")?;
+                write_bindings(f, &attrs.bindings, indent + INDENT)?;
+                write!(f, "{pad}}}")
+            }
+            Self::Apply { function, argument } => {
+                match function.as_ref() {
+                    Self::Lambda { .. } | Self::Let { .. } => function.write_operand(f, indent)?,
+                    _ => function.write(f, indent)?,
+                }
+
+                f.write_char(' ')?;
+                argument.write_operand(f, indent)
+            }
+            Self::Add(lhs, rhs) => {
+                lhs.write(f, indent)?;
+                f.write_str(" + ")?;
+                rhs.write(f, indent)
+            }
+            Self::Select {
+                expr,
+                path,
+                default,
+            } => {
+                expr.write_operand(f, indent)?;
+
+                for name in path {
+                    f.write_char('.')?;
+                    write_name(f, name, false)?;
+                }
+
+                match default {
+                    Some(default) => {
+                        f.write_str(" or ")?;
+                        default.write_operand(f, indent)
+                    }
+                    None => Ok(()),
+                }
+            }
+            Self::Lambda {
+                formals,
+                ellipsis,
+                body,
+            } => {
+                if formals.is_empty() && !ellipsis {
+                    f.write_str("{ }:")?;
+                } else {
+                    f.write_str("{\n")?;
+
+                    for (name, default) in formals {
+                        write!(f, "{}{name}", " ".repeat(indent + INDENT))?;
+
+                        if let Some(default) = default {
+                            f.write_str(" ? ")?;
+                            default.write(f, indent + INDENT)?;
+                        }
+
+                        f.write_str(",\n")?;
+                    }
+
+                    if *ellipsis {
+                        writeln!(f, "{}...", " ".repeat(indent + INDENT))?;
+                    }
+
+                    write!(f, "{pad}}}:")?;
+                }
+
+                write!(f, "\n{pad}")?;
+                body.write(f, indent)
+            }
+            Self::Let { bindings, body } => {
+                f.write_str("let\n")?;
+                write_bindings(f, bindings, indent + INDENT)?;
+                write!(f, "{pad}in\n{pad}")?;
+                body.write(f, indent)
+            }
+        }
+    }
+
+    /// Write the expression, parenthesized if it is compound
+    fn write_operand(&self, f: &mut impl Write, indent: usize) -> fmt::Result {
+        match self.is_compound() {
+            true => {
+                f.write_char('(')?;
+                self.write(f, indent)?;
+                f.write_char(')')
+            }
+            false => self.write(f, indent),
+        }
+    }
+}
+
+impl AttrSet {
+    /// # New Attribute Set
+    ///
+    /// An attribute set of the given bindings
+    pub fn new(bindings: Vec<Binding>) -> Self {
+        Self { bindings }
+    }
+
+    /// # Get Attribute
+    ///
+    /// The value bound to a name, if there is one
+    pub fn get(&self, name: &str) -> Option<&Expr> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.name == name)
+            .map(|binding| &binding.value)
+    }
+
+    /// # Get Attribute Mutably
+    ///
+    /// The value bound to a name, if there is one, to change in place
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Expr> {
+        self.bindings
+            .iter_mut()
+            .find(|binding| binding.name == name)
+            .map(|binding| &mut binding.value)
+    }
+
+    /// # Insert Binding
+    ///
+    /// Bind a name, replacing the value (but keeping the place and comments) of any binding of
+    /// the same name, or adding it to the end of the set
+    pub fn insert(&mut self, binding: Binding) {
+        match self.get_mut(&binding.name) {
+            Some(value) => *value = binding.value,
+            None => self.bindings.push(binding),
+        }
+    }
+
+    /// # Remove Binding
+    ///
+    /// Remove the binding of a name, returning it if there was one
+    pub fn remove(&mut self, name: &str) -> Option<Binding> {
+        let pos = self
+            .bindings
+            .iter()
+            .position(|binding| binding.name == name)?;
+
+        Some(self.bindings.remove(pos))
+    }
+
+    /// # Merge Attribute Sets
+    ///
+    /// Insert every binding of another set into this one, as `//` would
+    pub fn merge(&mut self, other: AttrSet) {
+        for binding in other.bindings {
+            self.insert(binding);
+        }
+    }
+}
+
+impl Binding {
+    /// # New Binding
+    ///
+    /// Bind a name to a value, quoting the name only if it needs to be
+    pub fn new(name: impl Into<String>, value: Expr) -> Self {
+        Self {
+            name: name.into(),
+            value,
+            comments: Vec::new(),
+            quoted: false,
+        }
+    }
+
+    /// # Quoted Binding
+    ///
+    /// Bind a name to a value, always quoting the name
+    pub fn quoted(name: impl Into<String>, value: Expr) -> Self {
+        Self {
+            quoted: true,
+            ..Self::new(name, value)
+        }
+    }
+
+    /// # With Comments
+    ///
+    /// Print the given lines of comments above the binding
+    pub fn with_comments(mut self, comments: Vec<String>) -> Self {
+        self.comments = comments;
+        self
+    }
+}
+
+impl From<&Fetcher> for Expr {
+    fn from(fetcher: &Fetcher) -> Self {
+        let attrs = |function: &str, attrs: Vec<(&str, &str)>| {
+            let bindings = attrs
+                .into_iter()
+                .map(|(name, value)| Binding::new(name, Expr::string(value)))
+                .collect();
+
+            Expr::apply(Expr::ident(function), Expr::Attrs(AttrSet::new(bindings)))
+        };
+
+        match fetcher {
+            Fetcher::FetchUrl { url, hash } => {
+                attrs("fetchurl", vec![("url", url), ("hash", hash)])
+            }
+            Fetcher::FetchGit {
+                url,
+                rev,
+                hash,
+                fetch_lfs,
+            } => {
+                let mut fetchgit =
+                    attrs("fetchgit", vec![("url", url), ("rev", rev), ("hash", hash)]);
+
+                if let (true, Self::Apply { argument, .. }) = (*fetch_lfs, &mut fetchgit) {
+                    if let Some(attrs) = argument.attrs_mut() {
+                        attrs.insert(Binding::new("fetchLFS", Expr::ident("true")));
+                    }
+                }
+
+                fetchgit
+            }
+            Fetcher::FetchGitHub {
+                owner,
+                repo,
+                rev,
+                hash,
+            } => attrs(
+                "fetchFromGitHub",
+                vec![
+                    ("owner", owner),
+                    ("repo", repo),
+                    ("rev", rev),
+                    ("hash", hash),
+                ],
+            ),
+            Fetcher::FetchTarball { url, hash } => attrs(
+                "builtins.fetchTarball",
+                vec![("url", url), ("sha256", hash)],
+            ),
+            Fetcher::CopyToStore { path } => Expr::apply(
+                Expr::ident("copyPathToStore"),
+                Expr::Path(format!("./{path}")),
+            ),
+        }
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_comments(f, &self.comments, 0)?;
+        self.expr.write(f, 0)
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// Write bindings one per line at the given indentation, each after its comments
+fn write_bindings(f: &mut impl Write, bindings: &[Binding], indent: usize) -> fmt::Result {
+    for binding in bindings {
+        write_comments(f, &binding.comments, indent)?;
+        f.write_str(&" ".repeat(indent))?;
+        write_name(f, &binding.name, binding.quoted)?;
+        f.write_str(" = ")?;
+        binding.value.write(f, indent)?;
+        f.write_str(";\n")?;
+    }
+
+    Ok(())
+}
+
+/// Write lines of comments at the given indentation
+fn write_comments(f: &mut impl Write, comments: &[String], indent: usize) -> fmt::Result {
+    for comment in comments {
+        match comment.is_empty() {
+            true => writeln!(f, "{}#", " ".repeat(indent))?,
+            false => writeln!(f, "{}# {comment}", " ".repeat(indent))?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Write an attribute name, quoting it if asked to or if it is not a valid identifier
+fn write_name(f: &mut impl Write, name: &str, quoted: bool) -> fmt::Result {
+    let mut chars = name.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '\''))
+        && !KEYWORDS.contains(&name);
+
+    match quoted || !identifier {
+        true => write_string(f, name),
+        false => f.write_str(name),
+    }
+}
+
+/// Write a string literal, escaping anything which would end it or be interpolated
+fn write_string(f: &mut impl Write, contents: &str) -> fmt::Result {
+    f.write_char('"')?;
+
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '$' if chars.peek() == Some(&'{') => f.write_str("\\$")?,
+            c => f.write_char(c)?,
+        }
+    }
+
+    f.write_char('"')
+}
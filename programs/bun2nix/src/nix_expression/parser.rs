@@ -0,0 +1,782 @@
+use std::{collections::HashMap, iter::Peekable, str::Chars};
+
+use super::{BinLinks, Project, UpdateMode, UpdateScript};
+use crate::{
+    Package,
+    error::{Error, Result},
+    package::{Fetcher, digest_to_sri},
+};
+
+/// # Token
+///
+/// The subset of nix syntax which can appear in a generated `bun.nix` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Path(String),
+    Symbol(char),
+    Ellipsis,
+}
+
+/// The attributes of the `passthru` of a generated `bun.nix` which are understood, along with
+/// whether its git dependencies are taken from flake inputs
+#[derive(Default)]
+struct Passthru {
+    project: Option<Project>,
+    update_script: Option<UpdateScript>,
+    binaries: BinLinks,
+    flake_inputs: bool,
+}
+
+/// # Nix Expression Parser
+///
+/// Parses a `bun.nix` file generated by `bun2nix` back into the packages it contains.
+///
+/// This is not a general nix parser, only the shapes which `bun2nix` emits are understood,
+/// however it is tolerant of reformatting and comments, such as those introduced by running the
+/// output through `nixfmt`.
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    flake_inputs: bool,
+    tarballs: HashMap<String, Fetcher>,
+}
+
+impl Parser {
+    /// # Parse Packages
+    ///
+    /// Parse the contents of a generated `bun.nix` into its list of packages
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::nix_expression::Parser;
+    ///
+    /// let bun_nix = r#"
+    /// { copyPathToStore, fetchurl, ... }:
+    /// {
+    ///   "@workspace/lib" = copyPathToStore ./packages/lib;
+    ///   "is-odd@3.0.1" = fetchurl {
+    ///     url = "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz";
+    ///     hash = "sha512-CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==";
+    ///   };
+    /// }
+    /// "#;
+    ///
+    /// let packages = Parser::parse_packages(bun_nix).unwrap();
+    ///
+    /// assert_eq!(packages.len(), 2);
+    /// assert_eq!(packages[1].name, "is-odd@3.0.1");
+    /// ```
+    pub fn parse_packages(input: &str) -> Result<Vec<Package>> {
+        Self::parse_expression(input).map(|(packages, _)| packages)
+    }
+
+    /// # Parse Project
+    ///
+    /// Parse the name and version of the project recorded in the `passthru` of a generated
+    /// `bun.nix`, if there is one
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::nix_expression::Parser;
+    ///
+    /// let bun_nix = r#"
+    /// { copyPathToStore, ... }:
+    /// {
+    ///   passthru = {
+    ///     project = {
+    ///       name = "app";
+    ///       version = "1.2.0";
+    ///     };
+    ///   };
+    ///   "@workspace/lib" = copyPathToStore ./packages/lib;
+    /// }
+    /// "#;
+    ///
+    /// let project = Parser::parse_project(bun_nix).unwrap().unwrap();
+    ///
+    /// assert_eq!(project.name, "app");
+    /// assert_eq!(project.version.as_deref(), Some("1.2.0"));
+    /// assert_eq!(Parser::parse_packages(bun_nix).unwrap().len(), 1);
+    /// ```
+    pub fn parse_project(input: &str) -> Result<Option<Project>> {
+        Self::parse_expression(input).map(|(_, passthru)| passthru.project)
+    }
+
+    /// # Parse Update Script
+    ///
+    /// Parse the `passthru.updateScript` of a generated `bun.nix`, if there is one
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::nix_expression::{Parser, UpdateMode};
+    ///
+    /// let bun_nix = r#"
+    /// { copyPathToStore, ... }:
+    /// {
+    ///   passthru = {
+    ///     updateScript = [
+    ///       "bun2nix"
+    ///       "--lock-file"
+    ///       (toString ./. + "/../bun.lock")
+    ///       "--output-file"
+    ///       (toString ./. + "/bun.nix")
+    ///       "--update-script"
+    ///       "regenerate"
+    ///     ];
+    ///   };
+    /// }
+    /// "#;
+    ///
+    /// let script = Parser::parse_update_script(bun_nix).unwrap().unwrap();
+    ///
+    /// assert_eq!(script.mode, UpdateMode::Regenerate);
+    /// assert_eq!(script.lock_file, "../bun.lock");
+    /// assert_eq!(script.output_file, "bun.nix");
+    /// ```
+    pub fn parse_update_script(input: &str) -> Result<Option<UpdateScript>> {
+        Self::parse_expression(input).map(|(_, passthru)| passthru.update_script)
+    }
+
+    /// # Parse Flake Inputs
+    ///
+    /// Whether the git dependencies of a generated `bun.nix` are taken from flake inputs where
+    /// they are given
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::nix_expression::Parser;
+    ///
+    /// let bun_nix = r#"
+    /// { fetchFromGitHub, inputs ? { }, ... }:
+    /// {
+    ///   "github:colinhacks-zod-5bfc8f2" = inputs.bun-colinhacks-zod-5bfc8f2 or (fetchFromGitHub {
+    ///     owner = "colinhacks";
+    ///     repo = "zod";
+    ///     rev = "5bfc8f2";
+    ///     hash = "sha256-9LqJ2R2TyCf4u2wi2tDtSvevbhMaHthLTX6FtbBgvTA=";
+    ///   });
+    /// }
+    /// "#;
+    ///
+    /// assert!(Parser::parse_flake_inputs(bun_nix).unwrap());
+    /// assert_eq!(Parser::parse_packages(bun_nix).unwrap()[0].name, "github:colinhacks-zod-5bfc8f2");
+    /// ```
+    pub fn parse_flake_inputs(input: &str) -> Result<bool> {
+        Self::parse_expression(input).map(|(_, passthru)| passthru.flake_inputs)
+    }
+
+    /// # Parse Binaries
+    ///
+    /// Parse the `passthru.binaries` of a generated `bun.nix`, which is empty if it has none
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::nix_expression::Parser;
+    ///
+    /// let bun_nix = r#"
+    /// { copyPathToStore, ... }:
+    /// {
+    ///   passthru = {
+    ///     binaries = {
+    ///       "tsc" = "typescript/bin/tsc";
+    ///     };
+    ///   };
+    /// }
+    /// "#;
+    ///
+    /// let binaries = Parser::parse_binaries(bun_nix).unwrap();
+    ///
+    /// assert_eq!(binaries["tsc"], "typescript/bin/tsc");
+    /// ```
+    pub fn parse_binaries(input: &str) -> Result<BinLinks> {
+        Self::parse_expression(input).map(|(_, passthru)| passthru.binaries)
+    }
+
+    fn parse_expression(input: &str) -> Result<(Vec<Package>, Passthru)> {
+        let mut parser = Self {
+            tokens: tokenize(input)?,
+            pos: 0,
+            flake_inputs: false,
+            tarballs: HashMap::new(),
+        };
+
+        parser.skip_function_header()?;
+        parser.parse_shared_tarballs()?;
+        parser.expect(&Token::Symbol('{'))?;
+
+        let mut packages = Vec::new();
+        let mut passthru = Passthru::default();
+        while !parser.at_symbol('}') {
+            // Package names are always quoted, so a bare `passthru` is never a package
+            if parser.tokens.get(parser.pos) == Some(&Token::Ident("passthru".to_owned())) {
+                passthru = parser.parse_passthru()?;
+            } else {
+                packages.push(parser.parse_entry()?);
+            }
+        }
+
+        parser.expect(&Token::Symbol('}'))?;
+        passthru.flake_inputs = parser.flake_inputs;
+
+        Ok((packages, passthru))
+    }
+
+    /// # Parse node2nix Sources
+    ///
+    /// Parse the `sources` of a `node-packages.nix` file generated by `node2nix` into the
+    /// equivalent packages, so that projects packaged with it can be migrated to `bun2nix`
+    /// without fetching everything again.
+    ///
+    /// Everything outside of the `sources` attribute set is ignored.
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{nix_expression::Parser, package::Fetcher};
+    ///
+    /// let node_packages = r#"
+    /// {nodeEnv, fetchurl, fetchgit, nix-gitignore, stdenv, lib, globalBuildInputs ? []}:
+    ///
+    /// let
+    ///   sources = {
+    ///     "is-odd-3.0.1" = {
+    ///       name = "is-odd";
+    ///       packageName = "is-odd";
+    ///       version = "3.0.1";
+    ///       src = fetchurl {
+    ///         url = "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz";
+    ///         sha512 = "CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==";
+    ///       };
+    ///     };
+    ///   };
+    ///   args = {
+    ///     dependencies = [ sources."is-odd-3.0.1" ];
+    ///   };
+    /// in
+    /// {
+    ///   package = nodeEnv.buildNodePackage args;
+    /// }
+    /// "#;
+    ///
+    /// let packages = Parser::parse_node2nix(node_packages).unwrap();
+    ///
+    /// assert_eq!(packages[0].name, "is-odd@3.0.1");
+    /// assert_eq!(
+    ///     packages[0].fetcher.sri_hash(),
+    ///     Some("sha512-CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==")
+    /// );
+    /// ```
+    pub fn parse_node2nix(input: &str) -> Result<Vec<Package>> {
+        let mut parser = Self {
+            tokens: tokenize(input)?,
+            pos: 0,
+            flake_inputs: false,
+            tarballs: HashMap::new(),
+        };
+
+        parser.skip_to_sources()?;
+
+        let mut packages = Vec::new();
+        while !parser.at_symbol('}') {
+            packages.push(parser.parse_source()?);
+        }
+
+        Ok(packages)
+    }
+
+    fn skip_to_sources(&mut self) -> Result<()> {
+        let sources = [
+            Token::Ident("sources".to_owned()),
+            Token::Symbol('='),
+            Token::Symbol('{'),
+        ];
+
+        let start = self
+            .tokens
+            .windows(sources.len())
+            .position(|window| window == sources)
+            .ok_or_else(|| {
+                Error::InvalidNixExpression("no `sources` attribute set found".to_owned())
+            })?;
+
+        self.pos = start + sources.len();
+
+        Ok(())
+    }
+
+    fn parse_source(&mut self) -> Result<Package> {
+        let key = self.parse_key()?;
+
+        self.expect(&Token::Symbol('='))?;
+        self.expect(&Token::Symbol('{'))?;
+
+        let mut attrs = HashMap::new();
+        let mut fetcher = None;
+
+        while !self.at_symbol('}') {
+            let attr = self.parse_key()?;
+
+            self.expect(&Token::Symbol('='))?;
+
+            match attr.as_str() {
+                "src" => fetcher = Some(self.parse_node2nix_src()?),
+                "packageName" | "version" => match self.next()? {
+                    Token::Str(value) => {
+                        attrs.insert(attr, value);
+                    }
+                    token => return Err(unexpected(&token)),
+                },
+                _ => self.skip_value()?,
+            }
+
+            self.expect(&Token::Symbol(';'))?;
+        }
+
+        self.expect(&Token::Symbol('}'))?;
+        self.expect(&Token::Symbol(';'))?;
+
+        let fetcher = fetcher.ok_or_else(|| {
+            Error::InvalidNixExpression(format!("missing attribute `src` for `{key}`"))
+        })?;
+        let package_name = take(&mut attrs, "packageName")?;
+
+        let name = match &fetcher {
+            Fetcher::FetchGit { rev, .. } => format!("git:{rev}"),
+            Fetcher::CopyToStore { .. } => package_name,
+            _ => format!("{package_name}@{}", take(&mut attrs, "version")?),
+        };
+
+        Ok(Package::new(name, fetcher))
+    }
+
+    fn parse_node2nix_src(&mut self) -> Result<Fetcher> {
+        let function = match self.next()? {
+            Token::Path(path) => {
+                return Ok(Fetcher::CopyToStore {
+                    path: path.trim_start_matches("./").to_owned(),
+                });
+            }
+            Token::Ident(function) => function,
+            token => return Err(unexpected(&token)),
+        };
+
+        let mut attrs = self.parse_attrs()?;
+
+        match function.as_str() {
+            "fetchurl" => {
+                let hash = ["sha512", "sha256", "sha1"]
+                    .into_iter()
+                    .find_map(|algorithm| Some((algorithm, attrs.remove(algorithm)?)))
+                    .and_then(|(algorithm, digest)| digest_to_sri(algorithm, &digest))
+                    .ok_or_else(|| {
+                        Error::InvalidNixExpression("missing or invalid tarball hash".to_owned())
+                    })?;
+
+                Ok(Fetcher::FetchUrl {
+                    url: take(&mut attrs, "url")?,
+                    hash,
+                })
+            }
+            "fetchgit" => {
+                let sha256 = take(&mut attrs, "sha256")?;
+
+                Ok(Fetcher::FetchGit {
+                    url: take(&mut attrs, "url")?,
+                    rev: take(&mut attrs, "rev")?,
+                    hash: digest_to_sri("sha256", &sha256).ok_or_else(|| {
+                        Error::InvalidNixExpression(format!("invalid git hash `{sha256}`"))
+                    })?,
+                    fetch_lfs: attrs.remove("fetchLFS").is_some_and(|lfs| lfs == "true"),
+                })
+            }
+            other => Err(Error::InvalidNixExpression(format!(
+                "unknown fetcher `{other}`"
+            ))),
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Str(key) | Token::Ident(key) => Ok(key),
+            token => Err(unexpected(&token)),
+        }
+    }
+
+    /// Skip over a value which is not needed, stopping at the `;` which ends it
+    fn skip_value(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+
+        while depth > 0 || !self.at_symbol(';') {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn skip_function_header(&mut self) -> Result<()> {
+        self.expect(&Token::Symbol('{'))?;
+
+        // Skipping over default values such as `inputs ? { }`
+        let mut depth = 0;
+        while depth > 0 || !self.at_symbol('}') {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => depth -= 1,
+                _ => {}
+            }
+        }
+
+        self.expect(&Token::Symbol('}'))?;
+        self.expect(&Token::Symbol(':'))
+    }
+
+    /// Parse the tarballs shared by several packages from the `let` bindings before the
+    /// packages, if there are any
+    fn parse_shared_tarballs(&mut self) -> Result<()> {
+        let keyword = |word: &str| Token::Ident(word.to_owned());
+
+        if self.tokens.get(self.pos) != Some(&keyword("let")) {
+            return Ok(());
+        }
+
+        self.next()?;
+
+        while self.tokens.get(self.pos) != Some(&keyword("in")) {
+            let binding = self.parse_key()?;
+
+            self.expect(&Token::Symbol('='))?;
+            self.expect(&keyword("fetchurl"))?;
+
+            let mut attrs = self.parse_attrs()?;
+            let fetcher = Fetcher::FetchUrl {
+                url: take(&mut attrs, "url")?,
+                hash: take(&mut attrs, "hash")?,
+            };
+
+            self.expect(&Token::Symbol(';'))?;
+            self.tarballs.insert(binding, fetcher);
+        }
+
+        self.next()?;
+
+        Ok(())
+    }
+
+    fn parse_entry(&mut self) -> Result<Package> {
+        let name = match self.next()? {
+            Token::Str(name) | Token::Ident(name) => name,
+            token => return Err(unexpected(&token)),
+        };
+
+        self.expect(&Token::Symbol('='))?;
+
+        let mut function = match self.next()? {
+            Token::Ident(function) => function,
+            token => return Err(unexpected(&token)),
+        };
+
+        // Tarballs shared with other packages refer to their binding
+        if let Some(fetcher) = self.tarballs.get(&function) {
+            let fetcher = fetcher.clone();
+            self.expect(&Token::Symbol(';'))?;
+
+            return Ok(Package::new(name, fetcher));
+        }
+
+        // Git dependencies taken from a flake input, which fall back to the fetcher after `or`
+        let flake_input = function.starts_with("inputs.");
+        if flake_input {
+            self.expect(&Token::Ident("or".to_owned()))?;
+            self.expect(&Token::Symbol('('))?;
+            self.flake_inputs = true;
+
+            function = match self.next()? {
+                Token::Ident(function) => function,
+                token => return Err(unexpected(&token)),
+            };
+        }
+
+        let fetcher = match function.as_str() {
+            "copyPathToStore" => match self.next()? {
+                Token::Path(path) => Fetcher::CopyToStore {
+                    path: path.trim_start_matches("./").to_owned(),
+                },
+                token => return Err(unexpected(&token)),
+            },
+            "fetchurl" => {
+                let mut attrs = self.parse_attrs()?;
+                Fetcher::FetchUrl {
+                    url: take(&mut attrs, "url")?,
+                    hash: take(&mut attrs, "hash")?,
+                }
+            }
+            "fetchgit" => {
+                let mut attrs = self.parse_attrs()?;
+                Fetcher::FetchGit {
+                    url: take(&mut attrs, "url")?,
+                    rev: take(&mut attrs, "rev")?,
+                    hash: take(&mut attrs, "hash")?,
+                    fetch_lfs: attrs.remove("fetchLFS").is_some_and(|lfs| lfs == "true"),
+                }
+            }
+            "fetchFromGitHub" => {
+                let mut attrs = self.parse_attrs()?;
+                Fetcher::FetchGitHub {
+                    owner: take(&mut attrs, "owner")?,
+                    repo: take(&mut attrs, "repo")?,
+                    rev: take(&mut attrs, "rev")?,
+                    hash: take(&mut attrs, "hash")?,
+                }
+            }
+            "builtins.fetchTarball" => {
+                let mut attrs = self.parse_attrs()?;
+                Fetcher::FetchTarball {
+                    url: take(&mut attrs, "url")?,
+                    hash: take(&mut attrs, "sha256")?,
+                }
+            }
+            other => {
+                return Err(Error::InvalidNixExpression(format!(
+                    "unknown fetcher `{other}` for `{name}`"
+                )));
+            }
+        };
+
+        if flake_input {
+            self.expect(&Token::Symbol(')'))?;
+        }
+
+        self.expect(&Token::Symbol(';'))?;
+
+        Ok(Package::new(name, fetcher))
+    }
+
+    fn parse_passthru(&mut self) -> Result<Passthru> {
+        self.next()?;
+        self.expect(&Token::Symbol('='))?;
+        self.expect(&Token::Symbol('{'))?;
+
+        let mut passthru = Passthru::default();
+        while !self.at_symbol('}') {
+            let key = self.parse_key()?;
+            self.expect(&Token::Symbol('='))?;
+
+            match key.as_str() {
+                "project" => {
+                    let mut attrs = self.parse_attrs()?;
+                    passthru.project = Some(Project {
+                        name: take(&mut attrs, "name")?,
+                        version: attrs.remove("version"),
+                    });
+                }
+                "updateScript" => passthru.update_script = Some(self.parse_update_command()?),
+                "binaries" => passthru.binaries = self.parse_attrs()?.into_iter().collect(),
+                _ => self.skip_value()?,
+            }
+
+            self.expect(&Token::Symbol(';'))?;
+        }
+
+        self.expect(&Token::Symbol('}'))?;
+        self.expect(&Token::Symbol(';'))?;
+
+        Ok(passthru)
+    }
+
+    /// Parse the command of an update script, whose only paths are the lockfile and the output
+    fn parse_update_command(&mut self) -> Result<UpdateScript> {
+        self.expect(&Token::Symbol('['))?;
+
+        let mut program = None;
+        let mut paths = Vec::new();
+        while !self.at_symbol(']') {
+            match self.next()? {
+                Token::Str(arg) => {
+                    program.get_or_insert(arg);
+                }
+                Token::Symbol('(') => {
+                    self.expect(&Token::Ident("toString".to_owned()))?;
+                    self.expect(&Token::Path("./.".to_owned()))?;
+                    self.expect(&Token::Ident("+".to_owned()))?;
+
+                    match self.next()? {
+                        Token::Str(path) => paths.push(path.trim_start_matches('/').to_owned()),
+                        token => return Err(unexpected(&token)),
+                    }
+
+                    self.expect(&Token::Symbol(')'))?;
+                }
+                token => return Err(unexpected(&token)),
+            }
+        }
+
+        self.expect(&Token::Symbol(']'))?;
+
+        let mode = match program.as_deref() {
+            Some("sh") => UpdateMode::Upgrade,
+            _ => UpdateMode::Regenerate,
+        };
+
+        match <[String; 2]>::try_from(paths) {
+            Ok([lock_file, output_file]) => Ok(UpdateScript {
+                mode,
+                lock_file,
+                output_file,
+            }),
+            Err(_) => Err(Error::InvalidNixExpression(
+                "update script should have a lockfile and an output file".to_owned(),
+            )),
+        }
+    }
+
+    fn parse_attrs(&mut self) -> Result<HashMap<String, String>> {
+        self.expect(&Token::Symbol('{'))?;
+
+        let mut attrs = HashMap::new();
+        while !self.at_symbol('}') {
+            let key = match self.next()? {
+                Token::Ident(key) | Token::Str(key) => key,
+                token => return Err(unexpected(&token)),
+            };
+
+            self.expect(&Token::Symbol('='))?;
+
+            let value = match self.next()? {
+                Token::Str(value) | Token::Path(value) | Token::Ident(value) => value,
+                token => return Err(unexpected(&token)),
+            };
+
+            self.expect(&Token::Symbol(';'))?;
+
+            attrs.insert(key, value);
+        }
+
+        self.expect(&Token::Symbol('}'))?;
+
+        Ok(attrs)
+    }
+
+    fn at_symbol(&self, symbol: char) -> bool {
+        self.tokens.get(self.pos) == Some(&Token::Symbol(symbol))
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| Error::InvalidNixExpression("unexpected end of file".to_owned()))?;
+
+        self.pos += 1;
+
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<()> {
+        match self.next()? {
+            token if &token == expected => Ok(()),
+            token => Err(unexpected(&token)),
+        }
+    }
+}
+
+fn unexpected(token: &Token) -> Error {
+    Error::InvalidNixExpression(format!("unexpected token `{token:?}`"))
+}
+
+fn take(attrs: &mut HashMap<String, String>, key: &str) -> Result<String> {
+    attrs
+        .remove(key)
+        .ok_or_else(|| Error::InvalidNixExpression(format!("missing attribute `{key}`")))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut chars = input.chars().peekable();
+    let mut tokens = Vec::new();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Str(read_string(&mut chars)?));
+            }
+            '.' => {
+                let word = read_word(&mut chars);
+
+                match word.as_str() {
+                    "..." => tokens.push(Token::Ellipsis),
+                    _ => tokens.push(Token::Path(word)),
+                }
+            }
+            '{' | '}' | '[' | ']' | '(' | ')' | '=' | ';' | ':' | ',' | '@' => {
+                chars.next();
+                tokens.push(Token::Symbol(c));
+            }
+            _ => {
+                let word = read_word(&mut chars);
+
+                if word.is_empty() {
+                    return Err(Error::InvalidNixExpression(format!(
+                        "unexpected character `{c}`"
+                    )));
+                }
+
+                tokens.push(Token::Ident(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn read_word(chars: &mut Peekable<Chars>) -> String {
+    let mut word = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace()
+            || matches!(
+                c,
+                '{' | '}' | '[' | ']' | '(' | ')' | '=' | ';' | ':' | ',' | '"' | '#'
+            )
+        {
+            break;
+        }
+
+        word.push(c);
+        chars.next();
+    }
+
+    word
+}
+
+fn read_string(chars: &mut Peekable<Chars>) -> Result<String> {
+    let mut string = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Ok(string),
+            '\\' => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some(escaped) => string.push(escaped),
+                None => break,
+            },
+            c => string.push(c),
+        }
+    }
+
+    Err(Error::InvalidNixExpression(
+        "unterminated string literal".to_owned(),
+    ))
+}
@@ -0,0 +1,87 @@
+//! This module holds rendering of a NixOS module which runs a bun server application as a
+//! systemd service
+
+use askama::Template;
+
+use crate::{
+    PackageJson,
+    error::Result,
+    oci_image::image_name,
+    prune::{Prune, render_prune_list},
+};
+
+/// # NixOS Module
+///
+/// A `module.nix` exposing `services.<name>`, which runs the project with bun in the same way as
+/// [`crate::oci_image::OciImage`], with options for its port, environment and user
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{PackageJson, nixos_module::NixosModule};
+///
+/// let package_json: PackageJson = r#"{ "name": "server", "scripts": { "start": "bun ." } }"#
+///     .parse()
+///     .unwrap();
+///
+/// let nix = NixosModule::new(&package_json, &[]).unwrap().render().unwrap();
+///
+/// assert!(nix.contains(r#"options.services."server" = {"#));
+/// assert!(nix.contains(r#""start""#));
+/// ```
+#[derive(Template)]
+#[template(path = "nixos-module.nix_template")]
+pub struct NixosModule {
+    name: String,
+    version: String,
+    command: Vec<String>,
+    workspaces: Vec<String>,
+    prune: String,
+}
+
+impl NixosModule {
+    /// # New NixOS Module
+    ///
+    /// Create a module for the project with the given `package.json` and workspace paths
+    pub fn new<'a>(
+        package_json: &PackageJson,
+        workspaces: impl IntoIterator<Item = &'a String>,
+    ) -> Result<Self> {
+        let mut workspaces: Vec<String> = workspaces
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .cloned()
+            .collect();
+
+        workspaces.sort();
+
+        Ok(Self {
+            name: image_name(package_json.name.as_deref().unwrap_or("app")),
+            version: package_json
+                .version
+                .as_deref()
+                .unwrap_or("latest")
+                .to_owned(),
+            command: package_json.start_command()?,
+            workspaces,
+            prune: String::new(),
+        })
+    }
+
+    /// # With Prune
+    ///
+    /// Remove the given kinds of files from packages when building the module's `node_modules`
+    pub fn with_prune(mut self, prune: &[Prune]) -> Self {
+        self.prune = render_prune_list(prune);
+        self
+    }
+
+    /// # Render
+    ///
+    /// Render the module as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
@@ -0,0 +1,133 @@
+//! This module holds rendering of a standalone package expression for a bun application which
+//! follows the conventions of nixpkgs, as a starting point for upstreaming it
+
+use askama::Template;
+
+use crate::{PackageJson, error::Result, oci_image::image_name};
+
+/// # Nixpkgs Package
+///
+/// A `package.nix` for `pkgs/by-name`, which builds the project with `bun2nix.mkDerivation`'s
+/// hook and wraps bun to run its `start` script (or its `module` if it has none). It is taken
+/// only from the package set, with `meta` filled in from the `package.json` and placeholders
+/// left for the source and maintainers.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{PackageJson, nixpkgs_package::NixpkgsPackage};
+///
+/// let package_json: PackageJson = r#"
+/// {
+///   "name": "@acme/server",
+///   "version": "1.2.0",
+///   "description": "Serves things",
+///   "license": "Apache-2.0",
+///   "module": "index.ts"
+/// }
+/// "#
+/// .parse()
+/// .unwrap();
+///
+/// let nix = NixpkgsPackage::new(&package_json).unwrap().render().unwrap();
+///
+/// assert!(nix.contains(r#"pname = "acme-server";"#));
+/// assert!(nix.contains(r#"description = "Serves things";"#));
+/// assert!(nix.contains("license = lib.licenses.asl20;"));
+/// ```
+#[derive(Template)]
+#[template(path = "nixpkgs-package.nix_template")]
+pub struct NixpkgsPackage {
+    name: String,
+    version: String,
+    command: Vec<String>,
+    description: Option<String>,
+    homepage: Option<String>,
+    license: License,
+}
+
+/// The license of the package, as it is written in `meta.license`
+enum License {
+    /// One of `lib.licenses`
+    Known(&'static str),
+
+    /// A license with no equivalent in `lib.licenses`, kept as a comment to resolve by hand
+    Unknown(String),
+
+    /// No license was given
+    Missing,
+}
+
+impl NixpkgsPackage {
+    /// # New Nixpkgs Package
+    ///
+    /// Create a package expression for the project with the given `package.json`
+    pub fn new(package_json: &PackageJson) -> Result<Self> {
+        let license = match package_json.license.as_deref() {
+            Some(spdx) => match nixpkgs_license(spdx) {
+                Some(license) => License::Known(license),
+                None => License::Unknown(spdx.to_owned()),
+            },
+            None => License::Missing,
+        };
+
+        Ok(Self {
+            name: image_name(package_json.name.as_deref().unwrap_or("app")),
+            version: package_json
+                .version
+                .as_deref()
+                .unwrap_or("0.0.0")
+                .to_owned(),
+            command: package_json.start_command()?,
+            description: package_json.description.clone(),
+            homepage: package_json.homepage.clone(),
+            license,
+        })
+    }
+
+    /// # Render
+    ///
+    /// Render the package as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
+
+/// # Nixpkgs License
+///
+/// The attribute of `lib.licenses` for an SPDX license identifier, if it is a common one
+///
+/// ## Usage
+///```rust
+/// use bun2nix::nixpkgs_package::nixpkgs_license;
+///
+/// assert_eq!(nixpkgs_license("MIT"), Some("mit"));
+/// assert_eq!(nixpkgs_license("GPL-3.0-or-later"), Some("gpl3Plus"));
+/// assert_eq!(nixpkgs_license("MIT OR Apache-2.0"), None);
+/// ```
+pub fn nixpkgs_license(spdx: &str) -> Option<&'static str> {
+    Some(match spdx {
+        "0BSD" => "bsd0",
+        "AGPL-3.0-only" => "agpl3Only",
+        "AGPL-3.0-or-later" => "agpl3Plus",
+        "Apache-2.0" => "asl20",
+        "BSD-2-Clause" => "bsd2",
+        "BSD-3-Clause" => "bsd3",
+        "BlueOak-1.0.0" => "blueOak100",
+        "CC0-1.0" => "cc0",
+        "GPL-2.0-only" => "gpl2Only",
+        "GPL-2.0-or-later" => "gpl2Plus",
+        "GPL-3.0-only" => "gpl3Only",
+        "GPL-3.0-or-later" => "gpl3Plus",
+        "ISC" => "isc",
+        "LGPL-3.0-only" => "lgpl3Only",
+        "LGPL-3.0-or-later" => "lgpl3Plus",
+        "MIT" => "mit",
+        "MPL-2.0" => "mpl20",
+        "Unlicense" => "unlicense",
+        "Zlib" => "zlib",
+        _ => return None,
+    })
+}
@@ -0,0 +1,315 @@
+//! This module holds a comparison of a `node_modules` directory installed by bun against the
+//! packages of a generated `bun.nix`, for finding why a project which works locally does not
+//! work when built with nix
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use crate::{Package, PackageJson, error::Result};
+
+/// # Installed Package
+///
+/// A package found in a `node_modules` directory
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InstalledPackage {
+    /// The name of the package, from its `package.json`
+    pub name: String,
+
+    /// The version of the package, from its `package.json`
+    pub version: String,
+
+    /// The directory the package is installed to
+    pub path: PathBuf,
+}
+
+/// # Version Mismatch
+///
+/// A package installed at a version which `bun.nix` does not have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The package as it is installed
+    pub installed: InstalledPackage,
+
+    /// The versions of the package in `bun.nix`
+    pub expected: Vec<String>,
+}
+
+/// # Node Modules Drift
+///
+/// The differences between the packages installed to `node_modules` and those of a generated
+/// `bun.nix`. Only packages from the registry are compared by version, as only they are named
+/// for their version in `bun.nix`; the names of any other packages are given to
+/// [`Drift::new`] so that they are not reported as extraneous.
+///
+/// ## Usage
+///```rust
+/// use std::path::PathBuf;
+///
+/// use bun2nix::{
+///     Package,
+///     node_modules::{Drift, InstalledPackage},
+///     package::Fetcher,
+/// };
+///
+/// let npm = |ident: &str| {
+///     Package::new(
+///         ident.to_owned(),
+///         Fetcher::new_npm_package(ident, "sha512-abc".to_owned()).unwrap(),
+///     )
+/// };
+/// let installed = |name: &str, version: &str| InstalledPackage {
+///     name: name.to_owned(),
+///     version: version.to_owned(),
+///     path: PathBuf::from("node_modules").join(name),
+/// };
+///
+/// let drift = Drift::new(
+///     &[npm("is-odd@3.0.1"), npm("is-number@6.0.0"), npm("react@18.3.1")],
+///     &[
+///         installed("is-odd", "3.0.1"),
+///         installed("react", "18.2.0"),
+///         installed("left-pad", "1.3.0"),
+///         installed("zod", "3.22.4"),
+///     ],
+///     &["zod"],
+/// );
+///
+/// assert_eq!(drift.missing, ["is-number@6.0.0"]);
+/// assert_eq!(drift.mismatched[0].installed.name, "react");
+/// assert_eq!(drift.mismatched[0].expected, ["18.3.1"]);
+/// assert_eq!(drift.extraneous[0].name, "left-pad");
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Drift {
+    /// Packages in `bun.nix` which are not installed, by their name in `bun.nix`
+    pub missing: Vec<String>,
+
+    /// Packages installed at a version `bun.nix` does not have
+    pub mismatched: Vec<Mismatch>,
+
+    /// Packages installed which are not in `bun.nix` at all
+    pub extraneous: Vec<InstalledPackage>,
+}
+
+impl Drift {
+    /// # New Drift
+    ///
+    /// Compare the packages of a `bun.nix` against those installed, where `other_names` are
+    /// the names packages not from the registry are installed under
+    pub fn new(expected: &[Package], installed: &[InstalledPackage], other_names: &[&str]) -> Self {
+        let mut versions: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+
+        for (name, version) in expected.iter().filter_map(Package::npm_identifier) {
+            versions.entry(name).or_default().insert(version);
+        }
+
+        let mut drift = Self::default();
+        let mut found = BTreeSet::new();
+
+        for pkg in installed {
+            match versions.get(pkg.name.as_str()) {
+                Some(expected) if expected.contains(pkg.version.as_str()) => {
+                    found.insert((pkg.name.as_str(), pkg.version.as_str()));
+                }
+                Some(expected) => drift.mismatched.push(Mismatch {
+                    installed: pkg.clone(),
+                    expected: expected.iter().map(|version| version.to_string()).collect(),
+                }),
+                None if other_names.contains(&pkg.name.as_str()) => (),
+                None => drift.extraneous.push(pkg.clone()),
+            }
+        }
+
+        // A package installed at the wrong version is only reported as mismatched
+        let mismatched: BTreeSet<&str> = drift
+            .mismatched
+            .iter()
+            .map(|mismatch| mismatch.installed.name.as_str())
+            .collect();
+
+        drift.missing = expected
+            .iter()
+            .filter_map(|pkg| Some((pkg, pkg.npm_identifier()?)))
+            .filter(|(_, ident)| !found.contains(ident) && !mismatched.contains(ident.0))
+            .map(|(pkg, _)| pkg.name.clone())
+            .collect();
+
+        drift
+    }
+
+    /// # Is Empty
+    ///
+    /// Whether `node_modules` matches `bun.nix`
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty() && self.extraneous.is_empty()
+    }
+
+    /// # Count
+    ///
+    /// The number of packages which differ
+    pub fn count(&self) -> usize {
+        self.missing.len() + self.mismatched.len() + self.extraneous.len()
+    }
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.missing {
+            writeln!(f, "- {name} (not installed)")?;
+        }
+
+        for mismatch in &self.mismatched {
+            writeln!(
+                f,
+                "~ {}@{} at {} (bun.nix has {})",
+                mismatch.installed.name,
+                mismatch.installed.version,
+                mismatch.installed.path.display(),
+                mismatch.expected.join(", ")
+            )?;
+        }
+
+        for pkg in &self.extraneous {
+            writeln!(
+                f,
+                "+ {}@{} at {} (not in bun.nix)",
+                pkg.name,
+                pkg.version,
+                pkg.path.display()
+            )?;
+        }
+
+        write!(
+            f,
+            "{} missing, {} mismatched, {} extraneous",
+            self.missing.len(),
+            self.mismatched.len(),
+            self.extraneous.len()
+        )
+    }
+}
+
+/// # Installed Packages
+///
+/// Find every package installed to a `node_modules` directory, including those nested in the
+/// `node_modules` of other packages and those in the store of bun's isolated linker
+/// (`node_modules/.bun`). Symlinks are not followed, as they point at workspace members or at
+/// packages which are found in the store. A missing directory has no packages.
+///
+/// ## Usage
+///```rust
+/// use std::fs;
+///
+/// use bun2nix::node_modules::installed_packages;
+///
+/// let dir = std::env::temp_dir().join("bun2nix-node-modules-doctest");
+/// let package = |path: &str, name: &str, version: &str| {
+///     fs::create_dir_all(dir.join(path)).unwrap();
+///     fs::write(
+///         dir.join(path).join("package.json"),
+///         format!(r#"{{ "name": "{name}", "version": "{version}" }}"#),
+///     )
+///     .unwrap();
+/// };
+///
+/// package("node_modules/is-odd", "is-odd", "3.0.1");
+/// package("node_modules/is-odd/node_modules/is-number", "is-number", "6.0.0");
+/// package("node_modules/@types/bun", "@types/bun", "1.2.4");
+/// fs::create_dir_all(dir.join("node_modules/.bin")).unwrap();
+///
+/// let installed: Vec<_> = installed_packages(&dir.join("node_modules"))
+///     .unwrap()
+///     .into_iter()
+///     .map(|pkg| format!("{}@{}", pkg.name, pkg.version))
+///     .collect();
+///
+/// assert_eq!(installed, ["@types/bun@1.2.4", "is-number@6.0.0", "is-odd@3.0.1"]);
+///
+/// fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn installed_packages(node_modules: &Path) -> Result<Vec<InstalledPackage>> {
+    let mut installed = Vec::new();
+
+    find_packages(node_modules, &mut installed)?;
+    installed.sort();
+
+    Ok(installed)
+}
+
+fn find_packages(node_modules: &Path, installed: &mut Vec<InstalledPackage>) -> Result<()> {
+    for dir in directories(node_modules)? {
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+
+        match name {
+            name if name == ".bun" => {
+                for entry in directories(&dir)? {
+                    find_packages(&entry.join("node_modules"), installed)?;
+                }
+            }
+            name if name.starts_with('.') => (),
+            name if name.starts_with('@') => {
+                for package in directories(&dir)? {
+                    find_package(&package, installed)?;
+                }
+            }
+            _ => find_package(&dir, installed)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn find_package(dir: &Path, installed: &mut Vec<InstalledPackage>) -> Result<()> {
+    let path = dir.join("package.json");
+
+    let package_json = match fs::read_to_string(&path) {
+        Ok(package_json) => package_json,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let Ok(package_json) = package_json.parse::<PackageJson>() else {
+        warn!(
+            "Skipping `{}`, which is not a valid `package.json`",
+            path.display()
+        );
+        return Ok(());
+    };
+
+    if let (Some(name), Some(version)) = (package_json.name, package_json.version) {
+        installed.push(InstalledPackage {
+            name,
+            version,
+            path: dir.to_owned(),
+        });
+    }
+
+    find_packages(&dir.join("node_modules"), installed)
+}
+
+/// The directories in a directory, not following symlinks, or none if it does not exist
+fn directories(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut dirs = Vec::new();
+
+    for entry in entries {
+        let entry = entry?;
+
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+
+    Ok(dirs)
+}
@@ -0,0 +1,163 @@
+//! This module holds rendering of a package set as an npm `package-lock.json`, so that projects
+//! can be built with the `buildNpmPackage` and `importNpmLock` tooling in nixpkgs using the same
+//! hashes bun2nix already knows about
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::{Map, Value, json};
+
+use crate::{Package, error::Result, lockfile::Workspace, package::Fetcher};
+
+/// # Render npm Lockfile
+///
+/// Render the workspaces and packages of a bun lockfile as a version 3 npm `package-lock.json`.
+///
+/// Registry tarballs keep the url and integrity hash from the bun lockfile, git dependencies are
+/// pinned to their revision, and workspace or file packages are linked to their path. The
+/// dependencies of individual packages are not recorded, as they are not needed to reproduce
+/// the `./node_modules` tree.
+///
+/// ## Usage
+///```rust
+/// use std::collections::{BTreeMap, HashMap};
+///
+/// use bun2nix::{Package, npm_lock::render_npm_lock, package::Fetcher};
+///
+/// let pkg = Package::new(
+///     "is-odd@3.0.1".to_owned(),
+///     Fetcher::new_npm_package("is-odd@3.0.1", "sha512-abc".to_owned()).unwrap(),
+/// )
+/// .with_lockfile_key("is-odd".to_owned());
+///
+/// let lock: serde_json::Value =
+///     serde_json::from_str(&render_npm_lock(&HashMap::new(), &[pkg]).unwrap()).unwrap();
+///
+/// assert_eq!(lock["lockfileVersion"], 3);
+/// assert_eq!(lock["packages"]["node_modules/is-odd"]["version"], "3.0.1");
+/// assert_eq!(lock["packages"]["node_modules/is-odd"]["integrity"], "sha512-abc");
+/// ```
+pub fn render_npm_lock(
+    workspaces: &HashMap<String, Workspace>,
+    packages: &[Package],
+) -> Result<String> {
+    let mut entries = Map::new();
+    entries.insert(String::new(), Value::Object(Map::new()));
+
+    let mut workspaces: Vec<_> = workspaces.iter().collect();
+    workspaces.sort_by_key(|(path, _)| *path);
+
+    for (path, workspace) in &workspaces {
+        entries.insert(path.to_string(), workspace_entry(workspace));
+    }
+
+    for pkg in packages {
+        let key = pkg
+            .lockfile_key
+            .as_deref()
+            .or(pkg.npm_identifier().map(|(name, _)| name))
+            .unwrap_or(&pkg.name);
+
+        entries.insert(npm_path(key), package_entry(pkg));
+    }
+
+    let mut lock = Map::new();
+
+    if let Some((_, root)) = workspaces.first().filter(|(path, _)| path.is_empty()) {
+        lock.insert("name".to_owned(), json!(root.name));
+    }
+
+    lock.insert("lockfileVersion".to_owned(), json!(3));
+    lock.insert("requires".to_owned(), json!(true));
+    lock.insert("packages".to_owned(), Value::Object(entries));
+
+    let mut lock = serde_json::to_string_pretty(&lock)?;
+    lock.push('\n');
+
+    Ok(lock)
+}
+
+/// # npm Path
+///
+/// Convert the key of a package in a bun lockfile to its path in an npm lockfile
+///
+/// ## Usage
+///```rust
+/// use bun2nix::npm_lock::npm_path;
+///
+/// assert_eq!(npm_path("is-odd"), "node_modules/is-odd");
+/// assert_eq!(
+///     npm_path("@types/bun/@types/node"),
+///     "node_modules/@types/bun/node_modules/@types/node"
+/// );
+/// ```
+pub fn npm_path(key: &str) -> String {
+    let mut names = Vec::new();
+    let mut segments = key.split('/').peekable();
+
+    while let Some(segment) = segments.next() {
+        match segments.next_if(|_| segment.starts_with('@')) {
+            Some(name) => names.push(format!("{segment}/{name}")),
+            None => names.push(segment.to_owned()),
+        }
+    }
+
+    format!("node_modules/{}", names.join("/node_modules/"))
+}
+
+fn workspace_entry(workspace: &Workspace) -> Value {
+    let mut entry = Map::new();
+
+    if let Some(name) = &workspace.name {
+        entry.insert("name".to_owned(), json!(name));
+    }
+
+    if !workspace.dependencies.is_empty() {
+        entry.insert("dependencies".to_owned(), sorted(&workspace.dependencies));
+    }
+
+    if !workspace.dev_dependencies.is_empty() {
+        entry.insert(
+            "devDependencies".to_owned(),
+            sorted(&workspace.dev_dependencies),
+        );
+    }
+
+    Value::Object(entry)
+}
+
+fn sorted(dependencies: &HashMap<String, String>) -> Value {
+    json!(dependencies.iter().collect::<BTreeMap<_, _>>())
+}
+
+fn package_entry(pkg: &Package) -> Value {
+    match &pkg.fetcher {
+        Fetcher::FetchUrl { url, hash } => {
+            let (name, version) = pkg.npm_identifier().unwrap_or_default();
+
+            let mut entry = json!({
+                "version": version,
+                "resolved": url,
+                "integrity": hash,
+            });
+
+            let aliased = pkg
+                .lockfile_key
+                .as_deref()
+                .is_some_and(|key| npm_path(key).rsplit("node_modules/").next() != Some(name));
+
+            if aliased {
+                entry["name"] = json!(name);
+            }
+
+            entry
+        }
+        Fetcher::FetchGit { url, rev, .. } => json!({ "resolved": format!("git+{url}#{rev}") }),
+        Fetcher::FetchGitHub {
+            owner, repo, rev, ..
+        } => json!({
+            "resolved": format!("git+ssh://git@github.com/{owner}/{repo}.git#{rev}"),
+        }),
+        Fetcher::FetchTarball { url, .. } => json!({ "resolved": url }),
+        Fetcher::CopyToStore { path } => json!({ "resolved": path, "link": true }),
+    }
+}
@@ -0,0 +1,118 @@
+//! This module holds rendering of a container image expression for a bun application, which
+//! can be built and loaded straight into docker or podman
+
+use askama::Template;
+
+use crate::{
+    PackageJson,
+    error::Result,
+    prune::{Prune, render_prune_list},
+};
+
+/// # OCI Image
+///
+/// An `image.nix` wrapping the project in a `dockerTools.streamLayeredImage`, which runs the
+/// `start` script of the `package.json` (or its `module` if it has none) with bun. The
+/// `node_modules` is built from `bun.nix` as its own store path, and so ends up in its own layer.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{PackageJson, oci_image::OciImage, prune::Prune};
+///
+/// let package_json: PackageJson = r#"{ "name": "@acme/server", "module": "index.ts" }"#
+///     .parse()
+///     .unwrap();
+///
+/// let nix = OciImage::new(&package_json, &[]).unwrap().render().unwrap();
+///
+/// assert!(nix.contains(r#"name = "acme-server";"#));
+/// assert!(nix.contains(r#""index.ts""#));
+/// assert!(!nix.contains("prune = "));
+///
+/// let nix = OciImage::new(&package_json, &[])
+///     .unwrap()
+///     .with_prune(&[Prune::Docs, Prune::Maps])
+///     .render()
+///     .unwrap();
+///
+/// assert!(nix.contains(r#"prune = [ "docs" "maps" ];"#));
+/// ```
+#[derive(Template)]
+#[template(path = "oci-image.nix_template")]
+pub struct OciImage {
+    name: String,
+    version: String,
+    command: Vec<String>,
+    workspaces: Vec<String>,
+    prune: String,
+}
+
+impl OciImage {
+    /// # New OCI Image
+    ///
+    /// Create an image for the project with the given `package.json` and workspace paths
+    pub fn new<'a>(
+        package_json: &PackageJson,
+        workspaces: impl IntoIterator<Item = &'a String>,
+    ) -> Result<Self> {
+        let mut workspaces: Vec<String> = workspaces
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .cloned()
+            .collect();
+
+        workspaces.sort();
+
+        Ok(Self {
+            name: image_name(package_json.name.as_deref().unwrap_or("app")),
+            version: package_json
+                .version
+                .as_deref()
+                .unwrap_or("latest")
+                .to_owned(),
+            command: package_json.start_command()?,
+            workspaces,
+            prune: String::new(),
+        })
+    }
+
+    /// # With Prune
+    ///
+    /// Remove the given kinds of files from packages when building the image's `node_modules`
+    pub fn with_prune(mut self, prune: &[Prune]) -> Self {
+        self.prune = render_prune_list(prune);
+        self
+    }
+
+    /// # Render
+    ///
+    /// Render the image as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
+
+/// # Image Name
+///
+/// Convert the name of a package to a valid image name
+///
+/// ## Usage
+///```rust
+/// use bun2nix::oci_image::image_name;
+///
+/// assert_eq!(image_name("@Acme/Server"), "acme-server");
+/// assert_eq!(image_name("my_app.v2"), "my_app.v2");
+/// ```
+pub fn image_name(name: &str) -> String {
+    name.trim_start_matches('@')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '.' | '_' | '-' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '-',
+        })
+        .collect()
+}
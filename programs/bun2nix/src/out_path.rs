@@ -0,0 +1,170 @@
+//! This module holds the paths packages are linked to in the bun cache and `node_modules`,
+//! normalized so that every host writes them alike, and the search for paths which differ only
+//! by case, which clash on case-insensitive filesystems such as the default one on macOS
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{Package, dependency_graph::install_path};
+
+/// # Normalize Path
+///
+/// A relative path from the lockfile as it is written to `bun.nix` - composed to unicode NFC
+/// (macOS may hand out decomposed names), separated by `/` even if written on Windows, and
+/// without empty, `.` or trailing components
+///
+/// ## Usage
+///```rust
+/// use bun2nix::out_path::normalize_path;
+///
+/// assert_eq!(normalize_path("./vendor//is-odd/"), "vendor/is-odd");
+/// assert_eq!(normalize_path(r"..\packages\lib"), "../packages/lib");
+/// assert_eq!(normalize_path("packages/app/../lib"), "packages/lib");
+/// assert_eq!(normalize_path("../../lib"), "../../lib");
+/// assert_eq!(normalize_path("vendor/cafe\u{301}"), "vendor/caf\u{e9}");
+/// assert_eq!(normalize_path("./"), ".");
+/// ```
+pub fn normalize_path(path: &str) -> String {
+    let path: String = path
+        .nfc()
+        .map(|c| if c == '\\' { '/' } else { c })
+        .collect();
+
+    let mut components: Vec<&str> = Vec::new();
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." if components.last().is_some_and(|last| *last != "..") => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+
+    let normalized = components.join("/");
+
+    match (path.starts_with('/'), normalized.is_empty()) {
+        (true, _) => format!("/{normalized}"),
+        (false, true) => ".".to_owned(),
+        (false, false) => normalized,
+    }
+}
+
+/// # Case Collision
+///
+/// Paths which differ only by case or unicode normalization, and so are the same file on a
+/// case-insensitive filesystem, where linking one overwrites or merges into the other
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseCollision {
+    /// Where the paths clash
+    pub location: OutDir,
+
+    /// The clashing paths, in order
+    pub paths: Vec<String>,
+}
+
+/// # Output Directory
+///
+/// A directory which the outputs of several packages are linked into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OutDir {
+    /// The bun cache joined together by `fetchBunDeps`, with an entry per package name
+    BunCache,
+
+    /// The `node_modules` tree bun installs, with a directory per lockfile key
+    NodeModules,
+}
+
+impl Display for OutDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BunCache => write!(f, "the bun cache"),
+            Self::NodeModules => write!(f, "`node_modules`"),
+        }
+    }
+}
+
+impl Display for CaseCollision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paths: Vec<String> = self.paths.iter().map(|path| format!("`{path}`")).collect();
+
+        write!(f, "{} clash in {}", paths.join(" and "), self.location)
+    }
+}
+
+/// # Case Collisions
+///
+/// Every set of packages whose entries in the bun cache or directories in `node_modules` only
+/// differ by case or unicode normalization.
+///
+/// The entries bun names the cache after hash the prerelease and build of a version, and the url
+/// of a tarball, but fold to the same path whenever the names of their packages do, so comparing
+/// the names finds every clash.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{
+///     Package,
+///     out_path::{OutDir, case_collisions},
+///     package::Fetcher,
+/// };
+///
+/// let pkg = |name: &str, key: &str| Package {
+///     lockfile_key: Some(key.to_owned()),
+///     ..Package::new(name.to_owned(), Fetcher::CopyToStore { path: key.to_owned() })
+/// };
+///
+/// let collisions = case_collisions(&[
+///     pkg("JSONStream@1.3.5", "JSONStream"),
+///     pkg("jsonstream@1.3.5", "tool/jsonstream"),
+///     pkg("jsonstream@1.3.5", "jsonstream"),
+///     pkg("is-odd@3.0.1", "is-odd"),
+/// ]);
+///
+/// assert_eq!(collisions.len(), 2);
+/// assert_eq!(collisions[0].location, OutDir::BunCache);
+/// assert_eq!(
+///     collisions[0].to_string(),
+///     "`JSONStream@1.3.5` and `jsonstream@1.3.5` clash in the bun cache"
+/// );
+/// assert_eq!(
+///     collisions[1].to_string(),
+///     "`node_modules/JSONStream` and `node_modules/jsonstream` clash in `node_modules`"
+/// );
+/// ```
+pub fn case_collisions(packages: &[Package]) -> Vec<CaseCollision> {
+    let mut folded: BTreeMap<(OutDir, String), BTreeSet<String>> = BTreeMap::new();
+
+    for pkg in packages {
+        folded
+            .entry((OutDir::BunCache, fold(&pkg.name)))
+            .or_default()
+            .insert(pkg.name.clone());
+
+        if let Some(key) = &pkg.lockfile_key {
+            folded
+                .entry((OutDir::NodeModules, fold(key)))
+                .or_default()
+                .insert(install_path(key));
+        }
+    }
+
+    folded
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((location, _), paths)| CaseCollision {
+            location,
+            paths: paths.into_iter().collect(),
+        })
+        .collect()
+}
+
+/// A path as a case-insensitive filesystem compares it
+fn fold(path: &str) -> String {
+    path.nfc().flat_map(char::to_lowercase).collect()
+}
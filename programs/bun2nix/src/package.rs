@@ -1,15 +1,32 @@
 //! This module holds the core implementation for the package type and related methods
 
 use std::{
+    collections::BTreeMap,
     fmt::Debug,
     hash::{Hash, Hasher},
 };
 
 use serde::Serialize;
 
+use crate::{git_refs::is_commit, platform::Platform};
+
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+mod fetch_command;
 mod fetcher;
+mod hash;
+mod nar;
+mod nix;
+mod pattern;
+mod prefetch;
 
+pub use fetch_command::FetchCommand;
 pub use fetcher::Fetcher;
+pub use hash::{digest_to_sri, hash_sri, nix32_to_sri, sha256_sri, sri_to_hex};
+pub use nar::nar_sri;
+pub use nix::{DEFAULT_NIX_BIN, Nix};
+pub use pattern::PackagePattern;
+pub use prefetch::{GitPrefetch, Prefetch, uses_lfs};
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase", default)]
@@ -23,6 +40,24 @@ pub struct Package {
 
     /// The fetch method to use for the package
     pub fetcher: Fetcher,
+
+    /// The key of the package in the bun lockfile, which gives its place in the
+    /// `./node_modules` tree, such as `parent/child` for a copy nested under `parent`
+    #[serde(skip)]
+    pub lockfile_key: Option<String>,
+
+    /// The platforms the package can be installed on
+    #[serde(skip)]
+    pub platform: Platform,
+
+    /// The tag or branch a git dependency was declared by, if it was not declared by a commit
+    #[serde(skip)]
+    pub git_ref: Option<String>,
+
+    /// The binaries the package links into `node_modules/.bin`, by name, with their paths
+    /// inside the package
+    #[serde(skip)]
+    pub bin: BTreeMap<String, String>,
 }
 
 impl Package {
@@ -31,8 +66,133 @@ impl Package {
     /// Creates a given package using it's name
     /// and fetcher information
     pub fn new(name: String, fetcher: Fetcher) -> Self {
-        Self { name, fetcher }
+        Self {
+            name,
+            fetcher,
+            lockfile_key: None,
+            platform: Platform::default(),
+            git_ref: None,
+            bin: BTreeMap::new(),
+        }
+    }
+
+    /// # With Lockfile Key
+    ///
+    /// Record the key the package was found under in the bun lockfile
+    pub fn with_lockfile_key(mut self, key: String) -> Self {
+        self.lockfile_key = Some(key);
+        self
+    }
+
+    /// # With Platform
+    ///
+    /// Record the platforms the package can be installed on
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
     }
+
+    /// # With Binaries
+    ///
+    /// Record the binaries the package links into `node_modules/.bin`
+    pub fn with_bin(mut self, bin: BTreeMap<String, String>) -> Self {
+        self.bin = bin;
+        self
+    }
+
+    /// # Pin Git Revision
+    ///
+    /// Fetch a git or GitHub dependency at the given commit rather than the tag or branch it
+    /// was declared by, which is kept as its git ref
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{Package, package::Fetcher};
+    ///
+    /// let mut pkg = Package::new(
+    ///     "github:colinhacks-zod-v3.22.4".to_owned(),
+    ///     Fetcher::FetchGitHub {
+    ///         owner: "colinhacks".to_owned(),
+    ///         repo: "zod".to_owned(),
+    ///         rev: "v3.22.4".to_owned(),
+    ///         hash: String::new(),
+    ///     },
+    /// );
+    ///
+    /// pkg.pin_git_rev("5bfc8f2");
+    ///
+    /// assert_eq!(pkg.name, "github:colinhacks-zod-5bfc8f2");
+    /// assert_eq!(pkg.git_ref.as_deref(), Some("v3.22.4"));
+    /// ```
+    pub fn pin_git_rev(&mut self, commit: &str) {
+        let (rev, name) = match &mut self.fetcher {
+            Fetcher::FetchGit { rev, .. } => (rev, format!("git:{commit}")),
+            Fetcher::FetchGitHub {
+                owner, repo, rev, ..
+            } => (rev, format!("github:{owner}-{repo}-{commit}")),
+            _ => return,
+        };
+
+        let declared = std::mem::replace(rev, commit.to_owned());
+
+        self.git_ref.get_or_insert(declared);
+        self.name = name;
+    }
+
+    /// # Is Pinned
+    ///
+    /// Whether the package is fetched at a fixed revision, which is only not the case for git
+    /// and GitHub dependencies fetched by a tag or branch
+    pub fn is_pinned(&self) -> bool {
+        match &self.fetcher {
+            Fetcher::FetchGit { rev, .. } | Fetcher::FetchGitHub { rev, .. } => is_commit(rev),
+            _ => true,
+        }
+    }
+
+    /// # Needs Prefetch
+    ///
+    /// Whether the hash for this package is not yet known
+    /// and must be prefetched by nix
+    pub fn needs_prefetch(&self) -> bool {
+        self.fetcher.prefetch_url().is_some()
+    }
+
+    /// # Npm Identifier
+    ///
+    /// The registry name and version of this package, or `None`
+    /// if it is not fetched from a registry
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{Package, package::Fetcher};
+    ///
+    /// let pkg = Package::new(
+    ///     "@types/bun@1.2.4".to_owned(),
+    ///     Fetcher::new_npm_package("@types/bun@1.2.4", "sha512-abc".to_owned()).unwrap(),
+    /// );
+    ///
+    /// assert_eq!(pkg.npm_identifier(), Some(("@types/bun", "1.2.4")));
+    /// ```
+    pub fn npm_identifier(&self) -> Option<(&str, &str)> {
+        if !matches!(self.fetcher, Fetcher::FetchUrl { .. }) {
+            return None;
+        }
+
+        match self.name.rfind('@') {
+            Some(pos) if pos > 0 => Some((&self.name[..pos], &self.name[pos + 1..])),
+            _ => None,
+        }
+    }
+}
+
+/// # Normalize Packages
+///
+/// Sort a package set by name and drop all but the first of any packages with the same name, as
+/// they are written to the Nix expression
+pub fn normalize(packages: &mut Vec<Package>) {
+    packages.sort();
+    packages.dedup_by(|a, b| a.name == b.name);
 }
 
 impl Hash for Package {
@@ -0,0 +1,189 @@
+//! This module holds [proptest](https://docs.rs/proptest) strategies for generating packages,
+//! for checking properties of the package model and of code built on top of it.
+//!
+//! Generated names, urls and paths are restricted to the characters found in real lockfiles,
+//! so that every generated package can be rendered into a valid Nix expression.
+//!
+//! ## Usage
+//!```rust
+//! use bun2nix::{Package, nix_expression::Parser, package::Fetcher};
+//! use proptest::{prelude::*, test_runner::TestRunner};
+//!
+//! let mut runner = TestRunner::default();
+//!
+//! // Rendering a package set and parsing it back gives the same packages
+//! runner
+//!     .run(&prop::collection::vec(any::<Package>(), 0..8), |mut packages| {
+//!         let nix = bun2nix::render_nix_expression(packages.clone()).unwrap();
+//!
+//!         packages.sort();
+//!         packages.dedup_by(|a, b| a.name == b.name);
+//!
+//!         prop_assert_eq!(Parser::parse_packages(&nix).unwrap(), packages);
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//!
+//! // Rendering is idempotent once the package set has been sorted and deduplicated
+//! runner
+//!     .run(&prop::collection::vec(any::<Package>(), 0..8), |packages| {
+//!         let nix = bun2nix::render_nix_expression(packages).unwrap();
+//!         let parsed = Parser::parse_packages(&nix).unwrap();
+//!
+//!         prop_assert_eq!(bun2nix::render_nix_expression(parsed).unwrap(), nix);
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//!
+//! // Fetchers survive serialization
+//! runner
+//!     .run(&any::<Fetcher>(), |fetcher| {
+//!         let json = serde_json::to_string(&fetcher).unwrap();
+//!
+//!         prop_assert_eq!(serde_json::from_str::<Fetcher>(&json).unwrap(), fetcher);
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use proptest::{collection::vec, prelude::*};
+
+use crate::{Package, package::Fetcher};
+
+/// # Npm Name
+///
+/// Generate the name of an npm package, which may be scoped
+pub fn npm_name() -> impl Strategy<Value = String> {
+    "(@[a-z][a-z0-9-]{0,8}/)?[a-z][a-z0-9._-]{0,12}"
+}
+
+/// # Version
+///
+/// Generate a semver version, which may have a prerelease tag
+pub fn version() -> impl Strategy<Value = String> {
+    "(0|[1-9][0-9]{0,2})\\.(0|[1-9][0-9]{0,2})\\.(0|[1-9][0-9]{0,2})(-(alpha|beta|rc)\\.[0-9])?"
+}
+
+/// # Npm Identifier
+///
+/// Generate an npm identifier, such as `@types/bun@1.2.4`
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::{Fetcher, arbitrary::npm_identifier};
+/// use proptest::{prelude::*, test_runner::TestRunner};
+///
+/// // Npm urls can be parsed back into the identifier they were built from
+/// TestRunner::default()
+///     .run(&npm_identifier(), |ident| {
+///         let url = Fetcher::to_npm_url(&ident).unwrap();
+///
+///         prop_assert_eq!(Fetcher::parse_npm_url(&url), Some(ident));
+///         Ok(())
+///     })
+///     .unwrap();
+/// ```
+pub fn npm_identifier() -> impl Strategy<Value = String> {
+    (npm_name(), version()).prop_map(|(name, version)| format!("{name}@{version}"))
+}
+
+/// # SRI Hash
+///
+/// Generate a `sha512` or `sha256` hash in SRI format
+pub fn sri_hash() -> impl Strategy<Value = String> {
+    prop_oneof![
+        vec(any::<u8>(), 64).prop_map(|digest| format!("sha512-{}", STANDARD.encode(digest))),
+        vec(any::<u8>(), 32).prop_map(|digest| format!("sha256-{}", STANDARD.encode(digest))),
+    ]
+}
+
+/// # Prefetched Hash
+///
+/// Generate the hash of a fetcher whose hash must be prefetched, which is empty when it has not
+/// been prefetched yet
+pub fn prefetched_hash() -> impl Strategy<Value = String> {
+    prop_oneof![Just(String::new()), sri_hash()]
+}
+
+/// # Git Revision
+///
+/// Generate a full git commit hash
+pub fn git_rev() -> impl Strategy<Value = String> {
+    "[0-9a-f]{40}"
+}
+
+/// # Url
+///
+/// Generate an https url
+pub fn url() -> impl Strategy<Value = String> {
+    "https://[a-z]{1,8}\\.(com|org|dev)(/[a-zA-Z0-9._-]{1,12}){0,3}"
+}
+
+/// # Store Path
+///
+/// Generate a relative path to copy to the store, such as a workspace member
+pub fn store_path() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_-]{0,8}(/[a-z][a-z0-9_-]{0,8}){0,2}"
+}
+
+impl Arbitrary for Fetcher {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            (npm_identifier(), sri_hash()).prop_map(|(ident, hash)| Fetcher::FetchUrl {
+                url: Fetcher::to_npm_url(&ident).expect("npm identifiers contain an `@`"),
+                hash,
+            }),
+            (url(), git_rev(), prefetched_hash()).prop_map(|(url, rev, hash)| Fetcher::FetchGit {
+                url,
+                rev,
+                hash,
+                fetch_lfs: false,
+            }),
+            (
+                "[a-zA-Z0-9-]{1,12}",
+                "[a-zA-Z0-9._-]{1,12}",
+                git_rev(),
+                prefetched_hash()
+            )
+                .prop_map(|(owner, repo, rev, hash)| Fetcher::FetchGitHub {
+                    owner,
+                    repo,
+                    rev,
+                    hash,
+                }),
+            (url(), prefetched_hash()).prop_map(|(url, hash)| Fetcher::FetchTarball { url, hash }),
+            store_path().prop_map(|path| Fetcher::CopyToStore { path }),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Package {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generate a package named the way it would be when read from a bun lockfile
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (any::<Fetcher>(), npm_name())
+            .prop_map(|(fetcher, name)| {
+                let name = match &fetcher {
+                    Fetcher::FetchUrl { url, .. } => {
+                        Fetcher::parse_npm_url(url).expect("generated from an npm identifier")
+                    }
+                    Fetcher::FetchGit { rev, .. } => format!("git:{rev}"),
+                    Fetcher::FetchGitHub {
+                        owner, repo, rev, ..
+                    } => format!("github:{owner}-{repo}-{rev}"),
+                    Fetcher::FetchTarball { url, .. } => format!("tarball:{url}"),
+                    Fetcher::CopyToStore { .. } => name,
+                };
+
+                Package::new(name, fetcher)
+            })
+            .boxed()
+    }
+}
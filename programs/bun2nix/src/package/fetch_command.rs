@@ -0,0 +1,154 @@
+//! This module holds the implementation of user supplied commands for downloading tarballs
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    error::{Error, Result},
+    interrupt,
+    package::{Prefetch, sha256_sri},
+};
+
+/// Placeholder replaced with the url of the tarball to download
+const URL_PLACEHOLDER: &str = "{url}";
+
+/// Placeholder replaced with the path the tarball should be written to
+const PATH_PLACEHOLDER: &str = "{path}";
+
+/// Used to give each download made by this process a unique path
+static DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// # Fetch Command
+///
+/// A command template used to download tarballs instead of nix, for environments where all
+/// downloads must go through a specific tool.
+///
+/// The template is split on whitespace (no shell is involved), and every `{url}` and `{path}`
+/// is replaced with the url of the tarball and the path it should be written to.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::FetchCommand;
+///
+/// let command: FetchCommand = "mytool fetch {url} --out {path}".parse().unwrap();
+///
+/// assert_eq!(
+///     command.args("https://example.com/pkg.tgz", "/tmp/pkg.tgz".as_ref()),
+///     ["mytool", "fetch", "https://example.com/pkg.tgz", "--out", "/tmp/pkg.tgz"]
+/// );
+///
+/// assert!("mytool fetch {url}".parse::<FetchCommand>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchCommand {
+    template: Vec<String>,
+}
+
+impl FromStr for FetchCommand {
+    type Err = Error;
+
+    fn from_str(template: &str) -> Result<Self> {
+        let template: Vec<String> = template.split_whitespace().map(str::to_owned).collect();
+
+        let uses = |placeholder| template.iter().any(|arg| arg.contains(placeholder));
+
+        if template.is_empty() || !uses(URL_PLACEHOLDER) || !uses(PATH_PLACEHOLDER) {
+            return Err(Error::InvalidFetchCommand(template.join(" ")));
+        }
+
+        Ok(Self { template })
+    }
+}
+
+impl FetchCommand {
+    /// # Command Arguments
+    ///
+    /// The program and arguments to run to download a given url to a given path
+    pub fn args(&self, url: &str, path: &Path) -> Vec<String> {
+        let path = path.to_string_lossy();
+
+        self.template
+            .iter()
+            .map(|arg| {
+                arg.replace(URL_PLACEHOLDER, url)
+                    .replace(PATH_PLACEHOLDER, &path)
+            })
+            .collect()
+    }
+
+    /// # Fetch Tarball
+    ///
+    /// Download a tarball with the command and calculate its flat sha256 hash, as used by
+    /// `pkgs.fetchurl`
+    pub fn fetch(&self, url: &str) -> Result<Prefetch> {
+        #[cfg(target_arch = "wasm32")]
+        return Err(Error::UnsupportedWASMCliAction(url.to_owned()));
+
+        let dir = download_dir();
+        fs::create_dir_all(&dir)?;
+
+        let result = self.fetch_into(url, &dir.join("download"));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        result
+    }
+
+    /// # Download Tarball
+    ///
+    /// Download a tarball with the command, returning its contents
+    pub fn download(&self, url: &str) -> Result<Vec<u8>> {
+        #[cfg(target_arch = "wasm32")]
+        return Err(Error::UnsupportedWASMCliAction(url.to_owned()));
+
+        let dir = download_dir();
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join("download");
+        let result = self
+            .run(url, &path)
+            .and_then(|()| fs::read(&path).map_err(Error::FetchingFailed));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        result
+    }
+
+    fn fetch_into(&self, url: &str, path: &Path) -> Result<Prefetch> {
+        self.run(url, path)?;
+
+        let mut file = fs::File::open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Prefetch {
+            hash: sha256_sri(&mut file)?,
+            store_path: None,
+            size: Some(size),
+            fetch_lfs: false,
+        })
+    }
+
+    fn run(&self, url: &str, path: &Path) -> Result<()> {
+        let args = self.args(url, path);
+
+        let output = interrupt::run(Command::new(&args[0]).args(&args[1..]))?;
+
+        match output.status.success() {
+            true => Ok(()),
+            false => Err(Error::FetchingError(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            )),
+        }
+    }
+}
+
+fn download_dir() -> PathBuf {
+    let n = DOWNLOADS.fetch_add(1, Ordering::Relaxed);
+
+    env::temp_dir().join(format!("bun2nix-fetch-{}-{n}", process::id()))
+}
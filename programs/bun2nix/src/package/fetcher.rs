@@ -31,6 +31,9 @@ pub enum Fetcher {
         /// The hash of the downloaded results
         /// This must be calculated via nix-prefetch
         hash: String,
+        /// Whether the repository stores files with git lfs,
+        /// which must be fetched for the hash to match
+        fetch_lfs: bool,
     },
     /// A package which must be retrieved with nix's `pkgs.fetchFromGitHub`
     #[template(path = "fetchgithub.nix_template")]
@@ -73,6 +76,199 @@ impl Fetcher {
         Ok(Self::FetchUrl { url, hash })
     }
 
+    /// # Prefetch URL
+    ///
+    /// The url which must be passed to `nix flake prefetch` in
+    /// order to calculate the hash for this fetcher, or `None`
+    /// if the hash is already known
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::package::Fetcher;
+    ///
+    /// let fetcher = Fetcher::FetchGitHub {
+    ///     owner: "colinhacks".to_owned(),
+    ///     repo: "zod".to_owned(),
+    ///     rev: "8552233".to_owned(),
+    ///     hash: String::new(),
+    /// };
+    ///
+    /// assert_eq!(
+    ///     fetcher.prefetch_url().unwrap(),
+    ///     "github:colinhacks/zod?ref=8552233"
+    /// );
+    /// ```
+    pub fn prefetch_url(&self) -> Option<String> {
+        match self {
+            Self::FetchGit { url, rev, hash, .. } if hash.is_empty() => {
+                Some(format!("git+{}?rev={}", url, rev))
+            }
+            Self::FetchGitHub {
+                owner,
+                repo,
+                rev,
+                hash,
+            } if hash.is_empty() => Some(format!("github:{}/{}?ref={}", owner, repo, rev)),
+            Self::FetchTarball { url, hash } if hash.is_empty() => Some(url.to_owned()),
+            _ => None,
+        }
+    }
+
+    /// # From Prefetch URL
+    ///
+    /// The fetcher a prefetch url was made for, without its
+    /// hash, or `None` if it is not one `prefetch_url` makes
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::package::Fetcher;
+    ///
+    /// let fetcher = Fetcher::FetchGitHub {
+    ///     owner: "colinhacks".to_owned(),
+    ///     repo: "zod".to_owned(),
+    ///     rev: "8552233".to_owned(),
+    ///     hash: String::new(),
+    /// };
+    ///
+    /// assert_eq!(
+    ///     Fetcher::from_prefetch_url("github:colinhacks/zod?ref=8552233"),
+    ///     Some(fetcher)
+    /// );
+    /// assert_eq!(Fetcher::from_prefetch_url("lfs:github:colinhacks/zod?ref=8552233"), None);
+    /// ```
+    pub fn from_prefetch_url(prefetch_url: &str) -> Option<Self> {
+        if let Some(rest) = prefetch_url.strip_prefix("git+") {
+            let (url, rev) = rest.rsplit_once("?rev=")?;
+
+            return Some(Self::FetchGit {
+                url: url.to_owned(),
+                rev: rev.to_owned(),
+                hash: String::new(),
+                fetch_lfs: false,
+            });
+        }
+
+        if let Some(rest) = prefetch_url.strip_prefix("github:") {
+            let (repo, rev) = rest.rsplit_once("?ref=")?;
+            let (owner, repo) = repo.split_once('/')?;
+
+            return Some(Self::FetchGitHub {
+                owner: owner.to_owned(),
+                repo: repo.to_owned(),
+                rev: rev.to_owned(),
+                hash: String::new(),
+            });
+        }
+
+        let tarball = prefetch_url.starts_with("https://") || prefetch_url.starts_with("http://");
+
+        tarball.then(|| Self::FetchTarball {
+            url: prefetch_url.to_owned(),
+            hash: String::new(),
+        })
+    }
+
+    /// # Archive Url
+    ///
+    /// The plain https url of the archive a tarball or GitHub
+    /// fetcher unpacks, for prefetching without flake references
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::package::Fetcher;
+    ///
+    /// let fetcher = Fetcher::FetchGitHub {
+    ///     owner: "owner".to_owned(),
+    ///     repo: "repo".to_owned(),
+    ///     rev: "abc123".to_owned(),
+    ///     hash: String::new(),
+    /// };
+    ///
+    /// assert_eq!(
+    ///     fetcher.archive_url().unwrap(),
+    ///     "https://github.com/owner/repo/archive/abc123.tar.gz"
+    /// );
+    /// ```
+    pub fn archive_url(&self) -> Option<String> {
+        match self {
+            Self::FetchGitHub {
+                owner, repo, rev, ..
+            } => Some(format!(
+                "https://github.com/{}/{}/archive/{}.tar.gz",
+                owner, repo, rev
+            )),
+            Self::FetchTarball { url, .. } => Some(url.to_owned()),
+            _ => None,
+        }
+    }
+
+    /// # Source Kind
+    ///
+    /// A short, human readable name for where this fetcher
+    /// retrieves its package from
+    pub fn source_kind(&self) -> &'static str {
+        match self {
+            Self::FetchUrl { .. } => "npm",
+            Self::FetchGit { .. } => "git",
+            Self::FetchGitHub { .. } => "github",
+            Self::FetchTarball { .. } => "tarball",
+            Self::CopyToStore { .. } => "path",
+        }
+    }
+
+    /// # Source Url
+    ///
+    /// The remote location this fetcher retrieves its package
+    /// from, or `None` if it is copied from a local path
+    pub fn url(&self) -> Option<String> {
+        match self {
+            Self::FetchUrl { url, .. }
+            | Self::FetchGit { url, .. }
+            | Self::FetchTarball { url, .. } => Some(url.to_owned()),
+            Self::FetchGitHub { owner, repo, .. } => {
+                Some(format!("https://github.com/{}/{}", owner, repo))
+            }
+            Self::CopyToStore { .. } => None,
+        }
+    }
+
+    /// # Set Hash
+    ///
+    /// Fill in the hash of a fetcher after it has been prefetched
+    pub fn set_hash(&mut self, new_hash: String) {
+        match self {
+            Self::FetchUrl { hash, .. }
+            | Self::FetchGit { hash, .. }
+            | Self::FetchGitHub { hash, .. }
+            | Self::FetchTarball { hash, .. } => *hash = new_hash,
+            Self::CopyToStore { .. } => (),
+        }
+    }
+
+    /// # Set Fetch LFS
+    ///
+    /// Mark a git fetcher as fetching git lfs objects, once
+    /// prefetching has found that its repository uses them
+    pub fn set_fetch_lfs(&mut self, lfs: bool) {
+        if let Self::FetchGit { fetch_lfs, .. } = self {
+            *fetch_lfs = lfs;
+        }
+    }
+
+    /// # SRI Hash
+    ///
+    /// The hash of the fetched results, which is empty if it
+    /// has not been prefetched yet, or `None` for local paths
+    pub fn sri_hash(&self) -> Option<&str> {
+        match self {
+            Self::FetchUrl { hash, .. }
+            | Self::FetchGit { hash, .. }
+            | Self::FetchGitHub { hash, .. }
+            | Self::FetchTarball { hash, .. } => Some(hash),
+            Self::CopyToStore { .. } => None,
+        }
+    }
+
     /// # NPM url converter
     ///
     /// Produce a url needed to fetch from the npm api from a package
@@ -109,4 +305,33 @@ impl Fetcher {
             user, name, name, ver
         ))
     }
+
+    /// # NPM url parser
+    ///
+    /// Recover the npm identifier a url produced by
+    /// [`Fetcher::to_npm_url`] was built from, or `None` if
+    /// it is not a tarball url of the npm registry
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::package::Fetcher;
+    ///
+    /// let url = "https://registry.npmjs.org/@alloc/quick-lru/-/quick-lru-5.2.0.tgz";
+    ///
+    /// assert_eq!(
+    ///     Fetcher::parse_npm_url(url).as_deref(),
+    ///     Some("@alloc/quick-lru@5.2.0")
+    /// );
+    /// assert_eq!(Fetcher::parse_npm_url("https://example.com/pkg.tgz"), None);
+    /// ```
+    pub fn parse_npm_url(url: &str) -> Option<String> {
+        let path = url.strip_prefix("https://registry.npmjs.org/")?;
+        let (name, file) = path.split_once("/-/")?;
+
+        let basename = name.rsplit('/').next()?;
+        let ver = file.strip_prefix(basename)?.strip_prefix('-')?;
+        let ver = ver.strip_suffix(".tgz")?;
+
+        Some(format!("{}@{}", name, ver))
+    }
 }
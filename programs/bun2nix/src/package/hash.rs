@@ -0,0 +1,192 @@
+//! This module holds helpers for converting between the different hash encodings used by nix
+
+use std::{
+    fmt::Write,
+    io::{self, Read},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha2::{Digest, Sha256, Sha512};
+
+/// The alphabet used by nix's custom base32 encoding, which omits `e`, `o`, `t` and `u`
+const NIX32_ALPHABET: &[u8; 32] = b"0123456789abcdfghijklmnpqrsvwxyz";
+
+/// # Nix32 to SRI
+///
+/// Convert a hash in nix's base32 encoding (as output by `nix-prefetch-url` and older versions
+/// of `nix-prefetch-git`) into an sri hash for the given algorithm
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::nix32_to_sri;
+///
+/// assert_eq!(
+///     nix32_to_sri("sha256", "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73").unwrap(),
+///     "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+/// );
+/// ```
+pub fn nix32_to_sri(algorithm: &str, nix32: &str) -> Option<String> {
+    let size = digest_size(algorithm)?;
+
+    if nix32.len() != (size * 8 - 1) / 5 + 1 {
+        return None;
+    }
+
+    let mut bytes = vec![0u8; size];
+
+    for (n, c) in nix32.bytes().rev().enumerate() {
+        let digit = NIX32_ALPHABET.iter().position(|&a| a == c)? as u16;
+
+        let bit = n * 5;
+        let (i, j) = (bit / 8, bit % 8);
+
+        bytes[i] |= (digit << j) as u8;
+
+        let carry = (digit << j) >> 8;
+        match bytes.get_mut(i + 1) {
+            Some(byte) => *byte |= carry as u8,
+            None if carry != 0 => return None,
+            None => (),
+        }
+    }
+
+    Some(format!("{}-{}", algorithm, STANDARD.encode(bytes)))
+}
+
+/// # SRI to Hex
+///
+/// Split an sri hash into its algorithm and the digest in lowercase hex, as used by tools such
+/// as `sha256sum`
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::sri_to_hex;
+///
+/// assert_eq!(
+///     sri_to_hex("sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=").unwrap(),
+///     ("sha256", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_owned())
+/// );
+/// assert_eq!(sri_to_hex("not a hash"), None);
+/// ```
+pub fn sri_to_hex(sri: &str) -> Option<(&str, String)> {
+    let (algorithm, digest) = sri.split_once('-')?;
+    let bytes = STANDARD.decode(digest).ok()?;
+
+    let hex = bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    });
+
+    Some((algorithm, hex))
+}
+
+/// # Digest to SRI
+///
+/// Convert a digest for the given algorithm into an sri hash, accepting any of the encodings
+/// nix understands in the `sha1`, `sha256` and `sha512` arguments of its fetchers (hex, nix32
+/// or base64), as well as sri hashes themselves
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::digest_to_sri;
+///
+/// let sri = "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+///
+/// assert_eq!(
+///     digest_to_sri("sha256", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap(),
+///     sri
+/// );
+/// assert_eq!(
+///     digest_to_sri("sha256", "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73").unwrap(),
+///     sri
+/// );
+/// assert_eq!(digest_to_sri("sha256", "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=").unwrap(), sri);
+/// assert_eq!(digest_to_sri("sha256", sri).unwrap(), sri);
+/// assert_eq!(digest_to_sri("sha256", "not a hash"), None);
+/// ```
+pub fn digest_to_sri(algorithm: &str, digest: &str) -> Option<String> {
+    let size = digest_size(algorithm)?;
+
+    let digest = digest
+        .strip_prefix(algorithm)
+        .and_then(|sri| sri.strip_prefix('-'))
+        .unwrap_or(digest);
+
+    if digest.len() == size * 2 {
+        let bytes = (0..digest.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(digest.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        return Some(format!("{algorithm}-{}", STANDARD.encode(bytes)));
+    }
+
+    if let Some(sri) = nix32_to_sri(algorithm, digest) {
+        return Some(sri);
+    }
+
+    match STANDARD.decode(digest) {
+        Ok(bytes) if bytes.len() == size => Some(format!("{algorithm}-{digest}")),
+        _ => None,
+    }
+}
+
+/// # SHA256 SRI
+///
+/// Calculate the flat sha256 sri hash of everything read from a reader, as `pkgs.fetchurl`
+/// calculates it for a downloaded file
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::sha256_sri;
+///
+/// assert_eq!(
+///     sha256_sri(&mut "".as_bytes()).unwrap(),
+///     "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+/// );
+/// ```
+pub fn sha256_sri(reader: &mut impl Read) -> io::Result<String> {
+    digest_sri::<Sha256>("sha256", reader)
+}
+
+/// # Hash SRI
+///
+/// Calculate the flat sri hash of everything read from a reader with the given algorithm, or
+/// `None` if the algorithm is not supported
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::hash_sri;
+///
+/// assert!(hash_sri("sha512", &mut "".as_bytes()).unwrap().unwrap().starts_with("sha512-z4PhNX"));
+/// assert_eq!(hash_sri("md5", &mut "".as_bytes()).unwrap(), None);
+/// ```
+pub fn hash_sri(algorithm: &str, reader: &mut impl Read) -> io::Result<Option<String>> {
+    match algorithm {
+        "sha256" => digest_sri::<Sha256>(algorithm, reader).map(Some),
+        "sha512" => digest_sri::<Sha512>(algorithm, reader).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn digest_sri<D: Digest + io::Write>(
+    algorithm: &str,
+    reader: &mut impl Read,
+) -> io::Result<String> {
+    let mut hasher = D::new();
+    io::copy(reader, &mut hasher)?;
+
+    Ok(format!(
+        "{algorithm}-{}",
+        STANDARD.encode(hasher.finalize())
+    ))
+}
+
+fn digest_size(algorithm: &str) -> Option<usize> {
+    match algorithm {
+        "sha1" => Some(20),
+        "sha256" => Some(32),
+        "sha512" => Some(64),
+        _ => None,
+    }
+}
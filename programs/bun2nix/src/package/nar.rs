@@ -0,0 +1,254 @@
+//! This module holds an in-process implementation of the hash nix gives a path, which is the
+//! sha256 hash of its serialisation as a nix archive (NAR)
+
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Read},
+    mem,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rayon::{ThreadPool, ThreadPoolBuilder, prelude::*};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+/// The most bytes of file contents read ahead at once. Files larger than this are streamed
+/// into the hash rather than read in parallel.
+const READ_AHEAD_BYTES: u64 = 64 << 20;
+
+/// The thread pool files are read on, with a thread for each core, shared by every path
+/// hashed by this process
+static HASH_POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+/// A part of the archive, in the order it is hashed
+enum Part {
+    /// Serialised tokens, which need nothing to be read
+    Tokens(Vec<u8>),
+
+    /// The contents of a regular file, along with their padding
+    File { path: PathBuf, size: u64 },
+}
+
+/// # NAR SRI Hash
+///
+/// Calculate the sha256 sri hash nix would give a path if it were added to the store, which
+/// is the hash `nix-hash --type sha256` prints, without running nix. The tree is serialised as
+/// a nix archive in order, while the files in it are read ahead in parallel on a thread for
+/// each core, so that large directories hash as fast as the disk can read them.
+///
+/// ## Usage
+///```rust
+/// use std::{fs, os::unix::fs::{PermissionsExt, symlink}};
+///
+/// use bun2nix::package::nar_sri;
+///
+/// let dir = std::env::temp_dir().join("bun2nix-nar-doctest");
+/// let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("bin")).unwrap();
+///
+/// fs::write(dir.join("index.js"), "module.exports = 1;\n").unwrap();
+/// fs::set_permissions(dir.join("index.js"), fs::Permissions::from_mode(0o644)).unwrap();
+///
+/// fs::write(dir.join("bin/cli.js"), "#!/usr/bin/env node\nrequire(\"../index.js\");\n").unwrap();
+/// fs::set_permissions(dir.join("bin/cli.js"), fs::Permissions::from_mode(0o755)).unwrap();
+///
+/// symlink("index.js", dir.join("main.js")).unwrap();
+///
+/// // `nix-hash --type sha256 --base32` prints 1m4i5flagkqx1rgxbh1knf9j5d5zsggpiqq989drilp2ii0zxnjq
+/// assert_eq!(
+///     nar_sri(&dir).unwrap(),
+///     "sha256-WNr+QYzi0phbQgnjeN/Tv7Qik7MzwNVfDh3Pp6grkdQ="
+/// );
+///
+/// fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn nar_sri(path: &Path) -> Result<String> {
+    let mut parts = Vec::new();
+    let mut tokens = Vec::new();
+
+    token(&mut tokens, b"nix-archive-1");
+    serialise(path, &mut tokens, &mut parts)?;
+    parts.push(Part::Tokens(tokens));
+
+    let mut hasher = Sha256::new();
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+
+    for part in parts {
+        match part {
+            Part::File { path, size } if size > READ_AHEAD_BYTES => {
+                hash_batch(&mut hasher, mem::take(&mut batch))?;
+                batch_bytes = 0;
+
+                let copied = io::copy(&mut File::open(&path)?.take(size), &mut hasher)?;
+                check_size(&path, size, copied)?;
+                hasher.update(padding(size));
+            }
+            part => {
+                if let Part::File { size, .. } = part {
+                    batch_bytes += size;
+                }
+
+                batch.push(part);
+
+                if batch_bytes >= READ_AHEAD_BYTES {
+                    hash_batch(&mut hasher, mem::take(&mut batch))?;
+                    batch_bytes = 0;
+                }
+            }
+        }
+    }
+
+    hash_batch(&mut hasher, batch)?;
+
+    Ok(format!("sha256-{}", STANDARD.encode(hasher.finalize())))
+}
+
+/// Read the files of a batch in parallel, then hash every part of it in order
+fn hash_batch(hasher: &mut Sha256, batch: Vec<Part>) -> Result<()> {
+    let contents: Vec<io::Result<Vec<u8>>> = hash_pool()?.install(|| {
+        batch
+            .par_iter()
+            .map(|part| match part {
+                Part::Tokens(_) => Ok(Vec::new()),
+                Part::File { path, .. } => fs::read(path),
+            })
+            .collect()
+    });
+
+    for (part, contents) in batch.into_iter().zip(contents) {
+        match part {
+            Part::Tokens(tokens) => hasher.update(tokens),
+            Part::File { path, size } => {
+                let contents = contents?;
+                check_size(&path, size, contents.len() as u64)?;
+
+                hasher.update(contents);
+                hasher.update(padding(size));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Serialise the node at a path, leaving the contents of regular files to be read later
+fn serialise(path: &Path, tokens: &mut Vec<u8>, parts: &mut Vec<Part>) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+
+    token(tokens, b"(");
+    token(tokens, b"type");
+
+    if metadata.is_symlink() {
+        token(tokens, b"symlink");
+        token(tokens, b"target");
+        token(tokens, &os_bytes(fs::read_link(path)?.as_os_str()));
+    } else if metadata.is_file() {
+        token(tokens, b"regular");
+
+        if is_executable(&metadata) {
+            token(tokens, b"executable");
+            token(tokens, b"");
+        }
+
+        token(tokens, b"contents");
+        tokens.extend_from_slice(&metadata.len().to_le_bytes());
+
+        parts.push(Part::Tokens(mem::take(tokens)));
+        parts.push(Part::File {
+            path: path.to_owned(),
+            size: metadata.len(),
+        });
+    } else if metadata.is_dir() {
+        token(tokens, b"directory");
+
+        let mut names = fs::read_dir(path)?
+            .map(|entry| Ok(entry?.file_name()))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // Entries are sorted by the bytes of their names
+        names.sort_by_cached_key(|name| os_bytes(name));
+
+        for name in names {
+            token(tokens, b"entry");
+            token(tokens, b"(");
+            token(tokens, b"name");
+            token(tokens, &os_bytes(&name));
+            token(tokens, b"node");
+            serialise(&path.join(name), tokens, parts)?;
+            token(tokens, b")");
+        }
+    } else {
+        return Err(Error::FetchingError(format!(
+            "`{}` is not a regular file, directory or symlink, so it cannot be hashed",
+            path.display()
+        )));
+    }
+
+    token(tokens, b")");
+
+    Ok(())
+}
+
+/// Write a token of the archive, as its length followed by its bytes padded to eight bytes
+fn token(tokens: &mut Vec<u8>, bytes: &[u8]) {
+    tokens.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    tokens.extend_from_slice(bytes);
+    tokens.extend_from_slice(padding(bytes.len() as u64));
+}
+
+fn padding(len: u64) -> &'static [u8] {
+    &[0; 8][..((8 - len % 8) % 8) as usize]
+}
+
+/// A file which changed size while being hashed would give an archive nix could never
+/// produce
+fn check_size(path: &Path, expected: u64, read: u64) -> Result<()> {
+    match read == expected {
+        true => Ok(()),
+        false => Err(Error::FetchingError(format!(
+            "`{}` changed while it was being hashed",
+            path.display()
+        ))),
+    }
+}
+
+fn hash_pool() -> Result<&'static ThreadPool> {
+    if let Some(pool) = HASH_POOL.get() {
+        return Ok(pool);
+    }
+
+    let pool = ThreadPoolBuilder::new()
+        .thread_name(|i| format!("bun2nix-hash-{i}"))
+        .build()
+        .map_err(Error::ThreadPool)?;
+
+    Ok(HASH_POOL.get_or_init(|| pool))
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn os_bytes(name: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    name.as_bytes().to_owned()
+}
+
+#[cfg(not(unix))]
+fn os_bytes(name: &std::ffi::OsStr) -> Vec<u8> {
+    name.to_string_lossy().into_owned().into_bytes()
+}
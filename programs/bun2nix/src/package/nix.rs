@@ -0,0 +1,100 @@
+//! This module holds the configuration of the nix installation used to prefetch packages
+
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// The name of the nix binary to look up on the `PATH` if no other is given
+pub const DEFAULT_NIX_BIN: &str = "nix";
+
+/// # Nix Installation
+///
+/// The nix binary used to prefetch packages, and whether it has been found to lack
+/// `nix flake prefetch` (as is the case for older versions of nix and some variants), in which
+/// case the `nix-prefetch-url` installed alongside it is used instead.
+///
+/// ## Usage
+///```rust
+/// use std::path::Path;
+///
+/// use bun2nix::package::Nix;
+///
+/// let nix = Nix::new("/opt/nix/bin/nix");
+///
+/// assert_eq!(nix.bin(), Path::new("/opt/nix/bin/nix"));
+/// assert_eq!(nix.prefetch_url_bin(), Path::new("/opt/nix/bin/nix-prefetch-url"));
+/// assert_eq!(Nix::default().prefetch_url_bin(), Path::new("nix-prefetch-url"));
+/// assert_eq!(nix.hash_bin(), Path::new("/opt/nix/bin/nix-hash"));
+/// assert_eq!(nix.instantiate_bin(), Path::new("/opt/nix/bin/nix-instantiate"));
+/// ```
+#[derive(Debug)]
+pub struct Nix {
+    bin: PathBuf,
+    legacy: AtomicBool,
+}
+
+impl Default for Nix {
+    fn default() -> Self {
+        Self::new(DEFAULT_NIX_BIN)
+    }
+}
+
+impl Nix {
+    /// # New Nix Installation
+    ///
+    /// Prefetch with the given nix binary, which may be a path or a name to look up on the
+    /// `PATH`
+    pub fn new(bin: impl Into<PathBuf>) -> Self {
+        Self {
+            bin: bin.into(),
+            legacy: AtomicBool::new(false),
+        }
+    }
+
+    /// # Nix Binary
+    ///
+    /// The nix binary to run
+    pub fn bin(&self) -> &Path {
+        &self.bin
+    }
+
+    /// # `nix-prefetch-url` Binary
+    ///
+    /// The `nix-prefetch-url` installed alongside the nix binary, or the one on the `PATH` if
+    /// the nix binary was not given as a path
+    pub fn prefetch_url_bin(&self) -> PathBuf {
+        self.bin.with_file_name("nix-prefetch-url")
+    }
+
+    /// # `nix-hash` Binary
+    ///
+    /// The `nix-hash` installed alongside the nix binary, or the one on the `PATH` if the nix
+    /// binary was not given as a path
+    pub fn hash_bin(&self) -> PathBuf {
+        self.bin.with_file_name("nix-hash")
+    }
+
+    /// # `nix-instantiate` Binary
+    ///
+    /// The `nix-instantiate` installed alongside the nix binary, or the one on the `PATH` if
+    /// the nix binary was not given as a path
+    pub fn instantiate_bin(&self) -> PathBuf {
+        self.bin.with_file_name("nix-instantiate")
+    }
+
+    /// # Is Legacy
+    ///
+    /// Whether `nix flake prefetch` has been found not to work with this installation
+    pub fn is_legacy(&self) -> bool {
+        self.legacy.load(Ordering::Relaxed)
+    }
+
+    /// # Use Legacy
+    ///
+    /// Prefetch with `nix-prefetch-url` from now on, returning whether it was not already
+    /// being used
+    pub fn use_legacy(&self) -> bool {
+        !self.legacy.swap(true, Ordering::Relaxed)
+    }
+}
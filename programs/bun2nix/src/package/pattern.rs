@@ -0,0 +1,75 @@
+//! This module holds patterns picking out packages by their name or where they are fetched from
+
+use std::{convert::Infallible, str::FromStr};
+
+use crate::package::Fetcher;
+
+/// # Package Pattern
+///
+/// A glob matching packages by their npm `name@version` or name, or by the url they are fetched
+/// from, where `*` matches any run of characters and `?` matches any single character
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package::{Fetcher, PackagePattern};
+///
+/// let lodash = Fetcher::new_npm_package("lodash@4.17.21", String::new()).unwrap();
+/// let zod = Fetcher::FetchGitHub {
+///     owner: "colinhacks".to_owned(),
+///     repo: "zod".to_owned(),
+///     rev: "5bfc8f2".to_owned(),
+///     hash: String::new(),
+/// };
+///
+/// let pattern = |pattern: &str| pattern.parse::<PackagePattern>().unwrap();
+///
+/// assert!(pattern("lodash").matches(&lodash));
+/// assert!(pattern("lodash@4.*").matches(&lodash));
+/// assert!(!pattern("lodash@3.*").matches(&lodash));
+/// assert!(pattern("https://github.com/colinhacks/*").matches(&zod));
+/// assert!(pattern("*/zod").matches(&zod));
+/// assert!(!pattern("zod").matches(&lodash));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackagePattern(String);
+
+impl PackagePattern {
+    /// # Matches
+    ///
+    /// Whether the package fetched by a fetcher matches the pattern
+    pub fn matches(&self, fetcher: &Fetcher) -> bool {
+        let url = fetcher.url();
+        let ident = url.as_deref().and_then(Fetcher::parse_npm_url);
+        let name = ident
+            .as_deref()
+            .and_then(|ident| Some(&ident[..ident.get(1..)?.find('@')? + 1]));
+
+        [url.as_deref(), ident.as_deref(), name]
+            .iter()
+            .any(|candidate| {
+                candidate
+                    .is_some_and(|candidate| glob_matches(self.0.as_bytes(), candidate.as_bytes()))
+            })
+    }
+}
+
+impl FromStr for PackagePattern {
+    type Err = Infallible;
+
+    fn from_str(pattern: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Self(pattern.to_owned()))
+    }
+}
+
+/// Whether some text matches a glob of `*` and `?` wildcards
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.split_first(), text.split_first()) {
+        (None, _) => text.is_empty(),
+        (Some((b'*', rest)), _) => {
+            glob_matches(rest, text) || (!text.is_empty() && glob_matches(pattern, &text[1..]))
+        }
+        (Some((b'?', rest)), Some((_, text))) => glob_matches(rest, text),
+        (Some((c, rest)), Some((t, text))) if c == t => glob_matches(rest, text),
+        _ => false,
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{
+    error::{Error, Result},
+    git_ssh, interrupt,
+    package::{Fetcher, Nix, nar_sri, nix32_to_sri},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs, io,
+    path::Path,
+    process::{self, Command},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The number of repositories cloned over SSH so far, to give each its own directory
+static SSH_CLONES: AtomicUsize = AtomicUsize::new(0);
+
+/// # Package Prefetch
+///
+/// Represents the result of a `nix flake prefetch`
+/// for a given package we don't know the hash for
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Prefetch {
+    /// The sri hash calculated by nix
+    pub hash: String,
+    /// The store path the package was fetched to
+    #[serde(default, rename = "storePath")]
+    pub store_path: Option<String>,
+    /// The number of bytes fetched, if known
+    #[serde(skip)]
+    pub size: Option<u64>,
+    /// Whether git lfs objects were fetched, which the emitted fetcher must fetch too
+    #[serde(skip)]
+    pub fetch_lfs: bool,
+}
+
+/// # Git Prefetch
+///
+/// The structured output of `nix-prefetch-git --json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitPrefetch {
+    /// The url of the repository which was fetched
+    pub url: String,
+    /// The commit which was fetched
+    pub rev: String,
+    /// The commit date
+    #[serde(default)]
+    pub date: Option<String>,
+    /// The store path of the checkout
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The hash of the checkout in nix's base32 encoding
+    pub sha256: String,
+    /// The sri hash of the checkout (only output by newer versions)
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// Whether submodules were fetched
+    #[serde(default)]
+    pub fetch_submodules: bool,
+    /// Whether git lfs objects were fetched
+    #[serde(default, rename = "fetchLFS")]
+    pub fetch_lfs: bool,
+    /// Whether the full history was fetched
+    #[serde(default)]
+    pub deep_clone: bool,
+    /// Whether the `.git` directory was kept
+    #[serde(default)]
+    pub leave_dot_git: bool,
+}
+
+impl Prefetch {
+    /// # Prefetch Fetcher
+    ///
+    /// Calculate the hash for a fetcher using the prefetch
+    /// tool matching the fetcher nix will use
+    ///
+    /// If `nix flake prefetch` is not available from the
+    /// given nix installation, `nix-prefetch-url` is used
+    /// instead for the rest of the run
+    pub fn prefetch_fetcher(fetcher: &Fetcher, nix: &Nix) -> Result<Self> {
+        if let Fetcher::FetchGit { url, rev, .. } = fetcher {
+            if git_ssh::is_ssh_url(url) {
+                return GitPrefetch::prefetch_over_ssh(url, rev);
+            }
+
+            let git = GitPrefetch::prefetch(url, rev)?;
+
+            return Ok(Self {
+                hash: git.sri_hash()?,
+                size: git.path.as_deref().and_then(store_path_size),
+                store_path: git.path,
+                fetch_lfs: git.fetch_lfs,
+            });
+        }
+
+        let url = fetcher.prefetch_url().ok_or(Error::NothingToPrefetch)?;
+
+        if !nix.is_legacy() {
+            match Self::prefetch_package(&url, nix) {
+                Err(err) if is_unsupported(&err) => {
+                    if nix.use_legacy() {
+                        warn!(
+                            "`{} flake prefetch` is not available, falling back to `{}`",
+                            nix.bin().display(),
+                            nix.prefetch_url_bin().display()
+                        );
+                    }
+                }
+                result => return result,
+            }
+        }
+
+        let url = fetcher.archive_url().ok_or(Error::NothingToPrefetch)?;
+
+        Self::prefetch_legacy(&url, nix)
+    }
+
+    /// # Prefetch Package
+    ///
+    /// Prefetch a package as a url and calculate it's
+    /// sha256
+    pub fn prefetch_package(url: &str, nix: &Nix) -> Result<Self> {
+        let stdout = run_prefetch_command(
+            url,
+            Command::new(nix.bin()).args([
+                "--extra-experimental-features",
+                "nix-command flakes",
+                "flake",
+                "prefetch",
+                url,
+                "--json",
+            ]),
+        )?;
+
+        let mut prefetch: Self = serde_json::from_str(&stdout)?;
+        prefetch.size = prefetch.store_path.as_deref().and_then(store_path_size);
+
+        Ok(prefetch)
+    }
+
+    /// # Prefetch Package With `nix-prefetch-url`
+    ///
+    /// Prefetch and unpack an archive with `nix-prefetch-url`,
+    /// converting the base32 hash it prints to sri format
+    pub fn prefetch_legacy(url: &str, nix: &Nix) -> Result<Self> {
+        Self::prefetch_url(url, nix, &["--unpack"])
+    }
+
+    /// # Prefetch File
+    ///
+    /// Download a file with `nix-prefetch-url` without unpacking it, for the flat hash
+    /// `fetchurl` checks it against
+    pub fn prefetch_file(url: &str, nix: &Nix) -> Result<Self> {
+        Self::prefetch_url(url, nix, &[])
+    }
+
+    /// Run `nix-prefetch-url` with the given extra arguments, converting the base32 hash it
+    /// prints to sri format
+    fn prefetch_url(url: &str, nix: &Nix, args: &[&str]) -> Result<Self> {
+        let stdout = run_prefetch_command(
+            url,
+            Command::new(nix.prefetch_url_bin()).args(args).args([
+                "--print-path",
+                "--type",
+                "sha256",
+                url,
+            ]),
+        )?;
+
+        let mut lines = stdout.lines().map(str::trim);
+        let nix32 = lines.next().unwrap_or_default();
+
+        let hash = nix32_to_sri("sha256", nix32)
+            .ok_or_else(|| Error::FetchingError(format!("Invalid hash `{nix32}`")))?;
+        let store_path = lines.next().map(str::to_owned);
+
+        Ok(Self {
+            hash,
+            size: store_path.as_deref().and_then(store_path_size),
+            store_path,
+            fetch_lfs: false,
+        })
+    }
+
+    /// # Hash Local Path
+    ///
+    /// Calculate the hash nix would give a directory if it were fetched and unpacked to it,
+    /// in process and reading its files in parallel, so that a package which is already on
+    /// disk does not need to be fetched
+    pub fn hash_path(path: &Path) -> Result<Self> {
+        #[cfg(target_arch = "wasm32")]
+        return Err(Error::UnsupportedWASMCliAction(path.display().to_string()));
+
+        Ok(Self {
+            hash: nar_sri(path)?,
+            store_path: None,
+            size: None,
+            fetch_lfs: false,
+        })
+    }
+}
+
+impl GitPrefetch {
+    /// # Prefetch Git Repository
+    ///
+    /// Prefetch a git repository at a given revision with
+    /// `nix-prefetch-git`, which calculates the same hash as
+    /// `pkgs.fetchgit` (including submodules)
+    ///
+    /// A repository storing files with git lfs is fetched again
+    /// along with its lfs objects, as the first checkout only
+    /// holds pointers to them
+    pub fn prefetch(url: &str, rev: &str) -> Result<Self> {
+        let git = Self::run(url, rev, false)?;
+
+        match git
+            .path
+            .as_deref()
+            .is_some_and(|path| uses_lfs(Path::new(path)))
+        {
+            true => Self::run(url, rev, true),
+            false => Ok(git),
+        }
+    }
+
+    fn run(url: &str, rev: &str, fetch_lfs: bool) -> Result<Self> {
+        let mut command = Command::new("nix-prefetch-git");
+        command.args(["--url", url, "--rev", rev, "--fetch-submodules", "--quiet"]);
+
+        if fetch_lfs {
+            command.arg("--fetch-lfs");
+        }
+
+        let mut git: Self = serde_json::from_str(&run_prefetch_command(url, &mut command)?)?;
+
+        // Older versions of `nix-prefetch-git` do not say whether lfs objects were fetched
+        git.fetch_lfs |= fetch_lfs;
+
+        Ok(git)
+    }
+
+    /// # Prefetch Git Repository Over SSH
+    ///
+    /// Clone a repository fetched over SSH with the local `git`, so that the user's SSH agent
+    /// and keys are used, and hash the checkout the same way as `pkgs.fetchgit` would (with
+    /// submodules and without any `.git` directories)
+    pub fn prefetch_over_ssh(url: &str, rev: &str) -> Result<Prefetch> {
+        let n = SSH_CLONES.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("bun2nix-git-{}-{n}", process::id()));
+
+        let result = clone_over_ssh(url, rev, &dir).and_then(|fetch_lfs| {
+            Ok(Prefetch {
+                fetch_lfs,
+                ..Prefetch::hash_path(&dir)?
+            })
+        });
+
+        let _ = fs::remove_dir_all(&dir);
+
+        result
+    }
+
+    /// # SRI Hash
+    ///
+    /// The hash of the checkout in sri format, converting it
+    /// from nix's base32 format if an older version of
+    /// `nix-prefetch-git` did not output it directly
+    pub fn sri_hash(&self) -> Result<String> {
+        if let Some(hash) = &self.hash {
+            return Ok(hash.to_owned());
+        }
+
+        nix32_to_sri("sha256", &self.sha256)
+            .ok_or_else(|| Error::FetchingError(format!("Invalid hash `{}`", self.sha256)))
+    }
+}
+
+/// Clone a repository into a directory and check out a revision along with its submodules and
+/// any git lfs objects, leaving out every `.git` directory. Returns whether lfs objects were
+/// fetched.
+fn clone_over_ssh(url: &str, rev: &str, dir: &Path) -> Result<bool> {
+    // Fail rather than wait for credentials which cannot be typed in
+    let git = |args: &[&str]| {
+        let mut command = Command::new("git");
+        command.env("GIT_TERMINAL_PROMPT", "0").args(args);
+        command
+    };
+
+    let dir_arg = dir.to_string_lossy();
+
+    run_prefetch_command(
+        url,
+        &mut git(&["clone", "--quiet", "--no-checkout", url, &dir_arg]),
+    )?;
+
+    let run = |args: &[&str]| {
+        let output = interrupt::run(&mut git(&[&["-C", &dir_arg], args].concat()))?;
+
+        match output.status.success() {
+            true => Ok(()),
+            false => Err(Error::FetchingError(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            )),
+        }
+    };
+
+    run(&["checkout", "--quiet", rev])?;
+    run(&["submodule", "update", "--init", "--recursive", "--quiet"])?;
+
+    // The checkout only holds pointers to files stored with git lfs
+    let fetch_lfs = uses_lfs(dir);
+
+    if fetch_lfs {
+        run(&["lfs", "pull"])?;
+    }
+
+    remove_dot_git(dir)?;
+
+    Ok(fetch_lfs)
+}
+/// # Uses Git LFS
+///
+/// Whether a checkout stores any of its files with git lfs, as declared by a `filter=lfs`
+/// attribute in its `.gitattributes`
+///
+/// ## Usage
+///```rust
+/// use std::fs;
+///
+/// use bun2nix::package::uses_lfs;
+///
+/// let dir = std::env::temp_dir().join("bun2nix-lfs-doctest");
+/// fs::create_dir_all(&dir).unwrap();
+///
+/// fs::write(dir.join(".gitattributes"), "*.txt text\n").unwrap();
+/// assert!(!uses_lfs(&dir));
+///
+/// fs::write(dir.join(".gitattributes"), "*.wasm filter=lfs diff=lfs merge=lfs -text\n").unwrap();
+/// assert!(uses_lfs(&dir));
+///
+/// fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn uses_lfs(checkout: &Path) -> bool {
+    fs::read_to_string(checkout.join(".gitattributes")).is_ok_and(|attributes| {
+        attributes
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .any(|line| line.split_whitespace().any(|attr| attr == "filter=lfs"))
+    })
+}
+
+/// Remove every `.git` directory or file under a checkout, including those of submodules
+fn remove_dot_git(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        match (entry.file_name() == ".git", file_type.is_dir()) {
+            (true, true) => fs::remove_dir_all(path)?,
+            (true, false) => fs::remove_file(path)?,
+            (false, true) => remove_dot_git(&path)?,
+            (false, false) => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// The total size of the files under a store path, or `None` if it cannot be read
+fn store_path_size(path: &str) -> Option<u64> {
+    fn size(path: &Path) -> io::Result<u64> {
+        let metadata = fs::symlink_metadata(path)?;
+
+        if !metadata.is_dir() {
+            return Ok(metadata.len());
+        }
+
+        fs::read_dir(path)?.try_fold(0, |total, entry| Ok(total + size(&entry?.path())?))
+    }
+
+    size(Path::new(path)).ok()
+}
+
+/// Whether a prefetch failed because the nix installation does not support
+/// `nix flake prefetch` (or is missing entirely), rather than the fetch itself failing
+fn is_unsupported(err: &Error) -> bool {
+    const MESSAGES: [&str; 3] = [
+        "is not a recognised command",
+        "unrecognised flag",
+        "unknown command",
+    ];
+
+    match err {
+        Error::FetchingFailed(err) => err.kind() == io::ErrorKind::NotFound,
+        Error::FetchingError(stderr) => MESSAGES.iter().any(|message| stderr.contains(message)),
+        _ => false,
+    }
+}
+
+fn run_prefetch_command(url: &str, command: &mut Command) -> Result<String> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(Error::UnsupportedWASMCliAction(url.to_owned()));
+
+    warn!(
+        "
+Hash was not already known for `{url}`.
+
+This must be prefetched and hashed by `bun2nix` via
+nix. While the result is cached after the first prefetch,
+if you care about install speed, try looking for an alternative
+install for this package from npm.
+
+See:
+- https://nix.dev/manual/nix/2.28/command-ref/new-cli/nix3-flake-prefetch.html
+- https://github.com/oven-sh/bun/issues/19519
+
+Disable these warnings with `RUST_LOG=error` or `RUST_LOG=off`
+        "
+    );
+
+    let cmd_res = interrupt::run(command)?;
+
+    let stdout = str::from_utf8(&cmd_res.stdout).map_err(Error::InvalidUtf8String)?;
+
+    if !cmd_res.status.success() {
+        let stderr = str::from_utf8(&cmd_res.stderr).map_err(Error::InvalidUtf8String)?;
+        return Err(Error::FetchingError(stderr.to_string()));
+    }
+
+    Ok(stdout.to_owned())
+}
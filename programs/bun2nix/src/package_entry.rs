@@ -0,0 +1,138 @@
+//! This module holds the entry a single package from the registry would have in a generated
+//! `bun.nix`, for composing hand-written expressions and checking how its url is built
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    error::{Error, Result},
+    nix_expression::ast::{AttrSet, Binding, Expr},
+    package::Fetcher,
+    registry::VersionMetadata,
+};
+
+/// # Package Entry
+///
+/// The fetcher of a version of a package as `bun2nix` would write it, along with the tarball
+/// the registry serves it from and the tools in its `bin`
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{package_entry::PackageEntry, registry::VersionMetadata};
+///
+/// let metadata: VersionMetadata = serde_json::from_str(r#"
+/// {
+///   "version": "1.4.0",
+///   "bin": { "loose-envify": "cli.js" },
+///   "dist": {
+///     "tarball": "https://registry.npmjs.org/loose-envify/-/loose-envify-1.4.0.tgz",
+///     "integrity": "sha512-lyuxPGr/Wfhrlem2CL/UcnUc1zcqKAImBDzukY7Y5F/yQiNdko6+fRLevlw1HgMySw7f611UIY408EtxRSoK3Q=="
+///   }
+/// }
+/// "#)
+/// .unwrap();
+///
+/// let entry = PackageEntry::new("loose-envify", &metadata).unwrap();
+///
+/// assert_eq!(entry.name, "loose-envify@1.4.0");
+/// assert_eq!(entry.url, entry.tarball);
+/// assert_eq!(
+///     entry.to_nix(),
+///     [
+///         "{",
+///         "  # Provides `loose-envify` from `cli.js`",
+///         r#"  "loose-envify@1.4.0" = fetchurl {"#,
+///         r#"    url = "https://registry.npmjs.org/loose-envify/-/loose-envify-1.4.0.tgz";"#,
+///         r#"    hash = "sha512-lyuxPGr/Wfhrlem2CL/UcnUc1zcqKAImBDzukY7Y5F/yQiNdko6+fRLevlw1HgMySw7f611UIY408EtxRSoK3Q==";"#,
+///         "  };",
+///         "}",
+///     ]
+///     .join("\n")
+/// );
+///
+/// let json = entry.to_json().unwrap();
+/// assert!(json.contains(r#""bin": {"#));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "schema",
+    schemars(
+        description = "The entry a single package from the registry would have in a generated \
+                       `bun.nix`, as printed by `bun2nix pkg --format json`"
+    )
+)]
+pub struct PackageEntry {
+    /// The name of the entry, as `name@version`
+    pub name: String,
+
+    /// The url `bun2nix` builds for the tarball
+    pub url: String,
+
+    /// The hash of the tarball, from its integrity in the registry
+    pub hash: String,
+
+    /// The url the registry serves the tarball from
+    pub tarball: String,
+
+    /// The paths of the tools the package provides, by their name
+    pub bin: BTreeMap<String, String>,
+}
+
+impl PackageEntry {
+    /// # New Package Entry
+    ///
+    /// The entry for a version of a package, described by its registry metadata
+    pub fn new(name: &str, metadata: &VersionMetadata) -> Result<Self> {
+        let ident = format!("{name}@{}", metadata.version);
+
+        Ok(Self {
+            url: Fetcher::to_npm_url(&ident)?,
+            hash: metadata
+                .dist
+                .integrity
+                .clone()
+                .ok_or_else(|| Error::MissingIntegrity(ident.clone()))?,
+            tarball: metadata.dist.tarball.clone(),
+            bin: metadata
+                .bin
+                .as_ref()
+                .map(|bin| bin.binaries(name))
+                .unwrap_or_default(),
+            name: ident,
+        })
+    }
+
+    /// # To Nix
+    ///
+    /// The entry as a nix attribute set of just the package, which can be merged into a
+    /// package set with `//`
+    pub fn to_nix(&self) -> String {
+        let fetcher = Fetcher::FetchUrl {
+            url: self.url.clone(),
+            hash: self.hash.clone(),
+        };
+
+        let mut comments: Vec<String> = self
+            .bin
+            .iter()
+            .map(|(name, path)| format!("Provides `{name}` from `{path}`"))
+            .collect();
+
+        if self.tarball != self.url {
+            comments.push(format!("The registry serves it from `{}`", self.tarball));
+        }
+
+        let binding = Binding::quoted(&self.name, Expr::from(&fetcher)).with_comments(comments);
+
+        Expr::Attrs(AttrSet::new(vec![binding])).to_string()
+    }
+
+    /// # To JSON
+    ///
+    /// The entry as a JSON object
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
@@ -0,0 +1,394 @@
+//! This module holds a model of the fields of a `package.json` file which are of interest to
+//! `bun2nix`
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, de::IgnoredAny};
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+
+/// Map of dependency names to the version specifier they were requested with
+pub type Dependencies = HashMap<String, String>;
+
+#[derive(Default, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+/// # Package Json
+///
+/// A model of the fields that exist in a `package.json` file in order to serve as a
+/// deserialization target.
+///
+/// ## Usage
+///
+/// ```rust
+/// use bun2nix::PackageJson;
+///
+/// let package_json = r#"
+/// {
+///   "name": "example",
+///   "version": "1.0.0",
+///   "dependencies": {
+///     "is-odd": "^3.0.1"
+///   }
+/// }
+/// "#;
+///
+/// let value: PackageJson = package_json.parse().unwrap();
+///
+/// assert_eq!(value.name.as_deref(), Some("example"));
+/// assert_eq!(value.dependencies["is-odd"], "^3.0.1");
+///
+/// let workspace: PackageJson = r#"{ "workspaces": { "packages": ["packages/*"] } }"#
+///     .parse()
+///     .unwrap();
+///
+/// assert_eq!(workspace.workspaces, ["packages/*"]);
+///
+/// let library: PackageJson = r#"
+/// {
+///   "peerDependencies": { "react": "^19.0.0", "sass": "^1.3.0" },
+///   "peerDependenciesMeta": { "sass": { "optional": true } }
+/// }
+/// "#
+/// .parse()
+/// .unwrap();
+///
+/// let installed: Vec<_> = library.all_dependencies().map(|(name, _)| name).collect();
+///
+/// assert_eq!(installed, ["react"]);
+/// assert_eq!(library.optional_peers(), ["sass"]);
+/// ```
+pub struct PackageJson {
+    /// The name of the package
+    pub name: Option<String>,
+
+    /// The version of the package
+    pub version: Option<String>,
+
+    /// A short description of the package
+    pub description: Option<String>,
+
+    /// The url of the package's homepage
+    pub homepage: Option<String>,
+
+    /// The SPDX identifier or expression of the package's license, also accepting the legacy
+    /// `{ "type": "MIT" }` form
+    #[serde(deserialize_with = "PackageJson::deserialize_license")]
+    pub license: Option<String>,
+
+    /// The entrypoint of the package as an ES module
+    pub module: Option<String>,
+
+    /// The command line tools the package provides, as a single path or by their name
+    pub bin: Option<Bin>,
+
+    /// Scripts which can be run with `bun run`
+    pub scripts: HashMap<String, String>,
+
+    /// Dependencies of the package
+    pub dependencies: Dependencies,
+
+    /// Dev dependencies of the package
+    pub dev_dependencies: Dependencies,
+
+    /// Optional dependencies of the package
+    pub optional_dependencies: Dependencies,
+
+    /// Peer dependencies of the package
+    pub peer_dependencies: Dependencies,
+
+    /// Extra information about peer dependencies, such as whether they are optional
+    pub peer_dependencies_meta: HashMap<String, PeerDependencyMeta>,
+
+    /// The package manager the project is managed with, such as `bun@1.2.19`
+    pub package_manager: Option<String>,
+
+    /// The packages allowed to run lifecycle scripts, replacing bun's default allowlist
+    pub trusted_dependencies: Option<Vec<String>>,
+
+    /// Globs matching the directories of the members of the workspace this package is the root
+    /// of, given either as a list or as the `packages` field of an object
+    #[serde(deserialize_with = "PackageJson::deserialize_workspaces")]
+    pub workspaces: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+/// # Bin
+///
+/// The `bin` field of a `package.json`
+pub enum Bin {
+    /// The path of a single tool, named after the package
+    Single(String),
+
+    /// The paths of each tool, by its name
+    Named(BTreeMap<String, String>),
+}
+
+impl Bin {
+    /// # Binaries
+    ///
+    /// The paths of the tools, by their name, where a single tool is named after the package it
+    /// belongs to without its scope
+    pub fn binaries(&self, package_name: &str) -> BTreeMap<String, String> {
+        match self {
+            Self::Single(path) => {
+                let name = package_name.rsplit('/').next().unwrap_or(package_name);
+
+                BTreeMap::from([(name.to_owned(), path.clone())])
+            }
+            Self::Named(binaries) => binaries.clone(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+/// # Peer Dependency Meta
+///
+/// Extra information about a peer dependency
+pub struct PeerDependencyMeta {
+    /// Whether the peer dependency may be left uninstalled
+    pub optional: bool,
+}
+
+impl PackageJson {
+    /// # All Dependencies
+    ///
+    /// Iterate over every dependency a project needs installed, regardless of the kind of
+    /// dependency it was declared as. As in bun, peer dependencies are installed too, unless
+    /// they are optional.
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.dependencies
+            .iter()
+            .chain(&self.dev_dependencies)
+            .chain(&self.optional_dependencies)
+            .chain(
+                self.peer_dependencies
+                    .iter()
+                    .filter(|(name, _)| !self.is_optional_peer(name)),
+            )
+    }
+
+    /// # Is Optional Peer
+    ///
+    /// Whether a peer dependency is marked as optional in `peerDependenciesMeta`, so is left
+    /// uninstalled unless something else depends on it
+    pub fn is_optional_peer(&self, name: &str) -> bool {
+        self.peer_dependencies_meta
+            .get(name)
+            .is_some_and(|meta| meta.optional)
+    }
+
+    /// # Optional Peers
+    ///
+    /// The names of the optional peer dependencies, sorted as in the `optionalPeers` field of a
+    /// bun lockfile workspace
+    pub fn optional_peers(&self) -> Vec<String> {
+        let mut peers: Vec<String> = self
+            .peer_dependencies
+            .keys()
+            .filter(|name| self.is_optional_peer(name))
+            .cloned()
+            .collect();
+
+        peers.sort();
+        peers
+    }
+
+    /// # Start Command
+    ///
+    /// The arguments to `bun` which start the project - running its `start` script, or its
+    /// `module` if it has no `start` script
+    pub fn start_command(&self) -> Result<Vec<String>> {
+        match (self.scripts.contains_key("start"), &self.module) {
+            (true, _) => Ok(vec!["run".to_owned(), "start".to_owned()]),
+            (false, Some(module)) => Ok(vec!["run".to_owned(), module.to_owned()]),
+            (false, None) => Err(Error::InvalidPackageJson(
+                "there is no `start` script or `module` to run the project with".to_owned(),
+            )),
+        }
+    }
+
+    /// # Binaries
+    ///
+    /// The paths of the command line tools the package provides, by their name, where a single
+    /// tool is named after the package without its scope
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::PackageJson;
+    ///
+    /// let single: PackageJson = r#"{ "name": "@acme/cli", "bin": "./cli.ts" }"#.parse().unwrap();
+    /// assert_eq!(single.binaries()["cli"], "./cli.ts");
+    ///
+    /// let named: PackageJson = r#"{ "bin": { "acme": "bin/acme.ts", "acmectl": "bin/ctl.ts" } }"#
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(named.binaries().len(), 2);
+    /// ```
+    pub fn binaries(&self) -> BTreeMap<String, String> {
+        self.bin
+            .as_ref()
+            .map(|bin| bin.binaries(self.name.as_deref().unwrap_or_default()))
+            .unwrap_or_default()
+    }
+
+    /// # Deserialize License
+    ///
+    /// Accept both an SPDX expression and the legacy object form of the `license` field
+    pub fn deserialize_license<'de, D>(data: D) -> std::result::Result<Option<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum License {
+            Spdx(String),
+            Object {
+                #[serde(rename = "type")]
+                kind: String,
+            },
+            Other(IgnoredAny),
+        }
+
+        Ok(match Option::<License>::deserialize(data)? {
+            Some(License::Spdx(license) | License::Object { kind: license }) => Some(license),
+            Some(License::Other(_)) | None => None,
+        })
+    }
+
+    /// # Deserialize Workspaces
+    ///
+    /// Accept both the list and object forms of the `workspaces` field, keeping only the globs
+    pub fn deserialize_workspaces<'de, D>(data: D) -> std::result::Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Workspaces {
+            List(Vec<String>),
+            Object {
+                #[serde(default)]
+                packages: Vec<String>,
+            },
+        }
+
+        Ok(match Workspaces::deserialize(data)? {
+            Workspaces::List(packages) | Workspaces::Object { packages } => packages,
+        })
+    }
+}
+
+/// # Lifecycle Script
+///
+/// A script of the root `package.json` which bun runs at the end of every `bun install`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LifecycleScript {
+    /// `postinstall`, run once the packages are installed
+    #[default]
+    Postinstall,
+
+    /// `prepare`, run after `postinstall`, and before the package is packed or published
+    Prepare,
+}
+
+impl LifecycleScript {
+    /// Every lifecycle script run after installing, in the order bun runs them
+    pub const ALL: [Self; 2] = [Self::Postinstall, Self::Prepare];
+
+    /// # Script Name
+    ///
+    /// The name of the script in the `scripts` of a `package.json`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Postinstall => "postinstall",
+            Self::Prepare => "prepare",
+        }
+    }
+}
+
+/// # Add Lifecycle Script
+///
+/// Add a command to a lifecycle script of a `package.json`, running it after any existing
+/// script. Returns the updated file, or `None` if any lifecycle script already runs the command,
+/// so that adding it is idempotent even if it was added to another script before. A script
+/// runs the command if it is one of the commands the script chains with `&&`, `||` or `;`.
+///
+/// The order of the fields in the file is kept, but it is reformatted with two space
+/// indentation.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::package_json::{LifecycleScript, add_lifecycle_script};
+///
+/// let package_json = r#"{ "name": "example", "scripts": { "postinstall": "husky" } }"#;
+///
+/// let updated = add_lifecycle_script(package_json, LifecycleScript::Postinstall, "bun2nix -o bun.nix")
+///     .unwrap()
+///     .unwrap();
+///
+/// assert!(updated.contains(r#""postinstall": "husky && bun2nix -o bun.nix""#));
+///
+/// for script in LifecycleScript::ALL {
+///     assert_eq!(add_lifecycle_script(&updated, script, "bun2nix -o bun.nix").unwrap(), None);
+/// }
+///
+/// let renamed = add_lifecycle_script(&updated, LifecycleScript::Prepare, "bun2nix -o nix/bun.nix")
+///     .unwrap()
+///     .unwrap();
+///
+/// assert!(renamed.contains(r#""prepare": "bun2nix -o nix/bun.nix""#));
+/// ```
+pub fn add_lifecycle_script(
+    package_json: &str,
+    script: LifecycleScript,
+    command: &str,
+) -> Result<Option<String>> {
+    let mut package_json: Map<String, Value> = serde_json::from_str(package_json)?;
+
+    let scripts = package_json
+        .entry("scripts")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| Error::InvalidPackageJson("`scripts` is not an object".to_owned()))?;
+
+    let runs_command = |script: LifecycleScript| {
+        scripts
+            .get(script.name())
+            .and_then(Value::as_str)
+            .is_some_and(|existing| {
+                existing
+                    .split(['&', '|', ';'])
+                    .any(|chained| chained.split_whitespace().eq(command.split_whitespace()))
+            })
+    };
+
+    if LifecycleScript::ALL.into_iter().any(runs_command) {
+        return Ok(None);
+    }
+
+    let updated = match scripts.get(script.name()).and_then(Value::as_str) {
+        Some(existing) if !existing.trim().is_empty() => format!("{existing} && {command}"),
+        _ => command.to_owned(),
+    };
+
+    scripts.insert(script.name().to_owned(), Value::String(updated));
+
+    let mut package_json = serde_json::to_string_pretty(&package_json)?;
+    package_json.push('\n');
+
+    Ok(Some(package_json))
+}
+
+impl FromStr for PackageJson {
+    type Err = Error;
+
+    fn from_str(package_json: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(serde_json::from_str(package_json)?)
+    }
+}
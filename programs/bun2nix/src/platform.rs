@@ -0,0 +1,412 @@
+//! This module holds the platforms a package can be installed on, as declared by the `os`, `cpu`
+//! and `libc` fields of its `package.json`, for selecting native packages built for a given
+//! system
+
+use std::{collections::HashSet, env, fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{Package, error::Error};
+
+/// # Platform
+///
+/// The operating systems, cpus and C standard libraries a package supports, where an empty list
+/// supports any. As in npm, entries prefixed with `!` exclude a value rather than allowing it.
+///
+/// Bun does not record the `libc` field in its lockfile, so it is inferred from the name of the
+/// package for native packages, following the `-gnu` and `-musl` suffixes used by packages such
+/// as `@swc/core`, `lightningcss` and `sharp`.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::platform::{Libc, Platform};
+///
+/// let mut platform = Platform {
+///     os: vec!["linux".to_owned()],
+///     cpu: vec!["x64".to_owned()],
+///     ..Platform::default()
+/// };
+/// platform.infer_libc("@swc/core-linux-x64-musl@1.11.24");
+///
+/// assert!(platform.supports_libc(Libc::Musl));
+/// assert!(!platform.supports_libc(Libc::Glibc));
+///
+/// assert!(Platform::default().supports_libc(Libc::Musl));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Platform {
+    /// The operating systems the package supports, such as `linux` or `darwin`
+    #[serde(deserialize_with = "string_or_list")]
+    pub os: Vec<String>,
+
+    /// The cpus the package supports, such as `x64` or `arm64`
+    #[serde(deserialize_with = "string_or_list")]
+    pub cpu: Vec<String>,
+
+    /// The C standard libraries the package supports, `glibc` or `musl`
+    #[serde(deserialize_with = "string_or_list")]
+    pub libc: Vec<String>,
+}
+
+/// # System
+///
+/// A system packages can be generated for, parsed from a nix system string such as
+/// `aarch64-darwin`, with the names npm uses for its operating system and cpu. A libc may be
+/// given as a suffix (`x86_64-linux-musl`), and otherwise linux systems use glibc.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::platform::{Libc, System};
+///
+/// let system: System = "aarch64-darwin".parse().unwrap();
+///
+/// assert_eq!((system.os.as_str(), system.cpu.as_str()), ("darwin", "arm64"));
+/// assert_eq!(system.libc, None);
+///
+/// let system: System = "x86_64-linux-musl".parse().unwrap();
+///
+/// assert_eq!((system.os.as_str(), system.cpu.as_str()), ("linux", "x64"));
+/// assert_eq!(system.libc, Some(Libc::Musl));
+///
+/// assert!("x86_64".parse::<System>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct System {
+    /// The operating system, as in the `os` field of a `package.json`
+    pub os: String,
+
+    /// The cpu, as in the `cpu` field of a `package.json`
+    pub cpu: String,
+
+    /// The C standard library of the system, if it has one native packages are built against
+    pub libc: Option<Libc>,
+}
+
+/// Nix names for cpus which npm names differently
+const NPM_CPUS: [(&str, &str); 7] = [
+    ("x86_64", "x64"),
+    ("aarch64", "arm64"),
+    ("i686", "ia32"),
+    ("armv6l", "arm"),
+    ("armv7l", "arm"),
+    ("powerpc64le", "ppc64"),
+    ("powerpc64", "ppc64"),
+];
+
+/// Nix names for operating systems which npm names differently
+const NPM_OSES: [(&str, &str); 1] = [("windows", "win32")];
+
+/// # Libc
+///
+/// A C standard library which native packages for linux are built against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+    /// The GNU C library, used by NixOS and most distributions
+    Glibc,
+
+    /// musl, used by Alpine and `pkgsMusl`
+    Musl,
+}
+
+impl Platform {
+    /// # Is Any
+    ///
+    /// Whether the package can be installed on any platform
+    pub fn is_any(&self) -> bool {
+        self.os.is_empty() && self.cpu.is_empty() && self.libc.is_empty()
+    }
+
+    /// # Infer Libc
+    ///
+    /// Set the C standard library of a native package from its name, if it does not declare
+    /// one and its name says which it is built against
+    pub fn infer_libc(&mut self, name: &str) {
+        if !self.libc.is_empty() || self.os.is_empty() {
+            return;
+        }
+
+        let name = match name.rfind('@') {
+            Some(pos) if pos > 0 => &name[..pos],
+            _ => name,
+        };
+
+        for part in name.split(['-', '/']) {
+            let libc = match part {
+                _ if part.contains("musl") => Libc::Musl,
+                _ if part.starts_with("gnu") || part == "glibc" => Libc::Glibc,
+                _ => continue,
+            };
+
+            self.libc = vec![libc.to_string()];
+            return;
+        }
+    }
+
+    /// # Supports System
+    ///
+    /// Whether the package can be installed on the operating system and cpu of a system
+    pub fn supports_system(&self, system: &System) -> bool {
+        allows(&self.os, &system.os) && allows(&self.cpu, &system.cpu)
+    }
+
+    /// # Supports Libc
+    ///
+    /// Whether the package can be installed on a system using the given C standard library
+    pub fn supports_libc(&self, libc: Libc) -> bool {
+        allows(&self.libc, &libc.to_string())
+    }
+}
+
+/// # Retain System
+///
+/// Keep only the packages of a package set which can be installed on a system, including
+/// selecting native packages for its libc if it has one
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{
+///     Package,
+///     package::Fetcher,
+///     platform::{Platform, retain_system},
+/// };
+///
+/// let native = |name: &str, os: &str, cpu: &str| {
+///     let platform = Platform {
+///         os: vec![os.to_owned()],
+///         cpu: vec![cpu.to_owned()],
+///         ..Platform::default()
+///     };
+///
+///     Package::new(name.to_owned(), Fetcher::new_npm_package(name, String::new()).unwrap())
+///         .with_platform(platform)
+/// };
+///
+/// let mut packages = vec![
+///     native("@esbuild/darwin-arm64@0.25.0", "darwin", "arm64"),
+///     native("@esbuild/linux-x64@0.25.0", "linux", "x64"),
+///     native("@esbuild/linux-arm64@0.25.0", "linux", "arm64"),
+///     native("esbuild@0.25.0", "!win32", "!ia32"),
+/// ];
+///
+/// retain_system(&mut packages, &"aarch64-darwin".parse().unwrap());
+///
+/// let names: Vec<_> = packages.iter().map(|pkg| pkg.name.as_str()).collect();
+/// assert_eq!(names, ["@esbuild/darwin-arm64@0.25.0", "esbuild@0.25.0"]);
+/// ```
+pub fn retain_system(packages: &mut Vec<Package>, system: &System) {
+    packages.retain(|pkg| pkg.platform.supports_system(system));
+
+    if let Some(libc) = system.libc {
+        retain_libc(packages, libc);
+    }
+}
+
+/// # Retain Platform
+///
+/// Keep only the packages of a package set which can be installed on the given system, or on
+/// any system using the given C standard library, keeping every package if neither is given
+pub fn retain_platform(packages: &mut Vec<Package>, system: Option<&System>, libc: Option<Libc>) {
+    match system {
+        Some(system) => retain_system(
+            packages,
+            &System {
+                libc: libc.or(system.libc),
+                ..system.clone()
+            },
+        ),
+        None => {
+            if let Some(libc) = libc {
+                retain_libc(packages, libc);
+            }
+        }
+    }
+}
+
+/// # Retain Libc
+///
+/// Keep only the packages of a package set which can be installed on a system using the given
+/// C standard library.
+///
+/// Native packages built against glibc are often only named for their platform, next to a
+/// `-musl` or `linuxmusl` variant (such as `@img/sharp-linux-x64` and
+/// `@img/sharp-linuxmusl-x64`), so linux packages with such a variant are taken to be built
+/// against glibc.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{
+///     Package,
+///     package::Fetcher,
+///     platform::{Libc, Platform, retain_libc},
+/// };
+///
+/// let native = |name: &str| {
+///     let mut platform = Platform {
+///         os: vec!["linux".to_owned()],
+///         ..Platform::default()
+///     };
+///     platform.infer_libc(name);
+///
+///     Package::new(name.to_owned(), Fetcher::new_npm_package(name, String::new()).unwrap())
+///         .with_platform(platform)
+/// };
+///
+/// let packages = || {
+///     vec![
+///         native("@img/sharp-linux-x64@0.34.5"),
+///         native("@img/sharp-linuxmusl-x64@0.34.5"),
+///         native("@esbuild/linux-x64@0.25.0"),
+///     ]
+/// };
+///
+/// let mut musl = packages();
+/// retain_libc(&mut musl, Libc::Musl);
+///
+/// let names: Vec<_> = musl.iter().map(|pkg| pkg.name.as_str()).collect();
+/// assert_eq!(names, ["@img/sharp-linuxmusl-x64@0.34.5", "@esbuild/linux-x64@0.25.0"]);
+///
+/// let mut glibc = packages();
+/// retain_libc(&mut glibc, Libc::Glibc);
+///
+/// let names: Vec<_> = glibc.iter().map(|pkg| pkg.name.as_str()).collect();
+/// assert_eq!(names, ["@img/sharp-linux-x64@0.34.5", "@esbuild/linux-x64@0.25.0"]);
+/// ```
+pub fn retain_libc(packages: &mut Vec<Package>, libc: Libc) {
+    let musl: HashSet<&str> = packages
+        .iter()
+        .filter(|pkg| pkg.platform.libc.iter().any(|libc| libc == "musl"))
+        .filter_map(|pkg| Some(pkg.npm_identifier()?.0))
+        .collect();
+
+    let glibc: HashSet<String> = packages
+        .iter()
+        .filter(|pkg| {
+            pkg.platform.libc.is_empty() && pkg.platform.os.iter().any(|os| os == "linux")
+        })
+        .filter_map(|pkg| pkg.npm_identifier())
+        .filter(|(name, _)| {
+            musl.contains(format!("{name}-musl").as_str())
+                || musl.contains(name.replacen("linux", "linuxmusl", 1).as_str())
+        })
+        .map(|(name, _)| name.to_owned())
+        .collect();
+
+    packages.retain(|pkg| match pkg.npm_identifier() {
+        Some((name, _)) if glibc.contains(name) => libc == Libc::Glibc,
+        _ => pkg.platform.supports_libc(libc),
+    });
+}
+
+/// Whether a list of allowed values, which may contain `!` prefixed exclusions, allows a value
+fn allows(list: &[String], value: &str) -> bool {
+    if list
+        .iter()
+        .any(|entry| entry.strip_prefix('!') == Some(value))
+    {
+        return false;
+    }
+
+    let mut allowed = list
+        .iter()
+        .filter(|entry| !entry.starts_with('!'))
+        .peekable();
+
+    allowed.peek().is_none() || allowed.any(|entry| entry == value)
+}
+
+/// Deserialize a field which may be given either as a single string or a list of them
+pub(crate) fn string_or_list<'de, D>(data: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(match StringOrList::deserialize(data)? {
+        StringOrList::String(value) => vec![value],
+        StringOrList::List(values) => values,
+    })
+}
+
+impl System {
+    /// # Host System
+    ///
+    /// The operating system and cpu `bun2nix` is running on, without a libc as it cannot be
+    /// told at compile time, or `None` if it has no nix name
+    pub fn host() -> Option<Self> {
+        let os = match env::consts::OS {
+            "macos" => "darwin",
+            os => os,
+        };
+
+        let system: Self = format!("{}-{os}", env::consts::ARCH).parse().ok()?;
+
+        Some(Self {
+            libc: None,
+            ..system
+        })
+    }
+}
+
+impl FromStr for System {
+    type Err = Error;
+
+    fn from_str(system: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidSystem(system.to_owned());
+
+        let mut parts = system.splitn(3, '-');
+        let (Some(cpu), Some(os)) = (parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+
+        if cpu.is_empty() || os.is_empty() {
+            return Err(invalid());
+        }
+
+        let libc = match parts.next() {
+            Some(libc) => Some(libc.parse().map_err(|_| invalid())?),
+            None if os == "linux" => Some(Libc::Glibc),
+            None => None,
+        };
+
+        let rename = |names: &[(&str, &str)], name: &str| {
+            names
+                .iter()
+                .find(|(nix, _)| *nix == name)
+                .map_or(name, |(_, npm)| npm)
+                .to_owned()
+        };
+
+        Ok(Self {
+            os: rename(&NPM_OSES, os),
+            cpu: rename(&NPM_CPUS, cpu),
+            libc,
+        })
+    }
+}
+
+impl fmt::Display for Libc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Glibc => "glibc",
+            Self::Musl => "musl",
+        })
+    }
+}
+
+impl FromStr for Libc {
+    type Err = Error;
+
+    fn from_str(libc: &str) -> std::result::Result<Self, Self::Err> {
+        match libc {
+            "glibc" | "gnu" => Ok(Self::Glibc),
+            "musl" => Ok(Self::Musl),
+            _ => Err(Error::InvalidLibc(libc.to_owned())),
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! This module holds the loading and running of resolver plugins, WASM modules which claim
+//! specifier protocols `bun2nix` does not know of and resolve them to tarballs, so that bespoke
+//! artifact protocols can be supported without patching `bun2nix`
+
+use std::{cell::RefCell, fs, path::Path};
+
+use serde::Deserialize;
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, TypedFunc};
+
+use crate::{
+    Package,
+    error::{Error, Result},
+    package::Fetcher,
+};
+
+/// The most fuel a plugin may spend on a single call, so a plugin which never returns fails
+/// rather than hanging the conversion
+const FUEL: u64 = 1_000_000_000;
+
+thread_local! {
+    /// The plugins consulted for specifiers deserialized on this thread, if any
+    static PLUGINS: RefCell<Option<Plugins>> = const { RefCell::new(None) };
+}
+
+/// # With Plugins
+///
+/// Run `parse`, consulting `plugins` for every specifier deserialized on this thread during it
+/// with a protocol `bun2nix` does not know of
+pub fn with_plugins<T>(plugins: Plugins, parse: impl FnOnce() -> T) -> T {
+    PLUGINS.with(|cell| cell.replace(Some(plugins)));
+    let parsed = parse();
+    PLUGINS.with(|cell| cell.take());
+
+    parsed
+}
+
+/// Resolve a specifier with the plugins active on this thread, if one of them claims it
+pub(crate) fn resolve_with_active(id: &str, spec: &str) -> Option<Result<Package>> {
+    PLUGINS.with(|cell| {
+        cell.borrow_mut()
+            .as_mut()
+            .and_then(|plugins| plugins.resolve(id, spec))
+    })
+}
+
+/// # Plugins
+///
+/// The resolver plugins loaded for a conversion, consulted in the order they were given
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{package::Fetcher, plugins::{Plugin, Plugins}};
+///
+/// let wasm = wat::parse_str(r#"
+///     (module
+///         (memory (export "memory") 1)
+///         (global $next (mut i32) (i32.const 1024))
+///         (data (i32.const 0) "artifactory:")
+///         (data (i32.const 16) "{\"url\":\"https://artifacts.example.com/my-lib-1.0.0.tgz\"}")
+///
+///         (func (export "alloc") (param $len i32) (result i32)
+///             (global.get $next)
+///             (global.set $next (i32.add (global.get $next) (local.get $len))))
+///
+///         (func (export "schemes") (result i64)
+///             (i64.const 12))
+///
+///         (func (export "resolve") (param i32 i32) (result i64)
+///             (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 56))))
+/// "#).unwrap();
+///
+/// let plugin = Plugin::from_wasm("artifactory.wasm", &wasm).unwrap();
+///
+/// assert_eq!(plugin.schemes(), ["artifactory:"]);
+///
+/// let mut plugins = Plugins::new(vec![plugin]);
+///
+/// let pkg = plugins
+///     .resolve("my-lib@artifactory:my-lib/1.0.0", "artifactory:my-lib/1.0.0")
+///     .unwrap()
+///     .unwrap();
+///
+/// assert_eq!(pkg.name, "tarball:https://artifacts.example.com/my-lib-1.0.0.tgz");
+/// assert_eq!(
+///     pkg.fetcher,
+///     Fetcher::FetchTarball {
+///         url: "https://artifacts.example.com/my-lib-1.0.0.tgz".to_owned(),
+///         hash: String::new(),
+///     }
+/// );
+///
+/// assert!(plugins.resolve("my-lib@s3:my-lib/1.0.0", "s3:my-lib/1.0.0").is_none());
+///
+/// // A string past the end of the plugin's memory is reported rather than read
+/// let wasm = wat::parse_str(r#"
+///     (module
+///         (memory (export "memory") 1)
+///         (func (export "alloc") (param i32) (result i32) (i32.const 0))
+///         (func (export "schemes") (result i64) (i64.const 0xffffffff))
+///         (func (export "resolve") (param i32 i32) (result i64) (i64.const 0)))
+/// "#).unwrap();
+///
+/// assert_eq!(
+///     Plugin::from_wasm("broken.wasm", &wasm).unwrap_err().to_string(),
+///     "The resolver plugin `broken.wasm` failed: it returned 4294967295 bytes at 0, past the end of its memory"
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct Plugins {
+    plugins: Vec<Plugin>,
+}
+
+impl Plugins {
+    /// # New Plugins
+    ///
+    /// Consult the given plugins, in order
+    pub fn new(plugins: Vec<Plugin>) -> Self {
+        Self { plugins }
+    }
+
+    /// # Load Plugins
+    ///
+    /// Load the plugins from the given `.wasm` files, in order
+    pub fn load(paths: &[impl AsRef<Path>]) -> Result<Self> {
+        paths
+            .iter()
+            .map(Plugin::load)
+            .collect::<Result<_>>()
+            .map(Self::new)
+    }
+
+    /// # Resolve Specifier
+    ///
+    /// Resolve the specifier of the lockfile identifier `id` with the first plugin claiming its
+    /// protocol, or `None` if no plugin claims it
+    pub fn resolve(&mut self, id: &str, spec: &str) -> Option<Result<Package>> {
+        self.plugins
+            .iter_mut()
+            .find(|plugin| plugin.claims(spec))
+            .map(|plugin| plugin.resolve(id))
+    }
+}
+
+/// # Plugin
+///
+/// A WASM module which claims specifier protocols and resolves them to tarballs.
+///
+/// It exports its `memory` and the following functions, where strings are passed as UTF-8 in
+/// its memory and returned packed into an `i64` as the pointer in the upper 32 bits and the
+/// length in the lower 32 bits:
+///
+/// - `alloc(len: i32) -> i32`: reserve `len` bytes for a string passed to the plugin
+/// - `schemes() -> i64`: the protocols the plugin claims, such as `artifactory:`, one per line
+/// - `resolve(ptr: i32, len: i32) -> i64`: resolve a lockfile identifier (e.g.
+///   `my-lib@artifactory:my-lib/1.0.0`) to a JSON object of the `url` of its tarball and
+///   optionally its SRI `hash`, or to `{"error": "..."}` if it cannot be resolved
+#[derive(Debug)]
+pub struct Plugin {
+    name: String,
+    schemes: Vec<String>,
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    resolve: TypedFunc<(i32, i32), i64>,
+}
+
+/// What a plugin resolved an identifier to
+#[derive(Debug, Deserialize)]
+struct Resolution {
+    url: Option<String>,
+    hash: Option<String>,
+    error: Option<String>,
+}
+
+impl Plugin {
+    /// # Load Plugin
+    ///
+    /// Load a plugin from a `.wasm` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let wasm = fs::read(path).map_err(|err| plugin_error(&name, err))?;
+
+        Self::from_wasm(&name, &wasm)
+    }
+
+    /// # Plugin From WASM
+    ///
+    /// Instantiate a plugin from the bytes of its WASM module, asking it for the protocols it
+    /// claims
+    pub fn from_wasm(name: &str, wasm: &[u8]) -> Result<Self> {
+        let fail = |err: wasmi::Error| plugin_error(name, err);
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(fail)?;
+        let mut store = Store::new(&engine, ());
+        store.set_fuel(FUEL).map_err(|err| plugin_error(name, err))?;
+
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(fail)?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| plugin_error(name, "it does not export its `memory`"))?;
+        let schemes = instance
+            .get_typed_func::<(), i64>(&store, "schemes")
+            .map_err(fail)?;
+
+        let mut plugin = Self {
+            name: name.to_owned(),
+            schemes: Vec::new(),
+            alloc: instance.get_typed_func(&store, "alloc").map_err(fail)?,
+            resolve: instance.get_typed_func(&store, "resolve").map_err(fail)?,
+            store,
+            memory,
+        };
+
+        plugin.refuel()?;
+        let packed = schemes.call(&mut plugin.store, ()).map_err(fail)?;
+        plugin.schemes = plugin
+            .read(packed)?
+            .lines()
+            .map(str::trim)
+            .filter(|scheme| !scheme.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        Ok(plugin)
+    }
+
+    /// # Plugin Schemes
+    ///
+    /// The protocols the plugin claims
+    pub fn schemes(&self) -> &[String] {
+        &self.schemes
+    }
+
+    /// # Claims Specifier
+    ///
+    /// Whether the plugin claims the protocol of a specifier
+    pub fn claims(&self, spec: &str) -> bool {
+        self.schemes.iter().any(|scheme| spec.starts_with(scheme))
+    }
+
+    /// # Resolve Identifier
+    ///
+    /// Resolve a lockfile identifier to a tarball package, whose hash is left empty to be
+    /// prefetched if the plugin does not give one
+    pub fn resolve(&mut self, id: &str) -> Result<Package> {
+        self.refuel()?;
+        let len = i32::try_from(id.len()).map_err(|err| plugin_error(&self.name, err))?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|err| plugin_error(&self.name, err))?;
+
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, id.as_bytes())
+            .map_err(|err| plugin_error(&self.name, err))?;
+
+        self.refuel()?;
+        let packed = self
+            .resolve
+            .call(&mut self.store, (ptr, len))
+            .map_err(|err| plugin_error(&self.name, err))?;
+
+        let resolution: Resolution = serde_json::from_str(&self.read(packed)?)
+            .map_err(|err| plugin_error(&self.name, err))?;
+
+        match resolution {
+            Resolution {
+                error: Some(error), ..
+            } => Err(plugin_error(
+                &self.name,
+                format_args!("it could not resolve `{id}`: {error}"),
+            )),
+            Resolution {
+                url: Some(url),
+                hash,
+                ..
+            } => Ok(Package::new(
+                format!("tarball:{url}"),
+                Fetcher::FetchTarball {
+                    url,
+                    hash: hash.unwrap_or_default(),
+                },
+            )),
+            Resolution { url: None, .. } => Err(plugin_error(
+                &self.name,
+                format_args!("it gave no `url` for `{id}`"),
+            )),
+        }
+    }
+
+    /// Give the plugin its fuel for a call
+    fn refuel(&mut self) -> Result<()> {
+        self.store
+            .set_fuel(FUEL)
+            .map_err(|err| plugin_error(&self.name, err))
+    }
+
+    /// Read a string returned by the plugin, packed as its pointer and length
+    fn read(&self, packed: i64) -> Result<String> {
+        let ptr = (packed as u64 >> 32) as usize;
+        let len = (packed as u64 & u64::from(u32::MAX)) as usize;
+
+        // The length is the plugin's to choose, so it is checked against its memory before
+        // anything is copied out of it
+        let bytes = ptr
+            .checked_add(len)
+            .and_then(|end| self.memory.data(&self.store).get(ptr..end))
+            .ok_or_else(|| {
+                plugin_error(
+                    &self.name,
+                    format_args!("it returned {len} bytes at {ptr}, past the end of its memory"),
+                )
+            })?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|err| plugin_error(&self.name, err))
+    }
+}
+
+/// The error for a plugin which could not be loaded or run
+fn plugin_error(name: &str, reason: impl ToString) -> Error {
+    Error::Plugin {
+        plugin: name.to_owned(),
+        reason: reason.to_string(),
+    }
+}
@@ -0,0 +1,184 @@
+//! This module holds policies forbidding packages from being used by a project, such as
+//! typosquats of popular packages or versions known to have been compromised
+
+use std::{fmt, str::FromStr};
+
+use semver::Version;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    Package,
+    error::{Error, Result},
+    resolver::VersionRange,
+};
+
+/// # Policy
+///
+/// A set of rules denying npm packages, read from a JSON policy file. Each rule names a package,
+/// optionally with an npm version range limiting it to some versions (every version is denied
+/// otherwise), along with the reason it is denied.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{Package, package::Fetcher, policy::Policy};
+///
+/// let policy: Policy = r#"
+/// {
+///   "deny": [
+///     { "package": "event-stream", "versions": "3.3.6", "reason": "Compromised release" },
+///     { "package": "crossenv", "reason": "Typosquat of `cross-env`" }
+///   ]
+/// }
+/// "#
+/// .parse()
+/// .unwrap();
+///
+/// let npm = |ident: &str| {
+///     Package::new(ident.to_owned(), Fetcher::new_npm_package(ident, String::new()).unwrap())
+/// };
+///
+/// let packages = [npm("event-stream@3.3.6"), npm("event-stream@4.0.1"), npm("crossenv@1.0.0")];
+/// let violations = policy.violations(&packages);
+///
+/// assert_eq!(violations.len(), 2);
+/// assert_eq!(
+///     violations[0].to_string(),
+///     "`event-stream@3.3.6` is denied by the rule for `event-stream` (3.3.6): Compromised release"
+/// );
+///
+/// assert!(policy.check(&packages[1..2]).is_ok());
+/// assert!(policy.check(&packages).is_err());
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// The rules for packages which must not be used
+    #[serde(default)]
+    pub deny: Vec<DenyRule>,
+}
+
+/// # Deny Rule
+///
+/// A package, or range of its versions, which must not be used
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DenyRule {
+    /// The name of the denied package
+    pub package: String,
+
+    /// The versions of the package which are denied, as they were written in the policy, along
+    /// with the range they were parsed to - every version is denied if there is none
+    #[serde(default, deserialize_with = "DenyRule::deserialize_versions")]
+    pub versions: Option<(String, VersionRange)>,
+
+    /// Why the package is denied
+    pub reason: Option<String>,
+}
+
+/// # Policy Violation
+///
+/// A package which is denied by a rule of the policy
+#[derive(Debug, Clone)]
+pub struct Violation<'a> {
+    /// The name of the package in the lockfile, such as `event-stream@3.3.6`
+    pub package: &'a str,
+
+    /// The rule which denies it
+    pub rule: &'a DenyRule,
+}
+
+impl Policy {
+    /// # Policy Violations
+    ///
+    /// Every npm package of the package set which is denied by a rule of the policy, in the order
+    /// of the packages
+    pub fn violations<'a>(&'a self, packages: &'a [Package]) -> Vec<Violation<'a>> {
+        packages
+            .iter()
+            .filter_map(|pkg| {
+                let (name, version) = pkg.npm_identifier()?;
+                let rule = self.deny.iter().find(|rule| rule.denies(name, version))?;
+
+                Some(Violation {
+                    package: &pkg.name,
+                    rule,
+                })
+            })
+            .collect()
+    }
+
+    /// # Check Policy
+    ///
+    /// Fail with a report of every violation if any package of the set is denied by the policy
+    pub fn check(&self, packages: &[Package]) -> Result<()> {
+        let violations = self.violations(packages);
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(Error::PolicyViolations(
+                violations.iter().map(Violation::to_string).collect(),
+            )),
+        }
+    }
+}
+
+impl DenyRule {
+    /// Whether the rule denies the given version of a package
+    fn denies(&self, name: &str, version: &str) -> bool {
+        if self.package != name {
+            return false;
+        }
+
+        match &self.versions {
+            None => true,
+            Some((_, range)) => match Version::parse(version) {
+                Ok(version) => range.matches(&version),
+                // Versions which cannot be compared to the range are denied to be safe
+                Err(_) => true,
+            },
+        }
+    }
+
+    /// Parse the version range of a rule, keeping the range as it was written for reports
+    fn deserialize_versions<'de, D>(
+        data: D,
+    ) -> std::result::Result<Option<(String, VersionRange)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Some(versions) = Option::<String>::deserialize(data)? else {
+            return Ok(None);
+        };
+
+        let range = versions.parse().map_err(serde::de::Error::custom)?;
+
+        Ok(Some((versions, range)))
+    }
+}
+
+impl fmt::Display for Violation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is denied by the rule for `{}`",
+            self.package, self.rule.package
+        )?;
+
+        if let Some((versions, _)) = &self.rule.versions {
+            write!(f, " ({versions})")?;
+        }
+
+        match &self.rule.reason {
+            Some(reason) => write!(f, ": {reason}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> std::result::Result<Self, Self::Err> {
+        serde_json::from_str(policy).map_err(|err| Error::InvalidPolicy(err.to_string()))
+    }
+}
@@ -0,0 +1,831 @@
+//! This module holds the orchestration of prefetching package hashes, which runs the prefetches
+//! for all packages in parallel and consults the cache first where one is available
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, Sender},
+    },
+    time::{Duration, Instant},
+};
+
+use rayon::{ThreadPoolBuilder, prelude::*};
+
+#[cfg(feature = "cache")]
+use crate::cache::{Cache, Timing};
+use crate::{
+    Package,
+    bun_cache::BunCache,
+    error::{Error, Result},
+    interrupt,
+    package::{FetchCommand, Fetcher, Nix, PackagePattern, Prefetch},
+    platform::{Libc, System},
+};
+
+/// The default number of prefetches to run at once
+pub const DEFAULT_JOBS: usize = 8;
+
+/// # Prefetcher
+///
+/// Calculates the hashes for packages which are not already known from the lockfile
+#[derive(Debug)]
+pub struct Prefetcher {
+    jobs: usize,
+    downloader: Option<Downloader>,
+    nix: Nix,
+    bun_cache: Option<BunCache>,
+    refetch: Vec<PackagePattern>,
+    #[cfg(feature = "cache")]
+    cache: Option<Mutex<Cache>>,
+    #[cfg(feature = "cache")]
+    pending: Mutex<Vec<(String, String)>>,
+    #[cfg(feature = "cache")]
+    cache_tarballs: bool,
+    stats: Mutex<PrefetchStats>,
+    prefetched: Mutex<HashMap<String, String>>,
+    events: Option<Sender<Prefetched>>,
+    in_flight: Mutex<HashMap<String, Instant>>,
+    queued: AtomicUsize,
+}
+
+/// # Prefetch Options
+///
+/// How [`crate::prefetch_lockfile`] turns the packages of a lockfile into a prefetched package
+/// set: the prefetcher missing hashes are calculated with, and the platform packages are
+/// selected for
+#[derive(Debug, Default)]
+pub struct PrefetchOptions {
+    pub(crate) prefetcher: Prefetcher,
+    pub(crate) system: Option<System>,
+    pub(crate) libc: Option<Libc>,
+}
+
+/// # Prefetch Statistics
+///
+/// Counts of where the hashes found by a prefetcher came from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PrefetchStats {
+    /// The number of hashes calculated by fetching the package
+    pub fetched: usize,
+
+    /// The number of hashes found in the cache
+    pub cached: usize,
+
+    /// The number of bytes fetched, counting only the fetches whose size is known
+    pub fetched_bytes: u64,
+
+    /// The number of hashes calculated from packages already extracted to bun's install cache
+    pub from_bun_cache: usize,
+}
+
+/// # Prefetched Hash
+///
+/// The result of prefetching a single package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prefetched {
+    /// The fetcher for the package, with its hash filled in
+    pub fetcher: Fetcher,
+
+    /// Whether the hash came from the cache rather than being calculated
+    pub cached: bool,
+}
+
+/// # Prefetch Status
+///
+/// A snapshot of the prefetches a prefetcher is running, for working out why a run seems stuck
+///
+/// ## Usage
+///```rust
+/// use std::time::Duration;
+///
+/// use bun2nix::prefetcher::PrefetchStatus;
+///
+/// let status = PrefetchStatus {
+///     in_flight: vec![("https://github.com/colinhacks/zod".to_owned(), Duration::from_secs(42))],
+///     queued: 3,
+/// };
+///
+/// assert_eq!(
+///     status.to_string(),
+///     "1 prefetch(es) in flight, 3 queued\n  42.0s https://github.com/colinhacks/zod"
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PrefetchStatus {
+    /// The url of each prefetch which is running and how long it has been running for, the
+    /// longest running first
+    pub in_flight: Vec<(String, Duration)>,
+
+    /// The number of prefetches waiting for a free job to start
+    pub queued: usize,
+}
+
+/// # Prefetch Event
+///
+/// Progress reported while prefetching a set of packages
+#[derive(Debug)]
+pub enum PrefetchEvent {
+    /// Prefetching of the package has started
+    Started,
+
+    /// Prefetching of the package has finished
+    Finished(Result<Prefetched>),
+}
+
+impl Default for Prefetcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_JOBS)
+    }
+}
+
+impl PrefetchOptions {
+    /// # With Prefetcher
+    ///
+    /// Calculate missing hashes with the given prefetcher, rather than a default one which
+    /// uses no cache
+    pub fn with_prefetcher(mut self, prefetcher: Prefetcher) -> Self {
+        self.prefetcher = prefetcher;
+        self
+    }
+
+    /// # With System
+    ///
+    /// Leave out the packages which cannot be installed on the given system
+    pub fn with_system(mut self, system: System) -> Self {
+        self.system = Some(system);
+        self
+    }
+
+    /// # With Libc
+    ///
+    /// Leave out the native packages built against a different C standard library
+    pub fn with_libc(mut self, libc: Libc) -> Self {
+        self.libc = Some(libc);
+        self
+    }
+}
+
+impl Prefetcher {
+    /// # New Prefetcher
+    ///
+    /// Create a prefetcher which runs up to `jobs` prefetches at once
+    pub fn new(jobs: usize) -> Self {
+        Self {
+            jobs: jobs.max(1),
+            downloader: None,
+            nix: Nix::default(),
+            bun_cache: None,
+            refetch: Vec::new(),
+            #[cfg(feature = "cache")]
+            cache: None,
+            #[cfg(feature = "cache")]
+            pending: Mutex::new(Vec::new()),
+            #[cfg(feature = "cache")]
+            cache_tarballs: false,
+            stats: Mutex::new(PrefetchStats::default()),
+            prefetched: Mutex::new(HashMap::new()),
+            events: None,
+            in_flight: Mutex::new(HashMap::new()),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// # With Cache
+    ///
+    /// Look up hashes in and store hashes to the given cache
+    #[cfg(feature = "cache")]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(Mutex::new(cache));
+        self
+    }
+
+    /// # With Tarball Cache
+    ///
+    /// Keep the tarballs downloaded to calculate flat hashes in the cache, compressed, and
+    /// hash a kept tarball rather than downloading it again. Only tarballs downloaded with a
+    /// fetch command or in process are kept, as nix keeps its own copies in the store.
+    #[cfg(feature = "cache")]
+    pub fn with_tarball_cache(mut self) -> Self {
+        self.cache_tarballs = true;
+        self
+    }
+
+    /// # With Fetch Command
+    ///
+    /// Download tarballs with the given command rather than nix, emitting them as `fetchurl`
+    /// with the flat hash of the downloaded file
+    pub fn with_fetch_command(mut self, fetch_command: FetchCommand) -> Self {
+        self.downloader = Some(Downloader::Command(fetch_command));
+        self
+    }
+
+    /// # With In Process Fetching
+    ///
+    /// Download tarballs in this process with the shared [`crate::registry::http_client`]
+    /// rather than with nix, reusing its connections between downloads and emitting them as
+    /// `fetchurl` with the flat hash of the downloaded file
+    #[cfg(feature = "resolver")]
+    pub fn with_in_process_fetching(mut self) -> Result<Self> {
+        self.downloader = Some(Downloader::Client(crate::registry::http_client()?));
+        Ok(self)
+    }
+
+    /// # With Bun Cache
+    ///
+    /// Hash packages which bun has already extracted to its install cache where they are found
+    /// there, rather than fetching them again
+    pub fn with_bun_cache(mut self, bun_cache: BunCache) -> Self {
+        self.bun_cache = Some(bun_cache);
+        self
+    }
+
+    /// # With Refetch
+    ///
+    /// Prefetch the packages matching any of the given patterns again rather than taking their
+    /// hashes from the cache or bun's install cache, replacing what the cache holds for them
+    pub fn with_refetch(mut self, refetch: Vec<PackagePattern>) -> Self {
+        self.refetch = refetch;
+        self
+    }
+
+    /// # With Nix
+    ///
+    /// Prefetch with the given nix installation rather than the `nix` on the `PATH`
+    pub fn with_nix(mut self, nix: Nix) -> Self {
+        self.nix = nix;
+        self
+    }
+
+    /// # With Events
+    ///
+    /// Send every hash found to `events` as soon as it is known, counting each package only
+    /// once however many times it is prefetched
+    pub fn with_events(mut self, events: Sender<Prefetched>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// # Prefetch
+    ///
+    /// Calculate the hash for a single fetcher, using the cache if possible
+    pub fn prefetch(&self, fetcher: &Fetcher) -> Result<Prefetched> {
+        interrupt::check()?;
+
+        let job = self.job(fetcher)?;
+
+        // Already prefetched by this prefetcher, and so already counted in its statistics
+        let prefetched = self.prefetched.lock().unwrap().get(&job.key).cloned();
+
+        if let Some(hash) = prefetched {
+            let fetch_lfs = self.fetches_lfs(fetcher, &job.key)?;
+
+            return Ok(Prefetched {
+                fetcher: job.finish(fetcher, hash, fetch_lfs),
+                cached: false,
+            });
+        }
+
+        let refetch = self.refetches(fetcher);
+
+        if let Some(hash) = self.cached_hash(&job.key)?.filter(|_| !refetch) {
+            self.stats.lock().unwrap().cached += 1;
+            let fetch_lfs = self.fetches_lfs(fetcher, &job.key)?;
+
+            return Ok(self.report(Prefetched {
+                fetcher: job.finish(fetcher, hash, fetch_lfs),
+                cached: true,
+            }));
+        }
+
+        // Downloaded tarballs are hashed flat, which an extracted copy cannot be
+        let local = match job.downloader {
+            Some(_) => None,
+            None if refetch => None,
+            None => self.hash_from_bun_cache(fetcher),
+        };
+
+        if let Some(hash) = local {
+            self.remember(&job.key, &hash, None)?;
+            self.stats.lock().unwrap().from_bun_cache += 1;
+
+            return Ok(self.report(Prefetched {
+                fetcher: job.finish(fetcher, hash, false),
+                cached: false,
+            }));
+        }
+
+        self.in_flight
+            .lock()
+            .unwrap()
+            .insert(job.url.clone(), Instant::now());
+
+        let prefetch = match job.downloader {
+            Some(downloader) => self.download(downloader, &job.url),
+            None => Prefetch::prefetch_fetcher(fetcher, &self.nix),
+        };
+
+        self.in_flight.lock().unwrap().remove(&job.url);
+
+        let Prefetch {
+            hash,
+            size,
+            fetch_lfs,
+            ..
+        } = prefetch?;
+
+        let lfs = matches!(fetcher, Fetcher::FetchGit { .. }).then_some(fetch_lfs);
+        self.remember(&job.key, &hash, lfs)?;
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.fetched += 1;
+        stats.fetched_bytes += size.unwrap_or_default();
+        drop(stats);
+
+        Ok(self.report(Prefetched {
+            fetcher: job.finish(fetcher, hash, fetch_lfs),
+            cached: false,
+        }))
+    }
+
+    /// # Prefetch From Cache
+    ///
+    /// Fill in the hashes of every package in the list which needs prefetching from the cache
+    /// (or bun's install cache) alone, without fetching anything. Returns the names of the
+    /// packages which were not found in either, and so still have no hash.
+    pub fn prefetch_cached(&self, packages: &mut [Package]) -> Result<Vec<String>> {
+        let mut missing = Vec::new();
+        let mut local = Vec::new();
+
+        for (i, pkg) in packages.iter_mut().enumerate() {
+            if !pkg.needs_prefetch() {
+                continue;
+            }
+
+            let job = self.job(&pkg.fetcher)?;
+
+            // Packages to be prefetched again have no hash until they are
+            if self.refetches(&pkg.fetcher) {
+                missing.push(i);
+                continue;
+            }
+
+            if let Some(hash) = self.cached_hash(&job.key)? {
+                self.stats.lock().unwrap().cached += 1;
+                let fetch_lfs = self.fetches_lfs(&pkg.fetcher, &job.key)?;
+                pkg.fetcher = self
+                    .report(Prefetched {
+                        fetcher: job.finish(&pkg.fetcher, hash, fetch_lfs),
+                        cached: true,
+                    })
+                    .fetcher;
+                continue;
+            }
+
+            match job.downloader {
+                Some(_) => missing.push(i),
+                None => local.push(i),
+            }
+        }
+
+        // Hashing local copies is bound by the disk and cpu rather than the network, so they are
+        // hashed on every core rather than `jobs` at a time
+        let hashes: Vec<Option<String>> = local
+            .par_iter()
+            .map(|&i| self.hash_from_bun_cache(&packages[i].fetcher))
+            .collect();
+
+        for (i, hash) in local.into_iter().zip(hashes) {
+            let Some(hash) = hash else {
+                missing.push(i);
+                continue;
+            };
+
+            let pkg = &mut packages[i];
+            let job = self.job(&pkg.fetcher)?;
+
+            self.remember(&job.key, &hash, None)?;
+            self.stats.lock().unwrap().from_bun_cache += 1;
+            pkg.fetcher = self
+                .report(Prefetched {
+                    fetcher: job.finish(&pkg.fetcher, hash, false),
+                    cached: false,
+                })
+                .fetcher;
+        }
+
+        self.flush()?;
+
+        missing.sort();
+
+        Ok(missing
+            .into_iter()
+            .map(|i| packages[i].name.clone())
+            .collect())
+    }
+
+    /// # Prefetch Packages
+    ///
+    /// Fill in the hashes of every package in the list which needs prefetching
+    pub fn prefetch_packages(&self, packages: &mut [Package]) -> Result<()> {
+        let pending = packages
+            .iter()
+            .enumerate()
+            .filter(|(_, pkg)| pkg.needs_prefetch())
+            .map(|(i, pkg)| (i, pkg.fetcher.clone()))
+            .collect();
+
+        let results = Mutex::new(Vec::new());
+
+        self.prefetch_each(pending, |i, event| {
+            if let PrefetchEvent::Finished(result) = event {
+                results.lock().unwrap().push((i, result));
+            }
+        })?;
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(i, _)| *i);
+
+        for (i, result) in results {
+            packages[i].fetcher = result?.fetcher;
+        }
+
+        Ok(())
+    }
+
+    /// # Prefetch Stream
+    ///
+    /// Prefetch the packages needing it as they arrive from a channel, until it is closed. The
+    /// hashes are remembered, so prefetching the same packages again afterwards (such as with
+    /// [`Prefetcher::prefetch_packages`]) does not fetch them twice.
+    ///
+    /// Failed prefetches are not reported here, and are retried when the packages are
+    /// prefetched again.
+    pub fn prefetch_stream(&self, packages: Receiver<Package>) -> Result<()> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+            .map_err(Error::ThreadPool)?;
+
+        pool.install(|| {
+            // Tags and branches are prefetched once they are pinned to a commit
+            packages
+                .into_iter()
+                .filter(|pkg| pkg.needs_prefetch() && pkg.is_pinned())
+                .par_bridge()
+                .for_each(|pkg| {
+                    let _ = self.prefetch(&pkg.fetcher);
+                })
+        });
+
+        self.flush()
+    }
+
+    /// # Prefetch Each
+    ///
+    /// Prefetch each of the given (index, fetcher) pairs in parallel, reporting progress for
+    /// each index as it happens
+    pub fn prefetch_each<F>(&self, fetchers: Vec<(usize, Fetcher)>, on_event: F) -> Result<()>
+    where
+        F: Fn(usize, PrefetchEvent) + Sync,
+    {
+        if fetchers.is_empty() {
+            return Ok(());
+        }
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+            .map_err(Error::ThreadPool)?;
+
+        self.queued.fetch_add(fetchers.len(), Ordering::Relaxed);
+
+        pool.install(|| {
+            fetchers.into_par_iter().for_each(|(i, fetcher)| {
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+                on_event(i, PrefetchEvent::Started);
+                on_event(i, PrefetchEvent::Finished(self.prefetch(&fetcher)));
+            })
+        });
+
+        self.flush()
+    }
+
+    /// # Flush Cache
+    ///
+    /// Write every hash prefetched since the last flush to the cache in a single transaction.
+    ///
+    /// This is done automatically at the end of [`Prefetcher::prefetch_each`] and
+    /// [`Prefetcher::prefetch_packages`], but must be called after using
+    /// [`Prefetcher::prefetch`] directly.
+    #[cfg(feature = "cache")]
+    pub fn flush(&self) -> Result<()> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        match &self.cache {
+            Some(cache) => cache.lock().unwrap().insert_many(&pending),
+            None => Ok(()),
+        }
+    }
+
+    /// # Flush Cache
+    ///
+    /// Without the cache there is nothing to write
+    #[cfg(not(feature = "cache"))]
+    pub fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Record Timing
+    ///
+    /// Store the timing of a run in the cache, if there is one
+    #[cfg(feature = "cache")]
+    pub fn record_timing(&self, timing: &Timing) -> Result<()> {
+        match &self.cache {
+            Some(cache) => cache.lock().unwrap().record_timing(timing),
+            None => Ok(()),
+        }
+    }
+
+    /// # Statistics
+    ///
+    /// Where the hashes found by this prefetcher so far came from
+    pub fn stats(&self) -> PrefetchStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// # Status
+    ///
+    /// The prefetches running right now, and how many are waiting to start
+    pub fn status(&self) -> PrefetchStatus {
+        let now = Instant::now();
+
+        let mut in_flight: Vec<_> = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(url, started)| (url.clone(), now.duration_since(*started)))
+            .collect();
+        in_flight.sort_by_key(|(_, elapsed)| std::cmp::Reverse(*elapsed));
+
+        PrefetchStatus {
+            in_flight,
+            queued: self.queued.load(Ordering::Relaxed),
+        }
+    }
+
+    /// # Jobs
+    ///
+    /// The maximum number of prefetches run at once
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Send a newly found hash to the events channel, if there is one
+    fn report(&self, prefetched: Prefetched) -> Prefetched {
+        if let Some(events) = &self.events {
+            // The receiver hanging up only means nobody is listening for events any more
+            let _ = events.send(prefetched.clone());
+        }
+
+        prefetched
+    }
+
+    fn job(&self, fetcher: &Fetcher) -> Result<Job<'_>> {
+        let url = fetcher.prefetch_url().ok_or(Error::NothingToPrefetch)?;
+
+        let downloader = match fetcher {
+            Fetcher::FetchTarball { .. } => self.downloader.as_ref(),
+            _ => None,
+        };
+
+        // Flat hashes of downloaded files differ from those nix calculates for the unpacked
+        // tarball, so they are cached separately
+        let key = match downloader {
+            Some(_) => format!("fetchurl:{url}"),
+            None => url.clone(),
+        };
+
+        Ok(Job {
+            url,
+            key,
+            downloader,
+        })
+    }
+
+    /// Remember a hash for the rest of the run, and store it in the cache at the next flush,
+    /// along with whether git lfs objects had to be fetched for it if it is a git repository
+    fn remember(&self, key: &str, hash: &str, fetch_lfs: Option<bool>) -> Result<()> {
+        let mut entries = vec![(key.to_owned(), hash.to_owned())];
+
+        if let Some(fetch_lfs) = fetch_lfs {
+            entries.push((lfs_key(key), fetch_lfs.to_string()));
+        }
+
+        for (key, value) in entries {
+            self.store_hash(&key, &value)?;
+            self.prefetched.lock().unwrap().insert(key, value);
+        }
+
+        Ok(())
+    }
+
+    /// Whether a git fetcher whose hash is already known must fetch git lfs objects
+    fn fetches_lfs(&self, fetcher: &Fetcher, key: &str) -> Result<bool> {
+        if !matches!(fetcher, Fetcher::FetchGit { .. }) {
+            return Ok(false);
+        }
+
+        let key = lfs_key(key);
+        let remembered = self.prefetched.lock().unwrap().get(&key).cloned();
+
+        let fetch_lfs = match remembered {
+            Some(fetch_lfs) => Some(fetch_lfs),
+            None => self.cached_hash(&key)?,
+        };
+
+        Ok(fetch_lfs.as_deref() == Some("true"))
+    }
+
+    /// Whether a fetcher matches any of the patterns of packages to prefetch again
+    fn refetches(&self, fetcher: &Fetcher) -> bool {
+        self.refetch.iter().any(|pattern| pattern.matches(fetcher))
+    }
+
+    /// Hash the copy of a package bun extracted to its install cache, if there is one. Failing
+    /// to hash it is not an error, as the package can still be fetched instead.
+    fn hash_from_bun_cache(&self, fetcher: &Fetcher) -> Option<String> {
+        let dir = self.bun_cache.as_ref()?.package_dir(fetcher)?;
+
+        Prefetch::hash_path(&dir).ok().map(|prefetch| prefetch.hash)
+    }
+
+    #[cfg(feature = "cache")]
+    fn download(&self, downloader: &Downloader, url: &str) -> Result<Prefetch> {
+        let cache = match &self.cache {
+            Some(cache) if self.cache_tarballs => cache,
+            _ => return downloader.fetch(url),
+        };
+
+        let key = format!("tarball:{url}");
+        let kept = cache.lock().unwrap().get_blob(&key)?;
+
+        let (tarball, size) = match kept {
+            Some(tarball) => (tarball, None),
+            None => {
+                let tarball = downloader.download(url)?;
+                cache.lock().unwrap().insert_blob(&key, &tarball)?;
+
+                let size = tarball.len() as u64;
+                (tarball, Some(size))
+            }
+        };
+
+        Ok(Prefetch {
+            hash: crate::package::sha256_sri(&mut tarball.as_slice())
+                .map_err(Error::FetchingFailed)?,
+            store_path: None,
+            size,
+            fetch_lfs: false,
+        })
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn download(&self, downloader: &Downloader, url: &str) -> Result<Prefetch> {
+        downloader.fetch(url)
+    }
+
+    #[cfg(feature = "cache")]
+    fn cached_hash(&self, url: &str) -> Result<Option<String>> {
+        match &self.cache {
+            Some(cache) => cache.lock().unwrap().get(url),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn cached_hash(&self, _url: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    #[cfg(feature = "cache")]
+    fn store_hash(&self, url: &str, hash: &str) -> Result<()> {
+        if self.cache.is_some() {
+            self.pending
+                .lock()
+                .unwrap()
+                .push((url.to_owned(), hash.to_owned()));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn store_hash(&self, _url: &str, _hash: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Display for PrefetchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} prefetch(es) in flight, {} queued",
+            self.in_flight.len(),
+            self.queued
+        )?;
+
+        for (url, elapsed) in &self.in_flight {
+            write!(f, "\n  {:.1}s {url}", elapsed.as_secs_f64())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How tarballs are downloaded when they are emitted as `fetchurl` with a flat hash
+#[derive(Debug)]
+enum Downloader {
+    /// With a user supplied command
+    Command(FetchCommand),
+
+    /// In this process, with the shared http client
+    #[cfg(feature = "resolver")]
+    Client(reqwest::blocking::Client),
+}
+
+impl Downloader {
+    /// Download a tarball and calculate its flat sha256 hash
+    fn fetch(&self, url: &str) -> Result<Prefetch> {
+        match self {
+            Self::Command(command) => command.fetch(url),
+            #[cfg(feature = "resolver")]
+            Self::Client(client) => {
+                let mut response = client
+                    .get(url)
+                    .send()
+                    .and_then(|res| res.error_for_status())
+                    .map_err(Error::RegistryRequest)?;
+
+                let size = response.content_length();
+
+                Ok(Prefetch {
+                    hash: crate::package::sha256_sri(&mut response)
+                        .map_err(Error::FetchingFailed)?,
+                    store_path: None,
+                    size,
+                    fetch_lfs: false,
+                })
+            }
+        }
+    }
+
+    /// Download a tarball, returning its contents
+    #[cfg(feature = "cache")]
+    fn download(&self, url: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Command(command) => command.download(url),
+            #[cfg(feature = "resolver")]
+            Self::Client(client) => client
+                .get(url)
+                .send()
+                .and_then(|res| res.error_for_status())
+                .and_then(|res| res.bytes())
+                .map(|bytes| bytes.to_vec())
+                .map_err(Error::RegistryRequest),
+        }
+    }
+}
+
+/// How the hash for a given fetcher is calculated
+struct Job<'a> {
+    url: String,
+    key: String,
+    downloader: Option<&'a Downloader>,
+}
+
+/// The key git lfs usage is remembered under for the prefetch with the given key
+fn lfs_key(key: &str) -> String {
+    format!("lfs:{key}")
+}
+
+impl Job<'_> {
+    /// The fetcher to emit once its hash is known
+    fn finish(self, fetcher: &Fetcher, hash: String, fetch_lfs: bool) -> Fetcher {
+        match self.downloader {
+            Some(_) => Fetcher::FetchUrl {
+                url: self.url,
+                hash,
+            },
+            None => {
+                let mut fetcher = fetcher.clone();
+                fetcher.set_hash(hash);
+                fetcher.set_fetch_lfs(fetch_lfs);
+                fetcher
+            }
+        }
+    }
+}
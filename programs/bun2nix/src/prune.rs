@@ -0,0 +1,55 @@
+//! This module holds the kinds of files which can be pruned from packages when building a
+//! `node_modules` for deployment, to shrink the closure of the application
+
+/// # Prune
+///
+/// A kind of file which packages are published with but which is not needed at runtime, and
+/// can be removed by `fetchBunDeps` when it builds the install cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Prune {
+    /// Readmes, changelogs and other markdown files, along with `doc` and `docs` directories
+    Docs,
+
+    /// Test directories (`test`, `tests`, `__tests__`), along with `*.test.*` and `*.spec.*`
+    /// files
+    Tests,
+
+    /// Source maps
+    Maps,
+}
+
+impl Prune {
+    /// The value of the kind of file in the `prune` argument of `fetchBunDeps`
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Docs => "docs",
+            Self::Tests => "tests",
+            Self::Maps => "maps",
+        }
+    }
+}
+
+/// # Render Prune List
+///
+/// Render the kinds of files to prune as the items of a nix list, each only once
+///
+/// ## Usage
+///```rust
+/// use bun2nix::prune::{Prune, render_prune_list};
+///
+/// let list = render_prune_list(&[Prune::Maps, Prune::Docs, Prune::Maps]);
+///
+/// assert_eq!(list, r#""docs" "maps""#);
+/// assert_eq!(render_prune_list(&[]), "");
+/// ```
+pub fn render_prune_list(prune: &[Prune]) -> String {
+    let mut prune = prune.to_vec();
+    prune.sort();
+    prune.dedup();
+
+    prune
+        .iter()
+        .map(|kind| format!("\"{}\"", kind.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,322 @@
+//! This module holds a minimal client for the npm registry api, used to look up metadata about
+//! packages which is not present in the bun lockfile
+
+#[cfg(feature = "cache")]
+use std::sync::{Arc, Mutex};
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "cache")]
+use crate::cache::Cache;
+use crate::{
+    error::{Error, Result},
+    package::hash_sri,
+    package_json::{Bin, Dependencies, PeerDependencyMeta},
+};
+
+/// The registry used by bun when none is configured
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// Accept header requesting the abbreviated "corgi" metadata format, which is much smaller
+/// than the full document
+const ABBREVIATED_METADATA: &str =
+    "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*";
+
+/// How long to wait for a tarball to respond before treating it as unavailable
+const AVAILABILITY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long idle connections are kept open to be reused by later requests
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How often idle connections are probed to keep them alive
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The http client shared by every request made by this process
+static CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+
+/// # HTTP Client
+///
+/// The client shared by every request this process makes, whether to the registry or for
+/// tarballs, so that connections are pooled and kept alive between requests rather than being
+/// opened (and their TLS handshake made) for each one. Requests to servers supporting HTTP/2
+/// are multiplexed over a single connection.
+pub fn http_client() -> Result<reqwest::blocking::Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client.clone());
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(concat!("bun2nix/", env!("CARGO_PKG_VERSION")))
+        .pool_idle_timeout(IDLE_TIMEOUT)
+        .tcp_keepalive(KEEPALIVE_INTERVAL)
+        .http2_adaptive_window(true)
+        .build()
+        .map_err(Error::RegistryRequest)?;
+
+    Ok(CLIENT.get_or_init(|| client).clone())
+}
+
+/// # Registry Client
+///
+/// A blocking client for an npm compatible package registry
+#[derive(Debug, Clone)]
+pub struct Registry {
+    url: String,
+    client: reqwest::blocking::Client,
+    #[cfg(feature = "cache")]
+    cache: Option<Arc<Mutex<Cache>>>,
+}
+
+impl Registry {
+    /// # New Registry Client
+    ///
+    /// Create a client for the registry at the given base url, sharing the connections of the
+    /// process wide [`http_client`]
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            url: url.trim_end_matches('/').to_owned(),
+            client: http_client()?,
+            #[cfg(feature = "cache")]
+            cache: None,
+        })
+    }
+
+    /// # With Cache
+    ///
+    /// Keep the metadata of package versions fetched from the registry in the given cache,
+    /// compressed, so that it is only ever fetched once. Published versions cannot be changed,
+    /// so their metadata never goes stale.
+    #[cfg(feature = "cache")]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(Arc::new(Mutex::new(cache)));
+        self
+    }
+
+    /// # Registry Url
+    ///
+    /// The base url of the registry, without a trailing slash
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// # Packument Url
+    ///
+    /// The url at which the registry serves the metadata document for a given package
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::registry::{Registry, DEFAULT_REGISTRY};
+    ///
+    /// let registry = Registry::new(DEFAULT_REGISTRY).unwrap();
+    ///
+    /// assert_eq!(
+    ///     registry.packument_url("@types/bun"),
+    ///     "https://registry.npmjs.org/@types%2fbun"
+    /// );
+    /// ```
+    pub fn packument_url(&self, name: &str) -> String {
+        format!("{}/{}", self.url, name.replace('/', "%2f"))
+    }
+
+    /// # Fetch Packument
+    ///
+    /// Fetch the metadata document describing every published version of a package
+    pub fn fetch_packument(&self, name: &str) -> Result<Packument> {
+        self.client
+            .get(self.packument_url(name))
+            .header(reqwest::header::ACCEPT, ABBREVIATED_METADATA)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.json())
+            .map_err(Error::RegistryRequest)
+    }
+
+    /// # Tarball Available
+    ///
+    /// Whether a tarball can currently be downloaded from the given url, which is false if the
+    /// request fails, times out or returns an error status
+    pub fn tarball_available(&self, url: &str) -> bool {
+        self.client
+            .head(url)
+            .timeout(AVAILABILITY_TIMEOUT)
+            .send()
+            .is_ok_and(|res| res.status().is_success())
+    }
+
+    /// # Tarball Hash
+    ///
+    /// Download a tarball and calculate its flat sri hash with the given algorithm, or `None` if
+    /// the algorithm is not supported
+    pub fn tarball_hash(&self, url: &str, algorithm: &str) -> Result<Option<String>> {
+        let mut response = self
+            .client
+            .get(url)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .map_err(Error::RegistryRequest)?;
+
+        hash_sri(algorithm, &mut response).map_err(Error::FetchingFailed)
+    }
+
+    /// # Fetch Version
+    ///
+    /// Fetch the full metadata document for a single published version of a package
+    pub fn fetch_version(&self, name: &str, version: &str) -> Result<VersionMetadata> {
+        let url = format!("{}/{}", self.packument_url(name), version);
+        let key = format!("metadata:{url}");
+
+        if let Some(body) = self.cached_blob(&key)? {
+            return Ok(serde_json::from_slice(&body)?);
+        }
+
+        let body = self
+            .client
+            .get(&url)
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.bytes())
+            .map_err(Error::RegistryRequest)?;
+
+        let metadata = serde_json::from_slice(&body)?;
+        self.store_blob(&key, &body)?;
+
+        Ok(metadata)
+    }
+
+    #[cfg(feature = "cache")]
+    fn cached_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match &self.cache {
+            Some(cache) => cache.lock().unwrap().get_blob(key),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn cached_blob(&self, _key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    #[cfg(feature = "cache")]
+    fn store_blob(&self, key: &str, data: &[u8]) -> Result<()> {
+        match &self.cache {
+            Some(cache) => cache.lock().unwrap().insert_blob(key, data),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn store_blob(&self, _key: &str, _data: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+/// # Packument
+///
+/// The registry's metadata document for a package, listing all of its published versions
+pub struct Packument {
+    /// The name of the package
+    pub name: String,
+
+    /// Tags pointing at specific versions, such as `latest`
+    #[serde(rename = "dist-tags")]
+    pub dist_tags: HashMap<String, String>,
+
+    /// Metadata for each published version
+    pub versions: HashMap<String, VersionMetadata>,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+/// # Version Metadata
+///
+/// The registry's metadata for a single published version of a package
+pub struct VersionMetadata {
+    /// The version string
+    pub version: String,
+
+    /// Dependencies of this version
+    pub dependencies: Dependencies,
+
+    /// Optional dependencies of this version
+    pub optional_dependencies: Dependencies,
+
+    /// Peer dependencies of this version
+    pub peer_dependencies: Dependencies,
+
+    /// Extra information about peer dependencies, such as whether they are optional
+    pub peer_dependencies_meta: HashMap<String, PeerDependencyMeta>,
+
+    /// Information about the published artifact
+    pub dist: Dist,
+
+    /// The deprecation message for this version, if the author has deprecated it
+    #[serde(deserialize_with = "deserialize_deprecation")]
+    pub deprecated: Option<String>,
+
+    /// Whether this version has `preinstall`, `install` or `postinstall` scripts
+    pub has_install_script: bool,
+
+    /// The version ranges of the engines this version runs on, such as `node` or `bun`
+    #[serde(deserialize_with = "deserialize_engines")]
+    pub engines: HashMap<String, String>,
+
+    /// The command line tools this version provides
+    #[serde(deserialize_with = "deserialize_bin")]
+    pub bin: Option<Bin>,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+/// # Dist
+///
+/// Information about the tarball published for a given version
+pub struct Dist {
+    /// The url the tarball can be downloaded from
+    pub tarball: String,
+
+    /// The sri hash of the tarball
+    pub integrity: Option<String>,
+
+    /// The total size of the files in the tarball once unpacked, in bytes
+    pub unpacked_size: Option<u64>,
+}
+
+/// Some registries publish `"deprecated": false` (or an empty string) for versions which are not
+/// deprecated, so anything other than a non-empty message is treated as no deprecation
+/// Some old packages list their engines as an array of strings, which npm ignores
+fn deserialize_engines<'de, D>(
+    deserializer: D,
+) -> std::result::Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Object(engines) => engines
+            .into_iter()
+            .filter_map(|(engine, range)| Some((engine, range.as_str()?.to_owned())))
+            .collect(),
+        _ => HashMap::new(),
+    })
+}
+
+fn deserialize_deprecation<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(message) if !message.is_empty() => Some(message),
+        _ => None,
+    })
+}
+
+/// Some old packages list their tools in a form npm ignores, which is treated as having none
+fn deserialize_bin<'de, D>(deserializer: D) -> std::result::Result<Option<Bin>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(serde_json::from_value(Value::deserialize(deserializer)?).ok())
+}
@@ -0,0 +1,362 @@
+//! This module holds a standalone dependency resolver, which can produce a package set from a
+//! `package.json` and registry metadata alone, for environments where running bun to produce a
+//! lockfile isn't possible
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    convert::Infallible,
+    str::FromStr,
+};
+
+use log::warn;
+use semver::Version;
+
+use crate::{
+    Package, PackageJson,
+    error::{Error, Result},
+    out_path::normalize_path,
+    package::Fetcher,
+    registry::{Packument, Registry, VersionMetadata},
+    workspaces::WorkspaceMember,
+};
+
+mod range;
+pub use range::VersionRange;
+
+/// # Resolver
+///
+/// Resolves the version ranges in a `package.json` (and those of all transitive dependencies)
+/// against registry metadata, choosing the highest version satisfying each range
+#[derive(Debug)]
+pub struct Resolver {
+    registry: Registry,
+    packuments: HashMap<String, Packument>,
+    members: HashMap<String, Option<Version>>,
+    link_workspace_packages: bool,
+}
+
+/// # Package Spec
+///
+/// A package to resolve, as `name@range` where the range defaults to `latest`
+///
+/// ## Usage
+///```rust
+/// use bun2nix::resolver::PackageSpec;
+///
+/// let spec: PackageSpec = "@biomejs/biome@^2".parse().unwrap();
+/// assert_eq!(spec.name, "@biomejs/biome");
+/// assert_eq!(spec.range, "^2");
+///
+/// let spec: PackageSpec = "cowsay".parse().unwrap();
+/// assert_eq!(spec.range, "latest");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageSpec {
+    /// The name of the package
+    pub name: String,
+
+    /// The version range or dist tag to resolve
+    pub range: String,
+}
+
+/// A single dependency edge waiting to be resolved
+struct Request {
+    name: String,
+    spec: String,
+    optional: bool,
+}
+
+impl Resolver {
+    /// # New Resolver
+    ///
+    /// Create a resolver which looks up package metadata from the given registry
+    pub fn new(registry: Registry) -> Self {
+        Self {
+            registry,
+            packuments: HashMap::new(),
+            members: HashMap::new(),
+            link_workspace_packages: true,
+        }
+    }
+
+    /// # With Link Workspace Packages
+    ///
+    /// Whether dependencies on members of the workspace are linked to them when the member's
+    /// version satisfies their range, as with bun's `install.linkWorkspacePackages`. When not,
+    /// only those using a `workspace:` specifier are linked, and the rest are taken from the
+    /// registry.
+    pub fn with_link_workspace_packages(mut self, link_workspace_packages: bool) -> Self {
+        self.link_workspace_packages = link_workspace_packages;
+        self
+    }
+
+    /// # Resolve Package Json
+    ///
+    /// Resolve every dependency of a `package.json` to a concrete version, producing the full
+    /// set of packages which need to be fetched
+    pub fn resolve(&mut self, package_json: &PackageJson) -> Result<Vec<Package>> {
+        self.resolve_requests(Self::requests(package_json).collect(), Vec::new())
+    }
+
+    /// # Resolve Workspaces
+    ///
+    /// Resolve the dependencies of every member of a workspace together. Members depending on
+    /// one another are linked to their path rather than being looked up in the registry, as bun
+    /// does - whether through a `workspace:` specifier or a range the member's version satisfies
+    /// (unless disabled with [`Resolver::with_link_workspace_packages`]), and whether the
+    /// dependency is direct or transitive.
+    pub fn resolve_workspaces(&mut self, members: &[WorkspaceMember]) -> Result<Vec<Package>> {
+        let mut packages = Vec::new();
+
+        for member in members.iter().filter(|member| !member.path.is_empty()) {
+            let Some(name) = &member.package_json.name else {
+                continue;
+            };
+
+            let version = member
+                .package_json
+                .version
+                .as_deref()
+                .and_then(|version| Version::parse(version).ok());
+
+            self.members.insert(name.clone(), version);
+            packages.push(Package::new(
+                name.clone(),
+                Fetcher::CopyToStore {
+                    path: normalize_path(&member.path),
+                },
+            ));
+        }
+
+        let queue = members
+            .iter()
+            .flat_map(|member| Self::requests(&member.package_json))
+            .collect();
+
+        self.resolve_requests(queue, packages)
+    }
+
+    /// Whether a dependency is satisfied by a member of the workspace, which it is linked to
+    fn links_to_member(&self, name: &str, spec: &str) -> bool {
+        let Some(version) = self.members.get(name) else {
+            return false;
+        };
+
+        if spec.starts_with("workspace:") {
+            return true;
+        }
+
+        if !self.link_workspace_packages {
+            return false;
+        }
+
+        match (version, spec.parse::<VersionRange>()) {
+            (Some(version), Ok(range)) => range.matches(version),
+            (None, _) => true,
+            (Some(_), Err(_)) => false,
+        }
+    }
+
+    fn requests(package_json: &PackageJson) -> impl Iterator<Item = Request> + '_ {
+        package_json.all_dependencies().map(|(name, spec)| Request {
+            name: name.to_owned(),
+            spec: spec.to_owned(),
+            optional: package_json.optional_dependencies.contains_key(name),
+        })
+    }
+
+    fn resolve_requests(
+        &mut self,
+        mut queue: VecDeque<Request>,
+        mut packages: Vec<Package>,
+    ) -> Result<Vec<Package>> {
+        let mut seen = HashSet::new();
+
+        while let Some(request) = queue.pop_front() {
+            if self.links_to_member(&request.name, &request.spec) {
+                continue;
+            }
+
+            let metadata = match self.resolve_request(&request.name, &request.spec) {
+                Ok(metadata) => metadata,
+                Err(err) if request.optional => {
+                    warn!(
+                        "Skipping optional dependency `{}@{}` which failed to resolve: {err}",
+                        request.name, request.spec
+                    );
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            let (name, _) = Self::split_alias(&request.name, &request.spec);
+            let id = format!("{}@{}", name, metadata.version);
+
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+
+            queue.extend(Self::dependency_requests(&metadata));
+            packages.push(Self::to_package(id, metadata)?);
+        }
+
+        Ok(packages)
+    }
+
+    /// # Resolve Request
+    ///
+    /// Find the metadata for the version of a package best matching a specifier
+    pub fn resolve_request(&mut self, name: &str, spec: &str) -> Result<VersionMetadata> {
+        let (name, range) = Self::split_alias(name, spec);
+
+        if Self::is_non_registry_spec(range) {
+            return Err(Error::UnsupportedResolverSpecifier(format!(
+                "{name}@{range}"
+            )));
+        }
+
+        let packument = self.packument(name)?;
+        let unresolvable = || Error::UnresolvableDependency(format!("{name}@{range}"));
+
+        let version = match range.parse::<VersionRange>() {
+            Ok(range) => {
+                let versions: Vec<Version> = packument
+                    .versions
+                    .keys()
+                    .filter_map(|version| Version::parse(version).ok())
+                    .collect();
+
+                range
+                    .max_satisfying(versions.iter())
+                    .ok_or_else(unresolvable)?
+                    .to_string()
+            }
+            Err(_) => packument
+                .dist_tags
+                .get(range)
+                .cloned()
+                .ok_or_else(unresolvable)?,
+        };
+
+        packument
+            .versions
+            .get(&version)
+            .cloned()
+            .ok_or_else(unresolvable)
+    }
+
+    fn packument(&mut self, name: &str) -> Result<&Packument> {
+        if !self.packuments.contains_key(name) {
+            let packument = self.registry.fetch_packument(name)?;
+            self.packuments.insert(name.to_owned(), packument);
+        }
+
+        Ok(&self.packuments[name])
+    }
+
+    fn dependency_requests(metadata: &VersionMetadata) -> impl Iterator<Item = Request> + '_ {
+        let required = metadata
+            .dependencies
+            .iter()
+            .map(|(name, spec)| (name, spec, false));
+
+        let optional = metadata
+            .optional_dependencies
+            .iter()
+            .map(|(name, spec)| (name, spec, true));
+
+        let peers = metadata
+            .peer_dependencies
+            .iter()
+            .filter(|(name, _)| {
+                !metadata
+                    .peer_dependencies_meta
+                    .get(*name)
+                    .is_some_and(|meta| meta.optional)
+            })
+            .map(|(name, spec)| (name, spec, false));
+
+        required
+            .chain(optional)
+            .chain(peers)
+            .map(|(name, spec, optional)| Request {
+                name: name.to_owned(),
+                spec: spec.to_owned(),
+                optional,
+            })
+    }
+
+    fn to_package(id: String, metadata: VersionMetadata) -> Result<Package> {
+        let hash = metadata
+            .dist
+            .integrity
+            .ok_or_else(|| Error::MissingIntegrity(id.clone()))?;
+
+        let fetcher = Fetcher::FetchUrl {
+            url: metadata.dist.tarball,
+            hash,
+        };
+
+        Ok(Package::new(id, fetcher))
+    }
+
+    /// # Split Alias
+    ///
+    /// Resolve `npm:` aliased dependencies to the real package name and range
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::resolver::Resolver;
+    ///
+    /// assert_eq!(
+    ///     Resolver::split_alias("my-react", "npm:react@^18"),
+    ///     ("react", "^18")
+    /// );
+    /// assert_eq!(
+    ///     Resolver::split_alias("react", "^18"),
+    ///     ("react", "^18")
+    /// );
+    /// ```
+    pub fn split_alias<'a>(name: &'a str, spec: &'a str) -> (&'a str, &'a str) {
+        let Some(aliased) = spec.strip_prefix("npm:") else {
+            return (name, spec);
+        };
+
+        match aliased.get(1..).and_then(|rest| rest.find('@')) {
+            Some(pos) => (&aliased[..=pos], &aliased[pos + 2..]),
+            None => (aliased, "latest"),
+        }
+    }
+
+    fn is_non_registry_spec(spec: &str) -> bool {
+        const PREFIXES: [&str; 8] = [
+            "git",
+            "github:",
+            "http:",
+            "https:",
+            "file:",
+            "link:",
+            "workspace:",
+            "portal:",
+        ];
+
+        PREFIXES.iter().any(|prefix| spec.starts_with(prefix))
+            || (spec.contains('/') && !spec.starts_with('@'))
+    }
+}
+
+impl FromStr for PackageSpec {
+    type Err = Infallible;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, range) = match spec.get(1..).and_then(|rest| rest.find('@')) {
+            Some(pos) => (&spec[..=pos], &spec[pos + 2..]),
+            None => (spec, "latest"),
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            range: range.to_owned(),
+        })
+    }
+}
@@ -0,0 +1,307 @@
+use std::str::FromStr;
+
+use semver::{Prerelease, Version};
+
+use crate::error::{Error, Result};
+
+/// # Version Range
+///
+/// An npm style version range, as found in the dependency fields of a `package.json`.
+///
+/// Supports the full npm range grammar: `||` separated sets of comparators, caret and tilde
+/// ranges, x-ranges (`1.x`, `*`) and hyphen ranges (`1.2.3 - 2`).
+///
+/// ## Usage
+///```rust
+/// use bun2nix::resolver::VersionRange;
+/// use semver::Version;
+///
+/// let range: VersionRange = "^1.2.3 || >=3.0.0 <3.1".parse().unwrap();
+///
+/// assert!(range.matches(&Version::parse("1.9.0").unwrap()));
+/// assert!(range.matches(&Version::parse("3.0.4").unwrap()));
+/// assert!(!range.matches(&Version::parse("2.0.0").unwrap()));
+/// assert!(!range.matches(&Version::parse("1.3.0-beta.1").unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRange {
+    sets: Vec<Vec<Comparator>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+/// A version where any of the trailing components may be missing or wildcards
+#[derive(Debug, Default)]
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Prerelease,
+}
+
+impl VersionRange {
+    /// # Matches
+    ///
+    /// Check if a given version satisfies this range.
+    ///
+    /// As with npm, prerelease versions only match if a comparator in the same set explicitly
+    /// mentions a prerelease of the same `major.minor.patch`.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.sets.iter().any(|set| {
+            set.iter().all(|comparator| comparator.matches(version))
+                && (version.pre.is_empty()
+                    || set.iter().any(|comparator| {
+                        !comparator.version.pre.is_empty()
+                            && comparator.version.major == version.major
+                            && comparator.version.minor == version.minor
+                            && comparator.version.patch == version.patch
+                    }))
+        })
+    }
+
+    /// # Max Satisfying
+    ///
+    /// Pick the highest of the given versions which satisfies this range
+    pub fn max_satisfying<'a>(
+        &self,
+        versions: impl Iterator<Item = &'a Version>,
+    ) -> Option<&'a Version> {
+        versions.filter(|version| self.matches(version)).max()
+    }
+
+    fn parse_set(set: &str) -> Result<Vec<Comparator>> {
+        if let Some((from, to)) = set.split_once(" - ") {
+            let from = Partial::parse(from.trim())?;
+            let to = Partial::parse(to.trim())?;
+
+            let mut comparators = Vec::new();
+            comparators.extend(from.lower_bound(Op::Gte));
+            comparators.extend(to.upper_bound(Op::Lte));
+
+            return Ok(comparators);
+        }
+
+        let mut tokens = Vec::new();
+        let mut pending_op = String::new();
+
+        for token in set.split_whitespace() {
+            if token
+                .chars()
+                .all(|c| matches!(c, '<' | '>' | '=' | '^' | '~'))
+            {
+                pending_op.push_str(token);
+            } else {
+                tokens.push(format!("{}{}", std::mem::take(&mut pending_op), token));
+            }
+        }
+
+        if !pending_op.is_empty() {
+            return Err(Error::InvalidVersionRange(set.to_owned()));
+        }
+
+        let mut comparators = Vec::new();
+        for token in tokens {
+            comparators.extend(Self::parse_comparator(&token)?);
+        }
+
+        Ok(comparators)
+    }
+
+    fn parse_comparator(token: &str) -> Result<Vec<Comparator>> {
+        let (op, rest) = ["~>", ">=", "<=", ">", "<", "=", "^", "~"]
+            .into_iter()
+            .find_map(|op| Some((op, token.strip_prefix(op)?)))
+            .unwrap_or(("", token));
+
+        let partial = Partial::parse(rest)?;
+
+        Ok(match op {
+            "^" => partial.caret(),
+            "~" | "~>" => partial.tilde(),
+            ">=" => partial.lower_bound(Op::Gte),
+            ">" => partial.lower_bound(Op::Gt),
+            "<=" => partial.upper_bound(Op::Lte),
+            "<" => partial.upper_bound(Op::Lt),
+            _ => partial.exact(),
+        })
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = Error;
+
+    fn from_str(range: &str) -> std::result::Result<Self, Self::Err> {
+        let sets = range
+            .split("||")
+            .map(|set| Self::parse_set(set.trim()))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { sets })
+    }
+}
+
+impl Comparator {
+    fn new(op: Op, major: u64, minor: u64, patch: u64, pre: Prerelease) -> Self {
+        let mut version = Version::new(major, minor, patch);
+        version.pre = pre;
+
+        Self { op, version }
+    }
+
+    /// A comparator which excludes all prereleases of the given version
+    fn below(major: u64, minor: u64, patch: u64) -> Self {
+        Self::new(Op::Lt, major, minor, patch, Prerelease::new("0").unwrap())
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let ordering = cmp_precedence(version, &self.version);
+
+        match self.op {
+            Op::Eq => ordering.is_eq(),
+            Op::Gt => ordering.is_gt(),
+            Op::Gte => ordering.is_ge(),
+            Op::Lt => ordering.is_lt(),
+            Op::Lte => ordering.is_le(),
+        }
+    }
+}
+
+/// Compare two versions ignoring build metadata, as the semver spec requires
+fn cmp_precedence(a: &Version, b: &Version) -> std::cmp::Ordering {
+    (a.major, a.minor, a.patch, &a.pre).cmp(&(b.major, b.minor, b.patch, &b.pre))
+}
+
+impl Partial {
+    fn parse(input: &str) -> Result<Self> {
+        let invalid = || Error::InvalidVersionRange(input.to_owned());
+
+        let input = input.trim_start_matches(['v', '=']);
+        if input.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let input = input.split_once('+').map_or(input, |(version, _)| version);
+        let (numbers, pre) = match input.split_once('-') {
+            Some((numbers, pre)) => (numbers, Prerelease::new(pre).map_err(|_| invalid())?),
+            None => (input, Prerelease::EMPTY),
+        };
+
+        let mut parts = numbers.split('.').map(|part| match part {
+            "x" | "X" | "*" => Ok(None),
+            number => number.parse().map(Some).map_err(|_| invalid()),
+        });
+
+        let major = parts.next().transpose()?.flatten();
+        let minor = parts.next().transpose()?.flatten();
+        let patch = parts.next().transpose()?.flatten();
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            major,
+            minor: major.and(minor),
+            patch: major.and(minor).and(patch),
+            pre,
+        })
+    }
+
+    fn exact(self) -> Vec<Comparator> {
+        match (self.major, self.minor, self.patch) {
+            (Some(major), Some(minor), Some(patch)) => {
+                vec![Comparator::new(Op::Eq, major, minor, patch, self.pre)]
+            }
+            _ => self.tilde(),
+        }
+    }
+
+    fn lower_bound(self, op: Op) -> Vec<Comparator> {
+        match (self.major, self.minor, self.patch, op) {
+            (None, ..) => vec![],
+            (Some(major), Some(minor), Some(patch), op) => {
+                vec![Comparator::new(op, major, minor, patch, self.pre)]
+            }
+            (Some(major), Some(minor), None, Op::Gt) => {
+                vec![Comparator::new(
+                    Op::Gte,
+                    major,
+                    minor + 1,
+                    0,
+                    Prerelease::EMPTY,
+                )]
+            }
+            (Some(major), None, _, Op::Gt) => {
+                vec![Comparator::new(Op::Gte, major + 1, 0, 0, Prerelease::EMPTY)]
+            }
+            (Some(major), minor, _, _) => vec![Comparator::new(
+                Op::Gte,
+                major,
+                minor.unwrap_or(0),
+                0,
+                Prerelease::EMPTY,
+            )],
+        }
+    }
+
+    fn upper_bound(self, op: Op) -> Vec<Comparator> {
+        match (self.major, self.minor, self.patch, op) {
+            (None, ..) if op == Op::Lt => vec![Comparator::below(0, 0, 0)],
+            (None, ..) => vec![],
+            (Some(major), Some(minor), Some(patch), op) => {
+                vec![Comparator::new(op, major, minor, patch, self.pre)]
+            }
+            (Some(major), Some(minor), None, Op::Lte) => {
+                vec![Comparator::below(major, minor + 1, 0)]
+            }
+            (Some(major), None, _, Op::Lte) => vec![Comparator::below(major + 1, 0, 0)],
+            (Some(major), minor, _, _) => vec![Comparator::below(major, minor.unwrap_or(0), 0)],
+        }
+    }
+
+    fn tilde(self) -> Vec<Comparator> {
+        let Some(major) = self.major else {
+            return vec![];
+        };
+
+        let upper = match self.minor {
+            Some(minor) => Comparator::below(major, minor + 1, 0),
+            None => Comparator::below(major + 1, 0, 0),
+        };
+
+        let mut comparators = self.lower_bound(Op::Gte);
+        comparators.push(upper);
+
+        comparators
+    }
+
+    fn caret(self) -> Vec<Comparator> {
+        let Some(major) = self.major else {
+            return vec![];
+        };
+
+        let upper = match (major, self.minor, self.patch) {
+            (0, Some(0), Some(patch)) => Comparator::below(0, 0, patch + 1),
+            (0, Some(minor), _) => Comparator::below(0, minor + 1, 0),
+            (major, _, _) => Comparator::below(major + 1, 0, 0),
+        };
+
+        let mut comparators = self.lower_bound(Op::Gte);
+        comparators.push(upper);
+
+        comparators
+    }
+}
@@ -0,0 +1,99 @@
+//! Handling of the signals sent to a run, so that it finishes early and cleans up after itself
+//! rather than aborting part way through writing its output, and can report what it is doing
+//! when it seems stuck
+
+use std::{
+    io::{self, Write},
+    process,
+    sync::{
+        Arc, Mutex, OnceLock, Weak,
+        atomic::{AtomicI32, Ordering},
+    },
+    thread,
+    time::Instant,
+};
+
+use bun2nix::Prefetcher;
+use log::warn;
+use signal_hook::{
+    consts::{SIGINT, SIGTERM, SIGUSR1},
+    iterator::Signals,
+};
+
+/// The signal which interrupted the run, if any
+static SIGNAL: AtomicI32 = AtomicI32::new(SIGINT);
+
+/// When the signal handlers were installed, which is close enough to the start of the run
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// The prefetcher whose status is reported on `SIGUSR1`, held weakly so that it is still
+/// dropped when the run is done with it
+static PREFETCHER: Mutex<Weak<Prefetcher>> = Mutex::new(Weak::new());
+
+/// # Install Signal Handlers
+///
+/// Interrupt the run on the first `SIGINT` or `SIGTERM`, cancelling prefetches which are still
+/// running, and exit immediately on the second. On `SIGUSR1`, print the status of the run to
+/// stderr.
+pub fn install() -> io::Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGUSR1])?;
+
+    STARTED.get_or_init(Instant::now);
+
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            match signal {
+                SIGUSR1 => dump_status(),
+                _ if bun2nix::interrupt::interrupted() => process::exit(exit_code(signal)),
+                _ => {
+                    SIGNAL.store(signal, Ordering::Relaxed);
+                    bun2nix::interrupt::interrupt();
+
+                    warn!(
+                        "Interrupted, cancelling prefetches (interrupt again to stop immediately)"
+                    );
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// # Watch Prefetcher
+///
+/// Report the status of the given prefetcher on `SIGUSR1`
+pub fn watch(prefetcher: &Arc<Prefetcher>) {
+    *PREFETCHER.lock().unwrap() = Arc::downgrade(prefetcher);
+}
+
+/// # Interrupted Exit Code
+///
+/// The status to exit with after being interrupted, which follows the shell convention of
+/// 128 plus the number of the signal
+pub fn interrupted_exit_code() -> i32 {
+    exit_code(SIGNAL.load(Ordering::Relaxed))
+}
+
+fn exit_code(signal: i32) -> i32 {
+    128 + signal
+}
+
+/// Print how long the run has taken and what the prefetcher is doing, straight to stderr so
+/// that it is shown whatever the log level
+fn dump_status() {
+    let elapsed = STARTED.get().map(Instant::elapsed).unwrap_or_default();
+    let status = PREFETCHER.lock().unwrap().upgrade().map(|p| p.status());
+
+    let mut stderr = io::stderr().lock();
+    let _ = write!(
+        stderr,
+        "bun2nix status after {:.1}s: ",
+        elapsed.as_secs_f64()
+    );
+
+    let _ = match status {
+        Some(status) => writeln!(stderr, "{status}"),
+        None => writeln!(stderr, "not prefetching"),
+    };
+}
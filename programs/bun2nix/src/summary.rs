@@ -0,0 +1,287 @@
+//! This module holds the summary printed at the end of a run, giving an at a glance view of
+//! what was generated and how
+
+use std::fmt::{self, Display};
+
+use crate::{
+    binaries::{Collision, Resolution},
+    diff::PackageDiff,
+    error::{Error, Result},
+    prefetcher::PrefetchStats,
+};
+
+/// Units accepted by [`parse_size`], with their size in bytes
+const SIZE_UNITS: [(&str, u64); 14] = [
+    ("", 1),
+    ("b", 1),
+    ("k", 1 << 10),
+    ("kb", 1 << 10),
+    ("kib", 1 << 10),
+    ("m", 1 << 20),
+    ("mb", 1 << 20),
+    ("mib", 1 << 20),
+    ("g", 1 << 30),
+    ("gb", 1 << 30),
+    ("gib", 1 << 30),
+    ("t", 1 << 40),
+    ("tb", 1 << 40),
+    ("tib", 1 << 40),
+];
+
+/// # Run Summary
+///
+/// What a run of `bun2nix` generated, where the hashes it needed came from, and how the output
+/// differs from the previous one
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{
+///     binaries::{Provider, Resolution},
+///     diff::PackageDiff,
+///     prefetcher::PrefetchStats,
+///     summary::Summary,
+/// };
+///
+/// let summary = Summary {
+///     packages: 120,
+///     prefetch: PrefetchStats {
+///         fetched: 2,
+///         cached: 3,
+///         fetched_bytes: 1536,
+///         from_bun_cache: 0,
+///     },
+///     diff: Some(PackageDiff::default()),
+///     binary_collisions: vec![Resolution {
+///         name: "tsc".to_owned(),
+///         winner: Provider {
+///             package: "typescript".to_owned(),
+///             path: "typescript/bin/tsc".to_owned(),
+///         },
+///         shadowed: vec!["tsc".to_owned()],
+///     }],
+///     unsettled_binaries: Vec::new(),
+///     pruned: vec!["left-pad@1.3.0".to_owned()],
+///     warnings: 1,
+/// };
+///
+/// assert_eq!(
+///     summary.to_string().lines().collect::<Vec<_>>(),
+///     [
+///         "Generated 120 packages",
+///         "  2 fetched (1.5 KiB), 3 from cache",
+///         "  0 added, 0 removed, 0 changed since the previous output",
+///         "  1 binary collision:",
+///         "    `tsc` is linked from `typescript`, shadowing `tsc`",
+///         "  1 unreachable package pruned:",
+///         "    left-pad@1.3.0",
+///         "  1 warning",
+///     ]
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// The number of packages in the output
+    pub packages: usize,
+
+    /// Where the hashes which had to be prefetched came from
+    pub prefetch: PrefetchStats,
+
+    /// The differences from the previous output, if there was one
+    pub diff: Option<PackageDiff>,
+
+    /// The binaries provided by more than one package, and which package each is linked from
+    pub binary_collisions: Vec<Resolution>,
+
+    /// The binaries provided by more than one package which were left for bun to link, as no
+    /// strategy to settle them was given
+    pub unsettled_binaries: Vec<Collision>,
+
+    /// The packages left out of the output as no selected workspace installs them
+    pub pruned: Vec<String>,
+
+    /// The number of warnings logged during the run
+    pub warnings: usize,
+}
+
+/// ANSI escape codes for the colors used in the summary
+const BOLD_GREEN: &str = "1;32";
+const GREEN: &str = "32";
+const RED: &str = "31";
+const YELLOW: &str = "33";
+
+impl Summary {
+    /// # Render Summary
+    ///
+    /// Render the summary, colored with ANSI escape codes if requested
+    pub fn render(&self, color: bool) -> String {
+        let paint = |text: String, code: &str| match color {
+            true => format!("\x1b[{code}m{text}\x1b[0m"),
+            false => text,
+        };
+
+        let mut lines = vec![paint(
+            format!("Generated {}", plural(self.packages, "package")),
+            BOLD_GREEN,
+        )];
+
+        let PrefetchStats {
+            fetched,
+            cached,
+            fetched_bytes,
+            from_bun_cache,
+        } = self.prefetch;
+
+        if fetched + cached + from_bun_cache > 0 {
+            let size = match fetched_bytes {
+                0 => String::new(),
+                bytes => format!(" ({})", format_size(bytes)),
+            };
+
+            let local = match from_bun_cache {
+                0 => String::new(),
+                count => format!(", {count} from bun's install cache"),
+            };
+
+            lines.push(format!(
+                "  {fetched} fetched{size}, {cached} from cache{local}"
+            ));
+        }
+
+        if let Some(diff) = &self.diff {
+            lines.push(format!(
+                "  {}, {}, {} since the previous output",
+                paint(format!("{} added", diff.added.len()), GREEN),
+                paint(format!("{} removed", diff.removed.len()), RED),
+                paint(format!("{} changed", diff.changed.len()), YELLOW),
+            ));
+        }
+
+        if !self.binary_collisions.is_empty() {
+            lines.push(format!(
+                "  {}:",
+                paint(
+                    plural(self.binary_collisions.len(), "binary collision"),
+                    YELLOW
+                )
+            ));
+
+            lines.extend(
+                self.binary_collisions
+                    .iter()
+                    .map(|collision| format!("    {collision}")),
+            );
+        }
+
+        if !self.unsettled_binaries.is_empty() {
+            lines.push(format!(
+                "  {} left to bun:",
+                paint(
+                    plural(self.unsettled_binaries.len(), "binary collision"),
+                    YELLOW
+                )
+            ));
+
+            lines.extend(
+                self.unsettled_binaries
+                    .iter()
+                    .map(|collision| format!("    {collision}")),
+            );
+        }
+
+        if !self.pruned.is_empty() {
+            lines.push(format!(
+                "  {} pruned:",
+                plural(self.pruned.len(), "unreachable package")
+            ));
+
+            lines.extend(self.pruned.iter().map(|name| format!("    {name}")));
+        }
+
+        if self.warnings > 0 {
+            lines.push(format!(
+                "  {}",
+                paint(plural(self.warnings, "warning"), YELLOW)
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("{count} {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
+
+/// # Format Size
+///
+/// Format a number of bytes as a human readable size
+///
+/// ## Usage
+///```rust
+/// use bun2nix::summary::format_size;
+///
+/// assert_eq!(format_size(512), "512 B");
+/// assert_eq!(format_size(1536), "1.5 KiB");
+/// assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+/// ```
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// # Parse Size
+///
+/// Parse a human readable size into a number of bytes, where units are powers of 1024
+///
+/// ## Usage
+///```rust
+/// use bun2nix::summary::parse_size;
+///
+/// assert_eq!(parse_size("512").unwrap(), 512);
+/// assert_eq!(parse_size("500M").unwrap(), 500 * 1024 * 1024);
+/// assert_eq!(parse_size("2 GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+/// assert!(parse_size("lots").is_err());
+/// ```
+pub fn parse_size(size: &str) -> Result<u64> {
+    let invalid = || Error::InvalidSize(size.to_owned());
+
+    let trimmed = size.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let unit = unit.trim().to_ascii_lowercase();
+    let (_, multiplier) = SIZE_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .ok_or_else(invalid)?;
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(*multiplier))
+        .ok_or_else(invalid)
+}
@@ -0,0 +1,95 @@
+//! This module holds the rendering of the output with a custom Jinja template, for projects
+//! which need a different shape of expression than `bun.nix`
+
+use minijinja::{Environment, UndefinedBehavior, Value};
+use serde_json::json;
+
+use crate::{
+    Package,
+    error::{Error, Result},
+    nix_expression::{Project, Variables, ast::Expr},
+};
+
+/// # Template Context
+///
+/// The context templates are rendered with:
+///
+/// - `packages`: a list of packages, each with its `name`, the `kind` of its source (`npm`,
+///   `git`, `github`, `tarball` or `path`), its `url` and `hash` where it has them, and the
+///   `nix` expression fetching it
+/// - `project`: the `name` and `version` of the project, if it is known
+/// - `variables`: the values given with `--set`
+/// - `terse`: whether comments are left out
+pub fn context(
+    packages: &[Package],
+    project: Option<&Project>,
+    variables: &Variables,
+    terse: bool,
+) -> Value {
+    let packages: Vec<_> = packages
+        .iter()
+        .map(|pkg| {
+            json!({
+                "name": pkg.name,
+                "kind": pkg.fetcher.source_kind(),
+                "url": pkg.fetcher.url(),
+                "hash": pkg.fetcher.sri_hash(),
+                "nix": Expr::from(&pkg.fetcher).to_string(),
+            })
+        })
+        .collect();
+
+    Value::from_serialize(json!({
+        "packages": packages,
+        "project": project.map(|project| json!({
+            "name": project.name,
+            "version": project.version,
+        })),
+        "variables": variables,
+        "terse": terse,
+    }))
+}
+
+/// # Render Template
+///
+/// Render a template with a context, failing on the first variable which is not in it or
+/// filter which does not exist. See [`crate::template_check`] to find every one of them.
+pub fn render(name: &str, source: &str, context: &Value) -> Result<String> {
+    let fail = |err: minijinja::Error| {
+        let line = err.line().map(|line| format!(":{line}")).unwrap_or_default();
+
+        Error::TemplateProblems(vec![format!("{name}{line}: {}", describe(&err))])
+    };
+
+    let mut env = environment();
+    env.add_template(name, source).map_err(fail)?;
+
+    env.get_template(name)
+        .and_then(|template| template.render(context))
+        .map_err(fail)
+}
+
+/// The environment templates are rendered in, where a variable which is not in the context is
+/// an error rather than empty
+pub(crate) fn environment<'source>() -> Environment<'source> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_keep_trailing_newline(true);
+
+    env
+}
+
+/// A description of a template error, without the location `minijinja` appends
+pub(crate) fn describe(err: &minijinja::Error) -> String {
+    let Some(detail) = err.detail() else {
+        return err.kind().to_string();
+    };
+
+    match detail
+        .strip_suffix(" is unknown")
+        .and_then(|unknown| unknown.split_once(' '))
+    {
+        Some((kind, name)) => format!("unknown {kind} `{name}`"),
+        None => format!("{}: {detail}", err.kind()),
+    }
+}
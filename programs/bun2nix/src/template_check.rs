@@ -0,0 +1,281 @@
+//! This module holds the checking of custom Jinja templates of the output against a fixture
+//! package set, so that a missing variable or filter is found before a real lockfile is converted
+
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+};
+
+use minijinja::{ErrorKind, UndefinedBehavior, Value, value::Rest};
+
+use crate::{
+    Package,
+    nix_expression::{Project, Variables},
+    package::Fetcher,
+    template::{context, describe, environment},
+};
+
+/// The most unknown filters and tests stubbed out before giving up on rendering a template
+const MAX_STUBS: usize = 64;
+
+/// # Template Check
+///
+/// The problems found rendering a template against the fixture context, and what it rendered
+/// to if it got that far
+///
+/// ## Usage
+///```rust
+/// use bun2nix::template_check::TemplateCheck;
+///
+/// let check = TemplateCheck::run(
+///     "my.nix.j2",
+///     "{\n{%- for pkg in packages %}\n  \"{{ pkg.name }}\" = {{ pkg.nix }};\n{%- endfor %}\n}\n",
+/// );
+///
+/// assert!(check.problems.is_empty());
+/// assert!(check.rendered.unwrap().contains(r#""is-odd@3.0.1" = fetchurl {"#));
+///
+/// let check = TemplateCheck::run(
+///     "my.nix.j2",
+///     "# {{ project.name | shout }}\n{{ banner }}\n{{ packages | to_nix(2) }}\n",
+/// );
+///
+/// assert_eq!(
+///     check.problems.iter().map(ToString::to_string).collect::<Vec<_>>(),
+///     [
+///         "my.nix.j2:1: unknown filter `shout`",
+///         "my.nix.j2:2: undefined variable `banner`",
+///         "my.nix.j2:3: unknown filter `to_nix`",
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateCheck {
+    /// Everything wrong with the template, in the order of the lines they are on
+    pub problems: Vec<Problem>,
+
+    /// The output of the template, if it could be rendered once unknown filters and tests were
+    /// stubbed out
+    pub rendered: Option<String>,
+}
+
+/// # Template Problem
+///
+/// A single mistake in a template, such as a variable which is not in the context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The name of the template
+    pub template: String,
+
+    /// The line of the template the mistake is on, if it is known
+    pub line: Option<usize>,
+
+    /// What is wrong
+    pub message: String,
+}
+
+impl TemplateCheck {
+    /// # Run Template Check
+    ///
+    /// Compile a template and render it against the fixture context (see [`fixture`]),
+    /// reporting syntax errors, variables which are not in the context, and unknown filters or
+    /// tests with the lines they are on.
+    ///
+    /// Unknown filters and tests are stubbed out and rendering is tried again, so that every
+    /// one of them is reported rather than only the first.
+    pub fn run(name: &str, source: &str) -> Self {
+        let mut check = Self::default();
+        let problem = |line, message| Problem {
+            template: name.to_owned(),
+            line,
+            message,
+        };
+
+        let mut env = environment();
+
+        if let Err(err) = env.add_template(name, source) {
+            check.problems.push(problem(err.line(), describe(&err)));
+            return check;
+        }
+
+        let context = fixture();
+        let globals: BTreeSet<String> = env.globals().map(|(name, _)| name.to_owned()).collect();
+
+        let mut missing: Vec<(Option<usize>, String)> = env
+            .get_template(name)
+            .map(|template| template.undeclared_variables(false))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|variable| {
+                context.get_attr(variable).is_ok_and(|value| value.is_undefined())
+                    && !globals.contains(variable)
+            })
+            .map(|variable| (first_use(source, &variable), variable))
+            .collect();
+
+        missing.sort();
+
+        for (line, variable) in &missing {
+            check
+                .problems
+                .push(problem(*line, format!("undefined variable `{variable}`")));
+        }
+
+        // Keep rendering past the missing variables to find any unknown filters after them
+        if !missing.is_empty() {
+            env.set_undefined_behavior(UndefinedBehavior::Chainable);
+        }
+
+        for _ in 0..MAX_STUBS {
+            let rendered = env
+                .get_template(name)
+                .and_then(|template| template.render(&context));
+
+            let err = match rendered {
+                Ok(rendered) => {
+                    check.rendered = Some(rendered);
+                    break;
+                }
+                Err(err) => err,
+            };
+
+            check.problems.push(problem(err.line(), describe(&err)));
+
+            let unknown = err.detail().and_then(|detail| {
+                detail
+                    .strip_prefix("filter ")
+                    .or_else(|| detail.strip_prefix("test "))?
+                    .strip_suffix(" is unknown")
+            });
+
+            match (err.kind(), unknown) {
+                (ErrorKind::UnknownFilter, Some(filter)) => {
+                    env.add_filter(filter.to_owned(), |value: Value, _: Rest<Value>| value)
+                }
+                (ErrorKind::UnknownTest, Some(test)) => {
+                    env.add_test(test.to_owned(), |_: Value, _: Rest<Value>| false)
+                }
+                _ => break,
+            }
+        }
+
+        check.problems.sort_by_key(|problem| problem.line);
+
+        check
+    }
+
+    /// # Is Ok
+    ///
+    /// Whether the template rendered without any problems
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// # Fixture Context
+///
+/// The context templates are checked against (see [`crate::template::context`]), with a
+/// package of every kind, a project and a `banner` variable
+pub fn fixture() -> Value {
+    let project = Project {
+        name: "my-app".to_owned(),
+        version: Some("1.0.0".to_owned()),
+    };
+    let variables = Variables::from([("banner".to_owned(), "Generated for my-app".to_owned())]);
+
+    context(&fixture_packages(), Some(&project), &variables, false)
+}
+
+/// A package set with a package from every kind of source
+fn fixture_packages() -> Vec<Package> {
+    let hash = "sha512-CQpnWPrDwmP1+SMHXZhtLtJv90yiyVfluGsX5iNCVkrhQtU3TQHsUWPG9wkdk9Lgd5yNpAg9jQEo90CBaXgWMA==";
+
+    vec![
+        Package::new(
+            "is-odd@3.0.1".to_owned(),
+            Fetcher::FetchUrl {
+                url: "https://registry.npmjs.org/is-odd/-/is-odd-3.0.1.tgz".to_owned(),
+                hash: hash.to_owned(),
+            },
+        ),
+        Package::new(
+            "git:45bf6a3".to_owned(),
+            Fetcher::FetchGit {
+                url: "https://github.com/iamkun/dayjs.git".to_owned(),
+                rev: "45bf6a3".to_owned(),
+                hash: "sha256-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_owned(),
+                fetch_lfs: false,
+            },
+        ),
+        Package::new(
+            "github:colinhacks-zod-5bfc8f2".to_owned(),
+            Fetcher::FetchGitHub {
+                owner: "colinhacks".to_owned(),
+                repo: "zod".to_owned(),
+                rev: "5bfc8f2".to_owned(),
+                hash: "sha256-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_owned(),
+            },
+        ),
+        Package::new(
+            "tarball:https://example.com/my-tarball.tgz".to_owned(),
+            Fetcher::FetchTarball {
+                url: "https://example.com/my-tarball.tgz".to_owned(),
+                hash: "sha256-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_owned(),
+            },
+        ),
+        Package::new(
+            "lib".to_owned(),
+            Fetcher::CopyToStore {
+                path: "packages/lib".to_owned(),
+            },
+        ),
+    ]
+}
+
+/// The line of the first tag or expression of a template which refers to a variable
+fn first_use(source: &str, variable: &str) -> Option<usize> {
+    let is_ident = |char: char| char.is_ascii_alphanumeric() || char == '_';
+
+    let mut offset = 0;
+
+    while let Some(start) = source[offset..].find('{').map(|start| offset + start) {
+        let close = match source[start..].get(1..2) {
+            Some("{") => "}}",
+            Some("%") => "%}",
+            _ => {
+                offset = start + 1;
+                continue;
+            }
+        };
+
+        let end = source[start..]
+            .find(close)
+            .map_or(source.len(), |end| start + end);
+        let tag = &source[start + 2..end];
+
+        let used = tag.match_indices(variable).any(|(at, _)| {
+            let before = tag[..at].chars().next_back();
+            let after = tag[at + variable.len()..].chars().next();
+
+            !before.is_some_and(|char| is_ident(char) || char == '.')
+                && !after.is_some_and(is_ident)
+        });
+
+        if used {
+            return Some(source[..start].matches('\n').count() + 1);
+        }
+
+        offset = end;
+    }
+
+    None
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.template, self.message),
+            None => write!(f, "{}: {}", self.template, self.message),
+        }
+    }
+}
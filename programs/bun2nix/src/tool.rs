@@ -0,0 +1,121 @@
+//! This module holds packaging a single package from the registry as a standalone tool, for
+//! running one-off command line tools without a project or lockfile
+
+use std::collections::BTreeMap;
+
+use askama::Template;
+
+use crate::{
+    Package, PackageJson,
+    error::{Error, Result},
+    nix_expression::NixExpression,
+    oci_image::image_name,
+    registry::VersionMetadata,
+    resolver::{PackageSpec, Resolver},
+};
+
+/// # Tool
+///
+/// A `tool.nix` building a package from the registry along with its dependencies, with a
+/// wrapper running each tool in its `bin` with bun. The package set is written inline, so the
+/// file is all that is needed to build it.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::{Package, package::Fetcher, registry::VersionMetadata, tool::Tool};
+///
+/// let metadata: VersionMetadata = serde_json::from_str(r#"
+/// {
+///   "version": "1.6.0",
+///   "bin": { "cowsay": "cli.js", "cowthink": "cli.js" },
+///   "dist": {
+///     "tarball": "https://registry.npmjs.org/cowsay/-/cowsay-1.6.0.tgz",
+///     "integrity": "sha512-abc"
+///   }
+/// }
+/// "#)
+/// .unwrap();
+///
+/// let packages = vec![Package::new(
+///     "cowsay@1.6.0".to_owned(),
+///     Fetcher::FetchUrl {
+///         url: metadata.dist.tarball.clone(),
+///         hash: "sha512-abc".to_owned(),
+///     },
+/// )];
+///
+/// let nix = Tool::new("cowsay", &metadata, packages).unwrap().render().unwrap();
+///
+/// assert!(nix.contains(r#"dependencies."cowsay" = "1.6.0";"#));
+/// assert!(nix.contains(r#""cowsay@1.6.0" = fetchurl {"#));
+/// assert!(nix.contains(r#"makeWrapper ${lib.getExe bun} "$out/bin/cowthink""#));
+/// assert!(nix.contains(r#"mainProgram = "cowsay";"#));
+/// ```
+#[derive(Template)]
+#[template(path = "tool.nix_template")]
+pub struct Tool {
+    name: String,
+    pname: String,
+    version: String,
+    binaries: BTreeMap<String, String>,
+    main_program: String,
+    bun_nix: String,
+}
+
+impl Tool {
+    /// # New Tool
+    ///
+    /// Package a version of a package, described by its registry metadata, with the given set
+    /// of packages it is installed with
+    pub fn new(name: &str, metadata: &VersionMetadata, packages: Vec<Package>) -> Result<Self> {
+        let binaries = metadata
+            .bin
+            .as_ref()
+            .map(|bin| bin.binaries(name))
+            .unwrap_or_default();
+
+        let unscoped = name.rsplit('/').next().unwrap_or(name);
+        let main_program = binaries
+            .keys()
+            .find(|bin| *bin == unscoped)
+            .or_else(|| binaries.keys().next())
+            .cloned()
+            .ok_or_else(|| Error::NoBinaries(format!("{name}@{}", metadata.version)))?;
+
+        let bun_nix = NixExpression::new(packages)?.with_terse(true).render()?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            pname: image_name(name),
+            version: metadata.version.clone(),
+            binaries,
+            main_program,
+            bun_nix: bun_nix.replace('\n', "\n      "),
+        })
+    }
+
+    /// # Resolve Tool
+    ///
+    /// Resolve the version of a package best matching a spec, along with its dependencies,
+    /// and package it
+    pub fn resolve(spec: &PackageSpec, resolver: &mut Resolver) -> Result<Self> {
+        let metadata = resolver.resolve_request(&spec.name, &spec.range)?;
+
+        let package_json = PackageJson {
+            dependencies: [(spec.name.clone(), metadata.version.clone())].into(),
+            ..PackageJson::default()
+        };
+
+        Self::new(&spec.name, &metadata, resolver.resolve(&package_json)?)
+    }
+
+    /// # Render
+    ///
+    /// Render the tool as a nix expression
+    pub fn render(&self) -> Result<String> {
+        let mut nix = Template::render(self)?;
+        nix.push('\n');
+
+        Ok(nix)
+    }
+}
@@ -0,0 +1,87 @@
+//! This module holds the packages whose lifecycle scripts bun runs on install, which is those on
+//! its built in allowlist unless the project lists its own `trustedDependencies`
+
+use std::collections::BTreeSet;
+
+use crate::Package;
+
+/// The packages bun trusts to run lifecycle scripts by default, one per line, mirroring bun's
+/// `src/install/default-trusted-dependencies.txt`
+const DEFAULT_TRUSTED_DEPENDENCIES: &str = include_str!("trusted_dependencies.txt");
+
+/// # Trusted Dependencies
+///
+/// The packages whose lifecycle scripts (such as `postinstall`) run when a project is installed.
+///
+/// As in bun, this is the built in allowlist unless the project sets `trustedDependencies` in
+/// its `package.json` (which bun records in the lockfile), in which case only those packages
+/// are trusted.
+///
+/// ## Usage
+///```rust
+/// use bun2nix::trusted_dependencies::TrustedDependencies;
+///
+/// let default = TrustedDependencies::new(None);
+///
+/// assert!(default.is_trusted("esbuild"));
+/// assert!(!default.is_trusted("is-odd"));
+///
+/// let configured = TrustedDependencies::new(Some(&["is-odd".to_owned()]));
+///
+/// assert!(configured.is_trusted("is-odd"));
+/// assert!(!configured.is_trusted("esbuild"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedDependencies {
+    names: BTreeSet<String>,
+}
+
+impl Default for TrustedDependencies {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl TrustedDependencies {
+    /// # New Trusted Dependencies
+    ///
+    /// Trust the configured packages if there are any, or bun's default allowlist if not
+    pub fn new(configured: Option<&[String]>) -> Self {
+        let names = match configured {
+            Some(configured) => configured.iter().cloned().collect(),
+            None => Self::defaults().map(str::to_owned).collect(),
+        };
+
+        Self { names }
+    }
+
+    /// # Default Trusted Dependencies
+    ///
+    /// The packages on bun's built in allowlist
+    pub fn defaults() -> impl Iterator<Item = &'static str> {
+        DEFAULT_TRUSTED_DEPENDENCIES
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// # Is Trusted
+    ///
+    /// Whether the package with the given name (without a version) may run lifecycle scripts
+    pub fn is_trusted(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// # Trusted Packages
+    ///
+    /// The packages of a package set which may run lifecycle scripts
+    pub fn trusted_packages<'a>(&self, packages: &'a [Package]) -> Vec<&'a Package> {
+        packages
+            .iter()
+            .filter(|pkg| {
+                pkg.npm_identifier()
+                    .is_some_and(|(name, _)| self.is_trusted(name))
+            })
+            .collect()
+    }
+}
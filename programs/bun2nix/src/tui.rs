@@ -0,0 +1,338 @@
+//! Interactive terminal interface for the `bun2nix` command line tool
+//!
+//! Shows every package in the lockfile alongside its live prefetch status, and allows
+//! packages which failed to prefetch to be retried before any output is written
+
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use bun2nix::{
+    Error, Package, Prefetcher, Result,
+    package::Fetcher,
+    prefetcher::{PrefetchEvent, Prefetched},
+};
+use log::LevelFilter;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+
+/// How long to wait for a key press before checking for new prefetch results
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// # Package Status
+///
+/// Where a given package is in the prefetching process
+#[derive(Debug, Clone)]
+enum Status {
+    /// The hash was already known from the lockfile
+    Known,
+    /// Waiting for a prefetch worker to pick the package up
+    Queued,
+    /// Currently being prefetched by nix
+    Fetching,
+    /// Successfully prefetched
+    Fetched,
+    /// The hash was found in the prefetch cache
+    Cached,
+    /// Prefetching failed with the given error
+    Failed(String),
+}
+
+impl Status {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Known => "known",
+            Self::Queued => "queued",
+            Self::Fetching => "fetching",
+            Self::Fetched => "fetched",
+            Self::Cached => "cached",
+            Self::Failed(_) => "failed",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Known => Color::DarkGray,
+            Self::Queued => Color::Gray,
+            Self::Fetching => Color::Yellow,
+            Self::Fetched => Color::Green,
+            Self::Cached => Color::Cyan,
+            Self::Failed(_) => Color::Red,
+        }
+    }
+}
+
+/// # Interactive App
+///
+/// State for the interactive terminal interface
+struct App {
+    packages: Vec<Package>,
+    statuses: Vec<Status>,
+    table: TableState,
+    prefetcher: Arc<Prefetcher>,
+    sender: Sender<(usize, PrefetchEvent)>,
+    receiver: Receiver<(usize, PrefetchEvent)>,
+}
+
+/// # Outcome
+///
+/// What the user chose to do upon leaving the interface
+enum Outcome {
+    Write,
+    Abort,
+}
+
+/// # Run Interactive Interface
+///
+/// Prefetch the given packages while displaying their progress live.
+///
+/// Returns the prefetched packages if the user chose to write the output, or `None` if they
+/// aborted.
+pub fn run(packages: Vec<Package>, prefetcher: Arc<Prefetcher>) -> Result<Option<Vec<Package>>> {
+    let previous_level = log::max_level();
+    log::set_max_level(LevelFilter::Off);
+
+    let mut app = App::new(packages, prefetcher);
+    app.spawn_prefetch(app.pending());
+
+    let mut terminal = ratatui::init();
+    let outcome = app.event_loop(&mut terminal);
+    ratatui::restore();
+
+    log::set_max_level(previous_level);
+
+    match outcome? {
+        Outcome::Write => Ok(Some(app.packages)),
+        Outcome::Abort => Ok(None),
+    }
+}
+
+impl App {
+    fn new(packages: Vec<Package>, prefetcher: Arc<Prefetcher>) -> Self {
+        let statuses = packages
+            .iter()
+            .map(|pkg| match pkg.needs_prefetch() {
+                true => Status::Queued,
+                false => Status::Known,
+            })
+            .collect();
+
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            packages,
+            statuses,
+            table: TableState::default().with_selected(0),
+            prefetcher,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Indices of all packages which are still waiting to be prefetched
+    fn pending(&self) -> Vec<usize> {
+        self.indices_where(|status| matches!(status, Status::Queued))
+    }
+
+    /// Indices of all packages which failed to prefetch
+    fn failed(&self) -> Vec<usize> {
+        self.indices_where(|status| matches!(status, Status::Failed(_)))
+    }
+
+    fn indices_where(&self, predicate: impl Fn(&Status) -> bool) -> Vec<usize> {
+        self.statuses
+            .iter()
+            .enumerate()
+            .filter(|(_, status)| predicate(status))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn count(&self, predicate: impl Fn(&Status) -> bool) -> usize {
+        self.statuses
+            .iter()
+            .filter(|status| predicate(status))
+            .count()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.statuses
+            .iter()
+            .all(|status| matches!(status, Status::Known | Status::Fetched | Status::Cached))
+    }
+
+    /// Prefetch the given packages in parallel on a background thread, reporting progress back
+    /// over the app's channel
+    fn spawn_prefetch(&mut self, indices: Vec<usize>) {
+        if indices.is_empty() {
+            return;
+        }
+
+        let fetchers: Vec<(usize, Fetcher)> = indices
+            .into_iter()
+            .filter(|&i| self.packages[i].needs_prefetch())
+            .map(|i| {
+                self.statuses[i] = Status::Queued;
+
+                (i, self.packages[i].fetcher.clone())
+            })
+            .collect();
+
+        let indices: Vec<usize> = fetchers.iter().map(|(i, _)| *i).collect();
+        let prefetcher = Arc::clone(&self.prefetcher);
+        let sender = self.sender.clone();
+
+        thread::spawn(move || {
+            let result = prefetcher.prefetch_each(fetchers, |i, event| {
+                let _ = sender.send((i, event));
+            });
+
+            // If the worker pool could not be started, report the error against every package
+            if let Err(err) = result {
+                for i in indices {
+                    let err = Error::FetchingError(err.to_string());
+                    let _ = sender.send((i, PrefetchEvent::Finished(Err(err))));
+                }
+            }
+        });
+    }
+
+    fn receive_prefetch_events(&mut self) {
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                (i, PrefetchEvent::Started) => self.statuses[i] = Status::Fetching,
+                (i, PrefetchEvent::Finished(Ok(Prefetched { fetcher, cached }))) => {
+                    self.packages[i].fetcher = fetcher;
+                    self.statuses[i] = match cached {
+                        true => Status::Cached,
+                        false => Status::Fetched,
+                    };
+                }
+                (i, PrefetchEvent::Finished(Err(err))) => {
+                    self.statuses[i] = Status::Failed(err.to_string().trim().to_owned())
+                }
+            }
+        }
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<Outcome> {
+        loop {
+            // Raw mode swallows `Ctrl-C`, but a `SIGTERM` still needs to stop the interface
+            bun2nix::interrupt::check()?;
+
+            self.receive_prefetch_events();
+
+            terminal
+                .draw(|frame| self.draw(frame))
+                .map_err(Error::TerminalError)?;
+
+            if !event::poll(POLL_INTERVAL).map_err(Error::TerminalError)? {
+                continue;
+            }
+
+            let Event::Key(key) = event::read().map_err(Error::TerminalError)? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(Outcome::Abort),
+                KeyCode::Char('w') | KeyCode::Enter if self.is_complete() => {
+                    return Ok(Outcome::Write);
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::Char('r') => {
+                    let selected = self
+                        .table
+                        .selected()
+                        .filter(|&i| matches!(self.statuses.get(i), Some(Status::Failed(_))));
+
+                    self.spawn_prefetch(selected.into_iter().collect());
+                }
+                KeyCode::Char('R') => self.spawn_prefetch(self.failed()),
+                _ => (),
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, details_area, help_area] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(6),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let rows = self
+            .packages
+            .iter()
+            .zip(&self.statuses)
+            .map(|(pkg, status)| {
+                Row::new([
+                    Cell::from(pkg.name.as_str()),
+                    Cell::from(pkg.fetcher.source_kind()),
+                    Cell::from(status.label()).style(Style::default().fg(status.color())),
+                ])
+            });
+
+        let title = format!(
+            " bun2nix: {} packages, {} known, {} cached, {} fetched, {} failed ",
+            self.packages.len(),
+            self.count(|s| matches!(s, Status::Known)),
+            self.count(|s| matches!(s, Status::Cached)),
+            self.count(|s| matches!(s, Status::Fetched)),
+            self.count(|s| matches!(s, Status::Failed(_))),
+        );
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(9),
+            ],
+        )
+        .header(
+            Row::new(["Package", "Source", "Status"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let details = match self.table.selected().and_then(|i| self.statuses.get(i)) {
+            Some(Status::Failed(err)) => err.as_str(),
+            _ => "",
+        };
+
+        frame.render_widget(
+            Paragraph::new(details)
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered().title(" Error ")),
+            details_area,
+        );
+
+        let help = match self.is_complete() {
+            true => "↑/↓ select · w write output · q abort",
+            false => "↑/↓ select · r retry selected · R retry all failed · q abort",
+        };
+
+        frame.render_widget(Line::from(help), help_area);
+    }
+}
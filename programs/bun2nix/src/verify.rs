@@ -0,0 +1,235 @@
+//! This module holds verification of the hashes in a generated `bun.nix` file, such as those
+//! emitted straight from the lockfile without fetching anything
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::Mutex,
+};
+
+use rayon::{ThreadPoolBuilder, prelude::*};
+
+use crate::{
+    Package, Prefetcher,
+    error::{Error, Result},
+    package::Fetcher,
+    registry::Registry,
+};
+
+/// # Verification
+///
+/// The outcome of checking the hash of a single package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    /// The hash matches what was fetched
+    Verified,
+
+    /// The hash differs from what was fetched
+    Mismatch {
+        /// The hash in the file
+        expected: String,
+        /// The hash of what was fetched
+        actual: String,
+    },
+
+    /// The file has no hash for the package
+    Missing {
+        /// The hash of what was fetched
+        actual: String,
+    },
+
+    /// The package could not be fetched, or its hash algorithm is not supported
+    Failed(String),
+}
+
+/// # Verification Report
+///
+/// The outcome of checking every package in a file
+#[derive(Debug, Default, Clone)]
+pub struct Report {
+    /// The outcome for each package with a hash, keyed by package name
+    pub results: BTreeMap<String, Verification>,
+
+    /// Packages in the lockfile which are missing from the file
+    pub absent: Vec<String>,
+
+    /// Packages in the file which are not in the lockfile
+    pub orphans: Vec<String>,
+}
+
+impl Report {
+    /// # Verify Packages
+    ///
+    /// Check the hashes of a package set. Tarballs are downloaded from the network and hashed,
+    /// while everything else is prefetched with the given prefetcher, which may use its cache.
+    pub fn verify(
+        packages: &[Package],
+        prefetcher: &Prefetcher,
+        registry: &Registry,
+    ) -> Result<Self> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(prefetcher.jobs())
+            .build()
+            .map_err(Error::ThreadPool)?;
+
+        let results = Mutex::new(BTreeMap::new());
+
+        pool.install(|| {
+            packages.par_iter().for_each(|pkg| {
+                if let Some(verification) = verify_package(pkg, prefetcher, registry) {
+                    results
+                        .lock()
+                        .unwrap()
+                        .insert(pkg.name.clone(), verification);
+                }
+            })
+        });
+
+        prefetcher.flush()?;
+
+        Ok(Self {
+            results: results.into_inner().unwrap(),
+            ..Default::default()
+        })
+    }
+
+    /// # Compare Against Lockfile
+    ///
+    /// Record which packages of a lockfile are missing from the file, and which packages in the
+    /// file are orphans no longer in the lockfile
+    ///
+    /// ## Usage
+    ///```rust
+    /// use bun2nix::{Package, package::Fetcher, verify::Report};
+    ///
+    /// let path = |path: &str| Fetcher::CopyToStore { path: path.to_owned() };
+    ///
+    /// let mut report = Report::default();
+    /// report.compare_lockfile(
+    ///     &[Package::new("a".to_owned(), path("a")), Package::new("b".to_owned(), path("b"))],
+    ///     &[Package::new("b".to_owned(), path("b")), Package::new("c".to_owned(), path("c"))],
+    /// );
+    ///
+    /// assert_eq!(report.absent, ["c"]);
+    /// assert_eq!(report.orphans, ["a"]);
+    /// assert!(!report.is_verified());
+    /// ```
+    pub fn compare_lockfile(&mut self, packages: &[Package], locked: &[Package]) {
+        let names = |packages: &[Package]| -> BTreeSet<String> {
+            packages.iter().map(|pkg| pkg.name.clone()).collect()
+        };
+
+        let (packages, locked) = (names(packages), names(locked));
+
+        self.absent = locked.difference(&packages).cloned().collect();
+        self.orphans = packages.difference(&locked).cloned().collect();
+    }
+
+    /// # Is Verified
+    ///
+    /// Whether every package was verified, and the file matches the lockfile if it was compared
+    /// against one
+    pub fn is_verified(&self) -> bool {
+        self.problem_count() == 0
+    }
+
+    /// # Problem Count
+    ///
+    /// The number of packages which were not verified, are missing or are orphans
+    pub fn problem_count(&self) -> usize {
+        self.problems().count() + self.absent.len() + self.orphans.len()
+    }
+
+    /// # Problems
+    ///
+    /// Every package which was not verified, with the reason why
+    pub fn problems(&self) -> impl Iterator<Item = (&String, &Verification)> {
+        self.results
+            .iter()
+            .filter(|(_, verification)| **verification != Verification::Verified)
+    }
+}
+
+fn verify_package(
+    pkg: &Package,
+    prefetcher: &Prefetcher,
+    registry: &Registry,
+) -> Option<Verification> {
+    let expected = pkg.fetcher.sri_hash()?;
+
+    let actual = match &pkg.fetcher {
+        Fetcher::FetchUrl { url, .. } => {
+            let algorithm = expected
+                .split_once('-')
+                .map_or("sha512", |(algorithm, _)| algorithm);
+
+            registry
+                .tarball_hash(url, algorithm)
+                .and_then(|actual| actual.ok_or_else(|| Error::InvalidSriHash(expected.to_owned())))
+        }
+        fetcher => {
+            let mut unhashed = fetcher.clone();
+            unhashed.set_hash(String::new());
+
+            prefetcher
+                .prefetch(&unhashed)
+                .map(|prefetched| prefetched.fetcher.sri_hash().unwrap_or_default().to_owned())
+        }
+    };
+
+    Some(match actual {
+        Err(err) => Verification::Failed(
+            err.to_string()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        Ok(actual) if expected.is_empty() => Verification::Missing { actual },
+        Ok(actual) if actual == expected => Verification::Verified,
+        Ok(actual) => Verification::Mismatch {
+            expected: expected.to_owned(),
+            actual,
+        },
+    })
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, verification) in self.problems() {
+            match verification {
+                Verification::Verified => (),
+                Verification::Mismatch { expected, actual } => {
+                    writeln!(f, "mismatch {name}: expected {expected}, got {actual}")?
+                }
+                Verification::Missing { actual } => writeln!(f, "missing  {name}: got {actual}")?,
+                Verification::Failed(err) => writeln!(f, "failed   {name}: {err}")?,
+            }
+        }
+
+        for name in &self.absent {
+            writeln!(f, "absent   {name}: in the lockfile but not the file")?;
+        }
+
+        for name in &self.orphans {
+            writeln!(f, "orphan   {name}: in the file but not the lockfile")?;
+        }
+
+        write!(
+            f,
+            "{} of {} package(s) verified",
+            self.results.len() - self.problems().count(),
+            self.results.len()
+        )?;
+
+        if !self.absent.is_empty() || !self.orphans.is_empty() {
+            write!(
+                f,
+                ", {} absent and {} orphaned",
+                self.absent.len(),
+                self.orphans.len()
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! This module holds discovery of the members of a workspace from the `workspaces` globs of a
+//! `package.json`, for when there is no lockfile to list them
+
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{PackageJson, error::Result, lockfile::Workspace};
+
+/// # Workspace Member
+///
+/// A package of a workspace, along with its path relative to the workspace root
+#[derive(Debug)]
+pub struct WorkspaceMember {
+    /// The path to the member from the workspace root, which is empty for the root itself
+    pub path: String,
+
+    /// The contents of the member's `package.json`
+    pub package_json: PackageJson,
+}
+
+impl From<&WorkspaceMember> for Workspace {
+    fn from(member: &WorkspaceMember) -> Self {
+        Workspace {
+            name: member.package_json.name.clone(),
+            dependencies: member.package_json.dependencies.clone(),
+            dev_dependencies: member.package_json.dev_dependencies.clone(),
+            optional_dependencies: member.package_json.optional_dependencies.clone(),
+            peer_dependencies: member.package_json.peer_dependencies.clone(),
+            optional_peers: member.package_json.optional_peers(),
+        }
+    }
+}
+
+/// # Discover Workspaces
+///
+/// Find every member of the workspace rooted at the given directory, by expanding the
+/// `workspaces` globs of its `package.json` against the directories which contain a
+/// `package.json` of their own.
+///
+/// Patterns prefixed with `!` exclude the directories they match, and `node_modules` and hidden
+/// directories are never searched. The root itself is always the first member, followed by the
+/// others sorted by path.
+pub fn discover_workspaces(root: &Path, package_json: PackageJson) -> Result<Vec<WorkspaceMember>> {
+    let (excluded, included): (Vec<_>, Vec<_>) = package_json
+        .workspaces
+        .iter()
+        .map(|pattern| pattern.trim_start_matches("./").trim_end_matches('/'))
+        .partition(|pattern| pattern.starts_with('!'));
+
+    let depth = match included.iter().any(|pattern| pattern.contains("**")) {
+        true => None,
+        false => included
+            .iter()
+            .map(|pattern| pattern.split('/').count())
+            .max(),
+    };
+
+    let mut paths = Vec::new();
+
+    if depth != Some(0) && !included.is_empty() {
+        package_dirs(root, "", depth, &mut paths)?;
+    }
+
+    paths.retain(|path| {
+        included.iter().any(|pattern| glob_match(pattern, path))
+            && !excluded
+                .iter()
+                .any(|pattern| glob_match(&pattern[1..], path))
+    });
+    paths.sort();
+
+    let mut members = vec![WorkspaceMember {
+        path: String::new(),
+        package_json,
+    }];
+
+    for path in paths {
+        let package_json = fs::read_to_string(root.join(&path).join("package.json"))?;
+
+        members.push(WorkspaceMember {
+            path,
+            package_json: package_json.parse()?,
+        });
+    }
+
+    Ok(members)
+}
+
+/// # Lockfile Workspaces
+///
+/// Convert discovered workspace members to the workspaces of a lockfile, keyed by their path
+pub fn lockfile_workspaces(members: &[WorkspaceMember]) -> HashMap<String, Workspace> {
+    members
+        .iter()
+        .map(|member| (member.path.clone(), Workspace::from(member)))
+        .collect()
+}
+
+/// # Glob Match
+///
+/// Check whether a `/` separated path matches a workspace glob, where `*` and `?` match within
+/// a single directory name and `**` matches any number of directories
+///
+/// ## Usage
+///```rust
+/// use bun2nix::workspaces::glob_match;
+///
+/// assert!(glob_match("packages/*", "packages/lib"));
+/// assert!(!glob_match("packages/*", "packages/lib/nested"));
+/// assert!(glob_match("packages/**", "packages/lib/nested"));
+/// assert!(glob_match("apps/web-?", "apps/web-1"));
+/// ```
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => path.split_first().is_some_and(|(name, path)| {
+            match_wildcard(segment, name) && match_segments(rest, path)
+        }),
+    }
+}
+
+fn match_wildcard(pattern: &str, name: &str) -> bool {
+    let mut chars = pattern.chars();
+
+    match chars.next() {
+        None => name.is_empty(),
+        Some('*') => name
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([name.len()])
+            .any(|i| match_wildcard(chars.as_str(), &name[i..])),
+        Some('?') => {
+            let mut name = name.chars();
+            name.next().is_some() && match_wildcard(chars.as_str(), name.as_str())
+        }
+        Some(c) => name
+            .strip_prefix(c)
+            .is_some_and(|name| match_wildcard(chars.as_str(), name)),
+    }
+}
+
+/// Collect the paths of every directory below `dir` containing a `package.json`, searching at
+/// most `depth` levels deep
+fn package_dirs(
+    root: &Path,
+    dir: &str,
+    depth: Option<usize>,
+    paths: &mut Vec<String>,
+) -> Result<()> {
+    for entry in fs::read_dir(root.join(dir))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if name == "node_modules" || name.starts_with('.') || !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let path = match dir.is_empty() {
+            true => name,
+            false => format!("{dir}/{name}"),
+        };
+
+        if depth != Some(1) {
+            package_dirs(root, &path, depth.map(|depth| depth - 1), paths)?;
+        }
+
+        if root.join(&path).join("package.json").is_file() {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}